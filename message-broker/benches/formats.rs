@@ -0,0 +1,77 @@
+//! Encode and decode throughput of the envelope formats on meter-value traffic.
+//!
+//! `cargo bench -p message-broker --bench formats`
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use message_broker::{Envelope, Format};
+use ocpp::{CallId, ProtocolVersion, RawOcppMessage};
+use serde_json::json;
+
+/// A periodic MeterValues CALL of a three phase charge point, like the boards send every 10 s
+fn meter_values() -> Envelope {
+    let phases = ["L1", "L2", "L3"];
+    let mut sampled_value = vec![json!({
+        "value": "51234.5",
+        "context": "Sample.Periodic",
+        "measurand": "Energy.Active.Import.Register",
+        "unit": "Wh"
+    })];
+    for (measurand, unit, value) in [
+        ("Current.Import", "A", "15.9"),
+        ("Voltage", "V", "231.2"),
+        ("Power.Active.Import", "W", "3676.1"),
+    ] {
+        for phase in phases {
+            sampled_value.push(json!({
+                "value": value,
+                "context": "Sample.Periodic",
+                "measurand": measurand,
+                "phase": phase,
+                "unit": unit
+            }));
+        }
+    }
+    Envelope::new(
+        ProtocolVersion::Ocpp16,
+        RawOcppMessage {
+            message_type: 2,
+            call_id: CallId::new("a7f2c1de-4b0e-4f3a-9d7e-2c51b8e0f913").unwrap(),
+            action: "MeterValues".to_string(),
+            payload: json!({
+                "connectorId": 1,
+                "transactionId": 1742,
+                "meterValue": [{"timestamp": "2025-06-01T12:00:10Z", "sampledValue": sampled_value}]
+            }),
+        },
+    )
+}
+
+fn formats(c: &mut Criterion) {
+    let envelope = meter_values();
+    let mut encode = c.benchmark_group("encode");
+    for format in Format::ALL {
+        let size = format.encode(&envelope).unwrap().len();
+        encode.throughput(Throughput::Bytes(size as u64));
+        encode.bench_with_input(
+            BenchmarkId::from_parameter(format.tag()),
+            &format,
+            |b, format| b.iter(|| format.encode(&envelope).unwrap()),
+        );
+    }
+    encode.finish();
+
+    let mut decode = c.benchmark_group("decode");
+    for format in Format::ALL {
+        let bytes = format.encode(&envelope).unwrap();
+        decode.throughput(Throughput::Bytes(bytes.len() as u64));
+        decode.bench_with_input(
+            BenchmarkId::from_parameter(format.tag()),
+            &bytes,
+            |b, bytes| b.iter(|| format.decode::<Envelope>(bytes).unwrap()),
+        );
+    }
+    decode.finish();
+}
+
+criterion_group!(benches, formats);
+criterion_main!(benches);
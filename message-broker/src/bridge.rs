@@ -0,0 +1,387 @@
+///// Bridge between a charge point runtime and its Redis streams /////
+use ocpp::ocpp_datagram::OCPPDatagram;
+use ocpp::{CallId, OcppEvent, ProtocolVersion, RawOcppMessage, convert};
+use serde_json::Value;
+
+use crate::envelope::{Envelope, Format};
+use crate::error::BrokerError;
+use crate::streams::{StreamBackend, StreamEntry};
+
+/// Consumer group reading the command streams unless configured otherwise
+pub const DEFAULT_GROUP: &str = "ocpp-bridge";
+
+/// Direction of an event as seen from the charge point
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Received from the CSMS
+    In,
+    /// Sent to the CSMS
+    Out,
+}
+
+/// `ocpp:<cp_id>:in` or `ocpp:<cp_id>:out`
+pub fn event_stream(charge_point_id: &str, direction: Direction) -> String {
+    let suffix = match direction {
+        Direction::In => "in",
+        Direction::Out => "out",
+    };
+    format!("ocpp:{}:{}", charge_point_id, suffix)
+}
+
+/// `ocpp:<cp_id>:commands`, calls other services want the charge point to send
+pub fn command_stream(charge_point_id: &str) -> String {
+    format!("ocpp:{}:commands", charge_point_id)
+}
+
+/// A CALL read from the command stream
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Command {
+    /// Id of the stream entry, acknowledged once the command is handled
+    pub id: String,
+    pub call_id: CallId,
+    pub action: String,
+    pub payload: Value,
+}
+
+impl Command {
+    fn from_entry(entry: &StreamEntry) -> Result<Self, BrokerError> {
+        let envelope = Envelope::from_entry(entry)?;
+        if envelope.message_type != 2 {
+            return Err(BrokerError::entry(
+                &entry.id,
+                format!("message type {} is no CALL", envelope.message_type),
+            ));
+        }
+        Ok(Self {
+            id: entry.id.clone(),
+            call_id: envelope.call_id,
+            action: envelope.action,
+            payload: envelope.payload,
+        })
+    }
+
+    pub fn to_raw(&self) -> RawOcppMessage {
+        RawOcppMessage {
+            message_type: 2,
+            call_id: self.call_id.clone(),
+            action: self.action.clone(),
+            payload: self.payload.clone(),
+        }
+    }
+}
+
+/// Publishes the events of one charge point and hands it the commands addressed to it
+pub struct Bridge<B: StreamBackend> {
+    backend: B,
+    charge_point_id: String,
+    version: ProtocolVersion,
+    group: String,
+    consumer: String,
+    group_created: bool,
+    in_format: Format,
+    out_format: Format,
+    command_format: Format,
+    /// Maximum number of commands read per poll
+    pub batch: usize,
+}
+
+impl<B: StreamBackend> Bridge<B> {
+    /// Reads commands as consumer `charge_point_id` of [`DEFAULT_GROUP`]
+    pub fn new(backend: B, charge_point_id: &str, version: ProtocolVersion) -> Self {
+        Self {
+            backend,
+            charge_point_id: charge_point_id.to_string(),
+            version,
+            group: DEFAULT_GROUP.to_string(),
+            consumer: charge_point_id.to_string(),
+            group_created: false,
+            in_format: Format::Json,
+            out_format: Format::Json,
+            command_format: Format::Json,
+            batch: 16,
+        }
+    }
+
+    /// Publishes the events of `direction` in `format`, readers take the format from the entries
+    pub fn event_format(mut self, direction: Direction, format: Format) -> Self {
+        match direction {
+            Direction::In => self.in_format = format,
+            Direction::Out => self.out_format = format,
+        }
+        self
+    }
+
+    /// Enqueues commands in `format`
+    pub fn command_format(mut self, format: Format) -> Self {
+        self.command_format = format;
+        self
+    }
+
+    /// Reads commands as `consumer` of `group`, i.e. to share them between replicas
+    pub fn consumer(mut self, group: &str, consumer: &str) -> Self {
+        self.group = group.to_string();
+        self.consumer = consumer.to_string();
+        self.group_created = false;
+        self
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    /// Appends `event` to the stream of `direction`, returns the entry id
+    pub fn publish(
+        &mut self,
+        direction: Direction,
+        event: &OcppEvent,
+    ) -> Result<String, BrokerError> {
+        let codec = |reason: String| BrokerError::Codec {
+            format: Format::Json,
+            reason,
+        };
+        let datagram =
+            OCPPDatagram::try_from(event.clone()).map_err(|err| codec(err.to_string()))?;
+        let raw = RawOcppMessage::try_from(datagram)
+            .map_err(|err| codec(format!("call error {}", err.error_description)))?;
+        let format = match direction {
+            Direction::In => self.in_format,
+            Direction::Out => self.out_format,
+        };
+        Envelope::new(self.version, raw).append(
+            &mut self.backend,
+            &event_stream(&self.charge_point_id, direction),
+            format,
+        )
+    }
+
+    /// All events published in `direction`, decoded with the version they were published with
+    pub fn events(&mut self, direction: Direction) -> Result<Vec<OcppEvent>, BrokerError> {
+        self.backend
+            .range(&event_stream(&self.charge_point_id, direction))?
+            .iter()
+            .map(decode_event)
+            .collect()
+    }
+
+    /// Appends a CALL to the command stream, what a service does to reach the charge point
+    pub fn enqueue_command(
+        &mut self,
+        call_id: &CallId,
+        action: &str,
+        payload: &Value,
+    ) -> Result<String, BrokerError> {
+        let raw = RawOcppMessage {
+            message_type: 2,
+            call_id: call_id.clone(),
+            action: action.to_string(),
+            payload: payload.clone(),
+        };
+        Envelope::new(self.version, raw).append(
+            &mut self.backend,
+            &command_stream(&self.charge_point_id),
+            self.command_format,
+        )
+    }
+
+    /// Hands pending and then new commands to `handler` and acknowledges those it accepts.
+    ///
+    /// Rejected commands stay pending and are handed out again on the next poll. An entry that
+    /// is no command is acknowledged so it cannot block the stream, and reported as error.
+    /// Returns the number of acknowledged commands.
+    pub fn poll_commands(
+        &mut self,
+        mut handler: impl FnMut(&Command) -> bool,
+    ) -> Result<usize, BrokerError> {
+        let stream = command_stream(&self.charge_point_id);
+        if !self.group_created {
+            self.backend.create_group(&stream, &self.group)?;
+            self.group_created = true;
+        }
+        let mut acked = 0;
+        for pending in [true, false] {
+            let entries = self.backend.read_group(
+                &stream,
+                &self.group,
+                &self.consumer,
+                pending,
+                self.batch,
+            )?;
+            for entry in entries {
+                let command = match Command::from_entry(&entry) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        self.backend.ack(&stream, &self.group, &entry.id)?;
+                        return Err(err);
+                    }
+                };
+                if handler(&command) {
+                    self.backend.ack(&stream, &self.group, &entry.id)?;
+                    acked += 1;
+                }
+            }
+        }
+        Ok(acked)
+    }
+}
+
+/// Decodes an entry written by [`Bridge::publish`]
+pub fn decode_event(entry: &StreamEntry) -> Result<OcppEvent, BrokerError> {
+    let envelope = Envelope::from_entry(entry)?;
+    Ok(convert(envelope.version, envelope.into_raw())?)
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::streams::MemoryBackend;
+    use serde_json::json;
+
+    fn bridge() -> Bridge<MemoryBackend> {
+        Bridge::new(MemoryBackend::new(), "CP-1", ProtocolVersion::Ocpp16)
+    }
+
+    fn boot_notification() -> OcppEvent {
+        let raw = RawOcppMessage {
+            message_type: 2,
+            call_id: CallId::new("19223201").unwrap(),
+            action: "BootNotification".to_string(),
+            payload: json!({"chargePointVendor": "VendorX", "chargePointModel": "SingleSocketCharger"}),
+        };
+        convert(ProtocolVersion::Ocpp16, raw).unwrap()
+    }
+
+    #[test]
+    fn given_event__when_publishing__then_stored_per_charge_point_and_direction() {
+        let mut bridge = bridge();
+
+        bridge
+            .publish(Direction::Out, &boot_notification())
+            .unwrap();
+
+        let entries = bridge.backend_mut().range("ocpp:CP-1:out").unwrap();
+        let envelope = Envelope::from_entry(&entries[0]).unwrap();
+        assert_eq!(envelope.action, "BootNotification");
+        assert_eq!(envelope.version, ProtocolVersion::Ocpp16);
+        assert!(
+            bridge
+                .backend_mut()
+                .range("ocpp:CP-1:in")
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            bridge.events(Direction::Out).unwrap(),
+            vec![boot_notification()]
+        );
+    }
+
+    #[test]
+    fn given_commands__when_polling__then_handled_and_acknowledged() {
+        let mut bridge = bridge();
+        let call_id = CallId::new("42").unwrap();
+        bridge
+            .enqueue_command(&call_id, "Reset", &json!({"type": "Soft"}))
+            .unwrap();
+
+        let mut received = Vec::new();
+        let acked = bridge
+            .poll_commands(|command| {
+                received.push(command.to_raw());
+                true
+            })
+            .unwrap();
+
+        assert_eq!(acked, 1);
+        assert_eq!(received[0].action, "Reset");
+        assert_eq!(received[0].call_id, call_id);
+        assert_eq!(
+            bridge
+                .backend()
+                .pending("ocpp:CP-1:commands", DEFAULT_GROUP),
+            0
+        );
+        assert_eq!(bridge.poll_commands(|_| true).unwrap(), 0);
+    }
+
+    #[test]
+    fn given_rejected_command__when_polling_again__then_redelivered() {
+        let mut bridge = bridge();
+        bridge
+            .enqueue_command(
+                &CallId::new("42").unwrap(),
+                "Reset",
+                &json!({"type": "Soft"}),
+            )
+            .unwrap();
+
+        assert_eq!(bridge.poll_commands(|_| false).unwrap(), 0);
+        assert_eq!(
+            bridge
+                .backend()
+                .pending("ocpp:CP-1:commands", DEFAULT_GROUP),
+            1
+        );
+
+        assert_eq!(bridge.poll_commands(|_| true).unwrap(), 1);
+        assert_eq!(
+            bridge
+                .backend()
+                .pending("ocpp:CP-1:commands", DEFAULT_GROUP),
+            0
+        );
+    }
+
+    #[test]
+    fn given_malformed_command__when_polling__then_error_and_acknowledged() {
+        let mut bridge = bridge();
+        bridge
+            .backend_mut()
+            .add("ocpp:CP-1:commands", &[("action", &b"Reset"[..])])
+            .unwrap();
+
+        assert!(matches!(
+            bridge.poll_commands(|_| true),
+            Err(BrokerError::Entry { .. })
+        ));
+        assert_eq!(
+            bridge
+                .backend()
+                .pending("ocpp:CP-1:commands", DEFAULT_GROUP),
+            0
+        );
+    }
+
+    #[test]
+    fn given_formats_per_stream__when_publishing__then_each_tagged_and_decoded() {
+        let mut bridge = bridge()
+            .event_format(Direction::Out, Format::Cbor)
+            .command_format(Format::MessagePack);
+        bridge
+            .publish(Direction::Out, &boot_notification())
+            .unwrap();
+        bridge
+            .enqueue_command(
+                &CallId::new("42").unwrap(),
+                "Reset",
+                &json!({"type": "Soft"}),
+            )
+            .unwrap();
+
+        let out = bridge.backend_mut().range("ocpp:CP-1:out").unwrap();
+        let commands = bridge.backend_mut().range("ocpp:CP-1:commands").unwrap();
+
+        assert_eq!(out[0].text("format").unwrap(), "cbor");
+        assert_eq!(commands[0].text("format").unwrap(), "msgpack");
+        assert_eq!(
+            bridge.events(Direction::Out).unwrap(),
+            vec![boot_notification()]
+        );
+        assert_eq!(bridge.poll_commands(|_| true).unwrap(), 1);
+    }
+}
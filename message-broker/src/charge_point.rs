@@ -0,0 +1,309 @@
+///// Local control of a charge point, without speaking OCPP /////
+use std::fmt;
+
+use ocpp::ci_string::CiString20Type;
+use ocpp::types::ChargePointStatus;
+
+/// What local system components, i.e. an HMI or an energy manager, may ask of a charge point.
+///
+/// Connectors are numbered from 1 like in OCPP.
+pub trait ChargePointControl {
+    /// Starts a transaction on `connector`, returns its transaction id
+    fn start_charging(&mut self, connector: u32, id_tag: &str) -> Result<i32, ControlError>;
+
+    /// Stops the transaction on `connector`, returns its transaction id
+    fn stop_charging(&mut self, connector: u32) -> Result<i32, ControlError>;
+
+    fn connector_status(&self, connector: u32) -> Result<ChargePointStatus, ControlError>;
+
+    /// Limits the current drawn on `connector` in ampere per phase
+    fn set_current_limit(&mut self, connector: u32, amps: f64) -> Result<(), ControlError>;
+
+    /// Changes since the last call, in the order they happened
+    fn drain_notifications(&mut self) -> Vec<Notification>;
+}
+
+/// Change of a charge point that is announced to local system components
+#[derive(Debug, Clone, PartialEq)]
+pub enum Notification {
+    StatusChanged {
+        connector: u32,
+        status: ChargePointStatus,
+    },
+    TransactionStarted {
+        connector: u32,
+        transaction_id: i32,
+        id_tag: String,
+    },
+    TransactionStopped {
+        connector: u32,
+        transaction_id: i32,
+    },
+    MeterValue {
+        connector: u32,
+        energy_wh: f64,
+        power_w: f64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ControlError {
+    UnknownConnector(u32),
+    /// The connector is charging already, reserved, unavailable or faulted
+    NotAvailable(u32, ChargePointStatus),
+    NoTransaction(u32),
+    /// The id tag exceeds the 20 characters allowed by OCPP
+    InvalidIdTag(String),
+    /// Outside of `0..=max` ampere
+    InvalidCurrentLimit {
+        amps: f64,
+        max: f64,
+    },
+}
+
+impl ControlError {
+    /// Short name, used as the last segment of D-Bus error names
+    pub fn name(&self) -> &'static str {
+        match self {
+            ControlError::UnknownConnector(_) => "UnknownConnector",
+            ControlError::NotAvailable(..) => "NotAvailable",
+            ControlError::NoTransaction(_) => "NoTransaction",
+            ControlError::InvalidIdTag(_) => "InvalidIdTag",
+            ControlError::InvalidCurrentLimit { .. } => "InvalidCurrentLimit",
+        }
+    }
+}
+
+impl fmt::Display for ControlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ControlError::UnknownConnector(connector) => {
+                write!(f, "no connector {}", connector)
+            }
+            ControlError::NotAvailable(connector, status) => {
+                write!(f, "connector {} is {:?}", connector, status)
+            }
+            ControlError::NoTransaction(connector) => {
+                write!(f, "no transaction on connector {}", connector)
+            }
+            ControlError::InvalidIdTag(id_tag) => write!(f, "invalid id tag {}", id_tag),
+            ControlError::InvalidCurrentLimit { amps, max } => {
+                write!(f, "current limit {} A outside of 0 to {} A", amps, max)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ControlError {}
+
+#[derive(Debug, Clone)]
+struct Connector {
+    status: ChargePointStatus,
+    transaction_id: Option<i32>,
+    current_limit: f64,
+}
+
+/// Charge point state kept in the process, changes are recorded as [`Notification`]s
+#[derive(Debug, Clone)]
+pub struct LocalChargePoint {
+    connectors: Vec<Connector>,
+    max_current: f64,
+    next_transaction_id: i32,
+    notifications: Vec<Notification>,
+}
+
+impl LocalChargePoint {
+    /// `connectors` available connectors rated for `max_current` ampere each
+    pub fn new(connectors: u32, max_current: f64) -> Self {
+        Self {
+            connectors: (0..connectors)
+                .map(|_| Connector {
+                    status: ChargePointStatus::Available,
+                    transaction_id: None,
+                    current_limit: max_current,
+                })
+                .collect(),
+            max_current,
+            next_transaction_id: 1,
+            notifications: Vec::new(),
+        }
+    }
+
+    pub fn current_limit(&self, connector: u32) -> Result<f64, ControlError> {
+        Ok(self.connector(connector)?.current_limit)
+    }
+
+    /// Announces a status reported by the hardware, i.e. a fault
+    pub fn set_status(
+        &mut self,
+        connector: u32,
+        status: ChargePointStatus,
+    ) -> Result<(), ControlError> {
+        let entry = self.connector_mut(connector)?;
+        if entry.status != status {
+            entry.status = status;
+            self.notifications
+                .push(Notification::StatusChanged { connector, status });
+        }
+        Ok(())
+    }
+
+    /// Announces a meter reading of `connector`
+    pub fn meter_value(
+        &mut self,
+        connector: u32,
+        energy_wh: f64,
+        power_w: f64,
+    ) -> Result<(), ControlError> {
+        self.connector(connector)?;
+        self.notifications.push(Notification::MeterValue {
+            connector,
+            energy_wh,
+            power_w,
+        });
+        Ok(())
+    }
+
+    fn connector(&self, connector: u32) -> Result<&Connector, ControlError> {
+        connector
+            .checked_sub(1)
+            .and_then(|index| self.connectors.get(index as usize))
+            .ok_or(ControlError::UnknownConnector(connector))
+    }
+
+    fn connector_mut(&mut self, connector: u32) -> Result<&mut Connector, ControlError> {
+        connector
+            .checked_sub(1)
+            .and_then(|index| self.connectors.get_mut(index as usize))
+            .ok_or(ControlError::UnknownConnector(connector))
+    }
+}
+
+impl ChargePointControl for LocalChargePoint {
+    fn start_charging(&mut self, connector: u32, id_tag: &str) -> Result<i32, ControlError> {
+        let status = self.connector(connector)?.status;
+        if !matches!(
+            status,
+            ChargePointStatus::Available | ChargePointStatus::Preparing
+        ) {
+            return Err(ControlError::NotAvailable(connector, status));
+        }
+        let id_tag = CiString20Type::new(id_tag)
+            .map_err(|_| ControlError::InvalidIdTag(id_tag.to_string()))?;
+        let transaction_id = self.next_transaction_id;
+        self.next_transaction_id += 1;
+        self.connector_mut(connector)?.transaction_id = Some(transaction_id);
+        self.notifications.push(Notification::TransactionStarted {
+            connector,
+            transaction_id,
+            id_tag: id_tag.into_string(),
+        });
+        self.set_status(connector, ChargePointStatus::Charging)?;
+        Ok(transaction_id)
+    }
+
+    fn stop_charging(&mut self, connector: u32) -> Result<i32, ControlError> {
+        let transaction_id = self
+            .connector_mut(connector)?
+            .transaction_id
+            .take()
+            .ok_or(ControlError::NoTransaction(connector))?;
+        self.notifications.push(Notification::TransactionStopped {
+            connector,
+            transaction_id,
+        });
+        self.set_status(connector, ChargePointStatus::Available)?;
+        Ok(transaction_id)
+    }
+
+    fn connector_status(&self, connector: u32) -> Result<ChargePointStatus, ControlError> {
+        Ok(self.connector(connector)?.status)
+    }
+
+    fn set_current_limit(&mut self, connector: u32, amps: f64) -> Result<(), ControlError> {
+        let max = self.max_current;
+        if !(0.0..=max).contains(&amps) {
+            return Err(ControlError::InvalidCurrentLimit { amps, max });
+        }
+        self.connector_mut(connector)?.current_limit = amps;
+        Ok(())
+    }
+
+    fn drain_notifications(&mut self) -> Vec<Notification> {
+        std::mem::take(&mut self.notifications)
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_available_connector__when_starting_and_stopping__then_notified_in_order() {
+        let mut charge_point = LocalChargePoint::new(2, 32.0);
+
+        let transaction_id = charge_point.start_charging(2, "04A2B3C4").unwrap();
+        assert_eq!(
+            charge_point.connector_status(2).unwrap(),
+            ChargePointStatus::Charging
+        );
+        assert_eq!(charge_point.stop_charging(2).unwrap(), transaction_id);
+
+        assert_eq!(
+            charge_point.drain_notifications(),
+            vec![
+                Notification::TransactionStarted {
+                    connector: 2,
+                    transaction_id,
+                    id_tag: "04A2B3C4".to_string()
+                },
+                Notification::StatusChanged {
+                    connector: 2,
+                    status: ChargePointStatus::Charging
+                },
+                Notification::TransactionStopped {
+                    connector: 2,
+                    transaction_id
+                },
+                Notification::StatusChanged {
+                    connector: 2,
+                    status: ChargePointStatus::Available
+                },
+            ]
+        );
+        assert!(charge_point.drain_notifications().is_empty());
+    }
+
+    #[test]
+    fn given_charging_connector__when_starting__then_not_available() {
+        let mut charge_point = LocalChargePoint::new(1, 32.0);
+        charge_point.start_charging(1, "04A2B3C4").unwrap();
+
+        assert_eq!(
+            charge_point.start_charging(1, "04A2B3C4"),
+            Err(ControlError::NotAvailable(1, ChargePointStatus::Charging))
+        );
+        assert_eq!(
+            charge_point.start_charging(0, "04A2B3C4"),
+            Err(ControlError::UnknownConnector(0))
+        );
+    }
+
+    #[test]
+    fn given_limit_above_rating__when_setting__then_error() {
+        let mut charge_point = LocalChargePoint::new(1, 16.0);
+
+        charge_point.set_current_limit(1, 10.0).unwrap();
+
+        assert_eq!(charge_point.current_limit(1).unwrap(), 10.0);
+        assert!(matches!(
+            charge_point.set_current_limit(1, 20.0),
+            Err(ControlError::InvalidCurrentLimit { .. })
+        ));
+        assert_eq!(
+            charge_point.stop_charging(1),
+            Err(ControlError::NoTransaction(1))
+        );
+    }
+}
@@ -0,0 +1,315 @@
+///// D-Bus service exposing a charge point to local system components /////
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use dbus::blocking::Connection;
+use dbus::blocking::stdintf::org_freedesktop_dbus::RequestNameReply;
+use dbus::channel::{MatchingReceiver, Sender};
+use dbus::message::MatchRule;
+use dbus::{Message, MethodErr};
+use dbus_crossroads::{Context, Crossroads};
+use ocpp::types::ChargePointStatus;
+
+use crate::charge_point::{ChargePointControl, ControlError, Notification};
+
+pub const BUS_NAME: &str = "io.ocpp.ChargePoint";
+pub const OBJECT_PATH: &str = "/io/ocpp/ChargePoint";
+pub const INTERFACE: &str = "io.ocpp.ChargePoint1";
+
+/// Serves `io.ocpp.ChargePoint1` for a [`ChargePointControl`] shared with the caller.
+///
+/// Notifications raised by method calls are emitted as signals right away, those raised by
+/// the caller, i.e. meter values, with the next [`ChargePointService::process`].
+pub struct ChargePointService<C> {
+    connection: Connection,
+    control: Arc<Mutex<C>>,
+}
+
+impl<C: ChargePointControl + Send + 'static> ChargePointService<C> {
+    /// Claims [`BUS_NAME`] on `connection` and registers the object at [`OBJECT_PATH`], failing
+    /// when another process owns the name.
+    ///
+    /// The system bus only lets a process own the name with a policy allowing it, see
+    /// `dbus/io.ocpp.ChargePoint.conf`.
+    pub fn new(connection: Connection, control: Arc<Mutex<C>>) -> Result<Self, dbus::Error> {
+        match connection.request_name(BUS_NAME, false, true, true)? {
+            RequestNameReply::PrimaryOwner | RequestNameReply::AlreadyOwner => {}
+            reply => {
+                return Err(dbus::Error::new_failed(&format!(
+                    "{BUS_NAME} not acquired: {reply:?}"
+                )));
+            }
+        }
+        let mut crossroads = Crossroads::new();
+        let interface = crossroads.register(INTERFACE, register_interface::<C>);
+        crossroads.insert(OBJECT_PATH, &[interface], control.clone());
+        connection.start_receive(
+            MatchRule::new_method_call(),
+            Box::new(move |message, connection| {
+                // errors are replied to the caller, there is nothing left to handle here
+                let _ = crossroads.handle_message(message, connection);
+                true
+            }),
+        );
+        Ok(Self {
+            connection,
+            control,
+        })
+    }
+
+    pub fn control(&self) -> &Arc<Mutex<C>> {
+        &self.control
+    }
+
+    /// Handles the method calls arriving within `timeout` and emits pending notifications
+    pub fn process(&self, timeout: Duration) -> Result<(), dbus::Error> {
+        self.connection.process(timeout)?;
+        let notifications = lock(&self.control).drain_notifications();
+        for notification in &notifications {
+            self.connection
+                .send(signal(notification))
+                .map_err(|_| dbus::Error::new_failed("failed to send signal"))?;
+        }
+        Ok(())
+    }
+}
+
+fn register_interface<C: ChargePointControl + Send + 'static>(
+    builder: &mut dbus_crossroads::IfaceBuilder<Arc<Mutex<C>>>,
+) {
+    builder.method(
+        "StartCharging",
+        ("connector", "id_tag"),
+        ("transaction_id",),
+        |context: &mut Context, control: &mut Arc<Mutex<C>>, (connector, id_tag): (u32, String)| {
+            call(context, control, |control| {
+                control
+                    .start_charging(connector, &id_tag)
+                    .map(|transaction_id| (transaction_id,))
+            })
+        },
+    );
+    builder.method(
+        "StopCharging",
+        ("connector",),
+        ("transaction_id",),
+        |context: &mut Context, control: &mut Arc<Mutex<C>>, (connector,): (u32,)| {
+            call(context, control, |control| {
+                control
+                    .stop_charging(connector)
+                    .map(|transaction_id| (transaction_id,))
+            })
+        },
+    );
+    builder.method(
+        "GetConnectorStatus",
+        ("connector",),
+        ("status",),
+        |context: &mut Context, control: &mut Arc<Mutex<C>>, (connector,): (u32,)| {
+            call(context, control, |control| {
+                control
+                    .connector_status(connector)
+                    .map(|status| (status_name(status),))
+            })
+        },
+    );
+    builder.method(
+        "SetCurrentLimit",
+        ("connector", "amps"),
+        (),
+        |context: &mut Context, control: &mut Arc<Mutex<C>>, (connector, amps): (u32, f64)| {
+            call(context, control, |control| {
+                control.set_current_limit(connector, amps)
+            })
+        },
+    );
+    builder.signal::<(u32, String), _>("StatusChanged", ("connector", "status"));
+    builder.signal::<(u32, i32, String), _>(
+        "TransactionStarted",
+        ("connector", "transaction_id", "id_tag"),
+    );
+    builder.signal::<(u32, i32), _>("TransactionStopped", ("connector", "transaction_id"));
+    builder.signal::<(u32, f64, f64), _>("MeterValue", ("connector", "energy_wh", "power_w"));
+}
+
+/// Runs `f` and queues the signals of the notifications it raised behind the reply
+fn call<C: ChargePointControl, R>(
+    context: &mut Context,
+    control: &Arc<Mutex<C>>,
+    f: impl FnOnce(&mut C) -> Result<R, ControlError>,
+) -> Result<R, MethodErr> {
+    let mut control = lock(control);
+    let result = f(&mut control);
+    for notification in control.drain_notifications() {
+        context.push_msg(signal(&notification));
+    }
+    result.map_err(|err| {
+        MethodErr::from((
+            format!("{}.Error.{}", INTERFACE, err.name()),
+            err.to_string(),
+        ))
+    })
+}
+
+/// A handler that panicked left the state as it was, keep serving it
+fn lock<C>(control: &Mutex<C>) -> std::sync::MutexGuard<'_, C> {
+    control
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// OCPP spelling, i.e. `"SuspendedEV"`
+fn status_name(status: ChargePointStatus) -> String {
+    match serde_json::to_value(status) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => format!("{:?}", status),
+    }
+}
+
+fn signal(notification: &Notification) -> Message {
+    let message = |name: &str| {
+        Message::new_signal(OBJECT_PATH, INTERFACE, name).expect("valid path, interface and member")
+    };
+    match notification {
+        Notification::StatusChanged { connector, status } => {
+            message("StatusChanged").append2(*connector, status_name(*status))
+        }
+        Notification::TransactionStarted {
+            connector,
+            transaction_id,
+            id_tag,
+        } => message("TransactionStarted").append3(*connector, *transaction_id, id_tag.as_str()),
+        Notification::TransactionStopped {
+            connector,
+            transaction_id,
+        } => message("TransactionStopped").append2(*connector, *transaction_id),
+        Notification::MeterValue {
+            connector,
+            energy_wh,
+            power_w,
+        } => message("MeterValue").append3(*connector, *energy_wh, *power_w),
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::charge_point::LocalChargePoint;
+    use dbus::channel::Channel;
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+
+    /// A dbus-daemon of its own, killed on drop
+    struct PrivateBus {
+        daemon: Child,
+        address: String,
+    }
+
+    impl PrivateBus {
+        fn start() -> Self {
+            let mut daemon = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address=1"])
+                .stdout(Stdio::piped())
+                .spawn()
+                .expect("dbus-daemon must be installed");
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.as_mut().unwrap())
+                .read_line(&mut address)
+                .unwrap();
+            Self {
+                daemon,
+                address: address.trim().to_string(),
+            }
+        }
+
+        fn connect(&self) -> Connection {
+            let mut channel = Channel::open_private(&self.address).unwrap();
+            channel.register().unwrap();
+            Connection::from(channel)
+        }
+    }
+
+    impl Drop for PrivateBus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    /// Serves a two connector charge point on `bus` until the returned flag is set
+    fn serve(bus: &PrivateBus) -> (Arc<AtomicBool>, thread::JoinHandle<()>) {
+        let stop = Arc::new(AtomicBool::new(false));
+        let connection = bus.connect();
+        let service = ChargePointService::new(
+            connection,
+            Arc::new(Mutex::new(LocalChargePoint::new(2, 32.0))),
+        );
+        let handle = {
+            let stop = stop.clone();
+            thread::spawn(move || {
+                let service = service.unwrap();
+                while !stop.load(Ordering::Relaxed) {
+                    service.process(Duration::from_millis(20)).unwrap();
+                }
+            })
+        };
+        (stop, handle)
+    }
+
+    #[test]
+    #[ignore = "needs dbus-daemon"]
+    fn given_private_bus__when_calling_methods__then_replies_and_signals() {
+        let bus = PrivateBus::start();
+        let (stop, handle) = serve(&bus);
+        let client = bus.connect();
+        let started = Arc::new(Mutex::new(Vec::new()));
+        {
+            let started = started.clone();
+            let rule = MatchRule::new_signal(INTERFACE, "TransactionStarted");
+            client
+                .add_match(
+                    rule,
+                    move |(connector, transaction_id, id_tag): (u32, i32, String), _, _| {
+                        started
+                            .lock()
+                            .unwrap()
+                            .push((connector, transaction_id, id_tag));
+                        true
+                    },
+                )
+                .unwrap();
+        }
+        let proxy = client.with_proxy(BUS_NAME, OBJECT_PATH, Duration::from_secs(5));
+
+        let (transaction_id,): (i32,) = proxy
+            .method_call(INTERFACE, "StartCharging", (1u32, "04A2B3C4"))
+            .unwrap();
+        let (status,): (String,) = proxy
+            .method_call(INTERFACE, "GetConnectorStatus", (1u32,))
+            .unwrap();
+        let () = proxy
+            .method_call(INTERFACE, "SetCurrentLimit", (1u32, 16.0f64))
+            .unwrap();
+        let error = proxy
+            .method_call::<(i32,), _, _, _>(INTERFACE, "StopCharging", (2u32,))
+            .unwrap_err();
+        for _ in 0..10 {
+            client.process(Duration::from_millis(20)).unwrap();
+        }
+
+        stop.store(true, Ordering::Relaxed);
+        handle.join().unwrap();
+        assert_eq!(status, "Charging");
+        assert_eq!(
+            error.name(),
+            Some("io.ocpp.ChargePoint1.Error.NoTransaction")
+        );
+        assert_eq!(
+            *started.lock().unwrap(),
+            vec![(1, transaction_id, "04A2B3C4".to_string())]
+        );
+    }
+}
@@ -0,0 +1,206 @@
+///// Envelope around the OCPP messages on the broker streams, in JSON, CBOR or MessagePack /////
+use ocpp::{CallId, ProtocolVersion, RawOcppMessage};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::error::BrokerError;
+use crate::streams::{StreamBackend, StreamEntry};
+
+/// Serialization format of an envelope, stored as tag in the `format` field of the entry header
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub enum Format {
+    /// Readable with `redis-cli`, the default
+    #[default]
+    Json,
+    /// RFC 8949, binary and smaller than JSON
+    Cbor,
+    MessagePack,
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::Json, Format::Cbor, Format::MessagePack];
+
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Cbor => "cbor",
+            Format::MessagePack => "msgpack",
+        }
+    }
+
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        Format::ALL.into_iter().find(|format| format.tag() == tag)
+    }
+
+    pub fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, BrokerError> {
+        let encoded = match self {
+            Format::Json => serde_json::to_vec(value).map_err(|err| err.to_string()),
+            Format::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::into_writer(value, &mut bytes)
+                    .map(|()| bytes)
+                    .map_err(|err| err.to_string())
+            }
+            // maps keep optional fields skippable, the default struct as array encoding does not
+            Format::MessagePack => rmp_serde::to_vec_named(value).map_err(|err| err.to_string()),
+        };
+        encoded.map_err(|reason| BrokerError::Codec {
+            format: *self,
+            reason,
+        })
+    }
+
+    pub fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, BrokerError> {
+        let decoded = match self {
+            Format::Json => serde_json::from_slice(bytes).map_err(|err| err.to_string()),
+            Format::Cbor => ciborium::from_reader(bytes).map_err(|err| err.to_string()),
+            Format::MessagePack => rmp_serde::from_slice(bytes).map_err(|err| err.to_string()),
+        };
+        decoded.map_err(|reason| BrokerError::Codec {
+            format: *self,
+            reason,
+        })
+    }
+}
+
+/// An OCPP message together with the version whose catalogue decodes it
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Envelope {
+    pub version: ProtocolVersion,
+    pub message_type: u8,
+    pub call_id: CallId,
+    pub action: String,
+    pub payload: Value,
+}
+
+impl Envelope {
+    pub fn new(version: ProtocolVersion, raw: RawOcppMessage) -> Self {
+        Self {
+            version,
+            message_type: raw.message_type,
+            call_id: raw.call_id,
+            action: raw.action,
+            payload: raw.payload,
+        }
+    }
+
+    pub fn into_raw(self) -> RawOcppMessage {
+        RawOcppMessage {
+            message_type: self.message_type,
+            call_id: self.call_id,
+            action: self.action,
+            payload: self.payload,
+        }
+    }
+
+    /// Adds an entry with the `format` tag as header and the encoded envelope as body
+    pub fn append<B: StreamBackend>(
+        &self,
+        backend: &mut B,
+        stream: &str,
+        format: Format,
+    ) -> Result<String, BrokerError> {
+        append(backend, stream, format, self)
+    }
+
+    /// Decodes an entry written by [`Envelope::append`] in whatever format it is tagged with
+    pub fn from_entry(entry: &StreamEntry) -> Result<Self, BrokerError> {
+        decode_entry(entry)
+    }
+}
+
+/// Adds an entry with the `format` tag as header and `value` encoded in it as body
+pub fn append<B: StreamBackend, T: Serialize>(
+    backend: &mut B,
+    stream: &str,
+    format: Format,
+    value: &T,
+) -> Result<String, BrokerError> {
+    let body = format.encode(value)?;
+    backend.add(
+        stream,
+        &[("format", format.tag().as_bytes()), ("envelope", &body)],
+    )
+}
+
+/// Decodes the body of an entry written by [`append`] in whatever format it is tagged with
+pub fn decode_entry<T: DeserializeOwned>(entry: &StreamEntry) -> Result<T, BrokerError> {
+    let tag = entry.text("format")?;
+    let format = Format::from_tag(tag)
+        .ok_or_else(|| BrokerError::entry(&entry.id, format!("unknown format {}", tag)))?;
+    format.decode(entry.field("envelope")?)
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::streams::MemoryBackend;
+    use serde_json::json;
+
+    fn meter_values() -> Envelope {
+        Envelope::new(
+            ProtocolVersion::Ocpp16,
+            RawOcppMessage {
+                message_type: 2,
+                call_id: CallId::new("19223201").unwrap(),
+                action: "MeterValues".to_string(),
+                payload: json!({
+                    "connectorId": 1,
+                    "transactionId": 42,
+                    "meterValue": [{
+                        "timestamp": "2019-08-24T14:15:22Z",
+                        "sampledValue": [
+                            {"value": "1234.5", "measurand": "Energy.Active.Import.Register"},
+                            {"value": "7.36", "measurand": "Power.Active.Import", "unit": "kW"}
+                        ]
+                    }]
+                }),
+            },
+        )
+    }
+
+    #[test]
+    fn given_each_format__when_round_tripping__then_unchanged() {
+        for format in Format::ALL {
+            let bytes = format.encode(&meter_values()).unwrap();
+
+            assert_eq!(format.decode::<Envelope>(&bytes).unwrap(), meter_values());
+            assert_eq!(Format::from_tag(format.tag()), Some(format));
+        }
+    }
+
+    #[test]
+    fn given_binary_formats__when_encoding__then_smaller_than_json() {
+        let json = Format::Json.encode(&meter_values()).unwrap().len();
+
+        assert!(Format::Cbor.encode(&meter_values()).unwrap().len() < json);
+        assert!(Format::MessagePack.encode(&meter_values()).unwrap().len() < json);
+    }
+
+    #[test]
+    fn given_entry__when_decoding__then_format_taken_from_header() {
+        let mut backend = MemoryBackend::new();
+        meter_values()
+            .append(&mut backend, "ocpp:CP-1:out", Format::Cbor)
+            .unwrap();
+
+        let entry = &backend.range("ocpp:CP-1:out").unwrap()[0];
+
+        assert_eq!(entry.text("format").unwrap(), "cbor");
+        assert_eq!(Envelope::from_entry(entry).unwrap(), meter_values());
+    }
+
+    #[test]
+    fn given_garbage__when_decoding__then_codec_error() {
+        assert!(matches!(
+            Format::MessagePack.decode::<Envelope>(b"{}"),
+            Err(BrokerError::Codec {
+                format: Format::MessagePack,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,86 @@
+///// Errors of the stream backends and the bridge /////
+use std::fmt;
+
+use ocpp::OcppError;
+use ocpp::transport::TransportError;
+
+use crate::envelope::Format;
+
+#[derive(Debug)]
+pub enum BrokerError {
+    Redis(redis::RedisError),
+    /// A stream or consumer group is not in the state a command expects, i.e. `NOGROUP`
+    Stream(String),
+    /// A stream entry is missing a field or a field does not parse
+    Entry {
+        id: String,
+        reason: String,
+    },
+    /// The event or command does not encode into, or decode from, an OCPP message
+    Ocpp(OcppError),
+    /// An envelope does not encode into, or decode from, its serialization format
+    Codec {
+        format: Format,
+        reason: String,
+    },
+    /// The connection a recording is replayed into failed
+    Transport(Box<TransportError>),
+    /// The MQTT broker refused a request or the connection to it broke
+    Mqtt(String),
+}
+
+impl BrokerError {
+    pub(crate) fn entry(id: &str, reason: impl Into<String>) -> Self {
+        BrokerError::Entry {
+            id: id.to_string(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for BrokerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BrokerError::Redis(err) => write!(f, "redis: {}", err),
+            BrokerError::Stream(err) => write!(f, "redis stream: {}", err),
+            BrokerError::Entry { id, reason } => {
+                write!(f, "invalid stream entry {}: {}", id, reason)
+            }
+            BrokerError::Ocpp(err) => write!(f, "{}", err),
+            BrokerError::Codec { format, reason } => {
+                write!(f, "invalid {} envelope: {}", format.tag(), reason)
+            }
+            BrokerError::Transport(err) => write!(f, "{}", err),
+            BrokerError::Mqtt(err) => write!(f, "mqtt: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for BrokerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BrokerError::Redis(err) => Some(err),
+            BrokerError::Ocpp(err) => Some(err),
+            BrokerError::Transport(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<redis::RedisError> for BrokerError {
+    fn from(err: redis::RedisError) -> Self {
+        BrokerError::Redis(err)
+    }
+}
+
+impl From<OcppError> for BrokerError {
+    fn from(err: OcppError) -> Self {
+        BrokerError::Ocpp(err)
+    }
+}
+
+impl From<TransportError> for BrokerError {
+    fn from(err: TransportError) -> Self {
+        BrokerError::Transport(Box::new(err))
+    }
+}
@@ -0,0 +1,488 @@
+///// Journal of every frame a charge point exchanged, and its replay /////
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use ocpp::call_error::{CallError, ErrorCode};
+use ocpp::datetime::{DateTime, rfc3339};
+use ocpp::transport::{FrameDirection, FrameRecorder, OcppConnection};
+use ocpp::{OcppFrame, ProtocolVersion, RawOcppMessage};
+
+use crate::bridge::Direction;
+use crate::envelope::{self, Envelope, Format};
+use crate::error::BrokerError;
+use crate::streams::StreamBackend;
+
+/// `ocpp:<cp_id>:journal`
+pub fn journal_stream(charge_point_id: &str) -> String {
+    format!("ocpp:{}:journal", charge_point_id)
+}
+
+/// A frame as it was sent or received, undecodable ones included.
+///
+/// A CALLERROR is kept with message type 4, the action of the call it answers as far as known
+/// and `errorCode`, `errorDescription` and `errorDetails` as payload.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Record {
+    #[serde(with = "rfc3339")]
+    pub timestamp: DateTime,
+    pub direction: Direction,
+    pub envelope: Envelope,
+}
+
+impl Record {
+    /// The CALLERROR of a record with message type 4
+    pub fn call_error(&self) -> Option<CallError> {
+        let envelope = &self.envelope;
+        if envelope.message_type != 4 {
+            return None;
+        }
+        let text = |field: &str| envelope.payload[field].as_str().unwrap_or_default();
+        Some(CallError {
+            call_id: envelope.call_id.clone(),
+            error_code: ErrorCode::parse(text("errorCode")).unwrap_or(ErrorCode::GenericError),
+            error_description: text("errorDescription").to_string(),
+            error_details: envelope.payload["errorDetails"].clone(),
+        })
+    }
+}
+
+/// Records of a time range, and the errors of the entries in it which did not decode
+#[derive(Debug, Default)]
+pub struct Records {
+    pub records: Vec<Record>,
+    pub undecodable: Vec<BrokerError>,
+}
+
+/// Appends the frames of one charge point to its journal stream.
+///
+/// Entry ids carry the timestamps of the records, so time ranges are read without going
+/// through the whole stream. A timestamp before the last record, i.e. after the clock was
+/// set back, takes the time of the last record for its id.
+pub struct Journal<B: StreamBackend> {
+    backend: B,
+    charge_point_id: String,
+    format: Format,
+    last_millis: u64,
+    errors: Vec<BrokerError>,
+}
+
+impl<B: StreamBackend> Journal<B> {
+    pub fn new(backend: B, charge_point_id: &str) -> Self {
+        Self {
+            backend,
+            charge_point_id: charge_point_id.to_string(),
+            format: Format::Json,
+            last_millis: 0,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Writes records in `format`, records already written keep theirs
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    /// Records `message` as exchanged at `timestamp`, returns the entry id
+    pub fn record(
+        &mut self,
+        timestamp: DateTime,
+        direction: Direction,
+        version: ProtocolVersion,
+        message: &RawOcppMessage,
+    ) -> Result<String, BrokerError> {
+        self.append(Record {
+            timestamp,
+            direction,
+            envelope: Envelope::new(version, message.clone()),
+        })
+    }
+
+    /// Records a frame of a connection, `action` as passed to a [`FrameRecorder`]
+    pub fn record_frame(
+        &mut self,
+        timestamp: DateTime,
+        direction: Direction,
+        version: ProtocolVersion,
+        frame: &OcppFrame,
+        action: &str,
+    ) -> Result<String, BrokerError> {
+        let message = match frame.clone().into_raw(|| action.to_string()) {
+            Ok(message) => message,
+            Err(error) => RawOcppMessage {
+                message_type: 4,
+                call_id: error.call_id,
+                action: action.to_string(),
+                payload: serde_json::json!({
+                    "errorCode": error.error_code.as_str(version),
+                    "errorDescription": error.error_description,
+                    "errorDetails": error.error_details,
+                }),
+            },
+        };
+        self.record(timestamp, direction, version, &message)
+    }
+
+    /// Records `message` as exchanged now
+    pub fn record_now(
+        &mut self,
+        direction: Direction,
+        version: ProtocolVersion,
+        message: &RawOcppMessage,
+    ) -> Result<String, BrokerError> {
+        self.record(chrono::Utc::now(), direction, version, message)
+    }
+
+    /// Errors of recording through [`FrameRecorder`], which cannot fail the connection
+    pub fn take_errors(&mut self) -> Vec<BrokerError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    /// Records with `from <= timestamp < to`, in the order they were recorded. Entries which
+    /// do not decode are passed over and reported.
+    pub fn records(&mut self, from: DateTime, to: DateTime) -> Result<Records, BrokerError> {
+        let entries = self.backend.range_between(
+            &journal_stream(&self.charge_point_id),
+            &millis(from).to_string(),
+            &millis(to).to_string(),
+        )?;
+        let mut records = Records::default();
+        for entry in entries {
+            match envelope::decode_entry::<Record>(&entry) {
+                Ok(record) if from <= record.timestamp && record.timestamp < to => {
+                    records.records.push(record)
+                }
+                Ok(_) => {}
+                Err(err) => records.undecodable.push(err),
+            }
+        }
+        Ok(records)
+    }
+
+    fn append(&mut self, record: Record) -> Result<String, BrokerError> {
+        let body = self.format.encode(&record)?;
+        let millis = millis(record.timestamp).max(self.last_millis);
+        let id = self.backend.add_at(
+            &journal_stream(&self.charge_point_id),
+            millis,
+            &[
+                ("format", self.format.tag().as_bytes()),
+                ("envelope", &body),
+            ],
+        )?;
+        self.last_millis = millis;
+        Ok(id)
+    }
+}
+
+/// Journals the frames of a connection as they go over the wire, see
+/// [`OcppConnection::set_recorder`]. The journal is shared with the caller, who reads the
+/// records and the errors of recording through it.
+pub struct JournalRecorder<B: StreamBackend>(pub Arc<Mutex<Journal<B>>>);
+
+impl<B: StreamBackend> FrameRecorder for JournalRecorder<B> {
+    fn record(
+        &mut self,
+        direction: FrameDirection,
+        version: ProtocolVersion,
+        frame: &OcppFrame,
+        action: &str,
+    ) {
+        let direction = match direction {
+            FrameDirection::Sent => Direction::Out,
+            FrameDirection::Received => Direction::In,
+        };
+        let mut journal = self
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Err(err) =
+            journal.record_frame(chrono::Utc::now(), direction, version, frame, action)
+        {
+            journal.errors.push(err);
+        }
+    }
+}
+
+/// Milliseconds since the epoch, the times of the entry ids
+fn millis(timestamp: DateTime) -> u64 {
+    timestamp.timestamp_millis().max(0) as u64
+}
+
+/// Pace of a replay
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Speed {
+    /// The gaps between the frames as recorded
+    Original,
+    /// The recorded gaps divided by the factor, built with [`Speed::accelerated`]
+    Accelerated(f64),
+    /// Without any gaps
+    Instant,
+}
+
+impl Speed {
+    /// `None` unless `factor` is finite and above zero
+    pub fn accelerated(factor: f64) -> Option<Self> {
+        (factor.is_finite() && factor > 0.0).then_some(Speed::Accelerated(factor))
+    }
+
+    fn gap(&self, previous: &DateTime, next: &DateTime) -> Duration {
+        let recorded = (*next - *previous).to_std().unwrap_or_default();
+        match self {
+            Speed::Original => recorded,
+            // a tiny factor stretches the gap beyond what a duration holds
+            Speed::Accelerated(factor) if factor.is_finite() && *factor > 0.0 => {
+                Duration::try_from_secs_f64(recorded.as_secs_f64() / factor)
+                    .unwrap_or(Duration::MAX)
+            }
+            Speed::Accelerated(_) | Speed::Instant => Duration::ZERO,
+        }
+    }
+}
+
+/// Where replayed frames go, i.e. a charge point runtime or a CSMS simulator
+pub trait ReplaySink {
+    fn feed(&mut self, record: &Record) -> Result<(), BrokerError>;
+}
+
+/// Collects the replayed records, i.e. for tests
+impl ReplaySink for Vec<Record> {
+    fn feed(&mut self, record: &Record) -> Result<(), BrokerError> {
+        self.push(record.clone());
+        Ok(())
+    }
+}
+
+/// Sends the frames to the other end of the connection, a CSMS simulator for the frames a
+/// charge point sent
+impl<S: Read + Write> ReplaySink for OcppConnection<S> {
+    fn feed(&mut self, record: &Record) -> Result<(), BrokerError> {
+        match record.call_error() {
+            Some(error) => Ok(self.send_call_error(&error)?),
+            None => Ok(self.send(&record.envelope.clone().into_raw())?),
+        }
+    }
+}
+
+/// Feeds `records` into `sink` at `speed`, `sleep` waits out the gaps in between.
+///
+/// Returns the number of fed records, stops at the first the sink fails on.
+pub fn replay<'a>(
+    records: impl IntoIterator<Item = &'a Record>,
+    speed: Speed,
+    sink: &mut impl ReplaySink,
+    mut sleep: impl FnMut(Duration),
+) -> Result<usize, BrokerError> {
+    let mut previous: Option<DateTime> = None;
+    let mut fed = 0;
+    for record in records {
+        if let Some(previous) = previous {
+            let gap = speed.gap(&previous, &record.timestamp);
+            if !gap.is_zero() {
+                sleep(gap);
+            }
+        }
+        previous = Some(record.timestamp);
+        sink.feed(record)?;
+        fed += 1;
+    }
+    Ok(fed)
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::streams::MemoryBackend;
+    use ocpp::CallId;
+    use ocpp::datetime::parse;
+    use serde_json::json;
+
+    fn heartbeat(call_id: &str) -> RawOcppMessage {
+        RawOcppMessage {
+            message_type: 2,
+            call_id: CallId::new(call_id).unwrap(),
+            action: "Heartbeat".to_string(),
+            payload: json!({}),
+        }
+    }
+
+    fn journal() -> Journal<MemoryBackend> {
+        let mut journal = Journal::new(MemoryBackend::new(), "CP-1").format(Format::Cbor);
+        for (seconds, direction, call_id) in [
+            ("00", Direction::Out, "1"),
+            ("10", Direction::In, "2"),
+            ("30", Direction::Out, "3"),
+            ("40", Direction::Out, "4"),
+        ] {
+            let timestamp = parse(&format!("2025-06-01T12:00:{}Z", seconds)).unwrap();
+            journal
+                .record(
+                    timestamp,
+                    direction,
+                    ProtocolVersion::Ocpp16,
+                    &heartbeat(call_id),
+                )
+                .unwrap();
+        }
+        journal
+    }
+
+    fn call_ids(records: &[Record]) -> Vec<&str> {
+        records
+            .iter()
+            .map(|record| record.envelope.call_id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn given_journal__when_reading_time_range__then_records_inside_in_order() {
+        let mut journal = journal();
+
+        let records = journal
+            .records(
+                parse("2025-06-01T12:00:10Z").unwrap(),
+                parse("2025-06-01T12:00:40Z").unwrap(),
+            )
+            .unwrap()
+            .records;
+
+        assert_eq!(call_ids(&records), vec!["2", "3"]);
+        assert_eq!(records[0].direction, Direction::In);
+        assert_eq!(records[0].envelope.clone().into_raw(), heartbeat("2"));
+    }
+
+    #[test]
+    fn given_records__when_replaying_accelerated__then_gaps_shortened() {
+        let records = journal()
+            .records(DateTime::MIN_UTC, DateTime::MAX_UTC)
+            .unwrap()
+            .records;
+        let outbound = records
+            .iter()
+            .filter(|record| record.direction == Direction::Out);
+        let mut sink = Vec::new();
+        let mut gaps = Vec::new();
+
+        let fed = replay(
+            outbound,
+            Speed::accelerated(10.0).unwrap(),
+            &mut sink,
+            |gap| gaps.push(gap),
+        )
+        .unwrap();
+
+        assert_eq!(fed, 3);
+        assert_eq!(call_ids(&sink), vec!["1", "3", "4"]);
+        assert_eq!(gaps, vec![Duration::from_secs(3), Duration::from_secs(1)]);
+    }
+
+    #[test]
+    fn given_records__when_replaying_at_original_or_instant_speed__then_gaps_kept_or_dropped() {
+        let records = journal()
+            .records(DateTime::MIN_UTC, DateTime::MAX_UTC)
+            .unwrap()
+            .records;
+        let mut original = Vec::new();
+        let mut instant = Vec::new();
+
+        replay(&records, Speed::Original, &mut Vec::new(), |gap| {
+            original.push(gap)
+        })
+        .unwrap();
+        replay(&records, Speed::Instant, &mut Vec::new(), |gap| {
+            instant.push(gap)
+        })
+        .unwrap();
+
+        assert_eq!(original, [10, 20, 10].map(Duration::from_secs).to_vec());
+        assert!(instant.is_empty());
+    }
+
+    #[test]
+    fn given_factor_not_positive_or_finite__when_accelerating__then_rejected() {
+        for factor in [0.0, -2.0, f64::NAN, f64::INFINITY] {
+            assert_eq!(Speed::accelerated(factor), None);
+        }
+        let previous = parse("2025-06-01T12:00:00Z").unwrap();
+        let next = parse("2025-06-01T13:00:00Z").unwrap();
+
+        assert_eq!(
+            Speed::accelerated(f64::MIN_POSITIVE)
+                .unwrap()
+                .gap(&previous, &next),
+            Duration::MAX
+        );
+    }
+
+    #[test]
+    fn given_undecodable_entry__when_reading__then_skipped_and_reported() {
+        let mut journal = journal();
+        let millis = millis(parse("2025-06-01T12:00:45Z").unwrap());
+        journal
+            .backend
+            .add_at(
+                &journal_stream("CP-1"),
+                millis,
+                &[("format", b"cbor"), ("envelope", b"garbage")],
+            )
+            .unwrap();
+
+        let records = journal
+            .records(DateTime::MIN_UTC, DateTime::MAX_UTC)
+            .unwrap();
+
+        assert_eq!(call_ids(&records.records), vec!["1", "2", "3", "4"]);
+        assert_eq!(records.undecodable.len(), 1);
+    }
+
+    #[test]
+    fn given_recorder_on_connection__when_frames_pass__then_journaled_call_error_included() {
+        let journal = Arc::new(Mutex::new(Journal::new(MemoryBackend::new(), "CP-1")));
+        let mut recorder = JournalRecorder(journal.clone());
+        let call_id = CallId::new("7").unwrap();
+
+        recorder.record(
+            FrameDirection::Received,
+            ProtocolVersion::Ocpp16,
+            &OcppFrame::Call {
+                call_id: call_id.clone(),
+                action: "Reset".to_string(),
+                payload: json!({"type": "Soft"}),
+            },
+            "Reset",
+        );
+        recorder.record(
+            FrameDirection::Sent,
+            ProtocolVersion::Ocpp16,
+            &OcppFrame::CallError {
+                call_id: call_id.clone(),
+                error_code: "NotSupported".to_string(),
+                error_description: "no reset".to_string(),
+                error_details: json!({}),
+            },
+            "",
+        );
+
+        let mut journal = journal.lock().unwrap();
+        let records = journal
+            .records(DateTime::MIN_UTC, DateTime::MAX_UTC)
+            .unwrap()
+            .records;
+        assert!(journal.take_errors().is_empty());
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].direction, Direction::In);
+        assert_eq!(records[0].envelope.action, "Reset");
+        assert_eq!(records[1].direction, Direction::Out);
+        let error = records[1].call_error().unwrap();
+        assert_eq!(error.call_id, call_id);
+        assert_eq!(error.error_code, ErrorCode::NotSupported);
+        assert_eq!(error.error_description, "no reset");
+    }
+}
@@ -0,0 +1,32 @@
+//! Message broker bridging the OCPP events of charge points onto Redis Streams.
+//!
+//! Every charge point gets an `ocpp:<cp_id>:in` and an `ocpp:<cp_id>:out` stream for the
+//! events it receives and sends, and reads the calls other services want it to send from
+//! `ocpp:<cp_id>:commands` with a consumer group, see [`bridge::Bridge`]. The streams live in
+//! Redis, [`redis_backend`], or in memory, [`streams::MemoryBackend`]. Each entry carries an
+//! [`envelope::Envelope`] in the JSON, CBOR or MessagePack [`envelope::Format`] of its stream.
+//! Every frame is also recorded with timestamp and direction in `ocpp:<cp_id>:journal`, from
+//! where [`journal::replay`] feeds a time range back into a runtime or a CSMS simulator.
+//!
+//! Local system components that do not speak OCPP control the charge point over D-Bus,
+//! [`dbus_service`] serves [`charge_point::ChargePointControl`] on the session or system bus.
+//! Home automation reaches it over MQTT instead, [`mqtt_bridge::MqttBridge`] mirrors the events
+//! to `wallbox/<cp_id>/...` topics and takes start, stop and limit commands from retained topics,
+//! on a real broker, [`mqtt_client`], or the embedded [`mqtt::MemoryBroker`].
+
+pub mod bridge;
+pub mod charge_point;
+pub mod dbus_service;
+pub mod envelope;
+pub mod error;
+pub mod journal;
+pub mod mqtt;
+pub mod mqtt_bridge;
+pub mod mqtt_client;
+pub mod redis_backend;
+pub mod streams;
+
+pub use bridge::{Bridge, Command, Direction};
+pub use envelope::{Envelope, Format};
+pub use error::BrokerError;
+pub use streams::{StreamBackend, StreamEntry};
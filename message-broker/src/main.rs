@@ -1,3 +1,193 @@
-fn main() {
-    println!("Hello, world!");
+//! Message broker command line tool.
+//!
+//! `message-broker publish <cp_id> <in|out> [ocpp1.6|ocpp2.0.1|ocpp2.1]` publishes the frames
+//! read line by line from stdin and records them in the journal, a CALLRESULT with the action of
+//! the CALL with its id read before, a CALLERROR in the journal only.
+//! `message-broker commands <cp_id>` prints the frames of the commands queued for a charge point
+//! and acknowledges them.
+//! `message-broker replay <cp_id> <from> <to> [original|instant|<factor>]` prints the recorded
+//! frames of a time range at their pace. `REDIS_URL` selects the server.
+//! `message-broker dbus [session|system] [connectors]` serves a local charge point on D-Bus.
+
+use std::io::BufRead;
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use message_broker::charge_point::LocalChargePoint;
+use message_broker::dbus_service::ChargePointService;
+use message_broker::journal::{Journal, Record, ReplaySink, Speed, replay};
+use message_broker::redis_backend::RedisBackend;
+use message_broker::{Bridge, BrokerError, Direction};
+use ocpp::pending_calls::PendingCalls;
+use ocpp::{OcppFrame, ProtocolVersion, convert, datetime};
+
+const USAGE: &str = "usage: message-broker publish <cp_id> <in|out> [ocpp1.6|ocpp2.0.1|ocpp2.1]\n       message-broker commands <cp_id>\n       message-broker replay <cp_id> <from> <to> [original|instant|<factor>]\n       message-broker dbus [session|system] [connectors]";
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    let url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string());
+    let result = match args.as_slice() {
+        ["publish", cp_id, direction, rest @ ..] => {
+            publish(&url, cp_id, direction, rest.first().copied())
+        }
+        ["commands", cp_id] => commands(&url, cp_id),
+        ["replay", cp_id, from, to, rest @ ..] => {
+            replay_frames(&url, cp_id, from, to, rest.first().copied())
+        }
+        ["dbus", rest @ ..] => dbus(rest),
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn publish(url: &str, cp_id: &str, direction: &str, version: Option<&str>) -> Result<(), String> {
+    let direction = match direction {
+        "in" => Direction::In,
+        "out" => Direction::Out,
+        _ => return Err(USAGE.to_string()),
+    };
+    let version = match version {
+        Some(version) => ProtocolVersion::from_subprotocol(version)
+            .ok_or_else(|| format!("unknown version {}", version))?,
+        None => ProtocolVersion::Ocpp16,
+    };
+    let backend = RedisBackend::open(url).map_err(|err| err.to_string())?;
+    let mut bridge = Bridge::new(backend, cp_id, version);
+    let mut journal = Journal::new(
+        RedisBackend::open(url).map_err(|err| err.to_string())?,
+        cp_id,
+    );
+    let mut calls = PendingCalls::new();
+    for line in std::io::stdin().lock().lines() {
+        let line = line.map_err(|err| err.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let frame = serde_json::from_str::<OcppFrame>(&line)
+            .map_err(|err| format!("invalid frame: {}", err))?;
+        let action = match &frame {
+            OcppFrame::Call { action, .. } => action.clone(),
+            OcppFrame::CallResult { call_id, .. } | OcppFrame::CallError { call_id, .. } => {
+                calls.received(call_id).map_err(|err| err.to_string())?
+            }
+        };
+        journal
+            .record_frame(chrono::Utc::now(), direction, version, &frame, &action)
+            .map_err(|err| err.to_string())?;
+        let Ok(raw) = frame.into_raw(|| action) else {
+            continue;
+        };
+        calls.sent(&raw);
+        let event = convert(version, raw).map_err(|err| err.to_string())?;
+        let id = bridge
+            .publish(direction, &event)
+            .map_err(|err| err.to_string())?;
+        println!("{}", id);
+    }
+    Ok(())
+}
+
+fn commands(url: &str, cp_id: &str) -> Result<(), String> {
+    let backend = RedisBackend::open(url)
+        .map_err(|err| err.to_string())?
+        .block(5_000);
+    let mut bridge = Bridge::new(backend, cp_id, ProtocolVersion::Ocpp16);
+    loop {
+        let polled = bridge.poll_commands(|command| {
+            let raw = command.to_raw();
+            println!(
+                "{}",
+                serde_json::json!([raw.message_type, raw.call_id, raw.action, raw.payload])
+            );
+            true
+        });
+        if let Err(err) = polled {
+            eprintln!("{}", err);
+        }
+    }
+}
+
+fn dbus(args: &[&str]) -> Result<(), String> {
+    let connection = match args.first() {
+        Some(&"system") => dbus::blocking::Connection::new_system(),
+        Some(&"session") | None => dbus::blocking::Connection::new_session(),
+        Some(_) => return Err(USAGE.to_string()),
+    }
+    .map_err(|err| err.to_string())?;
+    let connectors = match args.get(1) {
+        Some(connectors) => connectors
+            .parse()
+            .map_err(|err| format!("invalid connector count: {}", err))?,
+        None => 1,
+    };
+    let charge_point = Arc::new(Mutex::new(LocalChargePoint::new(connectors, 32.0)));
+    let service =
+        ChargePointService::new(connection, charge_point).map_err(|err| err.to_string())?;
+    loop {
+        service
+            .process(Duration::from_secs(1))
+            .map_err(|err| err.to_string())?;
+    }
+}
+
+/// Prints each frame with its direction
+struct Printer;
+
+impl ReplaySink for Printer {
+    fn feed(&mut self, record: &Record) -> Result<(), BrokerError> {
+        let envelope = &record.envelope;
+        println!(
+            "{} {}",
+            serde_json::json!(record.direction),
+            serde_json::json!([
+                envelope.message_type,
+                envelope.call_id,
+                envelope.action,
+                envelope.payload
+            ])
+        );
+        Ok(())
+    }
+}
+
+fn replay_frames(
+    url: &str,
+    cp_id: &str,
+    from: &str,
+    to: &str,
+    speed: Option<&str>,
+) -> Result<(), String> {
+    let timestamp = |value: &str| {
+        datetime::parse(value).map_err(|err| format!("invalid timestamp {}: {}", value, err))
+    };
+    let speed = match speed {
+        None | Some("original") => Speed::Original,
+        Some("instant") => Speed::Instant,
+        Some(factor) => factor
+            .parse()
+            .ok()
+            .and_then(Speed::accelerated)
+            .ok_or_else(|| format!("invalid speed {}: not a positive factor", factor))?,
+    };
+    let mut journal = Journal::new(
+        RedisBackend::open(url).map_err(|err| err.to_string())?,
+        cp_id,
+    );
+    let records = journal
+        .records(timestamp(from)?, timestamp(to)?)
+        .map_err(|err| err.to_string())?;
+    for err in &records.undecodable {
+        eprintln!("skipped: {}", err);
+    }
+    replay(&records.records, speed, &mut Printer, std::thread::sleep)
+        .map_err(|err| err.to_string())?;
+    Ok(())
 }
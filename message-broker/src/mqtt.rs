@@ -0,0 +1,320 @@
+///// MQTT transports: a client for a real broker and an embedded in-memory broker /////
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::error::BrokerError;
+
+/// Delivery guarantee of a publish or subscription
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum Qos {
+    AtMostOnce,
+    #[default]
+    AtLeastOnce,
+    ExactlyOnce,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MqttMessage {
+    pub topic: String,
+    pub payload: Vec<u8>,
+    /// Delivered from the retained store of the broker rather than live
+    pub retain: bool,
+}
+
+/// Published by the broker when a client goes away without disconnecting
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LastWill {
+    pub topic: String,
+    pub payload: Vec<u8>,
+    pub qos: Qos,
+    pub retain: bool,
+}
+
+/// The subset of an MQTT client the bridge needs
+pub trait MqttTransport {
+    /// Publishing an empty retained payload clears the retained message of `topic`
+    fn publish(
+        &mut self,
+        topic: &str,
+        payload: &[u8],
+        qos: Qos,
+        retain: bool,
+    ) -> Result<(), BrokerError>;
+
+    /// `filter` may contain the `+` and `#` wildcards
+    fn subscribe(&mut self, filter: &str, qos: Qos) -> Result<(), BrokerError>;
+
+    /// Next message of a subscription, `None` if none arrived within `timeout`
+    fn poll(&mut self, timeout: Duration) -> Result<Option<MqttMessage>, BrokerError>;
+}
+
+/// Whether `topic` matches the subscription `filter`, i.e. `wallbox/+/status/#`
+pub fn topic_matches(filter: &str, topic: &str) -> bool {
+    let mut levels = topic.split('/');
+    for pattern in filter.split('/') {
+        match (pattern, levels.next()) {
+            ("#", _) => return true,
+            ("+", Some(_)) => {}
+            (pattern, Some(level)) if pattern == level => {}
+            _ => return false,
+        }
+    }
+    levels.next().is_none()
+}
+
+/// Broker living in the process, with retained messages, wildcards and last wills but without
+/// persistence or QoS handshakes. For tests and boards without a broker.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryBroker {
+    state: Arc<Mutex<BrokerState>>,
+}
+
+#[derive(Debug, Default)]
+struct BrokerState {
+    retained: BTreeMap<String, Vec<u8>>,
+    clients: HashMap<String, ClientState>,
+    sessions: u64,
+}
+
+#[derive(Debug, Default)]
+struct ClientState {
+    /// Tells the session apart from the ones it took over or which take it over
+    generation: u64,
+    filters: Vec<String>,
+    inbox: VecDeque<MqttMessage>,
+    will: Option<LastWill>,
+}
+
+impl BrokerState {
+    /// The session of `client`, unless another connection took it over
+    fn session(&mut self, client: &MemoryClient) -> Option<&mut ClientState> {
+        self.clients
+            .get_mut(&client.client_id)
+            .filter(|session| session.generation == client.generation)
+    }
+
+    fn publish(&mut self, topic: &str, payload: &[u8], retain: bool) {
+        if retain {
+            if payload.is_empty() {
+                self.retained.remove(topic);
+            } else {
+                self.retained.insert(topic.to_string(), payload.to_vec());
+            }
+        }
+        for client in self.clients.values_mut() {
+            if client
+                .filters
+                .iter()
+                .any(|filter| topic_matches(filter, topic))
+            {
+                client.inbox.push_back(MqttMessage {
+                    topic: topic.to_string(),
+                    payload: payload.to_vec(),
+                    retain: false,
+                });
+            }
+        }
+    }
+}
+
+impl MemoryBroker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Connects `client_id`, an existing session of the same id is taken over. The client of
+    /// the old session is disconnected without its last will and cannot touch the new session.
+    pub fn connect(&self, client_id: &str, will: Option<LastWill>) -> MemoryClient {
+        let mut state = lock(&self.state);
+        state.sessions += 1;
+        let generation = state.sessions;
+        state.clients.insert(
+            client_id.to_string(),
+            ClientState {
+                generation,
+                will,
+                ..ClientState::default()
+            },
+        );
+        MemoryClient {
+            state: self.state.clone(),
+            client_id: client_id.to_string(),
+            generation,
+        }
+    }
+
+    pub fn retained(&self, topic: &str) -> Option<Vec<u8>> {
+        lock(&self.state).retained.get(topic).cloned()
+    }
+}
+
+/// Connection to a [`MemoryBroker`], dropping it without [`MemoryClient::disconnect`] is an
+/// unexpected disconnect and publishes the last will
+#[derive(Debug)]
+pub struct MemoryClient {
+    state: Arc<Mutex<BrokerState>>,
+    client_id: String,
+    generation: u64,
+}
+
+impl MemoryClient {
+    /// Disconnects cleanly, the last will is discarded
+    pub fn disconnect(self) {
+        if let Some(client) = lock(&self.state).session(&self) {
+            client.will = None;
+        }
+    }
+}
+
+impl Drop for MemoryClient {
+    fn drop(&mut self) {
+        let mut state = lock(&self.state);
+        if state.session(self).is_none() {
+            return;
+        }
+        if let Some(will) = state
+            .clients
+            .remove(&self.client_id)
+            .and_then(|client| client.will)
+        {
+            state.publish(&will.topic, &will.payload, will.retain);
+        }
+    }
+}
+
+impl MqttTransport for MemoryClient {
+    fn publish(
+        &mut self,
+        topic: &str,
+        payload: &[u8],
+        _qos: Qos,
+        retain: bool,
+    ) -> Result<(), BrokerError> {
+        lock(&self.state).publish(topic, payload, retain);
+        Ok(())
+    }
+
+    fn subscribe(&mut self, filter: &str, _qos: Qos) -> Result<(), BrokerError> {
+        let mut state = lock(&self.state);
+        let retained = state
+            .retained
+            .iter()
+            .filter(|(topic, _)| topic_matches(filter, topic))
+            .map(|(topic, payload)| MqttMessage {
+                topic: topic.clone(),
+                payload: payload.clone(),
+                retain: true,
+            })
+            .collect::<Vec<_>>();
+        let client = state
+            .session(self)
+            .ok_or_else(|| BrokerError::Mqtt(format!("{} is not connected", self.client_id)))?;
+        client.filters.push(filter.to_string());
+        client.inbox.extend(retained);
+        Ok(())
+    }
+
+    fn poll(&mut self, _timeout: Duration) -> Result<Option<MqttMessage>, BrokerError> {
+        Ok(lock(&self.state)
+            .session(self)
+            .and_then(|client| client.inbox.pop_front()))
+    }
+}
+
+fn lock(state: &Mutex<BrokerState>) -> std::sync::MutexGuard<'_, BrokerState> {
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_filters__when_matching__then_wildcards_cover_levels() {
+        assert!(topic_matches("wallbox/+/status/+", "wallbox/CP-1/status/1"));
+        assert!(topic_matches(
+            "wallbox/CP-1/#",
+            "wallbox/CP-1/meter/Voltage"
+        ));
+        assert!(topic_matches("wallbox/CP-1/#", "wallbox/CP-1"));
+        assert!(!topic_matches("wallbox/+/status", "wallbox/CP-1/status/1"));
+        assert!(!topic_matches("wallbox/CP-2/#", "wallbox/CP-1/status/1"));
+    }
+
+    #[test]
+    fn given_retained_message__when_subscribing_later__then_delivered_until_cleared() {
+        let broker = MemoryBroker::new();
+        let mut publisher = broker.connect("hmi", None);
+        publisher
+            .publish("wallbox/CP-1/command/stop", b"{}", Qos::AtLeastOnce, true)
+            .unwrap();
+
+        let mut subscriber = broker.connect("bridge", None);
+        subscriber
+            .subscribe("wallbox/CP-1/command/+", Qos::AtLeastOnce)
+            .unwrap();
+        let message = subscriber.poll(Duration::ZERO).unwrap().unwrap();
+        publisher
+            .publish("wallbox/CP-1/command/stop", b"", Qos::AtLeastOnce, true)
+            .unwrap();
+
+        assert!(message.retain);
+        assert_eq!(message.payload, b"{}");
+        assert_eq!(broker.retained("wallbox/CP-1/command/stop"), None);
+    }
+
+    #[test]
+    fn given_last_will__when_dropped_or_disconnected__then_published_only_on_drop() {
+        let broker = MemoryBroker::new();
+        let will = LastWill {
+            topic: "wallbox/CP-1/availability".to_string(),
+            payload: b"offline".to_vec(),
+            qos: Qos::AtLeastOnce,
+            retain: true,
+        };
+
+        broker.connect("clean", Some(will.clone())).disconnect();
+        assert_eq!(broker.retained("wallbox/CP-1/availability"), None);
+
+        drop(broker.connect("crashed", Some(will)));
+        assert_eq!(
+            broker.retained("wallbox/CP-1/availability"),
+            Some(b"offline".to_vec())
+        );
+    }
+
+    #[test]
+    fn given_session_taken_over__when_stale_client_dropped__then_new_session_kept_without_will() {
+        let broker = MemoryBroker::new();
+        let will = LastWill {
+            topic: "wallbox/CP-1/availability".to_string(),
+            payload: b"offline".to_vec(),
+            qos: Qos::AtLeastOnce,
+            retain: true,
+        };
+        let mut stale = broker.connect("bridge", Some(will.clone()));
+        let mut current = broker.connect("bridge", Some(will));
+        current
+            .subscribe("wallbox/CP-1/#", Qos::AtLeastOnce)
+            .unwrap();
+
+        assert!(stale.subscribe("wallbox/#", Qos::AtLeastOnce).is_err());
+        drop(stale);
+        current
+            .publish(
+                "wallbox/CP-1/status/1",
+                b"Charging",
+                Qos::AtLeastOnce,
+                false,
+            )
+            .unwrap();
+
+        assert_eq!(broker.retained("wallbox/CP-1/availability"), None);
+        let message = current.poll(Duration::ZERO).unwrap().unwrap();
+        assert_eq!(message.topic, "wallbox/CP-1/status/1");
+    }
+}
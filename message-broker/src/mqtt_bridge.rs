@@ -0,0 +1,522 @@
+///// Mirrors OCPP events to MQTT topics and takes commands from retained command topics /////
+use std::time::Duration;
+
+use ocpp::OcppEvent;
+use ocpp::ocpp_datagram::OCPPDatagram;
+use ocpp::typed_ocpp_message::MessageType;
+use serde_json::{Value, json};
+
+use crate::charge_point::{ChargePointControl, Notification};
+use crate::envelope::Format;
+use crate::error::BrokerError;
+use crate::mqtt::{LastWill, MqttTransport, Qos};
+
+/// Measurand of sampled values without one
+const DEFAULT_MEASURAND: &str = "Energy.Active.Import.Register";
+
+/// `wallbox/<cp_id>/<suffix>`
+pub fn topic(charge_point_id: &str, suffix: &str) -> String {
+    format!("wallbox/{}/{}", charge_point_id, suffix)
+}
+
+/// `offline`, retained on `wallbox/<cp_id>/availability`, to pass to the transport on connect
+pub fn last_will(charge_point_id: &str, qos: Qos) -> LastWill {
+    LastWill {
+        topic: topic(charge_point_id, "availability"),
+        payload: b"offline".to_vec(),
+        qos,
+        retain: true,
+    }
+}
+
+/// Topics below `wallbox/<cp_id>`:
+/// - `availability`: `online`, or `offline` by the last will, retained
+/// - `status/<connector>`: the connector status, retained, `status/<evse>/<connector>` for 2.0.1
+/// - `meter/<connector>/<measurand>`: the last sampled value, retained, with `/<phase>` appended
+///   for values of one phase
+/// - `event/<action>`: the payload of every message, as JSON
+/// - `command/start|stop|limit`: JSON commands, usually retained, cleared once handled
+/// - `command/<name>/result`: the outcome of a command
+pub struct MqttBridge<T: MqttTransport> {
+    transport: T,
+    charge_point_id: String,
+    qos: Qos,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CommandPayload {
+    connector: u32,
+    id_tag: Option<String>,
+    amps: Option<f64>,
+}
+
+impl<T: MqttTransport> MqttBridge<T> {
+    pub fn new(transport: T, charge_point_id: &str, qos: Qos) -> Self {
+        Self {
+            transport,
+            charge_point_id: charge_point_id.to_string(),
+            qos,
+        }
+    }
+
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Announces the charge point online and subscribes to its command topics
+    pub fn start(&mut self) -> Result<(), BrokerError> {
+        self.publish("availability", b"online", true)?;
+        self.transport
+            .subscribe(&topic(&self.charge_point_id, "command/+"), self.qos)
+    }
+
+    /// Publishes the topics derived from `event`, returns how many
+    pub fn mirror(&mut self, event: &OcppEvent) -> Result<usize, BrokerError> {
+        let datagram = OCPPDatagram::try_from(event.clone()).map_err(|err| BrokerError::Codec {
+            format: Format::Json,
+            reason: err.to_string(),
+        })?;
+        let mut messages = vec![(
+            format!("event/{}", datagram.action),
+            datagram.payload.to_string(),
+            false,
+        )];
+        if datagram.msg_type == MessageType::Call {
+            messages.extend(derived_topics(&datagram.action, &datagram.payload));
+        }
+        for (suffix, payload, retain) in &messages {
+            self.publish(suffix, payload.as_bytes(), *retain)?;
+        }
+        Ok(messages.len())
+    }
+
+    /// Publishes the status and meter topics of local changes, i.e. of commands
+    pub fn publish_notifications(
+        &mut self,
+        notifications: &[Notification],
+    ) -> Result<(), BrokerError> {
+        for notification in notifications {
+            match notification {
+                Notification::StatusChanged { connector, status } => {
+                    let status = serde_json::to_value(status).unwrap_or(Value::Null);
+                    let status = status.as_str().unwrap_or_default();
+                    self.publish(&format!("status/{}", connector), status.as_bytes(), true)?;
+                }
+                Notification::MeterValue {
+                    connector,
+                    energy_wh,
+                    power_w,
+                } => {
+                    let energy = energy_wh.to_string();
+                    let power = power_w.to_string();
+                    self.publish(
+                        &format!("meter/{}/Energy.Active.Import.Register", connector),
+                        energy.as_bytes(),
+                        true,
+                    )?;
+                    self.publish(
+                        &format!("meter/{}/Power.Active.Import", connector),
+                        power.as_bytes(),
+                        true,
+                    )?;
+                }
+                Notification::TransactionStarted { .. }
+                | Notification::TransactionStopped { .. } => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the commands arriving within `timeout` to `control`, returns how many were handled.
+    ///
+    /// Every command gets a result, retained commands are cleared so they do not run again
+    /// after a reconnect.
+    pub fn handle_commands(
+        &mut self,
+        control: &mut impl ChargePointControl,
+        timeout: Duration,
+    ) -> Result<usize, BrokerError> {
+        let prefix = topic(&self.charge_point_id, "command/");
+        let mut handled = 0;
+        let mut timeout = timeout;
+        while let Some(message) = self.transport.poll(timeout)? {
+            timeout = Duration::ZERO;
+            let Some(name) = message.topic.strip_prefix(&prefix) else {
+                continue;
+            };
+            // clearing a retained command echoes back empty
+            if message.payload.is_empty() {
+                continue;
+            }
+            let name = name.to_string();
+            let result = execute(control, &name, &message.payload);
+            if message.retain {
+                self.publish(&format!("command/{}", name), b"", true)?;
+            }
+            let result = match result {
+                Ok(result) => result,
+                Err(err) => json!({"error": err}),
+            };
+            self.publish(
+                &format!("command/{}/result", name),
+                result.to_string().as_bytes(),
+                false,
+            )?;
+            handled += 1;
+        }
+        Ok(handled)
+    }
+
+    fn publish(&mut self, suffix: &str, payload: &[u8], retain: bool) -> Result<(), BrokerError> {
+        self.transport.publish(
+            &topic(&self.charge_point_id, suffix),
+            payload,
+            self.qos,
+            retain,
+        )
+    }
+}
+
+fn execute(
+    control: &mut impl ChargePointControl,
+    name: &str,
+    payload: &[u8],
+) -> Result<Value, String> {
+    let command = serde_json::from_slice::<CommandPayload>(payload)
+        .map_err(|err| format!("invalid {} command: {}", name, err))?;
+    let connector = command.connector;
+    match name {
+        "start" => {
+            let id_tag = command.id_tag.ok_or("missing idTag")?;
+            control
+                .start_charging(connector, &id_tag)
+                .map(|transaction_id| json!({"transactionId": transaction_id}))
+        }
+        "stop" => control
+            .stop_charging(connector)
+            .map(|transaction_id| json!({"transactionId": transaction_id})),
+        "limit" => {
+            let amps = command.amps.ok_or("missing amps")?;
+            control
+                .set_current_limit(connector, amps)
+                .map(|()| json!({"amps": amps}))
+        }
+        _ => return Err(format!("unknown command {}", name)),
+    }
+    .map_err(|err| err.to_string())
+}
+
+/// `(suffix, payload, retain)` of the status and meter topics of a CALL
+fn derived_topics(action: &str, payload: &Value) -> Vec<(String, String, bool)> {
+    match action {
+        "StatusNotification" => {
+            let connector = &payload["connectorId"];
+            if connector.is_null() {
+                return Vec::new();
+            }
+            // 1.6 calls it status, 2.0.1 connectorStatus and numbers connectors per EVSE
+            let suffix = match (payload["status"].as_str(), &payload["evseId"]) {
+                (Some(status), _) => Some((format!("status/{}", connector), status)),
+                (None, evse) if !evse.is_null() => payload["connectorStatus"]
+                    .as_str()
+                    .map(|status| (format!("status/{}/{}", evse, connector), status)),
+                _ => None,
+            };
+            suffix
+                .map(|(suffix, status)| (suffix, status.to_string(), true))
+                .into_iter()
+                .collect()
+        }
+        "MeterValues" => {
+            let connector = &payload["connectorId"];
+            // 2.0.1 reports per EVSE
+            let connector = if connector.is_null() {
+                &payload["evseId"]
+            } else {
+                connector
+            };
+            if connector.is_null() {
+                return Vec::new();
+            }
+            let sampled_values = payload["meterValue"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .flat_map(|meter_value| {
+                    meter_value["sampledValue"].as_array().into_iter().flatten()
+                });
+            sampled_values
+                .filter_map(|sampled_value| {
+                    // signed data is for the CSMS, the topic carries the plain reading
+                    if sampled_value["format"] == "SignedData" {
+                        return None;
+                    }
+                    // 1.6 sends decimal strings, 2.0.1 numbers
+                    let value = match &sampled_value["value"] {
+                        Value::String(value) => value.clone(),
+                        Value::Number(value) => value.to_string(),
+                        _ => return None,
+                    };
+                    let measurand = sampled_value["measurand"]
+                        .as_str()
+                        .unwrap_or(DEFAULT_MEASURAND);
+                    let suffix = match sampled_value["phase"].as_str() {
+                        Some(phase) => format!("meter/{}/{}/{}", connector, measurand, phase),
+                        None => format!("meter/{}/{}", connector, measurand),
+                    };
+                    Some((suffix, value, true))
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::charge_point::LocalChargePoint;
+    use crate::mqtt::{MemoryBroker, MemoryClient};
+    use ocpp::{CallId, ProtocolVersion, RawOcppMessage, convert};
+
+    fn bridge(broker: &MemoryBroker) -> MqttBridge<MemoryClient> {
+        let client = broker.connect("bridge", Some(last_will("CP-1", Qos::AtLeastOnce)));
+        let mut bridge = MqttBridge::new(client, "CP-1", Qos::AtLeastOnce);
+        bridge.start().unwrap();
+        bridge
+    }
+
+    fn call(action: &str, payload: Value) -> OcppEvent {
+        let raw = RawOcppMessage {
+            message_type: 2,
+            call_id: CallId::new("19223201").unwrap(),
+            action: action.to_string(),
+            payload,
+        };
+        convert(ProtocolVersion::Ocpp16, raw).unwrap()
+    }
+
+    #[test]
+    fn given_status_and_meter_values__when_mirroring__then_retained_per_connector_and_measurand() {
+        let broker = MemoryBroker::new();
+        let mut bridge = bridge(&broker);
+
+        bridge
+            .mirror(&call(
+                "StatusNotification",
+                json!({"connectorId": 1, "errorCode": "NoError", "status": "Charging"}),
+            ))
+            .unwrap();
+        bridge
+            .mirror(&call(
+                "MeterValues",
+                json!({"connectorId": 1, "meterValue": [{
+                    "timestamp": "2025-06-01T12:00:00Z",
+                    "sampledValue": [
+                        {"value": "1234.5"},
+                        {"value": "OCMF|{}|{}", "format": "SignedData"},
+                        {"value": "230.1", "measurand": "Voltage", "phase": "L1"}
+                    ]
+                }]}),
+            ))
+            .unwrap();
+
+        assert_eq!(
+            broker.retained("wallbox/CP-1/availability"),
+            Some(b"online".to_vec())
+        );
+        assert_eq!(
+            broker.retained("wallbox/CP-1/status/1"),
+            Some(b"Charging".to_vec())
+        );
+        assert_eq!(
+            broker.retained("wallbox/CP-1/meter/1/Energy.Active.Import.Register"),
+            Some(b"1234.5".to_vec())
+        );
+        assert_eq!(
+            broker.retained("wallbox/CP-1/meter/1/Voltage/L1"),
+            Some(b"230.1".to_vec())
+        );
+    }
+
+    #[test]
+    fn given_v201_status_notifications__when_mirroring__then_retained_per_evse_and_connector() {
+        let broker = MemoryBroker::new();
+        let mut bridge = bridge(&broker);
+
+        for (evse_id, status) in [(1, "Occupied"), (2, "Available")] {
+            let raw = RawOcppMessage {
+                message_type: 2,
+                call_id: CallId::new("19223201").unwrap(),
+                action: "StatusNotification".to_string(),
+                payload: json!({
+                    "timestamp": "2025-06-01T12:00:00Z",
+                    "connectorStatus": status,
+                    "evseId": evse_id,
+                    "connectorId": 1
+                }),
+            };
+            bridge
+                .mirror(&convert(ProtocolVersion::Ocpp201, raw).unwrap())
+                .unwrap();
+        }
+
+        assert_eq!(
+            broker.retained("wallbox/CP-1/status/1/1"),
+            Some(b"Occupied".to_vec())
+        );
+        assert_eq!(
+            broker.retained("wallbox/CP-1/status/2/1"),
+            Some(b"Available".to_vec())
+        );
+        assert_eq!(broker.retained("wallbox/CP-1/status/1"), None);
+    }
+
+    #[test]
+    fn given_three_phase_meter_values__when_mirroring__then_retained_per_phase() {
+        let broker = MemoryBroker::new();
+        let mut bridge = bridge(&broker);
+        // the periodic MeterValues of benches/formats.rs
+        let mut sampled_value = vec![json!({
+            "value": "51234.5",
+            "context": "Sample.Periodic",
+            "measurand": "Energy.Active.Import.Register",
+            "unit": "Wh"
+        })];
+        for (measurand, unit, value) in [
+            ("Current.Import", "A", "15.9"),
+            ("Voltage", "V", "231.2"),
+            ("Power.Active.Import", "W", "3676.1"),
+        ] {
+            for phase in ["L1", "L2", "L3"] {
+                sampled_value.push(json!({
+                    "value": value,
+                    "context": "Sample.Periodic",
+                    "measurand": measurand,
+                    "phase": phase,
+                    "unit": unit
+                }));
+            }
+        }
+
+        let published = bridge
+            .mirror(&call(
+                "MeterValues",
+                json!({
+                    "connectorId": 1,
+                    "transactionId": 1742,
+                    "meterValue": [{"timestamp": "2025-06-01T12:00:10Z", "sampledValue": sampled_value}]
+                }),
+            ))
+            .unwrap();
+
+        assert_eq!(published, 11);
+        assert_eq!(
+            broker.retained("wallbox/CP-1/meter/1/Energy.Active.Import.Register"),
+            Some(b"51234.5".to_vec())
+        );
+        for phase in ["L1", "L2", "L3"] {
+            for (measurand, value) in [
+                ("Current.Import", "15.9"),
+                ("Voltage", "231.2"),
+                ("Power.Active.Import", "3676.1"),
+            ] {
+                let topic = format!("wallbox/CP-1/meter/1/{}/{}", measurand, phase);
+                assert_eq!(broker.retained(&topic), Some(value.as_bytes().to_vec()));
+            }
+        }
+        assert_eq!(broker.retained("wallbox/CP-1/meter/1/Voltage"), None);
+    }
+
+    #[test]
+    fn given_meter_values_of_two_connectors__when_publishing_notifications__then_retained_per_connector()
+     {
+        let broker = MemoryBroker::new();
+        let mut bridge = bridge(&broker);
+        let mut charge_point = LocalChargePoint::new(2, 32.0);
+        charge_point.meter_value(1, 1200.0, 3600.0).unwrap();
+        charge_point.meter_value(2, 800.0, 0.0).unwrap();
+
+        bridge
+            .publish_notifications(&charge_point.drain_notifications())
+            .unwrap();
+
+        assert_eq!(
+            broker.retained("wallbox/CP-1/meter/1/Energy.Active.Import.Register"),
+            Some(b"1200".to_vec())
+        );
+        assert_eq!(
+            broker.retained("wallbox/CP-1/meter/1/Power.Active.Import"),
+            Some(b"3600".to_vec())
+        );
+        assert_eq!(
+            broker.retained("wallbox/CP-1/meter/2/Energy.Active.Import.Register"),
+            Some(b"800".to_vec())
+        );
+        assert_eq!(
+            broker.retained("wallbox/CP-1/meter/2/Power.Active.Import"),
+            Some(b"0".to_vec())
+        );
+    }
+
+    #[test]
+    fn given_retained_commands__when_handling__then_applied_results_published_and_cleared() {
+        let broker = MemoryBroker::new();
+        let mut hmi = broker.connect("hmi", None);
+        hmi.subscribe("wallbox/CP-1/command/+/result", Qos::AtLeastOnce)
+            .unwrap();
+        hmi.publish(
+            "wallbox/CP-1/command/start",
+            br#"{"connector": 1, "idTag": "04A2B3C4"}"#,
+            Qos::AtLeastOnce,
+            true,
+        )
+        .unwrap();
+        hmi.publish(
+            "wallbox/CP-1/command/limit",
+            br#"{"connector": 1, "amps": 64.0}"#,
+            Qos::AtLeastOnce,
+            true,
+        )
+        .unwrap();
+        let mut charge_point = LocalChargePoint::new(1, 32.0);
+        let mut bridge = bridge(&broker);
+
+        let handled = bridge
+            .handle_commands(&mut charge_point, Duration::ZERO)
+            .unwrap();
+        bridge
+            .publish_notifications(&charge_point.drain_notifications())
+            .unwrap();
+
+        assert_eq!(handled, 2);
+        assert_eq!(broker.retained("wallbox/CP-1/command/start"), None);
+        assert_eq!(broker.retained("wallbox/CP-1/command/limit"), None);
+        assert_eq!(
+            broker.retained("wallbox/CP-1/status/1"),
+            Some(b"Charging".to_vec())
+        );
+        let results = std::iter::from_fn(|| hmi.poll(Duration::ZERO).unwrap())
+            .map(|message| (message.topic, String::from_utf8(message.payload).unwrap()))
+            .collect::<std::collections::BTreeMap<_, _>>();
+        assert_eq!(
+            results["wallbox/CP-1/command/start/result"],
+            r#"{"transactionId":1}"#
+        );
+        assert!(results["wallbox/CP-1/command/limit/result"].contains("outside of 0 to 32 A"));
+    }
+
+    #[test]
+    fn given_bridge__when_connection_lost__then_last_will_marks_offline() {
+        let broker = MemoryBroker::new();
+
+        drop(bridge(&broker));
+
+        assert_eq!(
+            broker.retained("wallbox/CP-1/availability"),
+            Some(b"offline".to_vec())
+        );
+    }
+}
@@ -0,0 +1,156 @@
+///// MQTT transport on a connection to a real broker, i.e. mosquitto /////
+use std::time::{Duration, Instant};
+
+use rumqttc::{Client, Connection, Event, MqttOptions, Packet, QoS};
+
+use crate::error::BrokerError;
+use crate::mqtt::{LastWill, MqttMessage, MqttTransport, Qos};
+
+/// Requests queued for the connection until the next [`poll`](MqttTransport::poll)
+pub const REQUEST_CAPACITY: usize = 64;
+
+#[derive(Debug, Clone)]
+pub struct MqttConfig {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    pub keep_alive: Duration,
+    pub last_will: Option<LastWill>,
+}
+
+impl MqttConfig {
+    pub fn new(host: &str, port: u16, client_id: &str) -> Self {
+        Self {
+            host: host.to_string(),
+            port,
+            client_id: client_id.to_string(),
+            keep_alive: Duration::from_secs(30),
+            last_will: None,
+        }
+    }
+
+    pub fn last_will(mut self, will: LastWill) -> Self {
+        self.last_will = Some(will);
+        self
+    }
+}
+
+/// The connection makes progress only while it is polled, publishes are sent on the next poll.
+/// Up to [`REQUEST_CAPACITY`] requests wait for it, further ones fail instead of blocking
+/// the caller that would have to poll.
+pub struct MqttClient {
+    client: Client,
+    connection: Connection,
+}
+
+impl MqttClient {
+    pub fn connect(config: &MqttConfig) -> Self {
+        let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+        options.set_keep_alive(config.keep_alive);
+        if let Some(will) = &config.last_will {
+            options.set_last_will(rumqttc::LastWill::new(
+                &will.topic,
+                will.payload.clone(),
+                qos(will.qos),
+                will.retain,
+            ));
+        }
+        let (client, connection) = Client::new(options, REQUEST_CAPACITY);
+        Self { client, connection }
+    }
+
+    /// Disconnects cleanly, the broker discards the last will
+    pub fn disconnect(mut self) -> Result<(), BrokerError> {
+        self.client.try_disconnect().map_err(mqtt_error)?;
+        // drive the connection until the DISCONNECT is out
+        while let Ok(Ok(event)) = self.connection.recv_timeout(Duration::from_secs(1)) {
+            if let Event::Outgoing(rumqttc::Outgoing::Disconnect) = event {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl MqttTransport for MqttClient {
+    fn publish(
+        &mut self,
+        topic: &str,
+        payload: &[u8],
+        qos: Qos,
+        retain: bool,
+    ) -> Result<(), BrokerError> {
+        self.client
+            .try_publish(topic, self::qos(qos), retain, payload.to_vec())
+            .map_err(mqtt_error)
+    }
+
+    fn subscribe(&mut self, filter: &str, qos: Qos) -> Result<(), BrokerError> {
+        self.client
+            .try_subscribe(filter, self::qos(qos))
+            .map_err(mqtt_error)
+    }
+
+    fn poll(&mut self, timeout: Duration) -> Result<Option<MqttMessage>, BrokerError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.connection.recv_timeout(remaining) {
+                Ok(Ok(Event::Incoming(Packet::Publish(publish)))) => {
+                    return Ok(Some(MqttMessage {
+                        topic: publish.topic,
+                        payload: publish.payload.to_vec(),
+                        retain: publish.retain,
+                    }));
+                }
+                Ok(Ok(_)) if !remaining.is_zero() => {}
+                Ok(Ok(_)) | Err(rumqttc::RecvTimeoutError::Timeout) => return Ok(None),
+                Ok(Err(err)) => return Err(mqtt_error(err)),
+                Err(rumqttc::RecvTimeoutError::Disconnected) => {
+                    return Err(BrokerError::Mqtt("connection closed".to_string()));
+                }
+            }
+        }
+    }
+}
+
+fn qos(qos: Qos) -> QoS {
+    match qos {
+        Qos::AtMostOnce => QoS::AtMostOnce,
+        Qos::AtLeastOnce => QoS::AtLeastOnce,
+        Qos::ExactlyOnce => QoS::ExactlyOnce,
+    }
+}
+
+fn mqtt_error(err: impl std::fmt::Display) -> BrokerError {
+    BrokerError::Mqtt(err.to_string())
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore = "needs an MQTT broker, MQTT_HOST defaults to 127.0.0.1:1883"]
+    fn given_broker__when_publishing_retained__then_received_by_later_subscriber() {
+        let host = std::env::var("MQTT_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
+        let topic = format!("message-broker/test/{}", std::process::id());
+        let mut publisher = MqttClient::connect(&MqttConfig::new(&host, 1883, "test-publisher"));
+        publisher
+            .publish(&topic, b"online", Qos::AtLeastOnce, true)
+            .unwrap();
+        publisher.poll(Duration::from_millis(500)).unwrap();
+
+        let mut subscriber = MqttClient::connect(&MqttConfig::new(&host, 1883, "test-subscriber"));
+        subscriber.subscribe(&topic, Qos::AtLeastOnce).unwrap();
+        let message = subscriber.poll(Duration::from_secs(5)).unwrap().unwrap();
+
+        assert_eq!(message.payload, b"online");
+        assert!(message.retain);
+        publisher
+            .publish(&topic, b"", Qos::AtLeastOnce, true)
+            .unwrap();
+        publisher.disconnect().unwrap();
+    }
+}
@@ -0,0 +1,158 @@
+///// Stream backend on a Redis connection /////
+use redis::streams::{StreamId, StreamRangeReply, StreamReadOptions, StreamReadReply};
+use redis::{Commands, Connection, RedisResult};
+
+use crate::error::BrokerError;
+use crate::streams::{StreamBackend, StreamEntry};
+
+pub struct RedisBackend {
+    connection: Connection,
+    /// Milliseconds `XREADGROUP` waits for new entries, `None` returns immediately
+    block: Option<usize>,
+}
+
+impl RedisBackend {
+    pub fn new(connection: Connection) -> Self {
+        Self {
+            connection,
+            block: None,
+        }
+    }
+
+    /// Connects to a Redis URL, i.e. `"redis://127.0.0.1:6379"`
+    pub fn open(url: &str) -> Result<Self, BrokerError> {
+        Ok(Self::new(redis::Client::open(url)?.get_connection()?))
+    }
+
+    /// Lets reads of new entries wait up to `ms` milliseconds
+    pub fn block(mut self, ms: usize) -> Self {
+        self.block = Some(ms);
+        self
+    }
+}
+
+impl StreamBackend for RedisBackend {
+    fn add(&mut self, stream: &str, fields: &[(&str, &[u8])]) -> Result<String, BrokerError> {
+        Ok(self.connection.xadd(stream, "*", fields)?)
+    }
+
+    /// Needs Redis 7 for the sequence assigned by the server
+    fn add_at(
+        &mut self,
+        stream: &str,
+        millis: u64,
+        fields: &[(&str, &[u8])],
+    ) -> Result<String, BrokerError> {
+        Ok(self
+            .connection
+            .xadd(stream, format!("{}-*", millis), fields)?)
+    }
+
+    fn range_between(
+        &mut self,
+        stream: &str,
+        start: &str,
+        end: &str,
+    ) -> Result<Vec<StreamEntry>, BrokerError> {
+        let reply: StreamRangeReply = self.connection.xrange(stream, start, end)?;
+        reply.ids.into_iter().map(entry).collect()
+    }
+
+    fn create_group(&mut self, stream: &str, group: &str) -> Result<(), BrokerError> {
+        let result: RedisResult<String> =
+            self.connection.xgroup_create_mkstream(stream, group, "0");
+        match result {
+            Err(err) if err.code() != Some("BUSYGROUP") => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn read_group(
+        &mut self,
+        stream: &str,
+        group: &str,
+        consumer: &str,
+        pending: bool,
+        count: usize,
+    ) -> Result<Vec<StreamEntry>, BrokerError> {
+        let mut options = StreamReadOptions::default()
+            .group(group, consumer)
+            .count(count);
+        let position = if pending {
+            "0"
+        } else {
+            if let Some(ms) = self.block {
+                options = options.block(ms);
+            }
+            ">"
+        };
+        // a blocking read that timed out replies nil
+        let reply: Option<StreamReadReply> =
+            self.connection
+                .xread_options(&[stream], &[position], &options)?;
+        reply
+            .into_iter()
+            .flat_map(|reply| reply.keys)
+            .flat_map(|key| key.ids)
+            .map(entry)
+            .collect()
+    }
+
+    fn ack(&mut self, stream: &str, group: &str, id: &str) -> Result<(), BrokerError> {
+        let _: usize = self.connection.xack(stream, group, &[id])?;
+        Ok(())
+    }
+}
+
+fn entry(stream_id: StreamId) -> Result<StreamEntry, BrokerError> {
+    let fields = stream_id
+        .map
+        .iter()
+        .map(|(name, value)| {
+            redis::from_redis_value::<Vec<u8>>(value)
+                .map(|value| (name.clone(), value))
+                .map_err(|err| BrokerError::entry(&stream_id.id, err.to_string()))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(StreamEntry {
+        id: stream_id.id,
+        fields,
+    })
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore = "needs a redis-server, REDIS_URL defaults to redis://127.0.0.1:6379"]
+    fn given_redis__when_reading_group__then_pending_until_acked() {
+        let url =
+            std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string());
+        let mut backend = RedisBackend::open(&url).unwrap();
+        let stream = format!("message-broker:test:{}", std::process::id());
+        backend.create_group(&stream, "bridge").unwrap();
+        backend.create_group(&stream, "bridge").unwrap();
+        let id = backend.add(&stream, &[("action", &b"Reset"[..])]).unwrap();
+
+        let new = backend
+            .read_group(&stream, "bridge", "a", false, 10)
+            .unwrap();
+        let pending = backend
+            .read_group(&stream, "bridge", "a", true, 10)
+            .unwrap();
+        backend.ack(&stream, "bridge", &id).unwrap();
+
+        assert_eq!(new[0].text("action").unwrap(), "Reset");
+        assert_eq!(pending[0].id, id);
+        assert!(
+            backend
+                .read_group(&stream, "bridge", "a", true, 10)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(backend.range(&stream).unwrap().len(), 1);
+        let _: RedisResult<usize> = backend.connection.del(&stream);
+    }
+}
@@ -0,0 +1,370 @@
+///// Stream backends: Redis Streams and an in-memory stand-in /////
+use std::collections::{BTreeMap, HashMap};
+
+use crate::error::BrokerError;
+
+/// One entry of a stream, field values are binary like in Redis
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct StreamEntry {
+    /// Assigned by the backend on `add`, i.e. `"1526919030474-0"`
+    pub id: String,
+    pub fields: BTreeMap<String, Vec<u8>>,
+}
+
+impl StreamEntry {
+    pub fn field(&self, name: &str) -> Result<&[u8], BrokerError> {
+        self.fields
+            .get(name)
+            .map(Vec::as_slice)
+            .ok_or_else(|| BrokerError::entry(&self.id, format!("missing field {}", name)))
+    }
+
+    /// A field holding UTF-8 text
+    pub fn text(&self, name: &str) -> Result<&str, BrokerError> {
+        std::str::from_utf8(self.field(name)?)
+            .map_err(|err| BrokerError::entry(&self.id, format!("{}: {}", name, err)))
+    }
+}
+
+/// The subset of the Redis Streams commands the bridge needs
+pub trait StreamBackend {
+    /// `XADD stream * field value ...`, returns the id of the new entry
+    fn add(&mut self, stream: &str, fields: &[(&str, &[u8])]) -> Result<String, BrokerError>;
+
+    /// `XADD stream <millis>-* field value ...`, the id tells the time of the entry instead of
+    /// the time it was added. Ids rise, `millis` before the last entry is an error.
+    fn add_at(
+        &mut self,
+        stream: &str,
+        millis: u64,
+        fields: &[(&str, &[u8])],
+    ) -> Result<String, BrokerError>;
+
+    /// `XRANGE stream start end`, bounds are ids or `-` and `+`, a millisecond alone spans all
+    /// entries of it
+    fn range_between(
+        &mut self,
+        stream: &str,
+        start: &str,
+        end: &str,
+    ) -> Result<Vec<StreamEntry>, BrokerError>;
+
+    /// `XRANGE stream - +`
+    fn range(&mut self, stream: &str) -> Result<Vec<StreamEntry>, BrokerError> {
+        self.range_between(stream, "-", "+")
+    }
+
+    /// `XGROUP CREATE stream group 0 MKSTREAM`, an existing group is left untouched
+    fn create_group(&mut self, stream: &str, group: &str) -> Result<(), BrokerError>;
+
+    /// `XREADGROUP GROUP group consumer COUNT count STREAMS stream >`.
+    ///
+    /// With `pending` the entries already delivered to `consumer` but not yet acknowledged
+    /// are read again instead of new ones, i.e. after a crash.
+    fn read_group(
+        &mut self,
+        stream: &str,
+        group: &str,
+        consumer: &str,
+        pending: bool,
+        count: usize,
+    ) -> Result<Vec<StreamEntry>, BrokerError>;
+
+    /// `XACK stream group id`
+    fn ack(&mut self, stream: &str, group: &str, id: &str) -> Result<(), BrokerError>;
+}
+
+/// Keeps streams in memory with the consumer group semantics of Redis, for tests and
+/// for running without a redis-server
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    streams: HashMap<String, MemoryStream>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryStream {
+    entries: Vec<StreamEntry>,
+    groups: HashMap<String, ConsumerGroup>,
+}
+
+#[derive(Debug, Default)]
+struct ConsumerGroup {
+    /// Number of entries delivered to the group so far
+    delivered: usize,
+    /// Entry id to the consumer it was delivered to
+    pending: BTreeMap<String, String>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Entries delivered to `group` but not acknowledged yet, `XPENDING`
+    pub fn pending(&self, stream: &str, group: &str) -> usize {
+        self.streams
+            .get(stream)
+            .and_then(|stream| stream.groups.get(group))
+            .map_or(0, |group| group.pending.len())
+    }
+
+    fn group(&mut self, stream: &str, group: &str) -> Result<&mut MemoryStream, BrokerError> {
+        match self.streams.get_mut(stream) {
+            Some(entries) if entries.groups.contains_key(group) => Ok(entries),
+            _ => Err(BrokerError::Stream(format!(
+                "NOGROUP no consumer group {} for stream {}",
+                group, stream
+            ))),
+        }
+    }
+}
+
+impl StreamBackend for MemoryBackend {
+    /// Counts milliseconds from 1 instead of taking the time
+    fn add(&mut self, stream: &str, fields: &[(&str, &[u8])]) -> Result<String, BrokerError> {
+        let entries = &mut self.streams.entry(stream.to_string()).or_default().entries;
+        let millis = entries.last().map_or(1, |entry| parse_id(&entry.id).0 + 1);
+        Ok(push(entries, (millis, 0), fields))
+    }
+
+    fn add_at(
+        &mut self,
+        stream: &str,
+        millis: u64,
+        fields: &[(&str, &[u8])],
+    ) -> Result<String, BrokerError> {
+        let entries = &mut self.streams.entry(stream.to_string()).or_default().entries;
+        let id = match entries.last().map(|entry| parse_id(&entry.id)) {
+            Some((last, sequence)) if last == millis => (millis, sequence + 1),
+            Some((last, _)) if last > millis => {
+                return Err(BrokerError::Stream(format!(
+                    "ERR The ID specified in XADD is equal or smaller than the target stream top item, {}",
+                    stream
+                )));
+            }
+            _ => (millis, 0),
+        };
+        Ok(push(entries, id, fields))
+    }
+
+    fn range_between(
+        &mut self,
+        stream: &str,
+        start: &str,
+        end: &str,
+    ) -> Result<Vec<StreamEntry>, BrokerError> {
+        let start = bound(start, 0)?;
+        let end = bound(end, u64::MAX)?;
+        Ok(self
+            .streams
+            .get(stream)
+            .map(|stream| {
+                stream
+                    .entries
+                    .iter()
+                    .filter(|entry| (start..=end).contains(&parse_id(&entry.id)))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    fn create_group(&mut self, stream: &str, group: &str) -> Result<(), BrokerError> {
+        self.streams
+            .entry(stream.to_string())
+            .or_default()
+            .groups
+            .entry(group.to_string())
+            .or_default();
+        Ok(())
+    }
+
+    fn read_group(
+        &mut self,
+        stream: &str,
+        group: &str,
+        consumer: &str,
+        pending: bool,
+        count: usize,
+    ) -> Result<Vec<StreamEntry>, BrokerError> {
+        let stream = self.group(stream, group)?;
+        let entries = &stream.entries;
+        let group = stream
+            .groups
+            .get_mut(group)
+            .expect("checked by MemoryBackend::group");
+        if pending {
+            return Ok(entries
+                .iter()
+                .filter(|entry| {
+                    group
+                        .pending
+                        .get(&entry.id)
+                        .is_some_and(|owner| owner == consumer)
+                })
+                .take(count)
+                .cloned()
+                .collect());
+        }
+        let new = entries
+            .iter()
+            .skip(group.delivered)
+            .take(count)
+            .cloned()
+            .collect::<Vec<_>>();
+        group.delivered += new.len();
+        for entry in &new {
+            group.pending.insert(entry.id.clone(), consumer.to_string());
+        }
+        Ok(new)
+    }
+
+    fn ack(&mut self, stream: &str, group: &str, id: &str) -> Result<(), BrokerError> {
+        let stream = self.group(stream, group)?;
+        if let Some(group) = stream.groups.get_mut(group) {
+            group.pending.remove(id);
+        }
+        Ok(())
+    }
+}
+
+fn push(
+    entries: &mut Vec<StreamEntry>,
+    (millis, sequence): (u64, u64),
+    fields: &[(&str, &[u8])],
+) -> String {
+    let id = format!("{}-{}", millis, sequence);
+    entries.push(StreamEntry {
+        id: id.clone(),
+        fields: fields
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_vec()))
+            .collect(),
+    });
+    id
+}
+
+/// `(millis, sequence)` of an id the backend assigned
+fn parse_id(id: &str) -> (u64, u64) {
+    let (millis, sequence) = id.split_once('-').unwrap_or((id, "0"));
+    (
+        millis.parse().unwrap_or_default(),
+        sequence.parse().unwrap_or_default(),
+    )
+}
+
+/// An `XRANGE` bound, a millisecond alone takes `sequence` to span it
+fn bound(id: &str, sequence: u64) -> Result<(u64, u64), BrokerError> {
+    let invalid = || BrokerError::Stream(format!("ERR Invalid stream ID specified {}", id));
+    match id {
+        "-" => Ok((0, 0)),
+        "+" => Ok((u64::MAX, u64::MAX)),
+        _ => match id.split_once('-') {
+            Some((millis, sequence)) => Ok((
+                millis.parse().map_err(|_| invalid())?,
+                sequence.parse().map_err(|_| invalid())?,
+            )),
+            None => Ok((id.parse().map_err(|_| invalid())?, sequence)),
+        },
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_group__when_reading__then_each_entry_delivered_once() {
+        let mut backend = MemoryBackend::new();
+        backend.create_group("commands", "bridge").unwrap();
+        backend
+            .add("commands", &[("action", &b"Reset"[..])])
+            .unwrap();
+        backend
+            .add("commands", &[("action", &b"UnlockConnector"[..])])
+            .unwrap();
+
+        let first = backend
+            .read_group("commands", "bridge", "a", false, 1)
+            .unwrap();
+        let second = backend
+            .read_group("commands", "bridge", "b", false, 10)
+            .unwrap();
+
+        assert_eq!(first[0].text("action").unwrap(), "Reset");
+        assert_eq!(second[0].text("action").unwrap(), "UnlockConnector");
+        assert!(
+            backend
+                .read_group("commands", "bridge", "a", false, 10)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn given_unacknowledged_entry__when_reading_pending__then_redelivered_until_acked() {
+        let mut backend = MemoryBackend::new();
+        backend.create_group("commands", "bridge").unwrap();
+        let id = backend
+            .add("commands", &[("action", &b"Reset"[..])])
+            .unwrap();
+        backend
+            .read_group("commands", "bridge", "a", false, 10)
+            .unwrap();
+
+        let pending = backend
+            .read_group("commands", "bridge", "a", true, 10)
+            .unwrap();
+        assert_eq!(pending[0].id, id);
+        assert!(
+            backend
+                .read_group("commands", "bridge", "b", true, 10)
+                .unwrap()
+                .is_empty()
+        );
+
+        backend.ack("commands", "bridge", &id).unwrap();
+        assert_eq!(backend.pending("commands", "bridge"), 0);
+        assert!(
+            backend
+                .read_group("commands", "bridge", "a", true, 10)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn given_entries_at_times__when_reading_range__then_ids_of_the_times_inside() {
+        let mut backend = MemoryBackend::new();
+        for millis in [1000, 2000, 2000, 3000] {
+            backend
+                .add_at("journal", millis, &[("action", &b"Heartbeat"[..])])
+                .unwrap();
+        }
+
+        let ids: Vec<String> = backend
+            .range_between("journal", "2000", "2999")
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.id)
+            .collect();
+
+        assert_eq!(ids, vec!["2000-0", "2000-1"]);
+        assert!(backend.add_at("journal", 2999, &[]).is_err());
+        assert_eq!(backend.range("journal").unwrap().len(), 4);
+    }
+
+    #[test]
+    fn given_no_group__when_reading__then_error() {
+        let mut backend = MemoryBackend::new();
+        backend
+            .add("commands", &[("action", &b"Reset"[..])])
+            .unwrap();
+
+        assert!(matches!(
+            backend.read_group("commands", "bridge", "a", false, 10),
+            Err(BrokerError::Stream(_))
+        ));
+    }
+}
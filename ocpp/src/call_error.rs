@@ -0,0 +1,134 @@
+///// CALLERROR frames /////
+use crate::{CallId, ProtocolVersion};
+
+/// Error codes of a CALLERROR frame
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ErrorCode {
+    NotImplemented,
+    NotSupported,
+    InternalError,
+    ProtocolError,
+    SecurityError,
+    /// Payload is not a valid message, spelled `FormatViolation` since 2.0.1
+    FormationViolation,
+    PropertyConstraintViolation,
+    /// Spelled `OccurrenceConstraintViolation` since 2.0.1
+    OccurenceConstraintViolation,
+    TypeConstraintViolation,
+    GenericError,
+    /// 2.0.1 only, answered with `NotSupported` on 1.6
+    MessageTypeNotSupported,
+    /// 2.0.1 only, answered with `ProtocolError` on 1.6
+    RpcFrameworkError,
+}
+
+impl ErrorCode {
+    /// Spelling used on the wire by `version`
+    pub fn as_str(self, version: ProtocolVersion) -> &'static str {
+        let v16 = version == ProtocolVersion::Ocpp16;
+        match self {
+            ErrorCode::NotImplemented => "NotImplemented",
+            ErrorCode::NotSupported => "NotSupported",
+            ErrorCode::InternalError => "InternalError",
+            ErrorCode::ProtocolError => "ProtocolError",
+            ErrorCode::SecurityError => "SecurityError",
+            ErrorCode::FormationViolation if v16 => "FormationViolation",
+            ErrorCode::FormationViolation => "FormatViolation",
+            ErrorCode::PropertyConstraintViolation => "PropertyConstraintViolation",
+            ErrorCode::OccurenceConstraintViolation if v16 => "OccurenceConstraintViolation",
+            ErrorCode::OccurenceConstraintViolation => "OccurrenceConstraintViolation",
+            ErrorCode::TypeConstraintViolation => "TypeConstraintViolation",
+            ErrorCode::GenericError => "GenericError",
+            ErrorCode::MessageTypeNotSupported if v16 => "NotSupported",
+            ErrorCode::MessageTypeNotSupported => "MessageTypeNotSupported",
+            ErrorCode::RpcFrameworkError if v16 => "ProtocolError",
+            ErrorCode::RpcFrameworkError => "RpcFrameworkError",
+        }
+    }
+
+    /// Error code of a received CALLERROR in the spelling of any version
+    pub fn parse(code: &str) -> Option<Self> {
+        let error_code = match code {
+            "NotImplemented" => ErrorCode::NotImplemented,
+            "NotSupported" => ErrorCode::NotSupported,
+            "InternalError" => ErrorCode::InternalError,
+            "ProtocolError" => ErrorCode::ProtocolError,
+            "SecurityError" => ErrorCode::SecurityError,
+            "FormationViolation" | "FormatViolation" => ErrorCode::FormationViolation,
+            "PropertyConstraintViolation" => ErrorCode::PropertyConstraintViolation,
+            "OccurenceConstraintViolation" | "OccurrenceConstraintViolation" => {
+                ErrorCode::OccurenceConstraintViolation
+            }
+            "TypeConstraintViolation" => ErrorCode::TypeConstraintViolation,
+            "GenericError" => ErrorCode::GenericError,
+            "MessageTypeNotSupported" => ErrorCode::MessageTypeNotSupported,
+            "RpcFrameworkError" => ErrorCode::RpcFrameworkError,
+            _ => return None,
+        };
+        Some(error_code)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CallError {
+    pub call_id: CallId,
+    pub error_code: ErrorCode,
+    pub error_description: String,
+    /// Free form details, an empty object if there are none
+    pub error_details: serde_json::Value,
+}
+
+impl CallError {
+    /// `[4, "<call id>", "<error code>", "<description>", {<details>}]`
+    pub fn to_frame(&self, version: ProtocolVersion) -> serde_json::Value {
+        serde_json::json!([
+            4,
+            self.call_id.0,
+            self.error_code.as_str(version),
+            self.error_description,
+            self.error_details,
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_code_spelling_depends_on_version() {
+        assert_eq!(
+            ErrorCode::OccurenceConstraintViolation.as_str(ProtocolVersion::Ocpp16),
+            "OccurenceConstraintViolation"
+        );
+        assert_eq!(
+            ErrorCode::OccurenceConstraintViolation.as_str(ProtocolVersion::Ocpp201),
+            "OccurrenceConstraintViolation"
+        );
+        assert_eq!(
+            ErrorCode::FormationViolation.as_str(ProtocolVersion::Ocpp21),
+            "FormatViolation"
+        );
+    }
+
+    #[test]
+    fn call_error_frame() {
+        let error = CallError {
+            call_id: CallId("42".to_string()),
+            error_code: ErrorCode::TypeConstraintViolation,
+            error_description: "expected integer".to_string(),
+            error_details: serde_json::json!({"path": "/interval"}),
+        };
+
+        assert_eq!(
+            error.to_frame(ProtocolVersion::Ocpp16),
+            serde_json::json!([
+                4,
+                "42",
+                "TypeConstraintViolation",
+                "expected integer",
+                {"path": "/interval"}
+            ])
+        );
+    }
+}
@@ -0,0 +1,154 @@
+///// Length limited string types of the OCPP specifications /////
+use std::fmt;
+use std::ops::Deref;
+
+use serde::de::{self, Deserialize, Deserializer};
+
+/// A string exceeding the maximum length of its type
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct LengthError {
+    pub max: usize,
+    pub len: usize,
+}
+
+impl fmt::Display for LengthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "string of {} characters exceeds the maximum of {}",
+            self.len, self.max
+        )
+    }
+}
+
+impl std::error::Error for LengthError {}
+
+/// Rejects `value` if it has more than `max` characters.
+///
+/// Reported through serde as an invalid length so it ends up as a constraint violation.
+pub(crate) fn check_length<E: de::Error>(value: &str, max: usize) -> Result<(), E> {
+    let len = value.chars().count();
+    if len > max {
+        return Err(E::invalid_length(
+            len,
+            &format!("at most {} characters", max).as_str(),
+        ));
+    }
+    Ok(())
+}
+
+/// Case insensitive string of at most `N` characters, `CiString<N>Type` in OCPP 1.6
+#[derive(Debug, Clone, Default, serde::Serialize)]
+#[serde(transparent)]
+pub struct CiString<const N: usize>(String);
+
+impl<const N: usize> CiString<N> {
+    pub fn new(value: impl Into<String>) -> Result<Self, LengthError> {
+        let value = value.into();
+        let len = value.chars().count();
+        if len > N {
+            return Err(LengthError { max: N, len });
+        }
+        Ok(Self(value))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl<const N: usize> Deref for CiString<N> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<const N: usize> fmt::Display for CiString<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl<const N: usize> PartialEq for CiString<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.eq_ignore_ascii_case(&other.0)
+    }
+}
+
+impl<const N: usize> Eq for CiString<N> {}
+
+impl<const N: usize> PartialEq<str> for CiString<N> {
+    fn eq(&self, other: &str) -> bool {
+        self.0.eq_ignore_ascii_case(other)
+    }
+}
+
+impl<const N: usize> PartialEq<&str> for CiString<N> {
+    fn eq(&self, other: &&str) -> bool {
+        self.0.eq_ignore_ascii_case(other)
+    }
+}
+
+impl<const N: usize> TryFrom<&str> for CiString<N> {
+    type Error = LengthError;
+
+    fn try_from(value: &str) -> Result<Self, LengthError> {
+        Self::new(value)
+    }
+}
+
+impl<const N: usize> TryFrom<String> for CiString<N> {
+    type Error = LengthError;
+
+    fn try_from(value: String) -> Result<Self, LengthError> {
+        Self::new(value)
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for CiString<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        check_length(&value, N)?;
+        Ok(Self(value))
+    }
+}
+
+pub type CiString20Type = CiString<20>;
+pub type CiString25Type = CiString<25>;
+pub type CiString50Type = CiString<50>;
+pub type CiString255Type = CiString<255>;
+pub type CiString500Type = CiString<500>;
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_too_long_string__when_deserializing__then_invalid_length() {
+        let result = serde_json::from_str::<CiString20Type>(r#""0123456789ABCDEFGHIJK""#);
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "invalid length 21, expected at most 20 characters"
+        );
+    }
+
+    #[test]
+    fn given_different_case__when_comparing__then_equal() {
+        let tag = CiString20Type::new("DEADBEEF").unwrap();
+
+        assert_eq!(tag, CiString20Type::new("deadbeef").unwrap());
+        assert_eq!(serde_json::to_string(&tag).unwrap(), r#""DEADBEEF""#);
+        assert_eq!(
+            CiString20Type::new("é".repeat(21)),
+            Err(LengthError { max: 20, len: 21 })
+        );
+    }
+}
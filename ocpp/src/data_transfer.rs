@@ -0,0 +1,305 @@
+///// DataTransfer vendor extensions /////
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::ci_string::{CiString, LengthError};
+use crate::ocpp_event::{DataTransferRequest, DataTransferResponse};
+use crate::types::DataTransferStatus;
+
+/// Returned by a handler to answer the DataTransfer with `Rejected`
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Rejected;
+
+#[derive(Debug)]
+pub enum DataTransferError {
+    /// The typed payload could not be encoded into or decoded from `data`
+    Data(serde_json::Error),
+    /// The other side answered with anything but `Accepted`
+    NotAccepted(DataTransferStatus),
+    /// `VENDOR_ID` or `MESSAGE_ID` is longer than allowed
+    Identifier(LengthError),
+}
+
+impl fmt::Display for DataTransferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataTransferError::Data(err) => write!(f, "invalid DataTransfer data: {}", err),
+            DataTransferError::NotAccepted(status) => {
+                write!(f, "DataTransfer answered with {:?}", status)
+            }
+            DataTransferError::Identifier(err) => write!(f, "invalid DataTransfer id: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for DataTransferError {}
+
+impl From<serde_json::Error> for DataTransferError {
+    fn from(err: serde_json::Error) -> Self {
+        DataTransferError::Data(err)
+    }
+}
+
+impl From<LengthError> for DataTransferError {
+    fn from(err: LengthError) -> Self {
+        DataTransferError::Identifier(err)
+    }
+}
+
+/// Outgoing vendor message, serialized as JSON into the `data` field
+pub trait VendorCall: Serialize {
+    const VENDOR_ID: &'static str;
+    const MESSAGE_ID: Option<&'static str>;
+    /// Type of the `data` field in an `Accepted` response, use `()` if there is none
+    type Response: DeserializeOwned;
+
+    fn to_request(&self) -> Result<DataTransferRequest, DataTransferError> {
+        Ok(DataTransferRequest {
+            vendor_id: CiString::new(Self::VENDOR_ID)?,
+            message_id: Self::MESSAGE_ID.map(CiString::new).transpose()?,
+            data: encode_data(self)?,
+        })
+    }
+
+    fn decode_response(
+        response: DataTransferResponse,
+    ) -> Result<Self::Response, DataTransferError> {
+        match response.status {
+            DataTransferStatus::Accepted => Ok(decode_data(response.data.as_deref())?),
+            status => Err(DataTransferError::NotAccepted(status)),
+        }
+    }
+}
+
+type ErasedHandler = Box<dyn FnMut(Option<&str>) -> DataTransferResponse + Send>;
+
+/// Dispatches incoming DataTransfer requests to typed handlers registered per
+/// `(vendorId, messageId)` pair.
+///
+/// Unregistered vendors are answered with `UnknownVendorId`, unregistered message ids of
+/// a known vendor with `UnknownMessageId` and undecodable data with `Rejected`.
+#[derive(Default)]
+pub struct DataTransferRegistry {
+    vendors: HashMap<String, HashMap<Option<String>, ErasedHandler>>,
+}
+
+impl DataTransferRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `handler` for the given pair, replacing any previous one.
+    ///
+    /// A missing `data` field is decoded from JSON `null`, so `()` or `Option<T>` can be
+    /// used as request type for messages without data.
+    pub fn register<Req, Resp, F>(
+        &mut self,
+        vendor_id: &str,
+        message_id: Option<&str>,
+        mut handler: F,
+    ) where
+        Req: DeserializeOwned,
+        Resp: Serialize,
+        F: FnMut(Req) -> Result<Resp, Rejected> + Send + 'static,
+    {
+        let erased = move |data: Option<&str>| {
+            let response = decode_data::<Req>(data)
+                .ok()
+                .and_then(|request| handler(request).ok())
+                .map(|response| encode_data(&response));
+            match response {
+                Some(Ok(data)) => DataTransferResponse {
+                    status: DataTransferStatus::Accepted,
+                    data,
+                },
+                _ => DataTransferResponse {
+                    status: DataTransferStatus::Rejected,
+                    data: None,
+                },
+            }
+        };
+        self.vendors
+            .entry(normalize(vendor_id))
+            .or_default()
+            .insert(message_id.map(normalize), Box::new(erased));
+    }
+
+    pub fn is_registered(&self, vendor_id: &str, message_id: Option<&str>) -> bool {
+        self.vendors
+            .get(&normalize(vendor_id))
+            .is_some_and(|messages| messages.contains_key(&message_id.map(normalize)))
+    }
+
+    /// Answers an incoming DataTransfer request
+    pub fn handle(&mut self, request: &DataTransferRequest) -> DataTransferResponse {
+        let Some(messages) = self.vendors.get_mut(&normalize(&request.vendor_id)) else {
+            return DataTransferResponse {
+                status: DataTransferStatus::UnknownVendorId,
+                data: None,
+            };
+        };
+        match messages.get_mut(&request.message_id.as_deref().map(normalize)) {
+            Some(handler) => handler(request.data.as_deref()),
+            None => DataTransferResponse {
+                status: DataTransferStatus::UnknownMessageId,
+                data: None,
+            },
+        }
+    }
+}
+
+/// vendorId and messageId are CiStrings, i.e. compared case-insensitive
+fn normalize(id: &str) -> String {
+    id.to_ascii_lowercase()
+}
+
+fn encode_data<T: Serialize + ?Sized>(value: &T) -> Result<Option<String>, serde_json::Error> {
+    match serde_json::to_value(value)? {
+        serde_json::Value::Null => Ok(None),
+        value => Ok(Some(value.to_string())),
+    }
+}
+
+fn decode_data<T: DeserializeOwned>(data: Option<&str>) -> Result<T, serde_json::Error> {
+    serde_json::from_str(data.unwrap_or("null"))
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct SetLedColor {
+        color: String,
+    }
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct LedState {
+        on: bool,
+    }
+
+    impl VendorCall for SetLedColor {
+        const VENDOR_ID: &'static str = "com.example";
+        const MESSAGE_ID: Option<&'static str> = Some("SetLedColor");
+        type Response = LedState;
+    }
+
+    fn request(
+        vendor_id: &str,
+        message_id: Option<&str>,
+        data: Option<&str>,
+    ) -> DataTransferRequest {
+        DataTransferRequest {
+            vendor_id: CiString::new(vendor_id).unwrap(),
+            message_id: message_id.map(|id| CiString::new(id).unwrap()),
+            data: data.map(str::to_string),
+        }
+    }
+
+    fn registry() -> DataTransferRegistry {
+        let mut registry = DataTransferRegistry::new();
+        registry.register("com.example", Some("SetLedColor"), |req: SetLedColor| {
+            if req.color == "red" {
+                Ok(LedState { on: true })
+            } else {
+                Err(Rejected)
+            }
+        });
+        registry.register("com.example", None, |_: ()| Ok(()));
+        registry
+    }
+
+    #[test]
+    fn given_registered_pair__when_handling__then_accepted_with_data() {
+        let response = registry().handle(&request(
+            "COM.EXAMPLE",
+            Some("setledcolor"),
+            Some(r#"{"color":"red"}"#),
+        ));
+
+        assert_eq!(
+            response,
+            DataTransferResponse {
+                status: DataTransferStatus::Accepted,
+                data: Some(r#"{"on":true}"#.to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn given_handler_without_data__when_handling__then_accepted_without_data() {
+        let response = registry().handle(&request("com.example", None, None));
+
+        assert_eq!(response.status, DataTransferStatus::Accepted);
+        assert_eq!(response.data, None);
+    }
+
+    #[test]
+    fn given_handler_refusing__when_handling__then_rejected() {
+        let response = registry().handle(&request(
+            "com.example",
+            Some("SetLedColor"),
+            Some(r#"{"color":"blue"}"#),
+        ));
+
+        assert_eq!(response.status, DataTransferStatus::Rejected);
+    }
+
+    #[test]
+    fn given_malformed_data__when_handling__then_rejected() {
+        let response = registry().handle(&request("com.example", Some("SetLedColor"), Some("{")));
+
+        assert_eq!(response.status, DataTransferStatus::Rejected);
+    }
+
+    #[test]
+    fn given_unknown_pair__when_handling__then_unknown_ids() {
+        let mut registry = registry();
+
+        let unknown_message = registry.handle(&request("com.example", Some("Reboot"), None));
+        let unknown_vendor = registry.handle(&request("org.other", Some("SetLedColor"), None));
+
+        assert_eq!(unknown_message.status, DataTransferStatus::UnknownMessageId);
+        assert_eq!(unknown_vendor.status, DataTransferStatus::UnknownVendorId);
+    }
+
+    #[test]
+    fn given_vendor_call__when_round_tripping__then_typed_response() {
+        let call = SetLedColor {
+            color: "red".to_string(),
+        };
+
+        let request = call.to_request().unwrap();
+        assert_eq!(request.vendor_id, "com.example");
+        assert_eq!(request.message_id.as_deref(), Some("SetLedColor"));
+        assert_eq!(request.data.as_deref(), Some(r#"{"color":"red"}"#));
+
+        let response = registry().handle(&request);
+        assert_eq!(
+            SetLedColor::decode_response(response).unwrap(),
+            LedState { on: true }
+        );
+    }
+
+    #[test]
+    fn given_not_accepted_response__when_decoding__then_error() {
+        let response = DataTransferResponse {
+            status: DataTransferStatus::UnknownVendorId,
+            data: None,
+        };
+
+        let result = SetLedColor::decode_response(response);
+
+        assert!(matches!(
+            result,
+            Err(DataTransferError::NotAccepted(
+                DataTransferStatus::UnknownVendorId
+            ))
+        ));
+    }
+}
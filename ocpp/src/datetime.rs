@@ -0,0 +1,89 @@
+///// RFC 3339 timestamps /////
+use chrono::{SecondsFormat, Utc};
+use serde::Serializer;
+use serde::de::{self, Deserialize, Deserializer, Unexpected};
+
+/// Point in time of a message field, normalized to UTC
+pub type DateTime = chrono::DateTime<Utc>;
+
+pub fn parse(value: &str) -> Result<DateTime, chrono::ParseError> {
+    chrono::DateTime::parse_from_rfc3339(value).map(|datetime| datetime.with_timezone(&Utc))
+}
+
+/// i.e. `"2019-08-24T14:15:22Z"`, fractional seconds only if there are any
+pub fn format(datetime: &DateTime) -> String {
+    datetime.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+/// `#[serde(with = "rfc3339")]` for `DateTime` fields
+pub mod rfc3339 {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(datetime: &DateTime, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format(datetime))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        parse(&value).map_err(|_| {
+            de::Error::invalid_value(Unexpected::Str(&value), &"an RFC 3339 timestamp")
+        })
+    }
+
+    /// `#[serde(default, with = "rfc3339::option")]` for `Option<DateTime>` fields
+    pub mod option {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(
+            datetime: &Option<DateTime>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match datetime {
+                Some(datetime) => super::serialize(datetime, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<DateTime>, D::Error> {
+            #[derive(serde::Deserialize)]
+            struct Wrapper(#[serde(with = "crate::datetime::rfc3339")] DateTime);
+
+            let wrapper = Option::<Wrapper>::deserialize(deserializer)?;
+            Ok(wrapper.map(|Wrapper(datetime)| datetime))
+        }
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, serde::Serialize, serde::Deserialize)]
+    struct Message {
+        #[serde(with = "rfc3339")]
+        timestamp: DateTime,
+    }
+
+    #[test]
+    fn given_offset_timestamp__when_deserializing__then_utc() {
+        let message: Message =
+            serde_json::from_str(r#"{"timestamp": "2019-08-24T16:15:22.5+02:00"}"#).unwrap();
+
+        assert_eq!(
+            serde_json::to_string(&message).unwrap(),
+            r#"{"timestamp":"2019-08-24T14:15:22.500Z"}"#
+        );
+    }
+
+    #[test]
+    fn given_malformed_timestamp__when_deserializing__then_invalid_value() {
+        let result = serde_json::from_str::<Message>(r#"{"timestamp": "24.08.2019 14:15"}"#);
+
+        assert!(result.unwrap_err().to_string().starts_with(
+            "invalid value: string \"24.08.2019 14:15\", expected an RFC 3339 timestamp"
+        ));
+    }
+}
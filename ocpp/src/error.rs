@@ -0,0 +1,170 @@
+///// Errors while decoding and correlating OCPP messages /////
+use std::fmt;
+
+use crate::call_error::{CallError, ErrorCode};
+use crate::raw_ocpp_message::RawOcppMessage;
+use crate::schema::Violation;
+use crate::{CallId, ProtocolVersion};
+
+#[derive(Debug)]
+pub enum OcppError {
+    /// The frame is not an OCPP message array, i.e. broken JSON or a missing call id
+    Frame(serde_json::Error),
+    UnknownMessageType(u8),
+    /// The crate was built without the message catalogue of this version
+    UnsupportedVersion(ProtocolVersion),
+    /// No message catalogue entry exists for the action
+    UnknownAction(String),
+    /// The payload does not decode into the typed message of its action, `error_code` tells
+    /// which schema constraint it violates
+    Payload {
+        action: String,
+        error_code: ErrorCode,
+        source: serde_json::Error,
+    },
+    Schema(Violation),
+    /// A CALLRESULT or CALLERROR whose call id matches no pending call
+    UnknownCallId(CallId),
+}
+
+impl OcppError {
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            OcppError::Frame(_) => ErrorCode::RpcFrameworkError,
+            OcppError::UnknownMessageType(_) => ErrorCode::MessageTypeNotSupported,
+            OcppError::UnsupportedVersion(_) => ErrorCode::NotSupported,
+            OcppError::UnknownAction(_) => ErrorCode::NotImplemented,
+            OcppError::Payload { error_code, .. } => *error_code,
+            OcppError::Schema(violation) => violation.error_code,
+            OcppError::UnknownCallId(_) => ErrorCode::ProtocolError,
+        }
+    }
+
+    pub fn to_call_error(&self, call_id: CallId) -> CallError {
+        match self {
+            OcppError::Schema(violation) => violation.to_call_error(call_id),
+            _ => CallError {
+                call_id,
+                error_code: self.error_code(),
+                error_description: self.to_string(),
+                error_details: serde_json::json!({}),
+            },
+        }
+    }
+
+    /// The CALLERROR to answer `message` with.
+    ///
+    /// Only received CALLs are answered, errors on results and correlation errors are
+    /// dropped since the other side does not expect an answer to them.
+    pub fn reply_to(&self, message: &RawOcppMessage) -> Option<CallError> {
+        match self {
+            OcppError::UnknownCallId(_) => None,
+            _ if message.message_type == 2 => Some(self.to_call_error(message.call_id.clone())),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for OcppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OcppError::Frame(err) => write!(f, "invalid OCPP frame: {}", err),
+            OcppError::UnknownMessageType(message_type) => {
+                write!(f, "unexpected message type {}", message_type)
+            }
+            OcppError::UnsupportedVersion(version) => {
+                write!(f, "no message catalogue for {}", version.subprotocol())
+            }
+            OcppError::UnknownAction(action) => write!(f, "unknown action {}", action),
+            OcppError::Payload { action, source, .. } => {
+                write!(f, "invalid {} payload: {}", action, source)
+            }
+            OcppError::Schema(violation) => {
+                write!(f, "{} at {}", violation.description, violation.path)
+            }
+            OcppError::UnknownCallId(call_id) => {
+                write!(f, "no pending call with id {}", call_id.0)
+            }
+        }
+    }
+}
+
+impl std::error::Error for OcppError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            OcppError::Frame(err) | OcppError::Payload { source: err, .. } => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(message_type: u8) -> RawOcppMessage {
+        RawOcppMessage {
+            message_type,
+            call_id: CallId("19223201".to_string()),
+            action: "BootNotification".to_string(),
+            payload: serde_json::json!({}),
+        }
+    }
+
+    fn payload_error(message_type: u8, action: &str, payload: serde_json::Value) -> OcppError {
+        let raw = RawOcppMessage {
+            message_type,
+            action: action.to_string(),
+            payload,
+            ..raw(message_type)
+        };
+        crate::convert(ProtocolVersion::Ocpp16, raw).unwrap_err()
+    }
+
+    #[test]
+    fn given_payload_errors__when_decoding__then_constraint_codes_of_the_schema() {
+        let boot = |payload| payload_error(3, "BootNotification", payload);
+
+        assert_eq!(
+            boot(serde_json::json!({"currentTime": "2019-08-24T14:15:22Z", "interval": 0}))
+                .error_code(),
+            ErrorCode::OccurenceConstraintViolation
+        );
+        assert_eq!(
+            boot(serde_json::json!({
+                "status": "Accepted",
+                "currentTime": "2019-08-24T14:15:22Z",
+                "interval": "60"
+            }))
+            .error_code(),
+            ErrorCode::TypeConstraintViolation
+        );
+        let status = payload_error(
+            2,
+            "StatusNotification",
+            serde_json::json!({"connectorId": 1, "errorCode": "NoError", "status": "Charged"}),
+        );
+        assert_eq!(status.error_code(), ErrorCode::PropertyConstraintViolation);
+    }
+
+    #[test]
+    fn given_error_on_call__when_replying__then_call_error() {
+        let error = OcppError::UnknownAction("Teleport".to_string());
+
+        let reply = error.reply_to(&raw(2)).unwrap();
+
+        assert_eq!(reply.error_code, ErrorCode::NotImplemented);
+        assert_eq!(reply.call_id, CallId("19223201".to_string()));
+        assert_eq!(reply.error_description, "unknown action Teleport");
+    }
+
+    #[test]
+    fn given_error_on_result__when_replying__then_dropped() {
+        let payload = payload_error(3, "BootNotification", serde_json::json!({}));
+        let correlation = OcppError::UnknownCallId(CallId("1".to_string()));
+
+        assert!(payload.reply_to(&raw(3)).is_none());
+        assert!(correlation.reply_to(&raw(2)).is_none());
+    }
+}
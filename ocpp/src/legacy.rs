@@ -0,0 +1,236 @@
+///// Adapter for the legacy `request_type` envelope of our internal tools /////
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::ci_string::{CiString, LengthError};
+use crate::ocpp_event::{AuthorizeRequest, BootNotificationRequest, HeartbeatRequest, OcppRequest};
+
+/// A request received in the legacy envelope, mapped onto the OCPP 1.6 catalogue.
+///
+/// `{"request_type": "Connect", "payload": {...}}` with snake_case fields. `Connect` is not an
+/// OCPP action, it becomes a BootNotification which remembers the charge point id.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LegacyRequest {
+    /// Only set by `Connect`
+    pub charge_point_id: Option<String>,
+    pub request: OcppRequest,
+}
+
+// ----------- Legacy wire format -----------
+
+#[derive(Serialize, Deserialize)]
+struct LegacyConnectRequest {
+    charge_point_id: String,
+    charge_point_model: String,
+    charge_point_vendor: String,
+    firmware_version: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LegacyAuthorizeRequest {
+    id_tag: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LegacyBootNotificationRequest {
+    charge_point_model: String,
+    charge_point_vendor: String,
+    firmware_version: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LegacyHeartbeatRequest;
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "request_type", content = "payload")]
+enum Envelope {
+    Connect(LegacyConnectRequest),
+    Authorize(LegacyAuthorizeRequest),
+    BootNotification(LegacyBootNotificationRequest),
+    Heartbeat(LegacyHeartbeatRequest),
+}
+
+fn boot_notification(
+    charge_point_model: String,
+    charge_point_vendor: String,
+    firmware_version: Option<String>,
+) -> Result<BootNotificationRequest, LengthError> {
+    Ok(BootNotificationRequest {
+        charge_point_vendor: CiString::new(charge_point_vendor)?,
+        charge_point_model: CiString::new(charge_point_model)?,
+        charge_point_serial_number: None,
+        charge_box_serial_number: None,
+        firmware_version: firmware_version.map(CiString::new).transpose()?,
+        iccid: None,
+        imsi: None,
+        meter_type: None,
+        meter_serial_number: None,
+    })
+}
+
+impl TryFrom<Envelope> for LegacyRequest {
+    type Error = LengthError;
+
+    fn try_from(envelope: Envelope) -> Result<Self, LengthError> {
+        let (charge_point_id, request) = match envelope {
+            Envelope::Connect(connect) => (
+                Some(connect.charge_point_id),
+                OcppRequest::BootNotification(boot_notification(
+                    connect.charge_point_model,
+                    connect.charge_point_vendor,
+                    connect.firmware_version,
+                )?),
+            ),
+            Envelope::Authorize(authorize) => (
+                None,
+                OcppRequest::Authorize(AuthorizeRequest {
+                    id_tag: CiString::new(authorize.id_tag)?,
+                }),
+            ),
+            Envelope::BootNotification(boot) => (
+                None,
+                OcppRequest::BootNotification(boot_notification(
+                    boot.charge_point_model,
+                    boot.charge_point_vendor,
+                    boot.firmware_version,
+                )?),
+            ),
+            Envelope::Heartbeat(LegacyHeartbeatRequest) => {
+                (None, OcppRequest::Heartbeat(HeartbeatRequest {}))
+            }
+        };
+        Ok(LegacyRequest {
+            charge_point_id,
+            request,
+        })
+    }
+}
+
+impl TryFrom<&LegacyRequest> for Envelope {
+    /// The action has no legacy representation
+    type Error = String;
+
+    fn try_from(legacy: &LegacyRequest) -> Result<Self, String> {
+        let envelope = match (&legacy.request, &legacy.charge_point_id) {
+            (OcppRequest::BootNotification(boot), Some(charge_point_id)) => {
+                Envelope::Connect(LegacyConnectRequest {
+                    charge_point_id: charge_point_id.clone(),
+                    charge_point_model: boot.charge_point_model.to_string(),
+                    charge_point_vendor: boot.charge_point_vendor.to_string(),
+                    firmware_version: boot.firmware_version.as_deref().map(str::to_string),
+                })
+            }
+            (OcppRequest::BootNotification(boot), None) => {
+                Envelope::BootNotification(LegacyBootNotificationRequest {
+                    charge_point_model: boot.charge_point_model.to_string(),
+                    charge_point_vendor: boot.charge_point_vendor.to_string(),
+                    firmware_version: boot.firmware_version.as_deref().map(str::to_string),
+                })
+            }
+            (OcppRequest::Authorize(authorize), _) => Envelope::Authorize(LegacyAuthorizeRequest {
+                id_tag: authorize.id_tag.to_string(),
+            }),
+            (OcppRequest::Heartbeat(_), _) => Envelope::Heartbeat(LegacyHeartbeatRequest),
+            (request, _) => {
+                return Err(format!("{:?} has no legacy request_type", request));
+            }
+        };
+        Ok(envelope)
+    }
+}
+
+impl<'de> Deserialize<'de> for LegacyRequest {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let envelope = Envelope::deserialize(deserializer)?;
+        LegacyRequest::try_from(envelope).map_err(|err| {
+            serde::de::Error::invalid_length(
+                err.len,
+                &format!("at most {} characters", err.max).as_str(),
+            )
+        })
+    }
+}
+
+impl Serialize for LegacyRequest {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Envelope::try_from(self)
+            .map_err(serde::ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+/// Accepts a request in either envelope, `{"action", "payload"}` or the legacy `{"request_type", "payload"}`
+pub fn parse_request(json: &str) -> Result<LegacyRequest, serde_json::Error> {
+    let value = serde_json::from_str::<serde_json::Value>(json)?;
+    if value.get("request_type").is_some() {
+        return serde_json::from_value(value);
+    }
+    Ok(LegacyRequest {
+        charge_point_id: None,
+        request: serde_json::from_value(value)?,
+    })
+}
+
+#[cfg(test)]
+mod serde_tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize_connect_request() {
+        let connect_request = LegacyRequest {
+            charge_point_id: Some("CP123".to_string()),
+            request: OcppRequest::BootNotification(
+                boot_notification(
+                    "ModelX".to_string(),
+                    "VendorY".to_string(),
+                    Some("1.0.0".to_string()),
+                )
+                .unwrap(),
+            ),
+        };
+
+        let result = serde_json::to_string(&connect_request);
+        assert!(result.is_ok());
+        let serialized = result.unwrap();
+        assert!(serialized.contains("\"request_type\":\"Connect\""));
+        assert!(serialized.contains("\"charge_point_id\":\"CP123\""));
+    }
+
+    #[test]
+    fn test_deserialize_connect_request() {
+        let raw = r#"{
+            "request_type": "Connect",
+            "payload": {
+                "charge_point_id": "CP123",
+                "charge_point_model": "ModelX",
+                "charge_point_vendor": "VendorY",
+                "firmware_version": "1.0.0"
+            }
+        }"#;
+
+        let request: LegacyRequest = serde_json::from_str(raw).unwrap();
+        assert_eq!(request.charge_point_id.as_deref(), Some("CP123"));
+        match request.request {
+            OcppRequest::BootNotification(req) => {
+                assert_eq!(req.charge_point_model, "ModelX");
+            }
+            _ => panic!("Expected BootNotification request"),
+        }
+    }
+
+    #[test]
+    fn test_parse_both_envelopes() {
+        let legacy =
+            parse_request(r#"{"request_type": "Authorize", "payload": {"id_tag": "DEADBEEF"}}"#)
+                .unwrap();
+        let current =
+            parse_request(r#"{"action": "Authorize", "payload": {"idTag": "DEADBEEF"}}"#).unwrap();
+
+        assert_eq!(legacy, current);
+        assert!(
+            parse_request(
+                r#"{"request_type": "Authorize", "payload": {"id_tag": "0123456789ABCDEFGHIJK"}}"#
+            )
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! OCPP (Open Charge Point Protocol) message model, codecs and charge point runtime.
+//!
+//! Decoding happens in stages, [`ocpp_frame`] splits a frame on the wire, [`raw_ocpp_message`]
+//! carries a call or result with its action and [`ocpp_event::convert`] decodes the payload with
+//! the catalogue of the negotiated [`ProtocolVersion`].
+//!
+//! Cargo features, all enabled by default:
+//! - `transport`: WebSocket connection to a CSMS, [`transport`]
+//! - `persistence`: file backed stores
+//! - `v201`: OCPP 2.0.1 and 2.1 catalogue, device model and transactions, [`v201`]
+
+pub mod call_error;
+pub mod ci_string;
+pub mod data_transfer;
+pub mod datetime;
+pub mod error;
+pub mod legacy;
+pub mod ocpp_datagram;
+pub mod ocpp_event;
+pub mod ocpp_frame;
+pub mod pending_calls;
+pub mod raw_ocpp_message;
+pub mod schema;
+#[cfg(feature = "transport")]
+pub mod transport;
+pub mod typed_ocpp_message;
+pub mod types;
+#[cfg(feature = "v201")]
+pub mod v201;
+
+pub use error::OcppError;
+pub use ocpp_event::{OcppEvent, OcppMessage, convert};
+pub use ocpp_frame::OcppFrame;
+pub use raw_ocpp_message::RawOcppMessage;
+pub use typed_ocpp_message::{MessageType, TypedOcppMessage};
+
+/// Wrapper struct for CallId to not confuse it with any other string
+#[derive(Debug, Clone, Eq, PartialEq, Hash, serde::Serialize)]
+pub struct CallId(String);
+
+impl CallId {
+    /// Message ids are limited to 36 characters, enough for a UUID
+    pub const MAX_LENGTH: usize = 36;
+
+    pub fn new(id: impl Into<String>) -> Result<Self, ci_string::LengthError> {
+        let id = id.into();
+        let len = id.chars().count();
+        if len > Self::MAX_LENGTH {
+            return Err(ci_string::LengthError {
+                max: Self::MAX_LENGTH,
+                len,
+            });
+        }
+        Ok(CallId(id))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for CallId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        ci_string::check_length(&id, Self::MAX_LENGTH)?;
+        Ok(CallId(id))
+    }
+}
+
+/// OCPP version spoken on a connection, decides which message catalogue is used
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum ProtocolVersion {
+    #[serde(rename = "ocpp1.6")]
+    Ocpp16,
+    #[serde(rename = "ocpp2.0.1")]
+    Ocpp201,
+    /// Backwards compatible with 2.0.1, served by the same message catalogue
+    #[serde(rename = "ocpp2.1")]
+    Ocpp21,
+}
+
+impl ProtocolVersion {
+    /// Maps the WebSocket subprotocol selected by the server, i.e. `"ocpp2.0.1"`
+    pub fn from_subprotocol(subprotocol: &str) -> Option<Self> {
+        match subprotocol {
+            "ocpp1.6" => Some(ProtocolVersion::Ocpp16),
+            "ocpp2.0.1" => Some(ProtocolVersion::Ocpp201),
+            "ocpp2.1" => Some(ProtocolVersion::Ocpp21),
+            _ => None,
+        }
+    }
+
+    pub fn subprotocol(&self) -> &'static str {
+        match self {
+            ProtocolVersion::Ocpp16 => "ocpp1.6",
+            ProtocolVersion::Ocpp201 => "ocpp2.0.1",
+            ProtocolVersion::Ocpp21 => "ocpp2.1",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subprotocol_round_trip() {
+        for version in [
+            ProtocolVersion::Ocpp16,
+            ProtocolVersion::Ocpp201,
+            ProtocolVersion::Ocpp21,
+        ] {
+            assert_eq!(
+                ProtocolVersion::from_subprotocol(version.subprotocol()),
+                Some(version)
+            );
+        }
+        assert_eq!(ProtocolVersion::from_subprotocol("ocpp1.5"), None);
+    }
+
+    #[test]
+    fn call_id_is_limited_to_36_characters() {
+        let uuid = "\"9f3c6d2e-0b7a-4c1e-8d5f-2a6b9e1c4f70\"";
+        assert!(serde_json::from_str::<CallId>(uuid).is_ok());
+
+        let result = serde_json::from_str::<CallId>("\"9f3c6d2e-0b7a-4c1e-8d5f-2a6b9e1c4f70-1\"");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "invalid length 38, expected at most 36 characters"
+        );
+    }
+}
@@ -1,17 +1,79 @@
-//! OCCP (Open Charge Point Protocol) related structures and requests.
+//! OCCP (Open Charge Point Protocol) command line tool.
+//!
+//! `ocpp decode [ocpp1.6|ocpp2.0.1|ocpp2.1] [action] [frame]` decodes a frame, a CALLRESULT
+//! with the action of the call it answers. `ocpp legacy [request]` maps a legacy
+//! `request_type` envelope. Without the JSON argument it is read from stdin.
 
-use crate::ocpp_mod::ocpp as ocpp_internal;
-mod ocpp;
-mod ocpp_mod;
+use std::io::Read;
+use std::process::ExitCode;
 
-fn main() {
-    let connect = ocpp_internal::ConnectRequest {
-        charge_point_id: "CP001".into(),
-        charge_point_model: "ModelZ".into(),
-        charge_point_vendor: "VendorX".into(),
-        firmware_version: None,
+use ocpp::{OcppFrame, ProtocolVersion, convert, legacy};
+
+const USAGE: &str =
+    "usage: ocpp decode [ocpp1.6|ocpp2.0.1|ocpp2.1] [action] [frame]\n       ocpp legacy [request]";
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    let result = match args.as_slice() {
+        ["decode", rest @ ..] => decode(rest),
+        ["legacy", rest @ ..] => input(rest.first().copied()).and_then(|json| {
+            legacy::parse_request(&json)
+                .map(|request| format!("{:#?}", request))
+                .map_err(|err| format!("invalid request: {}", err))
+        }),
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(output) => {
+            println!("{}", output);
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn decode(args: &[&str]) -> Result<String, String> {
+    let (version, args) = match args {
+        [version, rest @ ..] if version.starts_with("ocpp") => (
+            ProtocolVersion::from_subprotocol(version)
+                .ok_or_else(|| format!("unknown version {}", version))?,
+            rest,
+        ),
+        _ => (ProtocolVersion::Ocpp16, args),
+    };
+    let (action, frame) = match args {
+        [action, rest @ ..] if !action.trim_start().starts_with('[') => {
+            (Some(*action), rest.first().copied())
+        }
+        _ => (None, args.first().copied()),
+    };
+    let frame = serde_json::from_str::<OcppFrame>(&input(frame)?)
+        .map_err(|err| format!("invalid frame: {}", err))?;
+    if matches!(frame, OcppFrame::CallResult { .. }) && action.is_none() {
+        return Err("a CALLRESULT needs the action of its call".to_string());
+    }
+    let raw = match frame.into_raw(|| action.unwrap_or_default().to_string()) {
+        Ok(raw) => raw,
+        Err(call_error) => return Ok(format!("{:#?}", call_error)),
     };
+    let event = convert(version, raw).map_err(|err| err.to_string())?;
+    serde_json::to_string_pretty(&event).map_err(|err| err.to_string())
+}
 
-    let request = ocpp_internal::OcppRequest::Connect(connect);
-    // ocpp::handle(request);
-}
\ No newline at end of file
+/// The argument or, if missing, all of stdin
+fn input(arg: Option<&str>) -> Result<String, String> {
+    match arg {
+        Some(arg) => Ok(arg.to_string()),
+        None => {
+            let mut json = String::new();
+            std::io::stdin()
+                .read_to_string(&mut json)
+                .map_err(|err| err.to_string())?;
+            Ok(json)
+        }
+    }
+}
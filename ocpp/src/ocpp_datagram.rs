@@ -0,0 +1,264 @@
+///// OCPPDatagram, the message type of the former Qt/C++ stack /////
+use serde_json::Value;
+
+use crate::call_error::{CallError, ErrorCode};
+use crate::error::OcppError;
+use crate::ocpp_event::{OcppCall, OcppEvent, OcppMessage, convert};
+use crate::raw_ocpp_message::RawOcppMessage;
+use crate::typed_ocpp_message::{MessageType, TypedOcppMessage};
+use crate::{CallId, ProtocolVersion};
+
+/// Any OCPP message with all fields of all message types, like-for-like with the C++ class
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct OCPPDatagram {
+    /// Unique message ID, maximum length of 36 characters, to allow for UUIDs/GUIDs
+    pub id: CallId,
+    pub msg_type: MessageType,
+    /// Payload of the Action or Result; in case of an CALLERROR it contains the ErrorDetails.
+    pub payload: Value,
+    /// The OCPP message name without the "Request" suffix. Case-sensitive. For CALLRESULT and
+    /// CALLERROR the action is set by the proxy that relayed the request.
+    pub action: String,
+    /// Only for CALLERROR, `None` otherwise
+    pub error_code: Option<ErrorCode>,
+    /// Only for CALLERROR, empty otherwise
+    pub error_description: String,
+}
+
+impl OCPPDatagram {
+    pub fn new() -> Self {
+        Self {
+            id: CallId(String::new()),
+            msg_type: MessageType::Call,
+            payload: Value::Null,
+            action: String::new(),
+            error_code: None,
+            error_description: String::new(),
+        }
+    }
+
+    pub fn call(id: CallId, action: &str, payload: Value) -> Self {
+        Self {
+            id,
+            action: action.to_string(),
+            payload,
+            ..Self::new()
+        }
+    }
+
+    pub fn call_result(id: CallId, action: &str, payload: Value) -> Self {
+        Self {
+            msg_type: MessageType::CallResult,
+            ..Self::call(id, action, payload)
+        }
+    }
+
+    pub fn call_error(error: &CallError, action: &str) -> Self {
+        Self {
+            id: error.call_id.clone(),
+            msg_type: MessageType::CallError,
+            payload: error.error_details.clone(),
+            action: action.to_string(),
+            error_code: Some(error.error_code),
+            error_description: error.error_description.clone(),
+        }
+    }
+
+    pub fn is_call_error(&self) -> bool {
+        self.msg_type == MessageType::CallError
+    }
+
+    /// Decodes the payload with the message catalogue of `version`, a CALLERROR is no event
+    pub fn to_event(&self, version: ProtocolVersion) -> Result<OcppEvent, OcppError> {
+        let raw = RawOcppMessage::try_from(self.clone())
+            .map_err(|_| OcppError::UnknownMessageType(MessageType::CallError as u8))?;
+        convert(version, raw)
+    }
+}
+
+impl Default for OCPPDatagram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TryFrom<RawOcppMessage> for OCPPDatagram {
+    type Error = OcppError;
+
+    fn try_from(raw: RawOcppMessage) -> Result<Self, OcppError> {
+        let msg_type =
+            MessageType::try_from(raw.message_type).map_err(OcppError::UnknownMessageType)?;
+        Ok(Self {
+            id: raw.call_id,
+            msg_type,
+            payload: raw.payload,
+            action: raw.action,
+            error_code: None,
+            error_description: String::new(),
+        })
+    }
+}
+
+/// Calls and results only, a raw message has no room for the error code and description of
+/// a CALLERROR, which is returned as error instead
+impl TryFrom<OCPPDatagram> for RawOcppMessage {
+    type Error = CallError;
+
+    fn try_from(datagram: OCPPDatagram) -> Result<Self, CallError> {
+        if datagram.is_call_error() {
+            return Err(CallError {
+                call_id: datagram.id,
+                error_code: datagram.error_code.unwrap_or(ErrorCode::GenericError),
+                error_description: datagram.error_description,
+                error_details: datagram.payload,
+            });
+        }
+        Ok(RawOcppMessage {
+            message_type: datagram.msg_type as u8,
+            call_id: datagram.id,
+            action: datagram.action,
+            payload: datagram.payload,
+        })
+    }
+}
+
+impl From<TypedOcppMessage> for OCPPDatagram {
+    fn from(typed: TypedOcppMessage) -> Self {
+        Self {
+            id: typed.call_id,
+            msg_type: typed.message_type,
+            payload: typed.payload,
+            action: typed.action,
+            error_code: None,
+            error_description: String::new(),
+        }
+    }
+}
+
+impl From<OCPPDatagram> for TypedOcppMessage {
+    fn from(datagram: OCPPDatagram) -> Self {
+        TypedOcppMessage {
+            message_type: datagram.msg_type,
+            call_id: datagram.id,
+            action: datagram.action,
+            payload: datagram.payload,
+        }
+    }
+}
+
+impl TryFrom<OcppEvent> for OCPPDatagram {
+    type Error = serde_json::Error;
+
+    fn try_from(event: OcppEvent) -> Result<Self, serde_json::Error> {
+        let (msg_type, tagged) = match event.message {
+            OcppMessage::Call(OcppCall::Other(action, payload)) => {
+                return Ok(Self::call(event.call_id, &action, payload));
+            }
+            OcppMessage::Request(request) => (MessageType::Call, serde_json::to_value(request)?),
+            #[cfg(feature = "v201")]
+            OcppMessage::V201Request(request) => {
+                (MessageType::Call, serde_json::to_value(request)?)
+            }
+            OcppMessage::Response(response) => {
+                (MessageType::CallResult, serde_json::to_value(response)?)
+            }
+            #[cfg(feature = "v201")]
+            OcppMessage::V201Response(response) => {
+                (MessageType::CallResult, serde_json::to_value(response)?)
+            }
+        };
+        let Value::Object(mut tagged) = tagged else {
+            return Err(serde::ser::Error::custom(
+                "message did not serialize as an {action, payload} object",
+            ));
+        };
+        let action = match tagged.remove("action") {
+            Some(Value::String(action)) => action,
+            _ => String::new(),
+        };
+        Ok(Self {
+            id: event.call_id,
+            msg_type,
+            payload: tagged.remove("payload").unwrap_or(Value::Null),
+            action,
+            error_code: None,
+            error_description: String::new(),
+        })
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn heartbeat_result() -> RawOcppMessage {
+        RawOcppMessage {
+            message_type: 3,
+            call_id: CallId("19223201".to_string()),
+            action: "Heartbeat".to_string(),
+            payload: json!({"currentTime": "2019-08-24T14:15:22Z"}),
+        }
+    }
+
+    #[test]
+    fn given_raw_message__when_converting_back_and_forth__then_unchanged() {
+        let datagram = OCPPDatagram::try_from(heartbeat_result()).unwrap();
+
+        assert_eq!(datagram.msg_type, MessageType::CallResult);
+        assert_eq!(datagram.error_code, None);
+        assert_eq!(
+            TypedOcppMessage::from(datagram.clone()).message_type,
+            MessageType::CallResult
+        );
+        assert_eq!(
+            RawOcppMessage::try_from(datagram).unwrap(),
+            heartbeat_result()
+        );
+    }
+
+    #[test]
+    fn given_unknown_message_type__when_converting__then_error() {
+        let raw = RawOcppMessage {
+            message_type: 7,
+            ..heartbeat_result()
+        };
+
+        assert!(matches!(
+            OCPPDatagram::try_from(raw),
+            Err(OcppError::UnknownMessageType(7))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "v201")]
+    fn given_event__when_converting__then_action_and_payload_restored() {
+        let datagram = OCPPDatagram::try_from(heartbeat_result()).unwrap();
+        let event = datagram.to_event(ProtocolVersion::Ocpp201).unwrap();
+
+        let restored = OCPPDatagram::try_from(event).unwrap();
+
+        assert_eq!(restored, datagram);
+    }
+
+    #[test]
+    fn given_call_error__when_converting__then_error_fields_set() {
+        let error = CallError {
+            call_id: CallId("42".to_string()),
+            error_code: ErrorCode::OccurenceConstraintViolation,
+            error_description: "missing field `idTag`".to_string(),
+            error_details: json!({}),
+        };
+
+        let datagram = OCPPDatagram::call_error(&error, "Authorize");
+
+        assert!(datagram.is_call_error());
+        assert_eq!(
+            datagram.error_code,
+            Some(ErrorCode::OccurenceConstraintViolation)
+        );
+        assert_eq!(datagram.error_description, "missing field `idTag`");
+        assert_eq!(RawOcppMessage::try_from(datagram).unwrap_err(), error);
+    }
+}
@@ -0,0 +1,885 @@
+///// Stage 3: Deserialize whole OCPP response /////
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::de::value::MapDeserializer;
+
+use crate::ci_string::{CiString20Type, CiString25Type, CiString50Type, CiString255Type};
+use crate::datetime::{DateTime, rfc3339};
+use crate::error::OcppError;
+use crate::raw_ocpp_message::RawOcppMessage;
+use crate::schema::{self, PayloadKind};
+use crate::types::{
+    AvailabilityStatus, CancelReservationStatus, ChargePointErrorCode, ChargePointStatus,
+    ChargingProfileStatus, ChargingSchedule, ClearCacheStatus, ClearChargingProfileStatus,
+    ConfigurationStatus, DataTransferStatus, GetCompositeScheduleStatus, IdTagInfo, IdToken,
+    KeyValue, RegistrationStatus, RemoteStartStopStatus, ReservationStatus, ResetStatus,
+    TriggerMessageStatus, UnlockStatus, UpdateStatus,
+};
+#[cfg(feature = "v201")]
+use crate::v201;
+use crate::{CallId, ProtocolVersion};
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize)]
+pub struct OcppEvent {
+    /// Call id, i.e `"19223201"`
+    pub call_id: CallId,
+    /// Combines `message_type`, `action` and `payload` into a single message
+    pub message: OcppMessage,
+}
+/// converter function from Raw Format to OCPP Event Format, using the message catalogue of `version`
+pub fn convert(version: ProtocolVersion, ocpp_msg: RawOcppMessage) -> Result<OcppEvent, OcppError> {
+    let tagged_payload = serde_json::json!({
+        "action": ocpp_msg.action,
+        "payload": ocpp_msg.payload,
+    });
+    let event = OcppEvent {
+        call_id: ocpp_msg.call_id,
+        message: match (version, ocpp_msg.message_type) {
+            (ProtocolVersion::Ocpp16, 2) => {
+                OcppMessage::Request(decode(version, PayloadKind::Request, tagged_payload)?)
+            }
+            (ProtocolVersion::Ocpp16, 3) => {
+                OcppMessage::Response(decode(version, PayloadKind::Response, tagged_payload)?)
+            }
+            #[cfg(feature = "v201")]
+            (ProtocolVersion::Ocpp201 | ProtocolVersion::Ocpp21, 2) => {
+                OcppMessage::V201Request(decode(version, PayloadKind::Request, tagged_payload)?)
+            }
+            #[cfg(feature = "v201")]
+            (ProtocolVersion::Ocpp201 | ProtocolVersion::Ocpp21, 3) => {
+                OcppMessage::V201Response(decode(version, PayloadKind::Response, tagged_payload)?)
+            }
+            #[cfg(not(feature = "v201"))]
+            (version, 2 | 3) => return Err(OcppError::UnsupportedVersion(version)),
+            (_, message_type) => return Err(OcppError::UnknownMessageType(message_type)),
+        },
+    };
+    Ok(event)
+}
+
+/// Tells an action missing from the catalogue apart from a payload which does not fit its
+/// action, the CALLERROR code of the latter follows from the schema violation
+fn decode<T: Catalogue>(
+    version: ProtocolVersion,
+    kind: PayloadKind,
+    tagged_payload: serde_json::Value,
+) -> Result<T, OcppError> {
+    serde_json::from_value(tagged_payload.clone()).map_err(|source| {
+        let action = tagged_payload["action"].as_str().unwrap_or_default();
+        if is_unknown_action::<T>(action) {
+            OcppError::UnknownAction(action.to_string())
+        } else {
+            OcppError::Payload {
+                action: action.to_string(),
+                error_code: schema::classify(version, action, kind, &tagged_payload["payload"]),
+                source,
+            }
+        }
+    })
+}
+
+/// A message catalogue tagged with `action`, `strict` fails on actions it has no variant for
+pub(crate) trait Catalogue: DeserializeOwned {
+    fn strict<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>;
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum OcppMessage {
+    Call(OcppCall),
+    Request(OcppRequest),
+    Response(OcppResponse),
+    #[cfg(feature = "v201")]
+    V201Request(v201::OcppRequest),
+    #[cfg(feature = "v201")]
+    V201Response(v201::OcppResponse),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum OcppCall {
+    Other(String, serde_json::Value),
+}
+
+/// `remote = "Self"` keeps the derived impls strict, the trait impls handle `Other`
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(remote = "Self", tag = "action", content = "payload")]
+pub enum OcppRequest {
+    Authorize(AuthorizeRequest),
+    BootNotification(BootNotificationRequest),
+    DataTransfer(DataTransferRequest),
+    Heartbeat(HeartbeatRequest),
+    StatusNotification(StatusNotificationRequest),
+    /// Any action which has no typed payload yet
+    #[serde(skip)]
+    Other {
+        action: String,
+        payload: serde_json::Value,
+    },
+}
+
+impl serde::Serialize for OcppRequest {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            OcppRequest::Other { action, payload } => serialize_other(action, payload, serializer),
+            typed => OcppRequest::serialize(typed, serializer),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for OcppRequest {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_with_fallback(deserializer, |action, payload| OcppRequest::Other {
+            action,
+            payload,
+        })
+    }
+}
+
+impl Catalogue for OcppRequest {
+    fn strict<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        OcppRequest::deserialize(deserializer)
+    }
+}
+
+/// Deserializes a request catalogue, actions unknown to [`Catalogue::strict`] become `other`.
+///
+/// Payloads of typed actions still have to match, so their errors are not hidden by the fallback.
+pub(crate) fn deserialize_with_fallback<'de, D, T>(
+    deserializer: D,
+    other: fn(String, serde_json::Value) -> T,
+) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Catalogue,
+{
+    #[derive(serde::Deserialize)]
+    struct Envelope {
+        action: String,
+        #[serde(default)]
+        payload: serde_json::Value,
+    }
+
+    let Envelope { action, payload } = serde::Deserialize::deserialize(deserializer)?;
+    let tagged_payload = serde_json::json!({
+        "action": action,
+        "payload": payload,
+    });
+    match T::strict(tagged_payload) {
+        Ok(request) => Ok(request),
+        Err(_) if is_unknown_action::<T>(&action) => Ok(other(action, payload)),
+        Err(err) => Err(serde::de::Error::custom(err)),
+    }
+}
+
+/// Serializes an untyped request like the typed ones, `{"action": .., "payload": ..}`
+pub(crate) fn serialize_other<S: serde::Serializer>(
+    action: &str,
+    payload: &serde_json::Value,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    use serde::ser::SerializeStruct;
+
+    let mut state = serializer.serialize_struct("OcppRequest", 2)?;
+    state.serialize_field("action", action)?;
+    state.serialize_field("payload", payload)?;
+    state.end()
+}
+
+/// Error of probing a catalogue, serde reports an unknown tag through
+/// [`serde::de::Error::unknown_variant`] before it looks at the payload
+#[derive(Debug)]
+enum Probe {
+    UnknownAction,
+    Other,
+}
+
+impl fmt::Display for Probe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Probe::UnknownAction => write!(f, "unknown action"),
+            Probe::Other => write!(f, "not an unknown action"),
+        }
+    }
+}
+
+impl std::error::Error for Probe {}
+
+impl serde::de::Error for Probe {
+    fn custom<M: fmt::Display>(_: M) -> Self {
+        Probe::Other
+    }
+
+    fn unknown_variant(_: &str, _: &'static [&'static str]) -> Self {
+        Probe::UnknownAction
+    }
+}
+
+fn is_unknown_action<T: Catalogue>(action: &str) -> bool {
+    let probe = MapDeserializer::<_, Probe>::new(std::iter::once(("action", action)));
+    matches!(T::strict(probe), Err(Probe::UnknownAction))
+}
+
+// TODO: Verify if it's possible to use two fields (action + payload) to deserizalize this enum automatically
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "action", content = "payload")]
+pub enum OcppResponse {
+    Authorize(AuthorizeResponse),
+    BootNotification(BootNotificationResponse),
+    CancelReservation(CancelReservationResponse),
+    ClearCache(ClearCacheResponse),
+    ChangeAvailability(ChangeAvailabilityResponse),
+    ChangeConfiguration(ChangeConfigurationResponse),
+    ClearChargingProfile(ClearChargingProfileResponse),
+    DataTransfer(DataTransferResponse),
+    DiagnosticsStatusNotification(DiagnosticsStatusNotificationResponse),
+    FirmwareStatusNotification(FirmwareStatusNotificationResponse),
+    GetCompositeSchedule(GetCompositeScheduleResponse),
+    GetConfiguration(GetConfigurationResponse),
+    GetDiagnostics(GetDiagnosticsResponse),
+    GetLocalListVersion(GetLocalListVersionResponse),
+    Heartbeat(HeartbeatResponse),
+    MeterValues(MeterValuesResponse),
+    RemoteStartTransaction(RemoteStartTransactionResponse),
+    RemoteStopTransaction(RemoteStopTransactionResponse),
+    ReserveNow(ReserveNowResponse),
+    Reset(ResetResponse),
+    SendLocalList(SendLocalListResponse),
+    SetChargingProfile(SetChargingProfileResponse),
+    StartTransaction(StartTransactionResponse),
+    StatusNotification(StatusNotificationResponse),
+    StopTransaction(StopTransactionResponse),
+    TriggerMessage(TriggerMessageResponse),
+    UnlockConnector(UnlockConnectorResponse),
+    UpdateFirmware(UpdateFirmwareResponse),
+}
+
+impl Catalogue for OcppResponse {
+    fn strict<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <OcppResponse as serde::Deserialize>::deserialize(deserializer)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorizeRequest {
+    pub id_tag: IdToken,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BootNotificationRequest {
+    pub charge_point_vendor: CiString20Type,
+    pub charge_point_model: CiString20Type,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub charge_point_serial_number: Option<CiString25Type>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub charge_box_serial_number: Option<CiString25Type>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub firmware_version: Option<CiString50Type>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iccid: Option<CiString20Type>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub imsi: Option<CiString20Type>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meter_type: Option<CiString25Type>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meter_serial_number: Option<CiString25Type>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HeartbeatRequest {}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusNotificationRequest {
+    /// 0 for the charge point as a whole
+    pub connector_id: u32,
+    pub error_code: ChargePointErrorCode,
+    /// Free text, i.e. why charging is limited
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub info: Option<CiString50Type>,
+    pub status: ChargePointStatus,
+    #[serde(
+        default,
+        with = "rfc3339::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub timestamp: Option<DateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vendor_id: Option<CiString255Type>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vendor_error_code: Option<CiString50Type>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorizeResponse {
+    pub id_tag_info: IdTagInfo,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BootNotificationResponse {
+    pub status: RegistrationStatus,
+    #[serde(with = "rfc3339")]
+    pub current_time: DateTime,
+    pub interval: u32,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelReservationResponse {
+    pub status: CancelReservationStatus,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeAvailabilityResponse {
+    pub status: AvailabilityStatus,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeConfigurationResponse {
+    pub status: ConfigurationStatus,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClearChargingProfileResponse {
+    pub status: ClearChargingProfileStatus,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataTransferRequest {
+    pub vendor_id: CiString255Type,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<CiString50Type>,
+    /// Vendor specific data, usually a JSON document encoded as string
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataTransferResponse {
+    pub status: DataTransferStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsStatusNotificationResponse {}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FirmwareStatusNotificationResponse {}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetCompositeScheduleResponse {
+    pub status: GetCompositeScheduleStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connector_id: Option<u32>,
+    #[serde(
+        default,
+        with = "rfc3339::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub schedule_start: Option<DateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub charging_schedule: Option<ChargingSchedule>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetConfigurationResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub configuration_key: Option<Vec<KeyValue>>,
+    /// Requested keys the charge point does not know
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unknown_key: Option<Vec<CiString50Type>>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetDiagnosticsResponse {
+    /// Name of the uploaded file, absent if there is nothing to upload
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<CiString255Type>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetLocalListVersionResponse {
+    /// 0 if the list is empty, -1 if local authorization is not supported
+    pub list_version: i32,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeartbeatResponse {
+    #[serde(with = "rfc3339")]
+    pub current_time: DateTime,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeterValuesResponse {}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteStartTransactionResponse {
+    pub status: RemoteStartStopStatus,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteStopTransactionResponse {
+    pub status: RemoteStartStopStatus,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReserveNowResponse {
+    pub status: ReservationStatus,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResetResponse {
+    pub status: ResetStatus,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SendLocalListResponse {
+    pub status: UpdateStatus,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetChargingProfileResponse {
+    pub status: ChargingProfileStatus,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartTransactionResponse {
+    pub id_tag_info: IdTagInfo,
+    pub transaction_id: i32,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusNotificationResponse {}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StopTransactionResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_tag_info: Option<IdTagInfo>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TriggerMessageResponse {
+    pub status: TriggerMessageStatus,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnlockConnectorResponse {
+    pub status: UnlockStatus,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateFirmwareResponse {}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClearCacheResponse {
+    pub status: ClearCacheStatus,
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::call_error::ErrorCode;
+    #[cfg(feature = "v201")]
+    use crate::ci_string::CiString;
+    use crate::types::AuthorizationStatus;
+    use serde_json::json;
+
+    #[test]
+    fn given_valid_boot_notification_response__when_deserializing__then_ok() {
+        let json = r#"
+[
+    3,
+    "19223201",
+    "BootNotification",
+    {
+        "status": "Accepted",
+        "currentTime": "2019-08-24T14:15:22Z",
+        "interval": 0
+    }
+]
+"#;
+
+        let result = serde_json::from_str::<RawOcppMessage>(json).unwrap();
+        let result = convert(ProtocolVersion::Ocpp16, result);
+        println!("{:#?}", result);
+        assert!(result.is_ok());
+        let expected = OcppEvent {
+            call_id: CallId("19223201".to_string()),
+            message: OcppMessage::Response(OcppResponse::BootNotification(
+                BootNotificationResponse {
+                    status: RegistrationStatus::Accepted,
+                    current_time: crate::datetime::parse("2019-08-24T14:15:22Z").unwrap(),
+                    interval: 0,
+                },
+            )),
+        };
+
+        assert_eq!(result.unwrap(), expected);
+    }
+
+    #[test]
+    fn given_valid_cancel_reservation_response__when_deserializing__then_ok() {
+        let json = r#"
+[
+    3,
+    "19223202",
+    "CancelReservation",
+    {
+        "status": "Accepted"
+    }
+]
+"#;
+
+        let result = serde_json::from_str::<RawOcppMessage>(json).unwrap();
+        let result = convert(ProtocolVersion::Ocpp16, result);
+        println!("{:#?}", result);
+        assert!(result.is_ok());
+        let expected = OcppEvent {
+            call_id: CallId("19223202".to_string()),
+            message: OcppMessage::Response(OcppResponse::CancelReservation(
+                CancelReservationResponse {
+                    status: CancelReservationStatus::Accepted,
+                },
+            )),
+        };
+
+        assert_eq!(result.unwrap(), expected);
+    }
+
+    #[test]
+    fn given_start_transaction_and_heartbeat_responses__when_deserializing__then_fields_kept() {
+        let start = RawOcppMessage {
+            message_type: 3,
+            call_id: CallId("19223203".to_string()),
+            action: "StartTransaction".to_string(),
+            payload: json!({
+                "idTagInfo": {"status": "ConcurrentTx", "parentIdTag": "PARENT"},
+                "transactionId": 42
+            }),
+        };
+        let heartbeat = RawOcppMessage {
+            message_type: 3,
+            call_id: CallId("19223204".to_string()),
+            action: "Heartbeat".to_string(),
+            payload: json!({"currentTime": "2019-08-24T14:15:22Z"}),
+        };
+
+        let start = convert(ProtocolVersion::Ocpp16, start).unwrap();
+        let heartbeat = convert(ProtocolVersion::Ocpp16, heartbeat).unwrap();
+
+        let OcppMessage::Response(OcppResponse::StartTransaction(start)) = start.message else {
+            panic!("expected StartTransaction, got {:?}", start);
+        };
+        assert_eq!(start.transaction_id, 42);
+        assert_eq!(start.id_tag_info.status, AuthorizationStatus::ConcurrentTx);
+        assert_eq!(start.id_tag_info.parent_id_tag.unwrap(), "PARENT");
+        assert_eq!(
+            heartbeat.message,
+            OcppMessage::Response(OcppResponse::Heartbeat(HeartbeatResponse {
+                current_time: crate::datetime::parse("2019-08-24T14:15:22Z").unwrap(),
+            }))
+        );
+    }
+
+    #[test]
+    fn given_valid_clear_cache_response__when_deserializing__then_ok() {
+        let json = r#"
+[
+  3,
+  "19223201",
+  "ClearCache",
+  {
+    "status": "Rejected"
+  }
+]
+"#;
+
+        let result = serde_json::from_str::<RawOcppMessage>(json).unwrap();
+        let result = convert(ProtocolVersion::Ocpp16, result);
+        println!("{:#?}", result);
+        assert!(result.is_ok());
+        let expected = OcppEvent {
+            call_id: CallId("19223201".to_string()),
+            message: OcppMessage::Response(OcppResponse::ClearCache(ClearCacheResponse {
+                status: ClearCacheStatus::Rejected,
+            })),
+        };
+
+        assert_eq!(result.unwrap(), expected);
+    }
+
+    #[test]
+    fn given_valid_raw_ocpp_message_when_convert__then_ok() {
+        // let mut map = serde_json::Map::new();
+        // map.insert("status".to_string(),Value::String("Accepted".to_string()));
+        // map.insert("currentTime".to_string(),Value::String("12345".to_string()));
+        // map.insert("interval".to_string(),Value::Number(serde_json::Number::from(1234)));
+
+        let rawOcppMessage = RawOcppMessage {
+            message_type: 3,
+            call_id: CallId("192232".to_string()),
+            action: "BootNotification".to_string(),
+            // payload: Value::Object(map)
+            payload: json!({
+                    "status": "Accepted",
+                    "currentTime": "2019-08-24T14:15:22Z",
+                    "interval": 0,
+            }),
+        };
+
+        let expected = OcppEvent {
+            call_id: CallId("192232".to_string()),
+            message: OcppMessage::Response(OcppResponse::BootNotification(
+                BootNotificationResponse {
+                    status: RegistrationStatus::Accepted,
+                    current_time: crate::datetime::parse("2019-08-24T14:15:22Z").unwrap(),
+                    interval: 0,
+                },
+            )),
+        };
+        let ocppEvent = convert(ProtocolVersion::Ocpp16, rawOcppMessage).unwrap();
+
+        assert_eq!(ocppEvent, expected);
+    }
+
+    #[test]
+    fn given_valid_data_transfer_request__when_converting__then_ok() {
+        let json = r#"
+[
+  2,
+  "19223203",
+  "DataTransfer",
+  {
+    "vendorId": "com.example",
+    "messageId": "SetLedColor",
+    "data": "{\"color\":\"red\"}"
+  }
+]
+"#;
+
+        let result = serde_json::from_str::<RawOcppMessage>(json).unwrap();
+        let result = convert(ProtocolVersion::Ocpp16, result);
+        assert!(result.is_ok());
+        let expected = OcppEvent {
+            call_id: CallId("19223203".to_string()),
+            message: OcppMessage::Request(OcppRequest::DataTransfer(DataTransferRequest {
+                vendor_id: CiString255Type::new("com.example").unwrap(),
+                message_id: Some(CiString50Type::new("SetLedColor").unwrap()),
+                data: Some(r#"{"color":"red"}"#.to_string()),
+            })),
+        };
+
+        assert_eq!(result.unwrap(), expected);
+    }
+
+    #[test]
+    fn given_status_notification_with_info__when_converting__then_typed() {
+        let raw = RawOcppMessage {
+            message_type: 2,
+            call_id: CallId("19223205".to_string()),
+            action: "StatusNotification".to_string(),
+            payload: json!({
+                "connectorId": 1,
+                "errorCode": "NoError",
+                "info": "LPC limited to 4200 W",
+                "status": "SuspendedEVSE"
+            }),
+        };
+
+        let ocppEvent = convert(ProtocolVersion::Ocpp16, raw).unwrap();
+
+        let OcppMessage::Request(OcppRequest::StatusNotification(request)) = ocppEvent.message
+        else {
+            panic!("untyped {:?}", ocppEvent.message);
+        };
+        assert_eq!(request.status, ChargePointStatus::SuspendedEvse);
+        assert_eq!(request.info.unwrap().as_str(), "LPC limited to 4200 W");
+    }
+
+    #[test]
+    fn given_untyped_request__when_converting__then_other() {
+        let raw = RawOcppMessage {
+            message_type: 2,
+            call_id: CallId("19223204".to_string()),
+            action: "ClearCache".to_string(),
+            payload: json!({}),
+        };
+
+        let ocppEvent = convert(ProtocolVersion::Ocpp16, raw).unwrap();
+
+        assert_eq!(
+            ocppEvent.message,
+            OcppMessage::Request(OcppRequest::Other {
+                action: "ClearCache".to_string(),
+                payload: json!({}),
+            })
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "v201")]
+    fn given_ocpp201_boot_notification_response__when_converting__then_v201_catalogue() {
+        let raw = RawOcppMessage {
+            message_type: 3,
+            call_id: CallId("19223205".to_string()),
+            action: "BootNotification".to_string(),
+            payload: json!({
+                "currentTime": "2019-08-24T14:15:22Z",
+                "interval": 300,
+                "status": "Pending",
+                "statusInfo": {"reasonCode": "NotYetKnown"}
+            }),
+        };
+
+        let ocppEvent = convert(ProtocolVersion::Ocpp201, raw).unwrap();
+
+        assert_eq!(
+            ocppEvent.message,
+            OcppMessage::V201Response(v201::OcppResponse::BootNotification(
+                v201::messages::BootNotificationResponse {
+                    current_time: crate::datetime::parse("2019-08-24T14:15:22Z").unwrap(),
+                    interval: 300,
+                    status: v201::types::RegistrationStatus::Pending,
+                    status_info: Some(v201::types::StatusInfo {
+                        reason_code: CiString::new("NotYetKnown").unwrap(),
+                        additional_info: None,
+                    }),
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn given_change_availability_response__when_converting__then_version_shape() {
+        let raw = |payload| RawOcppMessage {
+            message_type: 3,
+            call_id: CallId("19223207".to_string()),
+            action: "ChangeAvailability".to_string(),
+            payload,
+        };
+
+        let ocpp16 = convert(ProtocolVersion::Ocpp16, raw(json!({"status": "Scheduled"}))).unwrap();
+
+        assert_eq!(
+            ocpp16.message,
+            OcppMessage::Response(OcppResponse::ChangeAvailability(
+                ChangeAvailabilityResponse {
+                    status: AvailabilityStatus::Scheduled,
+                }
+            ))
+        );
+        #[cfg(feature = "v201")]
+        {
+            let payload = json!({
+                "status": "Rejected",
+                "statusInfo": {"reasonCode": "InTransaction"}
+            });
+            let ocpp201 = convert(ProtocolVersion::Ocpp201, raw(payload)).unwrap();
+            assert_eq!(
+                ocpp201.message,
+                OcppMessage::V201Response(v201::OcppResponse::ChangeAvailability(
+                    v201::messages::ChangeAvailabilityResponse {
+                        status: v201::types::ChangeAvailabilityStatus::Rejected,
+                        status_info: Some(v201::types::StatusInfo {
+                            reason_code: CiString::new("InTransaction").unwrap(),
+                            additional_info: None,
+                        }),
+                    }
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn given_unknown_response_action__when_converting__then_unknown_action() {
+        let raw = RawOcppMessage {
+            message_type: 3,
+            call_id: CallId("19223206".to_string()),
+            action: "Teleport".to_string(),
+            payload: json!({}),
+        };
+
+        let result = convert(ProtocolVersion::Ocpp16, raw);
+
+        assert!(matches!(result, Err(OcppError::UnknownAction(action)) if action == "Teleport"));
+    }
+
+    #[test]
+    fn given_broken_payload__when_converting__then_payload_error_with_constraint_code() {
+        let raw = RawOcppMessage {
+            message_type: 3,
+            call_id: CallId("19223207".to_string()),
+            action: "BootNotification".to_string(),
+            payload: json!({"currentTime": "2019-08-24T14:15:22Z", "interval": 300}),
+        };
+
+        let error = convert(ProtocolVersion::Ocpp16, raw).unwrap_err();
+
+        assert!(matches!(error, OcppError::Payload { .. }));
+        assert_eq!(error.error_code(), ErrorCode::OccurenceConstraintViolation);
+    }
+
+    #[test]
+    fn given_call_error_frame__when_converting__then_unsupported_message_type() {
+        let raw = RawOcppMessage {
+            message_type: 4,
+            call_id: CallId("19223208".to_string()),
+            action: "Heartbeat".to_string(),
+            payload: json!({}),
+        };
+
+        let error = convert(ProtocolVersion::Ocpp201, raw).unwrap_err();
+
+        assert!(matches!(error, OcppError::UnknownMessageType(4)));
+        assert_eq!(error.error_code(), ErrorCode::MessageTypeNotSupported);
+    }
+
+    #[test]
+    fn given_too_long_vendor_id__when_converting__then_property_constraint_violation() {
+        let raw = RawOcppMessage {
+            message_type: 2,
+            call_id: CallId("19223209".to_string()),
+            action: "DataTransfer".to_string(),
+            payload: json!({"vendorId": "x".repeat(256)}),
+        };
+
+        let error = convert(ProtocolVersion::Ocpp16, raw).unwrap_err();
+
+        assert_eq!(error.error_code(), ErrorCode::PropertyConstraintViolation);
+        assert_eq!(
+            error.to_string(),
+            "invalid DataTransfer payload: invalid length 256, expected at most 255 characters"
+        );
+    }
+}
@@ -0,0 +1,275 @@
+///// OCPP-J frames as they are on the wire, one shape per message type /////
+use std::fmt;
+
+use serde::de::{self, SeqAccess, Visitor};
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::call_error::{CallError, ErrorCode};
+use crate::error::OcppError;
+use crate::raw_ocpp_message::RawOcppMessage;
+use crate::{CallId, ProtocolVersion};
+
+/// A frame is told apart by its message type, the number of elements follows from it
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum OcppFrame {
+    /// `[2, "<call id>", "<action>", {<payload>}]`
+    Call {
+        call_id: CallId,
+        action: String,
+        payload: serde_json::Value,
+    },
+    /// `[3, "<call id>", {<payload>}]`, the action is the one of the answered call
+    CallResult {
+        call_id: CallId,
+        payload: serde_json::Value,
+    },
+    /// `[4, "<call id>", "<error code>", "<description>", {<details>}]`
+    CallError {
+        call_id: CallId,
+        /// Spelled the way the version of the connection expects
+        error_code: String,
+        error_description: String,
+        error_details: serde_json::Value,
+    },
+}
+
+impl OcppFrame {
+    pub fn call_id(&self) -> &CallId {
+        match self {
+            OcppFrame::Call { call_id, .. }
+            | OcppFrame::CallResult { call_id, .. }
+            | OcppFrame::CallError { call_id, .. } => call_id,
+        }
+    }
+
+    pub fn call_error(error: &CallError, version: ProtocolVersion) -> Self {
+        OcppFrame::CallError {
+            call_id: error.call_id.clone(),
+            error_code: error.error_code.as_str(version).to_string(),
+            error_description: error.error_description.clone(),
+            error_details: error.error_details.clone(),
+        }
+    }
+
+    /// The raw message of a call, or of a result answering a call of `action`. A CALLERROR
+    /// has no room in a raw message and is returned as error, unknown error codes become
+    /// [`ErrorCode::GenericError`].
+    pub fn into_raw(self, action: impl FnOnce() -> String) -> Result<RawOcppMessage, CallError> {
+        match self {
+            OcppFrame::Call {
+                call_id,
+                action,
+                payload,
+            } => Ok(RawOcppMessage {
+                message_type: 2,
+                call_id,
+                action,
+                payload,
+            }),
+            OcppFrame::CallResult { call_id, payload } => Ok(RawOcppMessage {
+                message_type: 3,
+                call_id,
+                action: action(),
+                payload,
+            }),
+            OcppFrame::CallError {
+                call_id,
+                error_code,
+                error_description,
+                error_details,
+            } => Err(CallError {
+                call_id,
+                error_code: ErrorCode::parse(&error_code).unwrap_or(ErrorCode::GenericError),
+                error_description,
+                error_details,
+            }),
+        }
+    }
+}
+
+/// Calls and results only, the action of a result is dropped
+impl TryFrom<RawOcppMessage> for OcppFrame {
+    type Error = OcppError;
+
+    fn try_from(raw: RawOcppMessage) -> Result<Self, OcppError> {
+        match raw.message_type {
+            2 => Ok(OcppFrame::Call {
+                call_id: raw.call_id,
+                action: raw.action,
+                payload: raw.payload,
+            }),
+            3 => Ok(OcppFrame::CallResult {
+                call_id: raw.call_id,
+                payload: raw.payload,
+            }),
+            message_type => Err(OcppError::UnknownMessageType(message_type)),
+        }
+    }
+}
+
+impl Serialize for OcppFrame {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            OcppFrame::Call {
+                call_id,
+                action,
+                payload,
+            } => {
+                let mut seq = serializer.serialize_seq(Some(4))?;
+                seq.serialize_element(&2)?;
+                seq.serialize_element(call_id)?;
+                seq.serialize_element(action)?;
+                seq.serialize_element(payload)?;
+                seq.end()
+            }
+            OcppFrame::CallResult { call_id, payload } => {
+                let mut seq = serializer.serialize_seq(Some(3))?;
+                seq.serialize_element(&3)?;
+                seq.serialize_element(call_id)?;
+                seq.serialize_element(payload)?;
+                seq.end()
+            }
+            OcppFrame::CallError {
+                call_id,
+                error_code,
+                error_description,
+                error_details,
+            } => {
+                let mut seq = serializer.serialize_seq(Some(5))?;
+                seq.serialize_element(&4)?;
+                seq.serialize_element(call_id)?;
+                seq.serialize_element(error_code)?;
+                seq.serialize_element(error_description)?;
+                seq.serialize_element(error_details)?;
+                seq.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for OcppFrame {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct OcppFrameVisitor;
+
+        impl<'de> Visitor<'de> for OcppFrameVisitor {
+            type Value = OcppFrame;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str(
+                    "an OCPP frame: [2, call_id, action, payload], [3, call_id, payload] or \
+                     [4, call_id, error_code, error_description, error_details]",
+                )
+            }
+
+            fn visit_seq<V: SeqAccess<'de>>(self, mut seq: V) -> Result<OcppFrame, V::Error> {
+                let message_type = seq
+                    .next_element::<u8>()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let call_id = seq
+                    .next_element::<CallId>()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                let (frame, len) = match message_type {
+                    2 => {
+                        let action = seq
+                            .next_element()?
+                            .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+                        let payload = seq
+                            .next_element()?
+                            .ok_or_else(|| de::Error::invalid_length(3, &self))?;
+                        let frame = OcppFrame::Call {
+                            call_id,
+                            action,
+                            payload,
+                        };
+                        (frame, 4)
+                    }
+                    3 => {
+                        let payload = seq
+                            .next_element()?
+                            .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+                        (OcppFrame::CallResult { call_id, payload }, 3)
+                    }
+                    4 => {
+                        let error_code = seq
+                            .next_element()?
+                            .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+                        let error_description = seq
+                            .next_element()?
+                            .ok_or_else(|| de::Error::invalid_length(3, &self))?;
+                        let error_details = seq
+                            .next_element()?
+                            .ok_or_else(|| de::Error::invalid_length(4, &self))?;
+                        let frame = OcppFrame::CallError {
+                            call_id,
+                            error_code,
+                            error_description,
+                            error_details,
+                        };
+                        (frame, 5)
+                    }
+                    message_type => {
+                        return Err(de::Error::custom(format!(
+                            "unknown message type {}",
+                            message_type
+                        )));
+                    }
+                };
+                if seq.next_element::<de::IgnoredAny>()?.is_some() {
+                    return Err(de::Error::invalid_length(len + 1, &self));
+                }
+                Ok(frame)
+            }
+        }
+
+        deserializer.deserialize_seq(OcppFrameVisitor)
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn given_frames_of_every_type__when_round_tripping__then_element_count_kept() {
+        for frame in [
+            json!([2, "1", "Heartbeat", {}]),
+            json!([3, "1", {"currentTime": "2019-08-24T14:15:22Z"}]),
+            json!([4, "1", "NotImplemented", "unknown action", {}]),
+        ] {
+            let parsed = serde_json::from_value::<OcppFrame>(frame.clone()).unwrap();
+
+            assert_eq!(parsed.call_id().as_str(), "1");
+            assert_eq!(serde_json::to_value(&parsed).unwrap(), frame);
+        }
+    }
+
+    #[test]
+    fn given_element_count_not_matching_type__when_deserializing__then_error() {
+        assert!(serde_json::from_value::<OcppFrame>(json!([3, "1", "Heartbeat", {}])).is_err());
+        assert!(serde_json::from_value::<OcppFrame>(json!([4, "1", "GenericError"])).is_err());
+        assert!(serde_json::from_value::<OcppFrame>(json!([5, "1", {}])).is_err());
+    }
+
+    #[test]
+    fn given_call_error_frame__when_converting__then_code_parsed_in_both_spellings() {
+        let frame = serde_json::from_value::<OcppFrame>(json!([
+            4,
+            "1",
+            "OccurrenceConstraintViolation",
+            "missing field",
+            {}
+        ]))
+        .unwrap();
+
+        let error = frame.clone().into_raw(String::new).unwrap_err();
+
+        assert_eq!(error.error_code, ErrorCode::OccurenceConstraintViolation);
+        assert_eq!(
+            OcppFrame::call_error(&error, ProtocolVersion::Ocpp201),
+            frame
+        );
+    }
+}
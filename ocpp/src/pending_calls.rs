@@ -0,0 +1,82 @@
+///// Correlation of CALLRESULTs with the CALLs sent before /////
+use std::collections::HashMap;
+
+use crate::CallId;
+use crate::error::OcppError;
+use crate::raw_ocpp_message::RawOcppMessage;
+
+/// Actions of the sent CALLs which are still waiting for their result, keyed by call id
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct PendingCalls {
+    calls: HashMap<CallId, String>,
+}
+
+impl PendingCalls {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remembers an outgoing CALL, other message types are ignored
+    pub fn sent(&mut self, message: &RawOcppMessage) {
+        if message.message_type == 2 {
+            self.calls
+                .insert(message.call_id.clone(), message.action.clone());
+        }
+    }
+
+    /// Matches a received CALLRESULT or CALLERROR with its CALL, which is no longer pending
+    /// afterwards. Returns the action of the call, the result is decoded with it.
+    pub fn received(&mut self, call_id: &CallId) -> Result<String, OcppError> {
+        self.calls
+            .remove(call_id)
+            .ok_or_else(|| OcppError::UnknownCallId(call_id.clone()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.calls.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(message_type: u8, call_id: &str, action: &str) -> RawOcppMessage {
+        RawOcppMessage {
+            message_type,
+            call_id: CallId(call_id.to_string()),
+            action: action.to_string(),
+            payload: serde_json::json!({}),
+        }
+    }
+
+    #[test]
+    fn given_sent_call__when_result_arrives__then_action_and_no_longer_pending() {
+        let mut pending = PendingCalls::new();
+        pending.sent(&message(2, "1", "Heartbeat"));
+
+        assert_eq!(
+            pending.received(&CallId("1".to_string())).unwrap(),
+            "Heartbeat"
+        );
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn given_uncorrelated_result__when_received__then_correlation_error() {
+        let mut pending = PendingCalls::new();
+        pending.sent(&message(2, "1", "Heartbeat"));
+        pending.sent(&message(3, "2", "Authorize"));
+
+        assert!(matches!(
+            pending.received(&CallId("2".to_string())),
+            Err(OcppError::UnknownCallId(call_id)) if call_id == CallId("2".to_string())
+        ));
+        assert_eq!(pending.len(), 1);
+    }
+}
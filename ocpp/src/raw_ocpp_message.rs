@@ -1,31 +1,28 @@
 ///// Stage 1: Deserialize a raw OCPP message /////
+use crate::CallId;
+
 #[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct RawOcppMessage {
     pub message_type: u8,
-    pub call_id: String,
+    pub call_id: CallId,
     pub action: String,
     pub payload: serde_json::Value,
 }
 
 // macro is used for all the tests
+#[cfg(test)]
 macro_rules! ocpp_test {
     ($name:ident, $action:expr, $payload:expr) => {
         #[test]
         fn $name() {
-            let json = serde_json::json!([
-                3,
-                "19223201",
-                $action,
-                $payload
-            ])
-            .to_string();
+            let json = serde_json::json!([3, "19223201", $action, $payload]).to_string();
 
             let result = serde_json::from_str::<RawOcppMessage>(&json);
             assert!(result.is_ok());
 
             let expected = RawOcppMessage {
                 message_type: 3,
-                call_id: "19223201".to_string(),
+                call_id: CallId("19223201".to_string()),
                 action: $action.to_string(),
                 payload: $payload,
             };
@@ -35,13 +32,11 @@ macro_rules! ocpp_test {
     };
 }
 
-
 //test serialization of all event types
 
-
-
 #[allow(non_snake_case)]
 #[cfg(test)]
+mod tests {
     use super::*;
 
     #[test]
@@ -64,7 +59,7 @@ macro_rules! ocpp_test {
         assert!(result.is_ok());
         let expected = RawOcppMessage {
             message_type: 3,
-            call_id: "19223201".to_string(),
+            call_id: CallId("19223201".to_string()),
             action: "BootNotification".to_string(),
             payload: serde_json::json!({
                 "status": "Accepted",
@@ -75,4 +70,10 @@ macro_rules! ocpp_test {
 
         assert_eq!(result.unwrap(), expected);
     }
+
+    ocpp_test!(
+        given_valid_data_transfer_response__when_deserializing__then_ok,
+        "DataTransfer",
+        serde_json::json!({"status": "Accepted", "data": "{\"on\":true}"})
+    );
 }
@@ -0,0 +1,489 @@
+///// JSON Schema validation of OCPP payloads /////
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use jsonschema::Validator;
+use jsonschema::error::ValidationErrorKind;
+
+use crate::call_error::{CallError, ErrorCode};
+use crate::{CallId, ProtocolVersion};
+
+/// Whether a payload is the request of an action or the response to it
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum PayloadKind {
+    Request,
+    Response,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ValidationMode {
+    /// Every schema violation rejects the payload
+    Strict,
+    /// Unexpected properties and violated length or range limits are reported but tolerated,
+    /// anything which would break decoding the payload is still rejected
+    Lenient,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Violation {
+    pub error_code: ErrorCode,
+    /// JSON pointer to the offending value, i.e. `/idTagInfo/status`
+    pub path: String,
+    pub description: String,
+}
+
+impl Violation {
+    pub fn to_call_error(&self, call_id: CallId) -> CallError {
+        CallError {
+            call_id,
+            error_code: self.error_code,
+            error_description: self.description.clone(),
+            error_details: serde_json::json!({ "path": self.path }),
+        }
+    }
+}
+
+/// `(action, request schema, response schema)` of every action in `$dir`, file names follow
+/// the OCPP releases, `$request` is the suffix of the request schemas
+macro_rules! schemas {
+    ($dir:literal, $request:literal: $($action:literal),* $(,)?) => {
+        &[$((
+            $action,
+            include_str!(concat!("../schemas/", $dir, "/", $action, $request, ".json")),
+            include_str!(concat!("../schemas/", $dir, "/", $action, "Response.json")),
+        )),*]
+    };
+}
+
+/// The 1.6 request schemas carry no suffix
+const V16_SCHEMAS: &[(&str, &str, &str)] = schemas!("v16", "":
+    "Authorize",
+    "BootNotification",
+    "CancelReservation",
+    "ChangeAvailability",
+    "ChangeConfiguration",
+    "ClearCache",
+    "ClearChargingProfile",
+    "DataTransfer",
+    "DiagnosticsStatusNotification",
+    "FirmwareStatusNotification",
+    "GetCompositeSchedule",
+    "GetConfiguration",
+    "GetDiagnostics",
+    "GetLocalListVersion",
+    "Heartbeat",
+    "MeterValues",
+    "RemoteStartTransaction",
+    "RemoteStopTransaction",
+    "ReserveNow",
+    "Reset",
+    "SendLocalList",
+    "SetChargingProfile",
+    "StartTransaction",
+    "StatusNotification",
+    "StopTransaction",
+    "TriggerMessage",
+    "UnlockConnector",
+    "UpdateFirmware",
+);
+
+const V201_SCHEMAS: &[(&str, &str, &str)] = schemas!("v201", "Request":
+    "Authorize",
+    "BootNotification",
+    "CancelReservation",
+    "CertificateSigned",
+    "ChangeAvailability",
+    "ClearCache",
+    "ClearChargingProfile",
+    "ClearDisplayMessage",
+    "ClearVariableMonitoring",
+    "ClearedChargingLimit",
+    "CostUpdated",
+    "CustomerInformation",
+    "DataTransfer",
+    "DeleteCertificate",
+    "FirmwareStatusNotification",
+    "Get15118EVCertificate",
+    "GetBaseReport",
+    "GetCertificateStatus",
+    "GetChargingProfiles",
+    "GetCompositeSchedule",
+    "GetDisplayMessages",
+    "GetInstalledCertificateIds",
+    "GetLocalListVersion",
+    "GetLog",
+    "GetMonitoringReport",
+    "GetReport",
+    "GetTransactionStatus",
+    "GetVariables",
+    "Heartbeat",
+    "InstallCertificate",
+    "LogStatusNotification",
+    "MeterValues",
+    "NotifyChargingLimit",
+    "NotifyCustomerInformation",
+    "NotifyDisplayMessages",
+    "NotifyEVChargingNeeds",
+    "NotifyEVChargingSchedule",
+    "NotifyEvent",
+    "NotifyMonitoringReport",
+    "NotifyReport",
+    "PublishFirmware",
+    "PublishFirmwareStatusNotification",
+    "ReportChargingProfiles",
+    "RequestStartTransaction",
+    "RequestStopTransaction",
+    "ReservationStatusUpdate",
+    "ReserveNow",
+    "Reset",
+    "SecurityEventNotification",
+    "SendLocalList",
+    "SetChargingProfile",
+    "SetDisplayMessage",
+    "SetMonitoringBase",
+    "SetMonitoringLevel",
+    "SetNetworkProfile",
+    "SetVariableMonitoring",
+    "SetVariables",
+    "SignCertificate",
+    "StatusNotification",
+    "TransactionEvent",
+    "TriggerMessage",
+    "UnlockConnector",
+    "UnpublishFirmware",
+    "UpdateFirmware",
+);
+
+/// Checks payloads against the embedded OCPP schemas, per version, action and direction.
+///
+/// Every action of 1.6 and 2.0.1 has a schema, others are rejected with `NotImplemented` in
+/// strict mode and passed through unchecked in lenient mode.
+pub struct SchemaValidator {
+    mode: ValidationMode,
+    validators: HashMap<(ProtocolVersion, &'static str, PayloadKind), Validator>,
+}
+
+impl SchemaValidator {
+    pub fn new(mode: ValidationMode) -> Self {
+        let mut validators = HashMap::new();
+        let catalogues = [
+            (ProtocolVersion::Ocpp16, V16_SCHEMAS),
+            (ProtocolVersion::Ocpp201, V201_SCHEMAS),
+            (ProtocolVersion::Ocpp21, V201_SCHEMAS),
+        ];
+        for (version, schemas) in catalogues {
+            for (action, request, response) in schemas {
+                validators.insert((version, *action, PayloadKind::Request), compile(request));
+                validators.insert((version, *action, PayloadKind::Response), compile(response));
+            }
+        }
+        Self { mode, validators }
+    }
+
+    pub fn mode(&self) -> ValidationMode {
+        self.mode
+    }
+
+    pub fn has_schema(&self, version: ProtocolVersion, action: &str, kind: PayloadKind) -> bool {
+        self.validators.contains_key(&(version, action, kind))
+    }
+
+    /// Returns the tolerated violations, or the first one rejecting the payload
+    pub fn validate(
+        &self,
+        version: ProtocolVersion,
+        action: &str,
+        kind: PayloadKind,
+        payload: &serde_json::Value,
+    ) -> Result<Vec<Violation>, Violation> {
+        if !payload.is_object() {
+            return Err(Violation {
+                error_code: ErrorCode::FormationViolation,
+                path: String::new(),
+                description: "payload is not a JSON object".to_string(),
+            });
+        }
+        let Some(validator) = self.validators.get(&(version, action, kind)) else {
+            if self.mode == ValidationMode::Strict {
+                return Err(Violation {
+                    error_code: ErrorCode::NotImplemented,
+                    path: String::new(),
+                    description: format!("no schema for action {}", action),
+                });
+            }
+            return Ok(Vec::new());
+        };
+        let mut tolerated = Vec::new();
+        for (violation, tolerable) in validator.iter_errors(payload).map(violation) {
+            if self.mode == ValidationMode::Strict || !tolerable {
+                return Err(violation);
+            }
+            tolerated.push(violation);
+        }
+        Ok(tolerated)
+    }
+}
+
+/// CALLERROR code of a payload which does not decode into its typed message, taken from the
+/// schema violation breaking it. `FormationViolation` if the schema has no objection.
+pub fn classify(
+    version: ProtocolVersion,
+    action: &str,
+    kind: PayloadKind,
+    payload: &serde_json::Value,
+) -> ErrorCode {
+    static VALIDATOR: LazyLock<SchemaValidator> =
+        LazyLock::new(|| SchemaValidator::new(ValidationMode::Lenient));
+    match VALIDATOR.validate(version, action, kind, payload) {
+        Err(violation) => violation.error_code,
+        Ok(tolerated) => tolerated
+            .first()
+            .map_or(ErrorCode::FormationViolation, |violation| {
+                violation.error_code
+            }),
+    }
+}
+
+fn compile(schema: &str) -> Validator {
+    let schema = serde_json::from_str(schema).expect("embedded schema is valid JSON");
+    jsonschema::validator_for(&schema).expect("embedded schema is a valid JSON schema")
+}
+
+/// Maps a schema error onto its CALLERROR code, together with whether lenient mode tolerates it
+fn violation(error: jsonschema::ValidationError<'_>) -> (Violation, bool) {
+    let mut path = error.instance_path.as_str().to_string();
+    let (error_code, description, tolerable) = match &error.kind {
+        ValidationErrorKind::Type { .. } => {
+            (ErrorCode::TypeConstraintViolation, error.to_string(), false)
+        }
+        ValidationErrorKind::Required { property } => {
+            let property = property.as_str().unwrap_or_default();
+            path = format!("{}/{}", path, property);
+            let description = format!("missing required property {}", property);
+            (ErrorCode::OccurenceConstraintViolation, description, false)
+        }
+        ValidationErrorKind::AdditionalProperties { unexpected } => {
+            let description = format!("unexpected properties {}", unexpected.join(", "));
+            (ErrorCode::OccurenceConstraintViolation, description, true)
+        }
+        ValidationErrorKind::MinItems { .. } | ValidationErrorKind::MaxItems { .. } => (
+            ErrorCode::OccurenceConstraintViolation,
+            error.to_string(),
+            true,
+        ),
+        ValidationErrorKind::Enum { .. } => {
+            let description = format!("invalid enum value {}", error.instance);
+            (ErrorCode::PropertyConstraintViolation, description, false)
+        }
+        _ => (
+            ErrorCode::PropertyConstraintViolation,
+            error.to_string(),
+            true,
+        ),
+    };
+    let violation = Violation {
+        error_code,
+        path,
+        description,
+    };
+    (violation, tolerable)
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn validate(
+        mode: ValidationMode,
+        version: ProtocolVersion,
+        action: &str,
+        kind: PayloadKind,
+        payload: serde_json::Value,
+    ) -> Result<Vec<Violation>, Violation> {
+        SchemaValidator::new(mode).validate(version, action, kind, &payload)
+    }
+
+    #[test]
+    fn given_valid_boot_notification__when_validating__then_ok() {
+        let result = validate(
+            ValidationMode::Strict,
+            ProtocolVersion::Ocpp16,
+            "BootNotification",
+            PayloadKind::Response,
+            json!({"status": "Accepted", "currentTime": "2019-08-24T14:15:22Z", "interval": 0}),
+        );
+
+        assert_eq!(result, Ok(vec![]));
+    }
+
+    #[test]
+    fn given_wrong_type__when_validating__then_type_constraint_violation() {
+        let result = validate(
+            ValidationMode::Lenient,
+            ProtocolVersion::Ocpp16,
+            "BootNotification",
+            PayloadKind::Response,
+            json!({"status": "Accepted", "currentTime": "2019-08-24T14:15:22Z", "interval": "60"}),
+        );
+
+        let violation = result.unwrap_err();
+        assert_eq!(violation.error_code, ErrorCode::TypeConstraintViolation);
+        assert_eq!(violation.path, "/interval");
+    }
+
+    #[test]
+    fn given_missing_property__when_validating__then_occurence_constraint_violation() {
+        let result = validate(
+            ValidationMode::Lenient,
+            ProtocolVersion::Ocpp16,
+            "Authorize",
+            PayloadKind::Response,
+            json!({"idTagInfo": {"expiryDate": "2019-08-24T14:15:22Z"}}),
+        );
+
+        let violation = result.unwrap_err();
+        assert_eq!(
+            violation.error_code,
+            ErrorCode::OccurenceConstraintViolation
+        );
+        assert_eq!(violation.path, "/idTagInfo/status");
+    }
+
+    #[test]
+    fn given_invalid_enum__when_validating__then_property_constraint_violation() {
+        let result = validate(
+            ValidationMode::Lenient,
+            ProtocolVersion::Ocpp201,
+            "StatusNotification",
+            PayloadKind::Request,
+            json!({
+                "timestamp": "2024-05-01T10:00:00Z",
+                "connectorStatus": "Charging",
+                "evseId": 1,
+                "connectorId": 1
+            }),
+        );
+
+        let violation = result.unwrap_err();
+        assert_eq!(violation.error_code, ErrorCode::PropertyConstraintViolation);
+        assert_eq!(violation.path, "/connectorStatus");
+    }
+
+    #[test]
+    fn given_sloppy_payload__when_validating__then_strict_rejects_and_lenient_tolerates() {
+        let payload = json!({"idTag": "ABCDEFGHIJKLMNOPQRSTUVWXYZ", "vendorExtension": true});
+
+        let strict = validate(
+            ValidationMode::Strict,
+            ProtocolVersion::Ocpp16,
+            "Authorize",
+            PayloadKind::Request,
+            payload.clone(),
+        );
+        let lenient = validate(
+            ValidationMode::Lenient,
+            ProtocolVersion::Ocpp16,
+            "Authorize",
+            PayloadKind::Request,
+            payload,
+        );
+
+        assert!(strict.is_err());
+        let tolerated = lenient.unwrap();
+        assert_eq!(tolerated.len(), 2);
+        assert!(
+            tolerated
+                .iter()
+                .any(|v| v.path == "/idTag"
+                    && v.error_code == ErrorCode::PropertyConstraintViolation)
+        );
+    }
+
+    #[test]
+    fn given_non_object_payload__when_validating__then_formation_violation() {
+        let violation = validate(
+            ValidationMode::Lenient,
+            ProtocolVersion::Ocpp16,
+            "Heartbeat",
+            PayloadKind::Request,
+            json!([]),
+        )
+        .unwrap_err();
+
+        assert_eq!(violation.error_code, ErrorCode::FormationViolation);
+        let frame = violation
+            .to_call_error(CallId("1".to_string()))
+            .to_frame(ProtocolVersion::Ocpp201);
+        assert_eq!(frame[2], "FormatViolation");
+    }
+
+    #[test]
+    fn given_csms_call__when_validating__then_checked_against_its_schema() {
+        let violation = validate(
+            ValidationMode::Lenient,
+            ProtocolVersion::Ocpp16,
+            "RemoteStartTransaction",
+            PayloadKind::Request,
+            json!({"connectorId": 1}),
+        )
+        .unwrap_err();
+        let v201 = validate(
+            ValidationMode::Strict,
+            ProtocolVersion::Ocpp201,
+            "Authorize",
+            PayloadKind::Request,
+            json!({"idToken": {"idToken": "04A2", "type": "ISO14443"}}),
+        );
+
+        assert_eq!(violation.path, "/idTag");
+        assert_eq!(v201, Ok(vec![]));
+    }
+
+    #[test]
+    fn given_action_without_schema__when_validating__then_strict_rejects_and_lenient_passes() {
+        let payload = json!({"vendorExtension": true});
+
+        let strict = validate(
+            ValidationMode::Strict,
+            ProtocolVersion::Ocpp16,
+            "VendorSpecific",
+            PayloadKind::Request,
+            payload.clone(),
+        );
+        let lenient = validate(
+            ValidationMode::Lenient,
+            ProtocolVersion::Ocpp16,
+            "VendorSpecific",
+            PayloadKind::Request,
+            payload,
+        );
+
+        assert_eq!(strict.unwrap_err().error_code, ErrorCode::NotImplemented);
+        assert_eq!(lenient, Ok(vec![]));
+    }
+
+    #[test]
+    fn given_complete_catalogues__when_compiling__then_every_action_has_a_schema() {
+        let validator = SchemaValidator::new(ValidationMode::Strict);
+
+        assert_eq!(V16_SCHEMAS.len(), 28);
+        assert_eq!(V201_SCHEMAS.len(), 64);
+        for action in ["Reset", "SetChargingProfile", "TriggerMessage"] {
+            assert!(validator.has_schema(ProtocolVersion::Ocpp16, action, PayloadKind::Request));
+            assert!(validator.has_schema(ProtocolVersion::Ocpp201, action, PayloadKind::Response));
+        }
+    }
+
+    #[test]
+    fn all_embedded_schemas_compile() {
+        let validator = SchemaValidator::new(ValidationMode::Strict);
+
+        assert_eq!(
+            validator.validators.len(),
+            2 * (V16_SCHEMAS.len() + 2 * V201_SCHEMAS.len())
+        );
+    }
+}
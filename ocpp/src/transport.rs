@@ -0,0 +1,626 @@
+///// WebSocket transport with OCPP subprotocol negotiation /////
+use std::error::Error;
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+use tungstenite::client::IntoClientRequest;
+use tungstenite::error::{ProtocolError, SubProtocolError};
+use tungstenite::http::HeaderValue;
+use tungstenite::http::header::SEC_WEBSOCKET_PROTOCOL;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+use crate::ProtocolVersion;
+use crate::call_error::CallError;
+use crate::error::OcppError;
+use crate::ocpp_event::{OcppEvent, convert};
+use crate::ocpp_frame::OcppFrame;
+use crate::pending_calls::PendingCalls;
+use crate::raw_ocpp_message::RawOcppMessage;
+use crate::schema::{PayloadKind, SchemaValidator, ValidationMode};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TransportConfig {
+    /// CSMS endpoint including the charge point id, i.e. `ws://csms.example/ocpp/CP001`
+    pub url: String,
+    /// Offered subprotocols, most preferred first
+    pub subprotocols: Vec<ProtocolVersion>,
+}
+
+#[derive(Debug)]
+pub enum TransportError {
+    /// The configuration does not offer any subprotocol
+    NoSubprotocolOffered,
+    /// The server accepted the connection without selecting any of the offered subprotocols
+    NoSubprotocolSelected {
+        offered: Vec<ProtocolVersion>,
+    },
+    /// The server selected a subprotocol which was not offered
+    UnexpectedSubprotocol(String),
+    WebSocket(Box<tungstenite::Error>),
+    /// A frame is not a valid OCPP message for the negotiated version, with the CALLERROR
+    /// to answer it with if it was a received call
+    Ocpp {
+        error: Box<OcppError>,
+        reply: Option<Box<CallError>>,
+    },
+    /// The other side answered the call of `action` with a CALLERROR
+    Rejected {
+        action: String,
+        error: Box<CallError>,
+    },
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransportError::NoSubprotocolOffered => write!(f, "no OCPP subprotocol configured"),
+            TransportError::NoSubprotocolSelected { offered } => write!(
+                f,
+                "server selected none of the offered subprotocols ({})",
+                offered_header(offered)
+            ),
+            TransportError::UnexpectedSubprotocol(selected) => {
+                write!(
+                    f,
+                    "server selected subprotocol {:?} which was not offered",
+                    selected
+                )
+            }
+            TransportError::WebSocket(err) => write!(f, "websocket error: {}", err),
+            TransportError::Ocpp { error, .. } => write!(f, "{}", error),
+            TransportError::Rejected { action, error } => write!(
+                f,
+                "{} rejected with {:?}: {}",
+                action, error.error_code, error.error_description
+            ),
+        }
+    }
+}
+
+impl Error for TransportError {}
+
+impl From<tungstenite::Error> for TransportError {
+    fn from(err: tungstenite::Error) -> Self {
+        TransportError::WebSocket(Box::new(err))
+    }
+}
+
+impl From<OcppError> for TransportError {
+    fn from(error: OcppError) -> Self {
+        TransportError::Ocpp {
+            error: Box::new(error),
+            reply: None,
+        }
+    }
+}
+
+/// Value of the `Sec-WebSocket-Protocol` request header, i.e. `"ocpp2.0.1, ocpp1.6"`
+pub fn offered_header(offered: &[ProtocolVersion]) -> String {
+    offered
+        .iter()
+        .map(ProtocolVersion::subprotocol)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Checks the `Sec-WebSocket-Protocol` response header against the offered subprotocols
+pub fn negotiate(
+    offered: &[ProtocolVersion],
+    selected: Option<&str>,
+) -> Result<ProtocolVersion, TransportError> {
+    let Some(selected) = selected.map(str::trim).filter(|s| !s.is_empty()) else {
+        return Err(TransportError::NoSubprotocolSelected {
+            offered: offered.to_vec(),
+        });
+    };
+    ProtocolVersion::from_subprotocol(selected)
+        .filter(|version| offered.contains(version))
+        .ok_or_else(|| TransportError::UnexpectedSubprotocol(selected.to_string()))
+}
+
+/// Whether a frame was sent or received by this end of the connection
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FrameDirection {
+    Sent,
+    Received,
+}
+
+/// Sees every frame going over a connection, i.e. to journal it. Recording cannot fail the
+/// connection, a recorder keeps its errors itself.
+pub trait FrameRecorder {
+    /// `action` is the one of the call, for received results and errors the one of the
+    /// answered call. It is empty if no call of that id is pending, and for sent CALLERRORs,
+    /// which the call with the same id precedes.
+    fn record(
+        &mut self,
+        direction: FrameDirection,
+        version: ProtocolVersion,
+        frame: &OcppFrame,
+        action: &str,
+    );
+}
+
+/// WebSocket connection to a CSMS, bound to the negotiated protocol version so every
+/// frame is decoded with the matching message catalogue.
+pub struct OcppConnection<S> {
+    socket: WebSocket<S>,
+    version: ProtocolVersion,
+    validator: Option<SchemaValidator>,
+    pending: PendingCalls,
+    recorder: Option<Box<dyn FrameRecorder + Send>>,
+}
+
+impl OcppConnection<MaybeTlsStream<TcpStream>> {
+    pub fn connect(config: &TransportConfig) -> Result<Self, TransportError> {
+        let request = handshake_request(config)?;
+        let (socket, response) =
+            tungstenite::connect(request).map_err(|err| map_handshake_error(err, config))?;
+        Self::accepted(
+            socket,
+            response.headers().get(SEC_WEBSOCKET_PROTOCOL),
+            config,
+        )
+    }
+}
+
+impl<S: Read + Write> OcppConnection<S> {
+    /// Performs the handshake over an already established stream
+    pub fn client(stream: S, config: &TransportConfig) -> Result<Self, TransportError> {
+        let request = handshake_request(config)?;
+        let (socket, response) = tungstenite::client(request, stream).map_err(|err| match err {
+            tungstenite::HandshakeError::Failure(err) => map_handshake_error(err, config),
+            tungstenite::HandshakeError::Interrupted(_) => {
+                tungstenite::Error::Io(std::io::ErrorKind::WouldBlock.into()).into()
+            }
+        })?;
+        Self::accepted(
+            socket,
+            response.headers().get(SEC_WEBSOCKET_PROTOCOL),
+            config,
+        )
+    }
+
+    fn accepted(
+        socket: WebSocket<S>,
+        selected: Option<&HeaderValue>,
+        config: &TransportConfig,
+    ) -> Result<Self, TransportError> {
+        let selected = selected.and_then(|value| value.to_str().ok());
+        let version = negotiate(&config.subprotocols, selected)?;
+        Ok(Self {
+            socket,
+            version,
+            validator: None,
+            pending: PendingCalls::new(),
+            recorder: None,
+        })
+    }
+
+    pub fn version(&self) -> ProtocolVersion {
+        self.version
+    }
+
+    /// Validates every sent and received payload against the embedded schemas
+    pub fn set_validation(&mut self, mode: ValidationMode) {
+        self.validator = Some(SchemaValidator::new(mode));
+    }
+
+    /// Passes every frame sent or received from now on to `recorder`, undecodable ones
+    /// included
+    pub fn set_recorder(&mut self, recorder: impl FrameRecorder + Send + 'static) {
+        self.recorder = Some(Box::new(recorder));
+    }
+
+    /// Sends a call or a result, a result goes out without its action
+    pub fn send(&mut self, message: &RawOcppMessage) -> Result<(), TransportError> {
+        self.validate(message)?;
+        let frame = OcppFrame::try_from(message.clone())?;
+        let text = serde_json::to_string(&frame).map_err(OcppError::Frame)?;
+        self.socket.send(Message::text(text))?;
+        self.pending.sent(message);
+        self.record(FrameDirection::Sent, &frame, &message.action);
+        Ok(())
+    }
+
+    /// Answers a received call with a CALLERROR, i.e. the `reply` of [`TransportError::Ocpp`]
+    pub fn send_call_error(&mut self, error: &CallError) -> Result<(), TransportError> {
+        let frame = OcppFrame::call_error(error, self.version);
+        let text = serde_json::to_string(&frame).map_err(OcppError::Frame)?;
+        self.socket.send(Message::text(text))?;
+        self.record(FrameDirection::Sent, &frame, "");
+        Ok(())
+    }
+
+    /// Blocks until the next OCPP message arrives, skipping control frames.
+    /// A CALLERROR answering a sent call is [`TransportError::Rejected`].
+    pub fn receive(&mut self) -> Result<OcppEvent, TransportError> {
+        loop {
+            let frame = match self.socket.read()? {
+                Message::Text(text) => serde_json::from_str::<OcppFrame>(&text),
+                Message::Binary(bytes) => serde_json::from_slice::<OcppFrame>(&bytes),
+                _ => continue,
+            }
+            .map_err(OcppError::Frame)?;
+            return self.decode(frame);
+        }
+    }
+
+    fn decode(&mut self, frame: OcppFrame) -> Result<OcppEvent, TransportError> {
+        let action = match &frame {
+            OcppFrame::Call { action, .. } => Ok(action.clone()),
+            _ => self.pending.received(frame.call_id()),
+        };
+        self.record(
+            FrameDirection::Received,
+            &frame,
+            action.as_deref().unwrap_or_default(),
+        );
+        let action = action?;
+        let raw = match frame.into_raw(|| action.clone()) {
+            Ok(raw) => raw,
+            Err(error) => {
+                return Err(TransportError::Rejected {
+                    action,
+                    error: Box::new(error),
+                });
+            }
+        };
+        self.validate(&raw)
+            .and_then(|()| convert(self.version, raw.clone()))
+            .map_err(|error| TransportError::Ocpp {
+                reply: error.reply_to(&raw).map(Box::new),
+                error: Box::new(error),
+            })
+    }
+
+    fn record(&mut self, direction: FrameDirection, frame: &OcppFrame, action: &str) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(direction, self.version, frame, action);
+        }
+    }
+
+    fn validate(&self, message: &RawOcppMessage) -> Result<(), OcppError> {
+        let Some(validator) = &self.validator else {
+            return Ok(());
+        };
+        let kind = match message.message_type {
+            2 => PayloadKind::Request,
+            3 => PayloadKind::Response,
+            _ => return Ok(()),
+        };
+        validator
+            .validate(self.version, &message.action, kind, &message.payload)
+            .map(|_| ())
+            .map_err(OcppError::Schema)
+    }
+
+    pub fn close(&mut self) -> Result<(), TransportError> {
+        self.socket.close(None).map_err(TransportError::from)
+    }
+}
+
+fn handshake_request(
+    config: &TransportConfig,
+) -> Result<tungstenite::handshake::client::Request, TransportError> {
+    if config.subprotocols.is_empty() {
+        return Err(TransportError::NoSubprotocolOffered);
+    }
+    let mut request = config
+        .url
+        .as_str()
+        .into_client_request()
+        .map_err(TransportError::from)?;
+    let offered = HeaderValue::from_str(&offered_header(&config.subprotocols))
+        .map_err(|err| TransportError::from(tungstenite::Error::from(err)))?;
+    request
+        .headers_mut()
+        .insert(SEC_WEBSOCKET_PROTOCOL, offered);
+    Ok(request)
+}
+
+/// tungstenite already rejects bad subprotocol answers during the handshake
+fn map_handshake_error(err: tungstenite::Error, config: &TransportConfig) -> TransportError {
+    match err {
+        tungstenite::Error::Protocol(ProtocolError::SecWebSocketSubProtocolError(
+            SubProtocolError::NoSubProtocol,
+        )) => TransportError::NoSubprotocolSelected {
+            offered: config.subprotocols.clone(),
+        },
+        tungstenite::Error::Protocol(ProtocolError::SecWebSocketSubProtocolError(
+            SubProtocolError::InvalidSubProtocol,
+        )) => TransportError::UnexpectedSubprotocol("<invalid>".to_string()),
+        err => err.into(),
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::thread;
+
+    use tungstenite::handshake::server::{Request, Response};
+
+    use super::*;
+
+    /// Accepts one client, selecting the first entry of `supported` which was offered
+    #[allow(clippy::result_large_err)]
+    fn csms(supported: &'static [&'static str], frame: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}/ocpp/CP001", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let callback = |request: &Request, mut response: Response| {
+                let offered = request
+                    .headers()
+                    .get(SEC_WEBSOCKET_PROTOCOL)
+                    .and_then(|value| value.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string();
+                let selected = supported
+                    .iter()
+                    .find(|p| offered.split(',').any(|o| o.trim() == **p));
+                if let Some(selected) = selected {
+                    response
+                        .headers_mut()
+                        .insert(SEC_WEBSOCKET_PROTOCOL, HeaderValue::from_static(selected));
+                }
+                Ok(response)
+            };
+            if let Ok(mut socket) = tungstenite::accept_hdr(stream, callback) {
+                let _ = socket.send(Message::text(frame));
+                let _ = socket.read();
+            }
+        });
+        url
+    }
+
+    fn config(url: String, subprotocols: Vec<ProtocolVersion>) -> TransportConfig {
+        TransportConfig { url, subprotocols }
+    }
+
+    #[test]
+    fn given_offered_list__when_building_header__then_preference_order() {
+        let header = offered_header(&[
+            ProtocolVersion::Ocpp21,
+            ProtocolVersion::Ocpp201,
+            ProtocolVersion::Ocpp16,
+        ]);
+
+        assert_eq!(header, "ocpp2.1, ocpp2.0.1, ocpp1.6");
+    }
+
+    #[test]
+    fn given_selected_header__when_negotiating__then_version_or_error() {
+        let offered = [ProtocolVersion::Ocpp201, ProtocolVersion::Ocpp16];
+
+        assert_eq!(
+            negotiate(&offered, Some("ocpp1.6")).unwrap(),
+            ProtocolVersion::Ocpp16
+        );
+        assert!(matches!(
+            negotiate(&offered, None),
+            Err(TransportError::NoSubprotocolSelected { .. })
+        ));
+        assert!(matches!(
+            negotiate(&offered, Some("ocpp2.1")),
+            Err(TransportError::UnexpectedSubprotocol(selected)) if selected == "ocpp2.1"
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "v201")]
+    fn given_server_supporting_201__when_connecting__then_frames_use_201_catalogue() {
+        use crate::ocpp_event::OcppMessage;
+
+        let url = csms(&["ocpp2.0.1", "ocpp1.6"], r#"[2, "1", "Heartbeat", {}]"#);
+        let config = config(url, vec![ProtocolVersion::Ocpp21, ProtocolVersion::Ocpp201]);
+
+        let mut connection = OcppConnection::connect(&config).unwrap();
+        assert_eq!(connection.version(), ProtocolVersion::Ocpp201);
+
+        let event = connection.receive().unwrap();
+        assert!(matches!(event.message, OcppMessage::V201Request(_)));
+        connection.close().unwrap();
+    }
+
+    #[test]
+    fn given_validation__when_receiving_invalid_payload__then_call_error() {
+        let url = csms(&["ocpp1.6"], r#"[2, "7", "Authorize", {"idTag": 42}]"#);
+        let config = config(url, vec![ProtocolVersion::Ocpp16]);
+        let mut connection = OcppConnection::connect(&config).unwrap();
+        connection.set_validation(ValidationMode::Lenient);
+
+        let result = connection.receive();
+
+        let Err(TransportError::Ocpp {
+            error,
+            reply: Some(reply),
+        }) = result
+        else {
+            panic!("expected schema violation, got {:?}", result);
+        };
+        assert!(matches!(*error, OcppError::Schema(_)));
+        assert_eq!(
+            reply.to_frame(ProtocolVersion::Ocpp16),
+            serde_json::json!([4, "7", "TypeConstraintViolation", reply.error_description, {"path": "/idTag"}])
+        );
+    }
+
+    #[test]
+    fn given_result_without_pending_call__when_receiving__then_dropped() {
+        let url = csms(
+            &["ocpp1.6"],
+            r#"[3, "9", {"currentTime": "2019-08-24T14:15:22Z"}]"#,
+        );
+        let config = config(url, vec![ProtocolVersion::Ocpp16]);
+        let mut connection = OcppConnection::connect(&config).unwrap();
+
+        let result = connection.receive();
+
+        let Err(TransportError::Ocpp { error, reply }) = result else {
+            panic!("expected correlation error, got {:?}", result);
+        };
+        assert!(matches!(*error, OcppError::UnknownCallId(_)));
+        assert!(reply.is_none());
+    }
+
+    #[test]
+    #[allow(clippy::result_large_err)]
+    fn given_csms_answering__when_exchanging__then_result_and_error_frames_round_trip() {
+        use std::sync::{Arc, Mutex, mpsc};
+
+        use crate::ocpp_event::{OcppMessage, OcppResponse};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}/ocpp/CP001", listener.local_addr().unwrap());
+        let (sent, frames) = mpsc::channel();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let callback = |_: &Request, mut response: Response| {
+                response
+                    .headers_mut()
+                    .insert(SEC_WEBSOCKET_PROTOCOL, HeaderValue::from_static("ocpp1.6"));
+                Ok(response)
+            };
+            let mut socket = tungstenite::accept_hdr(stream, callback).unwrap();
+            let mut exchange = |replies: &[&str]| {
+                let _ = sent.send(socket.read().unwrap().into_text().unwrap().to_string());
+                for reply in replies {
+                    socket.send(Message::text(*reply)).unwrap();
+                }
+            };
+            exchange(&[r#"[3, "1", {"currentTime": "2019-08-24T14:15:22Z"}]"#]);
+            exchange(&[
+                r#"[4, "2", "NotImplemented", "no authorization here", {}]"#,
+                r#"[2, "csms-1", "Heartbeat", {}]"#,
+            ]);
+            exchange(&[r#"[2, "csms-2", "DataTransfer", {}]"#]);
+            exchange(&[]);
+        });
+        let call = |call_id: &str, action: &str, payload| RawOcppMessage {
+            message_type: 2,
+            call_id: crate::CallId::new(call_id).unwrap(),
+            action: action.to_string(),
+            payload,
+        };
+        struct Recorded(Arc<Mutex<Vec<(FrameDirection, String, String)>>>);
+        impl FrameRecorder for Recorded {
+            fn record(
+                &mut self,
+                direction: FrameDirection,
+                _: ProtocolVersion,
+                frame: &OcppFrame,
+                action: &str,
+            ) {
+                let call_id = frame.call_id().as_str().to_string();
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push((direction, call_id, action.to_string()));
+            }
+        }
+        let recorded = Arc::new(Mutex::new(Vec::new()));
+        let mut connection =
+            OcppConnection::connect(&config(url, vec![ProtocolVersion::Ocpp16])).unwrap();
+        connection.set_recorder(Recorded(recorded.clone()));
+
+        connection
+            .send(&call("1", "Heartbeat", serde_json::json!({})))
+            .unwrap();
+        let event = connection.receive().unwrap();
+        assert!(matches!(
+            event.message,
+            OcppMessage::Response(OcppResponse::Heartbeat(_))
+        ));
+
+        connection
+            .send(&call(
+                "2",
+                "Authorize",
+                serde_json::json!({"idTag": "04A1"}),
+            ))
+            .unwrap();
+        let Err(TransportError::Rejected { action, error }) = connection.receive() else {
+            panic!("expected the CALLERROR");
+        };
+        assert_eq!(action, "Authorize");
+        assert_eq!(
+            error.error_code,
+            crate::call_error::ErrorCode::NotImplemented
+        );
+        assert_eq!(error.error_description, "no authorization here");
+
+        let heartbeat = connection.receive().unwrap();
+        connection
+            .send(&RawOcppMessage {
+                message_type: 3,
+                call_id: heartbeat.call_id,
+                action: "Heartbeat".to_string(),
+                payload: serde_json::json!({"currentTime": "2019-08-24T14:15:22Z"}),
+            })
+            .unwrap();
+
+        let Err(TransportError::Ocpp {
+            reply: Some(reply), ..
+        }) = connection.receive()
+        else {
+            panic!("expected a payload violation");
+        };
+        connection.send_call_error(&reply).unwrap();
+
+        let frames: Vec<String> = frames.iter().take(4).collect();
+        assert_eq!(frames[0], r#"[2,"1","Heartbeat",{}]"#);
+        assert_eq!(
+            frames[2],
+            r#"[3,"csms-1",{"currentTime":"2019-08-24T14:15:22Z"}]"#
+        );
+        assert_eq!(
+            frames[3],
+            r#"[4,"csms-2","OccurenceConstraintViolation","invalid DataTransfer payload: missing field `vendorId`",{}]"#
+        );
+        assert!(connection.pending.is_empty());
+        let (sent, received) = (FrameDirection::Sent, FrameDirection::Received);
+        let expected = [
+            (sent, "1", "Heartbeat"),
+            (received, "1", "Heartbeat"),
+            (sent, "2", "Authorize"),
+            (received, "2", "Authorize"),
+            (received, "csms-1", "Heartbeat"),
+            (sent, "csms-1", "Heartbeat"),
+            (received, "csms-2", "DataTransfer"),
+            (sent, "csms-2", ""),
+        ]
+        .map(|(direction, call_id, action)| (direction, call_id.to_string(), action.to_string()));
+        assert_eq!(*recorded.lock().unwrap(), expected.to_vec());
+    }
+
+    #[test]
+    fn given_server_selecting_nothing__when_connecting__then_clear_error() {
+        let url = csms(&["ocpp1.5"], "");
+        let config = config(url, vec![ProtocolVersion::Ocpp201, ProtocolVersion::Ocpp16]);
+
+        let result = OcppConnection::connect(&config);
+
+        let Err(err) = result else {
+            panic!("expected handshake to fail");
+        };
+        assert!(matches!(err, TransportError::NoSubprotocolSelected { .. }));
+        assert_eq!(
+            err.to_string(),
+            "server selected none of the offered subprotocols (ocpp2.0.1, ocpp1.6)"
+        );
+    }
+
+    #[test]
+    fn given_empty_preference_list__when_connecting__then_error() {
+        let config = config("ws://127.0.0.1:1/ocpp/CP001".to_string(), vec![]);
+
+        assert!(matches!(
+            OcppConnection::connect(&config),
+            Err(TransportError::NoSubprotocolOffered)
+        ));
+    }
+}
@@ -7,10 +7,10 @@ pub struct TypedOcppMessage {
     pub payload: serde_json::Value,
 }
 
+use crate::CallId;
 use serde::de::{self, SeqAccess, Visitor};
 use serde::{Deserialize, Deserializer};
 use std::fmt;
-use crate::ocpp::CallId;
 
 impl<'de> Deserialize<'de> for TypedOcppMessage {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -34,7 +34,7 @@ impl<'de> Deserialize<'de> for TypedOcppMessage {
                     .next_element::<u8>()?
                     .ok_or_else(|| de::Error::invalid_length(0, &self))?;
                 let call_id = seq
-                    .next_element::<String>()?
+                    .next_element::<CallId>()?
                     .ok_or_else(|| de::Error::invalid_length(1, &self))?;
                 let action = seq
                     .next_element()?
@@ -43,18 +43,10 @@ impl<'de> Deserialize<'de> for TypedOcppMessage {
                     .next_element()?
                     .ok_or_else(|| de::Error::invalid_length(3, &self))?;
                 Ok(TypedOcppMessage {
-                    message_type: match message_type {
-                        2 => MessageType::Call,
-                        3 => MessageType::CallResult,
-                        4 => MessageType::CallError,
-                        _ => {
-                            return Err(de::Error::custom(format!(
-                                "Unknown message type: {}",
-                                message_type
-                            )));
-                        }
-                    },
-                    call_id: CallId(call_id),
+                    message_type: MessageType::try_from(message_type).map_err(|message_type| {
+                        de::Error::custom(format!("Unknown message type: {}", message_type))
+                    })?,
+                    call_id,
                     action,
                     payload,
                 })
@@ -65,13 +57,27 @@ impl<'de> Deserialize<'de> for TypedOcppMessage {
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum MessageType {
     Call = 2, //request
     CallResult = 3,
     CallError = 4,
 }
 
+impl TryFrom<u8> for MessageType {
+    /// The unknown message type
+    type Error = u8;
+
+    fn try_from(message_type: u8) -> Result<Self, u8> {
+        match message_type {
+            2 => Ok(MessageType::Call),
+            3 => Ok(MessageType::CallResult),
+            4 => Ok(MessageType::CallError),
+            _ => Err(message_type),
+        }
+    }
+}
+
 #[allow(non_snake_case)]
 #[cfg(test)]
 mod typed_ocpp_message_tests {
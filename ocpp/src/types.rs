@@ -0,0 +1,614 @@
+use rust_decimal::Decimal;
+
+use crate::ci_string::{CiString20Type, CiString50Type, CiString500Type};
+use crate::datetime::{DateTime, rfc3339};
+
+/// Identifier of an authorization, i.e. the UID of an RFID card
+pub type IdToken = CiString20Type;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Location {
+    Body,
+    Cable,
+    ChargingStation,
+    Connector,
+    #[serde(rename = "EVSE")]
+    Evse,
+    #[serde(rename = "EVSEBox")]
+    EvseBox,
+    #[serde(rename = "GPS")]
+    Gps,
+    Other,
+    PowerOutlet,
+    PowerSupply,
+    Room,
+}
+
+/// Connector status reported with StatusNotification
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ChargePointStatus {
+    Available,
+    Preparing,
+    Charging,
+    #[serde(rename = "SuspendedEVSE")]
+    SuspendedEvse,
+    #[serde(rename = "SuspendedEV")]
+    SuspendedEv,
+    Finishing,
+    Reserved,
+    Unavailable,
+    Faulted,
+}
+
+/// Error reported with StatusNotification, `NoError` unless the status is `Faulted`
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ChargePointErrorCode {
+    ConnectorLockFailure,
+    #[serde(rename = "EVCommunicationError")]
+    EvCommunicationError,
+    GroundFailure,
+    HighTemperature,
+    InternalError,
+    LocalListConflict,
+    NoError,
+    OtherError,
+    OverCurrentFailure,
+    PowerMeterFailure,
+    PowerSwitchFailure,
+    ReaderFailure,
+    ResetFailure,
+    UnderVoltage,
+    OverVoltage,
+    WeakSignal,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum AvailabilityStatus {
+    Accepted,
+    Rejected,
+    Scheduled,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum AuthorizationStatus {
+    Accepted,
+    Blocked,
+    Expired,
+    Invalid,
+    ConcurrentTx,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum CancelReservationStatus {
+    Accepted,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ChargingProfileStatus {
+    Accepted,
+    Rejected,
+    NotSupported,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ClearCacheStatus {
+    Accepted,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ClearChargingProfileStatus {
+    Accepted,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ConfigurationStatus {
+    Accepted,
+    Rejected,
+    RebootRequired,
+    NotSupported,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum DataTransferStatus {
+    Accepted,
+    Rejected,
+    UnknownMessageId,
+    UnknownVendorId,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum MessageTrigger {
+    BootNotification,
+    DiagnosticsStatusNotification,
+    FirmwareStatusNotification,
+    Heartbeat,
+    MeterValues,
+    StatusNotification,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Measurand {
+    #[serde(rename = "Current.Export")]
+    CurrentExport,
+    #[serde(rename = "Current.Import")]
+    CurrentImport,
+    #[serde(rename = "Current.Offered")]
+    CurrentOffered,
+    #[serde(rename = "Current.Available")]
+    CurrentAvailable,
+    #[serde(rename = "Energy.Active.Export.Register")]
+    EnergyActiveExportRegister,
+    #[serde(rename = "Energy.Active.Import.Register")]
+    EnergyActiveImportRegister,
+    #[serde(rename = "Energy.Reactive.Export.Register")]
+    EnergyReactiveExportRegister,
+    #[serde(rename = "Energy.Reactive.Import.Register")]
+    EnergyReactiveImportRegister,
+    #[serde(rename = "Energy.Active.Export.Interval")]
+    EnergyActiveExportInterval,
+    #[serde(rename = "Energy.Active.Import.Interval")]
+    EnergyActiveImportInterval,
+    #[serde(rename = "Energy.Reactive.Export.Interval")]
+    EnergyReactiveExportInterval,
+    #[serde(rename = "Energy.Reactive.Import.Interval")]
+    EnergyReactiveImportInterval,
+    Voltage,
+    #[serde(rename = "Power.Active.Export")]
+    PowerActiveExport,
+    #[serde(rename = "Power.Active.Import")]
+    PowerActiveImport,
+    #[serde(rename = "Power.Factor")]
+    PowerFactor,
+    #[serde(rename = "Power.Offered")]
+    PowerOffered,
+    #[serde(rename = "Power.Reactive.Export")]
+    PowerReactiveExport,
+    #[serde(rename = "Power.Reactive.Import")]
+    PowerReactiveImport,
+    #[serde(rename = "Power.Apparent")]
+    PowerApparent,
+    Frequency,
+    Resistance,
+    #[serde(rename = "Temperature")]
+    CommonTemperature,
+    #[serde(rename = "RPM")]
+    Rpm,
+    SoC,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ReadingContext {
+    #[serde(rename = "Interruption.Begin")]
+    InterruptionBegin,
+    #[serde(rename = "Interruption.End")]
+    InterruptionEnd,
+    #[serde(rename = "Sample.Clock")]
+    SampleClock,
+    #[serde(rename = "Sample.Periodic")]
+    SamplePeriodic,
+    #[serde(rename = "Transaction.Begin")]
+    TransactionBegin,
+    #[serde(rename = "Transaction.End")]
+    TransactionEnd,
+    Trigger,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Reason {
+    EmergencyStop,
+    EVDisconnected,
+    HardReset,
+    Local,
+    Other,
+    PowerLoss,
+    PowerSwitchOff,
+    PowerSwitchOn,
+    Remote,
+    SoftReset,
+    Unknown,
+    WakeUp,
+    UnlockCommand,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum RecurrencyKind {
+    Daily,
+    Hourly,
+    Minutely,
+    Monthly,
+    Weekly,
+    Yearly,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum RegistrationStatus {
+    Accepted,
+    Blocked,
+    Deleted,
+    Pending,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum RemoteStartStopStatus {
+    Accepted,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ReservationStatus {
+    Accepted,
+    Faulted,
+    Occupied,
+    Rejected,
+    Unavailable,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ResetStatus {
+    Accepted,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ResetType {
+    Hard,
+    Soft,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum TriggerMessageStatus {
+    Accepted,
+    Rejected,
+    NotImplemented,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum UnlockStatus {
+    Unlocked,
+    UnlockFailed,
+    NotSupported,
+}
+
+/// Result of SendLocalList
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum UpdateStatus {
+    Accepted,
+    Failed,
+    NotSupported,
+    VersionMismatch,
+}
+
+/// Answer of the central system whether an id tag may charge
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdTagInfo {
+    #[serde(
+        default,
+        with = "rfc3339::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub expiry_date: Option<DateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_id_tag: Option<IdToken>,
+    pub status: AuthorizationStatus,
+}
+
+/// Configuration key reported with GetConfiguration
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct KeyValue {
+    pub key: CiString50Type,
+    pub readonly: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<CiString500Type>,
+}
+
+/// Phase of a sampled value, the same in 1.6 and 2.0.1
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Phase {
+    L1,
+    L2,
+    L3,
+    N,
+    #[serde(rename = "L1-N")]
+    L1N,
+    #[serde(rename = "L2-N")]
+    L2N,
+    #[serde(rename = "L3-N")]
+    L3N,
+    #[serde(rename = "L1-L2")]
+    L1L2,
+    #[serde(rename = "L2-L3")]
+    L2L3,
+    #[serde(rename = "L3-L1")]
+    L3L1,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ValueFormat {
+    Raw,
+    /// The value is a signed data block, i.e. OCMF, the measurand still tells what was signed
+    SignedData,
+}
+
+/// Value of a sampled value, a decimal unless the format is [`ValueFormat::SignedData`]
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize)]
+#[serde(untagged)]
+pub enum SampledValueData {
+    Decimal(Decimal),
+    Signed(String),
+}
+
+impl SampledValueData {
+    pub fn as_decimal(&self) -> Option<Decimal> {
+        match self {
+            SampledValueData::Decimal(value) => Some(*value),
+            SampledValueData::Signed(_) => None,
+        }
+    }
+}
+
+impl From<Decimal> for SampledValueData {
+    fn from(value: Decimal) -> Self {
+        SampledValueData::Decimal(value)
+    }
+}
+
+/// Sampled values are decimals encoded as JSON strings in 1.6, the format tells whether the
+/// string is a decimal or signed data
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SampledValue {
+    pub value: SampledValueData,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<ReadingContext>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<ValueFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub measurand: Option<Measurand>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phase: Option<Phase>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+}
+
+impl<'de> serde::Deserialize<'de> for SampledValue {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Untyped {
+            value: String,
+            context: Option<ReadingContext>,
+            format: Option<ValueFormat>,
+            measurand: Option<Measurand>,
+            phase: Option<Phase>,
+            unit: Option<String>,
+        }
+
+        let untyped = Untyped::deserialize(deserializer)?;
+        let value = match untyped.format {
+            Some(ValueFormat::SignedData) => SampledValueData::Signed(untyped.value),
+            Some(ValueFormat::Raw) | None => untyped
+                .value
+                .parse::<Decimal>()
+                .map(SampledValueData::Decimal)
+                .map_err(|_| {
+                    serde::de::Error::invalid_value(
+                        serde::de::Unexpected::Str(&untyped.value),
+                        &"a decimal, the format is Raw",
+                    )
+                })?,
+        };
+        Ok(SampledValue {
+            value,
+            context: untyped.context,
+            format: untyped.format,
+            measurand: untyped.measurand,
+            phase: untyped.phase,
+            unit: untyped.unit,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeterValue {
+    #[serde(with = "rfc3339")]
+    pub timestamp: DateTime,
+    pub sampled_value: Vec<SampledValue>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum GetCompositeScheduleStatus {
+    Accepted,
+    Rejected,
+}
+
+/// Unit of the limits in a charging schedule
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ChargingRateUnit {
+    /// Ampere per phase
+    A,
+    /// Watt over all phases
+    W,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChargingSchedulePeriod {
+    /// Seconds from the start of the schedule
+    pub start_period: u32,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub limit: Decimal,
+    /// Three when absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number_phases: Option<u8>,
+}
+
+/// Limits over time, i.e. the composite schedule of a connector
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChargingSchedule {
+    /// Seconds, the last period lasts forever when absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u32>,
+    #[serde(
+        default,
+        with = "rfc3339::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub start_schedule: Option<DateTime>,
+    pub charging_rate_unit: ChargingRateUnit,
+    pub charging_schedule_period: Vec<ChargingSchedulePeriod>,
+    #[serde(
+        default,
+        with = "rust_decimal::serde::float_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub min_charging_rate: Option<Decimal>,
+}
+
+/// What a charging profile limits
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ChargingProfilePurpose {
+    /// The whole charge point, installed on connector 0
+    ChargePointMaxProfile,
+    /// Every transaction of the connector, or of all connectors on connector 0
+    TxDefaultProfile,
+    /// One transaction, overriding the default ones
+    TxProfile,
+}
+
+/// When the schedule of a charging profile starts
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ChargingProfileKind {
+    /// At `startSchedule`
+    Absolute,
+    /// At `startSchedule`, again every `recurrencyKind`
+    Recurring,
+    /// With the transaction
+    Relative,
+}
+
+/// The `csChargingProfiles` of SetChargingProfile and RemoteStartTransaction
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChargingProfile {
+    pub charging_profile_id: i32,
+    /// Only for a [`ChargingProfilePurpose::TxProfile`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_id: Option<i32>,
+    pub stack_level: u32,
+    pub charging_profile_purpose: ChargingProfilePurpose,
+    pub charging_profile_kind: ChargingProfileKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recurrency_kind: Option<RecurrencyKind>,
+    #[serde(
+        default,
+        with = "rfc3339::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub valid_from: Option<DateTime>,
+    #[serde(
+        default,
+        with = "rfc3339::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub valid_to: Option<DateTime>,
+    pub charging_schedule: ChargingSchedule,
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_meter_value__when_deserializing__then_typed_timestamp_and_decimal() {
+        let json = serde_json::json!({
+            "timestamp": "2019-08-24T14:15:22+02:00",
+            "sampledValue": [{"value": "1234.50", "measurand": "Energy.Active.Import.Register"}]
+        });
+
+        let meter_value: MeterValue = serde_json::from_value(json).unwrap();
+
+        assert_eq!(
+            meter_value.sampled_value[0].value.as_decimal(),
+            Some(Decimal::new(123450, 2))
+        );
+        assert_eq!(
+            serde_json::to_value(&meter_value).unwrap(),
+            serde_json::json!({
+                "timestamp": "2019-08-24T12:15:22Z",
+                "sampledValue": [{"value": "1234.50", "measurand": "Energy.Active.Import.Register"}]
+            })
+        );
+    }
+
+    #[test]
+    fn given_signed_data__when_deserializing__then_kept_as_string() {
+        let json = serde_json::json!({
+            "value": "OCMF|{\"FV\":\"1.0\"}|{\"SD\":\"3045\"}",
+            "format": "SignedData",
+            "measurand": "Energy.Active.Import.Register"
+        });
+
+        let sampled_value: SampledValue = serde_json::from_value(json).unwrap();
+
+        assert_eq!(sampled_value.format, Some(ValueFormat::SignedData));
+        assert_eq!(
+            sampled_value.value,
+            SampledValueData::Signed(r#"OCMF|{"FV":"1.0"}|{"SD":"3045"}"#.to_string())
+        );
+    }
+
+    #[test]
+    fn given_raw_value_not_a_decimal__when_deserializing__then_error() {
+        for json in [
+            serde_json::json!({"value": "OCMF|{}|{}", "format": "Raw"}),
+            serde_json::json!({"value": "12 kWh"}),
+        ] {
+            assert!(serde_json::from_value::<SampledValue>(json).is_err());
+        }
+    }
+
+    #[test]
+    fn given_charging_schedule__when_deserializing__then_limits_are_numbers() {
+        let json = serde_json::json!({
+            "startSchedule": "2025-06-01T12:00:00Z",
+            "chargingRateUnit": "A",
+            "chargingSchedulePeriod": [
+                {"startPeriod": 0, "limit": 16.0},
+                {"startPeriod": 3600, "limit": 8.5, "numberPhases": 1}
+            ]
+        });
+
+        let schedule: ChargingSchedule = serde_json::from_value(json.clone()).unwrap();
+
+        assert_eq!(schedule.charging_rate_unit, ChargingRateUnit::A);
+        assert_eq!(
+            schedule.charging_schedule_period[1].limit,
+            Decimal::new(85, 1)
+        );
+        assert_eq!(schedule.charging_schedule_period[1].number_phases, Some(1));
+        assert_eq!(serde_json::to_value(&schedule).unwrap(), json);
+    }
+
+    #[test]
+    fn given_1_6_period_with_phase_to_use__when_round_tripping__then_dropped() {
+        let json = serde_json::json!({
+            "startPeriod": 0, "limit": 16.0, "numberPhases": 1, "phaseToUse": 2
+        });
+
+        let period: ChargingSchedulePeriod = serde_json::from_value(json).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&period).unwrap(),
+            serde_json::json!({"startPeriod": 0, "limit": 16.0, "numberPhases": 1})
+        );
+    }
+}
@@ -0,0 +1,767 @@
+///// OCPP 2.0.1 Device Model: components, variables and their attributes /////
+use crate::ci_string::CiString;
+use crate::datetime::DateTime;
+use crate::types::Location;
+use crate::v201::messages::*;
+use crate::v201::types::*;
+
+/// Description of the charging station hardware, loadable from JSON:
+///
+/// ```json
+/// {"components": [{
+///     "name": "OCPPCommCtrlr",
+///     "variables": [{
+///         "name": "HeartbeatInterval",
+///         "attributes": [{"type": "Actual", "value": "60", "mutability": "ReadWrite", "persistent": true}],
+///         "characteristics": {"unit": "s", "dataType": "integer", "minLimit": 1, "supportsMonitoring": false}
+///     }]
+/// }]}
+/// ```
+#[derive(Debug, Clone, Default, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct DeviceModel {
+    pub components: Vec<ComponentEntry>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentEntry {
+    #[serde(flatten)]
+    pub component: Component,
+    /// Where the component is physically located, if relevant
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<Location>,
+    #[serde(default)]
+    pub variables: Vec<VariableEntry>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VariableEntry {
+    #[serde(flatten)]
+    pub variable: Variable,
+    pub attributes: Vec<VariableAttribute>,
+    pub characteristics: VariableCharacteristics,
+}
+
+impl VariableEntry {
+    fn attribute(&self, kind: AttributeType) -> Option<&VariableAttribute> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.kind.unwrap_or(AttributeType::Actual) == kind)
+    }
+
+    fn attribute_mut(&mut self, kind: AttributeType) -> Option<&mut VariableAttribute> {
+        self.attributes
+            .iter_mut()
+            .find(|attribute| attribute.kind.unwrap_or(AttributeType::Actual) == kind)
+    }
+
+    fn report_data(&self, component: &Component) -> ReportData {
+        let variable_attribute = self
+            .attributes
+            .iter()
+            .map(|attribute| {
+                let mut attribute = attribute.clone();
+                if attribute.mutability == Some(Mutability::WriteOnly) {
+                    attribute.value = None;
+                }
+                attribute
+            })
+            .collect();
+        ReportData {
+            component: component.clone(),
+            variable: self.variable.clone(),
+            variable_attribute,
+            variable_characteristics: Some(self.characteristics.clone()),
+        }
+    }
+}
+
+/// Variables reported for `SummaryInventory`
+const SUMMARY_VARIABLES: [&str; 3] = ["AvailabilityState", "Available", "Problem"];
+
+impl DeviceModel {
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    pub fn get_variables(&self, request: &GetVariablesRequest) -> GetVariablesResponse {
+        let get_variable_result = request
+            .get_variable_data
+            .iter()
+            .map(|data| self.get_variable(data))
+            .collect();
+        GetVariablesResponse {
+            get_variable_result,
+        }
+    }
+
+    fn get_variable(&self, data: &GetVariableData) -> GetVariableResult {
+        let kind = data.attribute_type.unwrap_or(AttributeType::Actual);
+        let mut result = GetVariableResult {
+            attribute_status: GetVariableStatus::Accepted,
+            attribute_type: data.attribute_type,
+            attribute_value: None,
+            component: data.component.clone(),
+            variable: data.variable.clone(),
+            attribute_status_info: None,
+        };
+        match self.find(&data.component, &data.variable) {
+            Err(status) => result.attribute_status = status.into(),
+            Ok(entry) => match entry.attribute(kind) {
+                None => result.attribute_status = GetVariableStatus::NotSupportedAttributeType,
+                Some(attribute) if attribute.mutability == Some(Mutability::WriteOnly) => {
+                    result.attribute_status = GetVariableStatus::Rejected;
+                    result.attribute_status_info = Some(status_info("WriteOnly"));
+                }
+                Some(attribute) => result.attribute_value = attribute.value.clone(),
+            },
+        }
+        result
+    }
+
+    pub fn set_variables(&mut self, request: &SetVariablesRequest) -> SetVariablesResponse {
+        let set_variable_result = request
+            .set_variable_data
+            .iter()
+            .map(|data| self.set_variable(data))
+            .collect();
+        SetVariablesResponse {
+            set_variable_result,
+        }
+    }
+
+    fn set_variable(&mut self, data: &SetVariableData) -> SetVariableResult {
+        let mut result = SetVariableResult {
+            attribute_type: data.attribute_type,
+            attribute_status: SetVariableStatus::Accepted,
+            component: data.component.clone(),
+            variable: data.variable.clone(),
+            attribute_status_info: None,
+        };
+        if let Err((status, reason)) = self.write(data, false) {
+            result.attribute_status = status;
+            result.attribute_status_info = reason.map(status_info);
+        }
+        result
+    }
+
+    fn write(
+        &mut self,
+        data: &SetVariableData,
+        restoring: bool,
+    ) -> Result<(), (SetVariableStatus, Option<&'static str>)> {
+        let entry = self
+            .find_mut(&data.component, &data.variable)
+            .map_err(|status| (status.into(), None))?;
+        let kind = data.attribute_type.unwrap_or(AttributeType::Actual);
+        let attribute = entry
+            .attribute(kind)
+            .ok_or((SetVariableStatus::NotSupportedAttributeType, None))?;
+        if !restoring {
+            if attribute.mutability == Some(Mutability::ReadOnly) {
+                return Err((SetVariableStatus::Rejected, Some("ReadOnly")));
+            }
+            validate(&data.attribute_value, &entry.characteristics)
+                .map_err(|reason| (SetVariableStatus::Rejected, Some(reason)))?;
+        }
+        let attribute = entry.attribute_mut(kind).expect("attribute checked above");
+        attribute.value = Some(
+            CiString::new(data.attribute_value.as_str()).expect("set values are shorter than 2500"),
+        );
+        Ok(())
+    }
+
+    /// Values of all persistent attributes, to be stored and [restored](Self::restore) after a reboot
+    pub fn persistent_values(&self) -> Vec<SetVariableData> {
+        self.components
+            .iter()
+            .flat_map(|entry| {
+                entry.variables.iter().flat_map(move |variable| {
+                    variable
+                        .attributes
+                        .iter()
+                        .filter(|attribute| attribute.persistent == Some(true))
+                        .filter_map(move |attribute| {
+                            Some(SetVariableData {
+                                attribute_type: attribute.kind,
+                                attribute_value: CiString::new(attribute.value.as_deref()?).ok()?,
+                                component: entry.component.clone(),
+                                variable: variable.variable.clone(),
+                            })
+                        })
+                })
+            })
+            .collect()
+    }
+
+    /// Applies previously stored [persistent values](Self::persistent_values), bypassing
+    /// mutability checks. Entries no longer present in the model are skipped.
+    pub fn restore(&mut self, values: &[SetVariableData]) {
+        for data in values {
+            let _ = self.write(data, true);
+        }
+    }
+
+    pub fn base_report(&self, report_base: ReportBase) -> Vec<ReportData> {
+        self.report_data(|_, variable| match report_base {
+            ReportBase::FullInventory => true,
+            ReportBase::ConfigurationInventory => variable.attributes.iter().any(|attribute| {
+                matches!(
+                    attribute.mutability,
+                    Some(Mutability::ReadWrite | Mutability::WriteOnly)
+                )
+            }),
+            ReportBase::SummaryInventory => SUMMARY_VARIABLES
+                .iter()
+                .any(|name| variable.variable.name.eq_ignore_ascii_case(name)),
+        })
+    }
+
+    /// Report for GetReport, all given filters have to match
+    pub fn report(&self, request: &GetReportRequest) -> Vec<ReportData> {
+        self.report_data(|entry, variable| {
+            let selected = request.component_variable.as_ref().is_none_or(|selection| {
+                selection.iter().any(|selected| {
+                    component_matches(&selected.component, &entry.component)
+                        && selected
+                            .variable
+                            .as_ref()
+                            .is_none_or(|v| variable_matches(v, &variable.variable))
+                })
+            });
+            let criteria = request
+                .component_criteria
+                .as_ref()
+                .is_none_or(|criteria| criteria.iter().any(|criterion| entry.meets(*criterion)));
+            selected && criteria
+        })
+    }
+
+    fn report_data(
+        &self,
+        filter: impl Fn(&ComponentEntry, &VariableEntry) -> bool,
+    ) -> Vec<ReportData> {
+        self.components
+            .iter()
+            .flat_map(|entry| {
+                entry
+                    .variables
+                    .iter()
+                    .filter(|variable| filter(entry, variable))
+                    .map(|variable| variable.report_data(&entry.component))
+            })
+            .collect()
+    }
+
+    pub fn handle_get_base_report(
+        &self,
+        request: &GetBaseReportRequest,
+        generated_at: DateTime,
+        page_size: usize,
+    ) -> (GetReportResponse, Vec<NotifyReportRequest>) {
+        let data = self.base_report(request.report_base);
+        report_response(request.request_id, data, generated_at, page_size)
+    }
+
+    pub fn handle_get_report(
+        &self,
+        request: &GetReportRequest,
+        generated_at: DateTime,
+        page_size: usize,
+    ) -> (GetReportResponse, Vec<NotifyReportRequest>) {
+        let data = self.report(request);
+        report_response(request.request_id, data, generated_at, page_size)
+    }
+
+    fn find(&self, component: &Component, variable: &Variable) -> Result<&VariableEntry, Unknown> {
+        let entry = self
+            .components
+            .iter()
+            .find(|entry| component_matches(component, &entry.component))
+            .ok_or(Unknown::Component)?;
+        entry
+            .variables
+            .iter()
+            .find(|entry| variable_matches(variable, &entry.variable))
+            .ok_or(Unknown::Variable)
+    }
+
+    fn find_mut(
+        &mut self,
+        component: &Component,
+        variable: &Variable,
+    ) -> Result<&mut VariableEntry, Unknown> {
+        let entry = self
+            .components
+            .iter_mut()
+            .find(|entry| component_matches(component, &entry.component))
+            .ok_or(Unknown::Component)?;
+        entry
+            .variables
+            .iter_mut()
+            .find(|entry| variable_matches(variable, &entry.variable))
+            .ok_or(Unknown::Variable)
+    }
+}
+
+impl ComponentEntry {
+    /// A criterion is met if the component has the matching boolean variable set to `true`
+    fn meets(&self, criterion: ComponentCriterion) -> bool {
+        let name = match criterion {
+            ComponentCriterion::Active => "Active",
+            ComponentCriterion::Available => "Available",
+            ComponentCriterion::Enabled => "Enabled",
+            ComponentCriterion::Problem => "Problem",
+        };
+        self.variables.iter().any(|variable| {
+            variable.variable.name == name
+                && variable
+                    .attribute(AttributeType::Actual)
+                    .and_then(|attribute| attribute.value.as_deref())
+                    == Some("true")
+        })
+    }
+}
+
+/// Splits report data into NotifyReport pages, `tbc` is set on all but the last page
+pub fn notify_report_pages(
+    request_id: i32,
+    generated_at: DateTime,
+    data: Vec<ReportData>,
+    page_size: usize,
+) -> Vec<NotifyReportRequest> {
+    let pages: Vec<_> = data.chunks(page_size.max(1)).map(<[_]>::to_vec).collect();
+    let last = pages.len().saturating_sub(1);
+    pages
+        .into_iter()
+        .enumerate()
+        .map(|(seq_no, page)| NotifyReportRequest {
+            request_id,
+            generated_at,
+            tbc: seq_no < last,
+            seq_no: seq_no as u32,
+            report_data: Some(page),
+        })
+        .collect()
+}
+
+fn report_response(
+    request_id: i32,
+    data: Vec<ReportData>,
+    generated_at: DateTime,
+    page_size: usize,
+) -> (GetReportResponse, Vec<NotifyReportRequest>) {
+    let status = if data.is_empty() {
+        GenericDeviceModelStatus::EmptyResultSet
+    } else {
+        GenericDeviceModelStatus::Accepted
+    };
+    let response = GetReportResponse {
+        status,
+        status_info: None,
+    };
+    (
+        response,
+        notify_report_pages(request_id, generated_at, data, page_size),
+    )
+}
+
+enum Unknown {
+    Component,
+    Variable,
+}
+
+impl From<Unknown> for GetVariableStatus {
+    fn from(unknown: Unknown) -> Self {
+        match unknown {
+            Unknown::Component => GetVariableStatus::UnknownComponent,
+            Unknown::Variable => GetVariableStatus::UnknownVariable,
+        }
+    }
+}
+
+impl From<Unknown> for SetVariableStatus {
+    fn from(unknown: Unknown) -> Self {
+        match unknown {
+            Unknown::Component => SetVariableStatus::UnknownComponent,
+            Unknown::Variable => SetVariableStatus::UnknownVariable,
+        }
+    }
+}
+
+/// Component and variable names are case-insensitive identifiers
+fn component_matches(requested: &Component, known: &Component) -> bool {
+    requested.name == known.name
+        && requested.instance == known.instance
+        && match (&requested.evse, &known.evse) {
+            (None, None) => true,
+            (Some(requested), Some(known)) => {
+                requested.id == known.id && requested.connector_id == known.connector_id
+            }
+            _ => false,
+        }
+}
+
+fn variable_matches(requested: &Variable, known: &Variable) -> bool {
+    requested.name == known.name && requested.instance == known.instance
+}
+
+fn status_info(reason_code: &str) -> StatusInfo {
+    StatusInfo {
+        reason_code: CiString::new(reason_code).expect("reason codes fit in 20 characters"),
+        additional_info: None,
+    }
+}
+
+/// Checks a new value against the variable characteristics, returns the reason code on failure
+fn validate(value: &str, characteristics: &VariableCharacteristics) -> Result<(), &'static str> {
+    let min = characteristics
+        .min_limit
+        .as_ref()
+        .and_then(serde_json::Number::as_f64);
+    let max = characteristics
+        .max_limit
+        .as_ref()
+        .and_then(serde_json::Number::as_f64);
+    let in_range = |number: f64| {
+        if min.is_some_and(|min| number < min) || max.is_some_and(|max| number > max) {
+            Err("ValueOutOfRange")
+        } else {
+            Ok(())
+        }
+    };
+    let allowed = |item: &str| {
+        characteristics
+            .values_list
+            .as_deref()
+            .is_none_or(|list| list.split(',').any(|allowed| allowed.trim() == item))
+    };
+    match characteristics.data_type {
+        DataType::Integer => in_range(value.parse::<i64>().map_err(|_| "InvalidValue")? as f64),
+        DataType::Decimal => in_range(
+            value
+                .parse::<f64>()
+                .ok()
+                .filter(|number| number.is_finite())
+                .ok_or("InvalidValue")?,
+        ),
+        DataType::Boolean => match value {
+            "true" | "false" => Ok(()),
+            _ => Err("InvalidValue"),
+        },
+        DataType::String => match max {
+            Some(max) if value.chars().count() as f64 > max => Err("ValueTooLarge"),
+            _ => Ok(()),
+        },
+        DataType::DateTime => crate::datetime::parse(value)
+            .map(|_| ())
+            .map_err(|_| "InvalidValue"),
+        DataType::OptionList if allowed(value) => Ok(()),
+        DataType::SequenceList | DataType::MemberList
+            if value.split(',').all(|item| allowed(item.trim())) =>
+        {
+            Ok(())
+        }
+        DataType::OptionList | DataType::SequenceList | DataType::MemberList => Err("InvalidValue"),
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HARDWARE: &str = r#"
+{
+  "components": [
+    {
+      "name": "OCPPCommCtrlr",
+      "variables": [
+        {
+          "name": "HeartbeatInterval",
+          "attributes": [{"type": "Actual", "value": "60", "mutability": "ReadWrite", "persistent": true}],
+          "characteristics": {"unit": "s", "dataType": "integer", "minLimit": 1, "maxLimit": 86400, "supportsMonitoring": false}
+        },
+        {
+          "name": "BasicAuthPassword",
+          "attributes": [{"value": "secret", "mutability": "WriteOnly"}],
+          "characteristics": {"dataType": "string", "maxLimit": 40, "supportsMonitoring": false}
+        }
+      ]
+    },
+    {
+      "name": "Connector",
+      "evse": {"id": 1, "connectorId": 1},
+      "location": "Cable",
+      "variables": [
+        {
+          "name": "Available",
+          "attributes": [{"value": "true", "mutability": "ReadOnly"}],
+          "characteristics": {"dataType": "boolean", "supportsMonitoring": true}
+        },
+        {
+          "name": "ConnectorType",
+          "attributes": [{"value": "cType2", "mutability": "ReadOnly", "constant": true}],
+          "characteristics": {"dataType": "OptionList", "valuesList": "cType2,sType2", "supportsMonitoring": false}
+        }
+      ]
+    }
+  ]
+}
+"#;
+
+    fn t0() -> DateTime {
+        crate::datetime::parse("2024-05-01T10:00:00Z").unwrap()
+    }
+
+    fn model() -> DeviceModel {
+        DeviceModel::from_json(HARDWARE).unwrap()
+    }
+
+    fn component(name: &str) -> Component {
+        Component {
+            name: CiString::new(name).unwrap(),
+            instance: None,
+            evse: None,
+        }
+    }
+
+    fn connector() -> Component {
+        Component {
+            evse: Some(Evse {
+                id: 1,
+                connector_id: Some(1),
+            }),
+            ..component("Connector")
+        }
+    }
+
+    fn variable(name: &str) -> Variable {
+        Variable {
+            name: CiString::new(name).unwrap(),
+            instance: None,
+        }
+    }
+
+    fn set(component: Component, name: &str, value: &str) -> SetVariableData {
+        SetVariableData {
+            attribute_type: None,
+            attribute_value: CiString::new(value).unwrap(),
+            component,
+            variable: variable(name),
+        }
+    }
+
+    fn get(component: Component, name: &str) -> GetVariableData {
+        GetVariableData {
+            attribute_type: None,
+            component,
+            variable: variable(name),
+        }
+    }
+
+    #[test]
+    fn given_hardware_json__when_loading__then_components_and_location() {
+        let model = model();
+
+        assert_eq!(model.components.len(), 2);
+        assert_eq!(model.components[1].location, Some(Location::Cable));
+        assert_eq!(model.components[1].component, connector());
+    }
+
+    #[test]
+    fn given_variables__when_getting__then_status_per_entry() {
+        let response = model().get_variables(&GetVariablesRequest {
+            get_variable_data: vec![
+                get(component("ocppcommctrlr"), "HeartbeatInterval"),
+                get(component("OCPPCommCtrlr"), "BasicAuthPassword"),
+                get(component("OCPPCommCtrlr"), "Unknown"),
+                get(component("Unknown"), "HeartbeatInterval"),
+                GetVariableData {
+                    attribute_type: Some(AttributeType::MaxSet),
+                    ..get(connector(), "Available")
+                },
+            ],
+        });
+
+        let statuses: Vec<_> = response
+            .get_variable_result
+            .iter()
+            .map(|result| result.attribute_status)
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                GetVariableStatus::Accepted,
+                GetVariableStatus::Rejected,
+                GetVariableStatus::UnknownVariable,
+                GetVariableStatus::UnknownComponent,
+                GetVariableStatus::NotSupportedAttributeType,
+            ]
+        );
+        assert_eq!(
+            response.get_variable_result[0].attribute_value.as_deref(),
+            Some("60")
+        );
+        assert_eq!(response.get_variable_result[1].attribute_value, None);
+    }
+
+    #[test]
+    fn given_values__when_setting__then_validated_against_characteristics() {
+        let mut model = model();
+
+        let response = model.set_variables(&SetVariablesRequest {
+            set_variable_data: vec![
+                set(component("OCPPCommCtrlr"), "HeartbeatInterval", "300"),
+                set(component("OCPPCommCtrlr"), "HeartbeatInterval", "0"),
+                set(component("OCPPCommCtrlr"), "HeartbeatInterval", "abc"),
+                set(connector(), "Available", "false"),
+            ],
+        });
+
+        let results: Vec<_> = response
+            .set_variable_result
+            .iter()
+            .map(|result| {
+                (
+                    result.attribute_status,
+                    result
+                        .attribute_status_info
+                        .as_ref()
+                        .map(|info| info.reason_code.as_str()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            results,
+            vec![
+                (SetVariableStatus::Accepted, None),
+                (SetVariableStatus::Rejected, Some("ValueOutOfRange")),
+                (SetVariableStatus::Rejected, Some("InvalidValue")),
+                (SetVariableStatus::Rejected, Some("ReadOnly")),
+            ]
+        );
+        let value = model.get_variables(&GetVariablesRequest {
+            get_variable_data: vec![get(component("OCPPCommCtrlr"), "HeartbeatInterval")],
+        });
+        assert_eq!(
+            value.get_variable_result[0].attribute_value.as_deref(),
+            Some("300")
+        );
+    }
+
+    #[test]
+    fn given_decimal_and_datetime__when_validating__then_only_finite_numbers_and_rfc3339() {
+        let characteristics = |data_type| VariableCharacteristics {
+            unit: None,
+            data_type,
+            min_limit: Some(0.into()),
+            max_limit: Some(100.into()),
+            values_list: None,
+            supports_monitoring: false,
+        };
+        let decimal = characteristics(DataType::Decimal);
+        let date_time = characteristics(DataType::DateTime);
+
+        assert_eq!(validate("12.5", &decimal), Ok(()));
+        assert_eq!(validate("NaN", &decimal), Err("InvalidValue"));
+        assert_eq!(validate("inf", &decimal), Err("InvalidValue"));
+        assert_eq!(validate("2024-05-01T10:00:00Z", &date_time), Ok(()));
+        assert_eq!(validate("yesterday", &date_time), Err("InvalidValue"));
+    }
+
+    #[test]
+    fn given_changed_persistent_value__when_restoring__then_value_survives() {
+        let mut model = model();
+        model.set_variables(&SetVariablesRequest {
+            set_variable_data: vec![set(component("OCPPCommCtrlr"), "HeartbeatInterval", "120")],
+        });
+        let stored = model.persistent_values();
+
+        let mut rebooted = self::model();
+        rebooted.restore(&stored);
+
+        assert_eq!(stored.len(), 1);
+        assert_eq!(rebooted, model);
+    }
+
+    #[test]
+    fn given_report_bases__when_reporting__then_filtered() {
+        let model = model();
+
+        assert_eq!(model.base_report(ReportBase::FullInventory).len(), 4);
+        let configuration: Vec<_> = model
+            .base_report(ReportBase::ConfigurationInventory)
+            .into_iter()
+            .map(|data| data.variable.name)
+            .collect();
+        assert_eq!(
+            configuration,
+            vec!["HeartbeatInterval", "BasicAuthPassword"]
+        );
+        let summary = model.base_report(ReportBase::SummaryInventory);
+        assert_eq!(summary.len(), 1);
+        assert_eq!(summary[0].variable.name, "Available");
+    }
+
+    #[test]
+    fn given_report_filters__when_reporting__then_matching_variables() {
+        let model = model();
+
+        let by_component = model.report(&GetReportRequest {
+            request_id: 1,
+            component_variable: Some(vec![ComponentVariable {
+                component: connector(),
+                variable: None,
+            }]),
+            component_criteria: None,
+        });
+        let by_criteria = model.report(&GetReportRequest {
+            request_id: 2,
+            component_variable: None,
+            component_criteria: Some(vec![ComponentCriterion::Problem]),
+        });
+
+        assert_eq!(by_component.len(), 2);
+        assert!(by_criteria.is_empty());
+    }
+
+    #[test]
+    fn given_large_report__when_paging__then_tbc_on_all_but_last() {
+        let request = GetBaseReportRequest {
+            request_id: 7,
+            report_base: ReportBase::FullInventory,
+        };
+
+        let (response, pages) = model().handle_get_base_report(&request, t0(), 3);
+
+        assert_eq!(response.status, GenericDeviceModelStatus::Accepted);
+        let paging: Vec<_> = pages
+            .iter()
+            .map(|page| {
+                (
+                    page.seq_no,
+                    page.tbc,
+                    page.report_data.as_ref().unwrap().len(),
+                )
+            })
+            .collect();
+        assert_eq!(paging, vec![(0, true, 3), (1, false, 1)]);
+        assert!(pages.iter().all(|page| page.request_id == 7));
+    }
+
+    #[test]
+    fn given_empty_result__when_reporting__then_empty_result_set() {
+        let request = GetReportRequest {
+            request_id: 3,
+            component_variable: None,
+            component_criteria: Some(vec![ComponentCriterion::Problem]),
+        };
+
+        let (response, pages) = model().handle_get_report(&request, t0(), 10);
+
+        assert_eq!(response.status, GenericDeviceModelStatus::EmptyResultSet);
+        assert!(pages.is_empty());
+    }
+}
@@ -0,0 +1,340 @@
+///// OCPP 2.0.1 request and response payloads /////
+use crate::ci_string::CiString;
+use crate::datetime::{DateTime, rfc3339};
+use crate::types::DataTransferStatus;
+use crate::v201::types::*;
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChargingStation {
+    pub model: CiString<20>,
+    pub vendor_name: CiString<50>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub serial_number: Option<CiString<25>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub firmware_version: Option<CiString<50>>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BootNotificationRequest {
+    pub reason: BootReason,
+    pub charging_station: ChargingStation,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BootNotificationResponse {
+    #[serde(with = "rfc3339")]
+    pub current_time: DateTime,
+    pub interval: u32,
+    pub status: RegistrationStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_info: Option<StatusInfo>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HeartbeatRequest {}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeartbeatResponse {
+    #[serde(with = "rfc3339")]
+    pub current_time: DateTime,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusNotificationRequest {
+    #[serde(with = "rfc3339")]
+    pub timestamp: DateTime,
+    pub connector_status: ConnectorStatus,
+    pub evse_id: u32,
+    pub connector_id: u32,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct StatusNotificationResponse {}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorizeRequest {
+    pub id_token: IdToken,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorizeResponse {
+    pub id_token_info: IdTokenInfo,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionEventRequest {
+    pub event_type: TransactionEventType,
+    #[serde(with = "rfc3339")]
+    pub timestamp: DateTime,
+    pub trigger_reason: TriggerReason,
+    pub seq_no: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offline: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number_of_phases_used: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cable_max_current: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reservation_id: Option<i32>,
+    pub transaction_info: Transaction,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<IdToken>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub evse: Option<Evse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meter_value: Option<Vec<MeterValue>>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionEventResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_cost: Option<serde_json::Number>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub charging_priority: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token_info: Option<IdTokenInfo>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeterValuesRequest {
+    pub evse_id: u32,
+    pub meter_value: Vec<MeterValue>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct MeterValuesResponse {}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetVariableData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribute_type: Option<AttributeType>,
+    pub component: Component,
+    pub variable: Variable,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetVariablesRequest {
+    pub get_variable_data: Vec<GetVariableData>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetVariableResult {
+    pub attribute_status: GetVariableStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribute_type: Option<AttributeType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribute_value: Option<CiString<2500>>,
+    pub component: Component,
+    pub variable: Variable,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribute_status_info: Option<StatusInfo>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetVariablesResponse {
+    pub get_variable_result: Vec<GetVariableResult>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetVariableData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribute_type: Option<AttributeType>,
+    pub attribute_value: CiString<1000>,
+    pub component: Component,
+    pub variable: Variable,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetVariablesRequest {
+    pub set_variable_data: Vec<SetVariableData>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetVariableResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribute_type: Option<AttributeType>,
+    pub attribute_status: SetVariableStatus,
+    pub component: Component,
+    pub variable: Variable,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribute_status_info: Option<StatusInfo>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetVariablesResponse {
+    pub set_variable_result: Vec<SetVariableResult>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetBaseReportRequest {
+    pub request_id: i32,
+    pub report_base: ReportBase,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetReportRequest {
+    pub request_id: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub component_variable: Option<Vec<ComponentVariable>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub component_criteria: Option<Vec<ComponentCriterion>>,
+}
+
+/// Answer to both GetBaseReport and GetReport
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetReportResponse {
+    pub status: GenericDeviceModelStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_info: Option<StatusInfo>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotifyReportRequest {
+    pub request_id: i32,
+    #[serde(with = "rfc3339")]
+    pub generated_at: DateTime,
+    /// "To be continued", set on every page but the last one
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub tbc: bool,
+    pub seq_no: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report_data: Option<Vec<ReportData>>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct NotifyReportResponse {}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestStartTransactionRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub evse_id: Option<u32>,
+    pub remote_start_id: i32,
+    pub id_token: IdToken,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestStartTransactionResponse {
+    pub status: RequestStartStopStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_id: Option<CiString<36>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_info: Option<StatusInfo>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestStopTransactionRequest {
+    pub transaction_id: CiString<36>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestStopTransactionResponse {
+    pub status: RequestStartStopStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_info: Option<StatusInfo>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResetRequest {
+    #[serde(rename = "type")]
+    pub kind: ResetType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub evse_id: Option<u32>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResetResponse {
+    pub status: ResetStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_info: Option<StatusInfo>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ClearCacheRequest {}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClearCacheResponse {
+    pub status: ClearCacheStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_info: Option<StatusInfo>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelReservationRequest {
+    pub reservation_id: i32,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelReservationResponse {
+    pub status: CancelReservationStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_info: Option<StatusInfo>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeAvailabilityRequest {
+    /// The whole charging station when absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub evse: Option<Evse>,
+    pub operational_status: OperationalStatus,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeAvailabilityResponse {
+    pub status: ChangeAvailabilityStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_info: Option<StatusInfo>,
+}
+
+/// Unlike 1.6, `data` may hold any JSON value
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataTransferRequest {
+    pub vendor_id: CiString<255>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<CiString<50>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataTransferResponse {
+    pub status: DataTransferStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_info: Option<StatusInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
@@ -0,0 +1,254 @@
+//! OCPP 2.0.1 message catalogue, selected with [`ProtocolVersion::Ocpp201`](super::ProtocolVersion).
+
+pub mod device_model;
+pub mod messages;
+pub mod transaction;
+pub mod types;
+
+use messages::*;
+
+use crate::ocpp_event::Catalogue;
+
+/// `remote = "Self"` keeps the derived impls strict, the trait impls handle `Other`
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(remote = "Self", tag = "action", content = "payload")]
+pub enum OcppRequest {
+    Authorize(AuthorizeRequest),
+    BootNotification(BootNotificationRequest),
+    CancelReservation(CancelReservationRequest),
+    ChangeAvailability(ChangeAvailabilityRequest),
+    ClearCache(ClearCacheRequest),
+    DataTransfer(DataTransferRequest),
+    GetBaseReport(GetBaseReportRequest),
+    GetReport(GetReportRequest),
+    GetVariables(GetVariablesRequest),
+    Heartbeat(HeartbeatRequest),
+    MeterValues(MeterValuesRequest),
+    NotifyReport(NotifyReportRequest),
+    RequestStartTransaction(RequestStartTransactionRequest),
+    RequestStopTransaction(RequestStopTransactionRequest),
+    Reset(ResetRequest),
+    SetVariables(SetVariablesRequest),
+    StatusNotification(StatusNotificationRequest),
+    TransactionEvent(TransactionEventRequest),
+    /// Any action which has no typed payload yet
+    #[serde(skip)]
+    Other {
+        action: String,
+        payload: serde_json::Value,
+    },
+}
+
+impl serde::Serialize for OcppRequest {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            OcppRequest::Other { action, payload } => {
+                super::ocpp_event::serialize_other(action, payload, serializer)
+            }
+            typed => OcppRequest::serialize(typed, serializer),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for OcppRequest {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        super::ocpp_event::deserialize_with_fallback(deserializer, |action, payload| {
+            OcppRequest::Other { action, payload }
+        })
+    }
+}
+
+impl Catalogue for OcppRequest {
+    fn strict<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        OcppRequest::deserialize(deserializer)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "action", content = "payload")]
+pub enum OcppResponse {
+    Authorize(AuthorizeResponse),
+    BootNotification(BootNotificationResponse),
+    CancelReservation(CancelReservationResponse),
+    ChangeAvailability(ChangeAvailabilityResponse),
+    ClearCache(ClearCacheResponse),
+    DataTransfer(DataTransferResponse),
+    GetBaseReport(GetReportResponse),
+    GetReport(GetReportResponse),
+    GetVariables(GetVariablesResponse),
+    Heartbeat(HeartbeatResponse),
+    MeterValues(MeterValuesResponse),
+    NotifyReport(NotifyReportResponse),
+    RequestStartTransaction(RequestStartTransactionResponse),
+    RequestStopTransaction(RequestStopTransactionResponse),
+    Reset(ResetResponse),
+    SetVariables(SetVariablesResponse),
+    StatusNotification(StatusNotificationResponse),
+    TransactionEvent(TransactionEventResponse),
+}
+
+impl Catalogue for OcppResponse {
+    fn strict<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <OcppResponse as serde::Deserialize>::deserialize(deserializer)
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::types::*;
+    use super::*;
+    use crate::ci_string::CiString;
+    use crate::types::{Measurand, ReadingContext};
+
+    #[test]
+    fn given_transaction_event_request__when_deserializing__then_ok() {
+        let json = serde_json::json!({
+            "action": "TransactionEvent",
+            "payload": {
+                "eventType": "Updated",
+                "timestamp": "2024-05-01T10:00:00Z",
+                "triggerReason": "MeterValuePeriodic",
+                "seqNo": 3,
+                "transactionInfo": {
+                    "transactionId": "TX-1",
+                    "chargingState": "SuspendedEV"
+                },
+                "evse": {"id": 1, "connectorId": 1},
+                "meterValue": [{
+                    "timestamp": "2024-05-01T10:00:00Z",
+                    "sampledValue": [{
+                        "value": 1234.5,
+                        "context": "Sample.Periodic",
+                        "measurand": "Energy.Active.Import.Register",
+                        "unitOfMeasure": {"unit": "Wh"}
+                    }]
+                }]
+            }
+        });
+
+        let result = serde_json::from_value::<OcppRequest>(json.clone()).unwrap();
+
+        let OcppRequest::TransactionEvent(event) = result else {
+            panic!("expected TransactionEvent, got {:?}", result);
+        };
+        assert_eq!(event.event_type, TransactionEventType::Updated);
+        assert_eq!(event.trigger_reason, TriggerReason::MeterValuePeriodic);
+        assert_eq!(event.seq_no, 3);
+        assert_eq!(
+            event.transaction_info.charging_state,
+            Some(ChargingState::SuspendedEv)
+        );
+        let sampled = &event.meter_value.unwrap()[0].sampled_value[0];
+        assert_eq!(
+            sampled.measurand,
+            Some(Measurand::EnergyActiveImportRegister)
+        );
+        assert_eq!(sampled.context, Some(ReadingContext::SamplePeriodic));
+        assert_eq!(sampled.value, rust_decimal::Decimal::new(12345, 1));
+    }
+
+    #[test]
+    fn given_get_variables_response__when_serializing__then_optional_fields_omitted() {
+        let response = OcppResponse::GetVariables(GetVariablesResponse {
+            get_variable_result: vec![GetVariableResult {
+                attribute_status: GetVariableStatus::Accepted,
+                attribute_type: None,
+                attribute_value: Some(CiString::new("60").unwrap()),
+                component: Component {
+                    name: CiString::new("OCPPCommCtrlr").unwrap(),
+                    instance: None,
+                    evse: None,
+                },
+                variable: Variable {
+                    name: CiString::new("HeartbeatInterval").unwrap(),
+                    instance: None,
+                },
+                attribute_status_info: None,
+            }],
+        });
+
+        let json = serde_json::to_value(&response).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "action": "GetVariables",
+                "payload": {
+                    "getVariableResult": [{
+                        "attributeStatus": "Accepted",
+                        "attributeValue": "60",
+                        "component": {"name": "OCPPCommCtrlr"},
+                        "variable": {"name": "HeartbeatInterval"}
+                    }]
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn given_notify_report__when_deserializing__then_generated_at_is_datetime() {
+        let json = serde_json::json!({
+            "action": "NotifyReport",
+            "payload": {"requestId": 1, "generatedAt": "2024-05-01T10:00:00Z", "seqNo": 0}
+        });
+
+        let result = serde_json::from_value::<OcppRequest>(json).unwrap();
+
+        let OcppRequest::NotifyReport(report) = result else {
+            panic!("expected NotifyReport, got {:?}", result);
+        };
+        assert_eq!(
+            report.generated_at,
+            crate::datetime::parse("2024-05-01T10:00:00Z").unwrap()
+        );
+    }
+
+    #[test]
+    fn given_too_long_model__when_deserializing_boot_notification__then_error() {
+        let json = serde_json::json!({
+            "action": "BootNotification",
+            "payload": {
+                "reason": "PowerUp",
+                "chargingStation": {"model": "M".repeat(21), "vendorName": "Vendor"}
+            }
+        });
+
+        let result = serde_json::from_value::<OcppRequest>(json);
+
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .starts_with("invalid length 21")
+        );
+    }
+
+    #[test]
+    fn given_unknown_action__when_deserializing__then_other() {
+        let json = serde_json::json!({"action": "CostUpdated", "payload": {"totalCost": 1}});
+
+        let result = serde_json::from_value::<OcppRequest>(json.clone()).unwrap();
+
+        assert_eq!(
+            result,
+            OcppRequest::Other {
+                action: "CostUpdated".to_string(),
+                payload: serde_json::json!({"totalCost": 1}),
+            }
+        );
+        assert_eq!(serde_json::to_value(&result).unwrap(), json);
+    }
+
+    #[test]
+    fn given_typed_action_with_invalid_payload__when_deserializing__then_error() {
+        let json = serde_json::json!({
+            "action": "Authorize",
+            "payload": {"idToken": {"idToken": 5, "type": "ISO14443"}}
+        });
+
+        let result = serde_json::from_value::<OcppRequest>(json);
+
+        assert!(result.unwrap_err().to_string().starts_with("invalid type"));
+    }
+}
@@ -0,0 +1,587 @@
+///// OCPP 2.0.1 TransactionEvent pipeline /////
+use std::collections::VecDeque;
+use std::fmt;
+#[cfg(feature = "persistence")]
+use std::fs;
+use std::io;
+#[cfg(feature = "persistence")]
+use std::io::Write;
+#[cfg(feature = "persistence")]
+use std::path::PathBuf;
+
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::ci_string::CiString;
+use crate::datetime::DateTime;
+use crate::types::{Measurand, ReadingContext};
+use crate::v201::messages::TransactionEventRequest;
+use crate::v201::types::*;
+
+#[derive(Debug)]
+pub enum TransactionError {
+    UnknownTransaction(String),
+    Store(io::Error),
+}
+
+impl fmt::Display for TransactionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransactionError::UnknownTransaction(id) => write!(f, "unknown transaction {}", id),
+            TransactionError::Store(err) => write!(f, "transaction store failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for TransactionError {}
+
+impl From<io::Error> for TransactionError {
+    fn from(err: io::Error) -> Self {
+        TransactionError::Store(err)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveTransaction {
+    pub transaction_id: CiString<36>,
+    pub evse: Evse,
+    /// `seqNo` of the last generated event
+    pub seq_no: u32,
+    pub charging_state: Option<ChargingState>,
+}
+
+/// Everything needed to continue after a reboot or outage
+#[derive(Debug, Clone, Default, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionState {
+    pub active: Vec<ActiveTransaction>,
+    /// Events generated but not yet confirmed by the CSMS, oldest first
+    pub queue: VecDeque<TransactionEventRequest>,
+}
+
+pub trait TransactionStore {
+    fn load(&mut self) -> io::Result<TransactionState>;
+    fn save(&mut self, state: &TransactionState) -> io::Result<()>;
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    pub state: TransactionState,
+}
+
+impl TransactionStore for MemoryStore {
+    fn load(&mut self) -> io::Result<TransactionState> {
+        Ok(self.state.clone())
+    }
+
+    fn save(&mut self, state: &TransactionState) -> io::Result<()> {
+        self.state = state.clone();
+        Ok(())
+    }
+}
+
+/// Keeps the state as JSON file, written and synced to a temporary file first so an outage
+/// never leaves a truncated state behind
+#[cfg(feature = "persistence")]
+#[derive(Debug)]
+pub struct FileStore {
+    path: PathBuf,
+}
+
+#[cfg(feature = "persistence")]
+impl FileStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[cfg(feature = "persistence")]
+impl TransactionStore for FileStore {
+    fn load(&mut self) -> io::Result<TransactionState> {
+        match fs::read_to_string(&self.path) {
+            Ok(json) => serde_json::from_str(&json).map_err(io::Error::other),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(TransactionState::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn save(&mut self, state: &TransactionState) -> io::Result<()> {
+        let json = serde_json::to_string(state).map_err(io::Error::other)?;
+        let tmp = self.path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(json.as_bytes())?;
+        // the data has to be on disk before the rename makes it the state
+        file.sync_all()?;
+        fs::rename(tmp, &self.path)?;
+        // and the rename itself has to be on disk before the event is handed out
+        #[cfg(unix)]
+        {
+            let dir = match self.path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => std::path::Path::new("."),
+            };
+            fs::File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+/// Generates TransactionEvent requests and queues them until the CSMS confirms them.
+///
+/// Every generated event is persisted before it is handed out, so events created while
+/// offline or lost in an outage are replayed in order from [`pending`](Self::pending).
+/// A failed save leaves the pipeline as it was before the call.
+///
+/// Transaction ids are random UUIDs, so they stay unique even if the store is wiped.
+pub struct TransactionPipeline<S> {
+    store: S,
+    state: TransactionState,
+    offline: bool,
+}
+
+impl<S: TransactionStore> TransactionPipeline<S> {
+    pub fn new(mut store: S) -> Result<Self, TransactionError> {
+        let state = store.load()?;
+        Ok(Self {
+            store,
+            state,
+            offline: false,
+        })
+    }
+
+    /// Events generated while offline are flagged with `offline: true`
+    pub fn set_offline(&mut self, offline: bool) {
+        self.offline = offline;
+    }
+
+    pub fn active(&self) -> &[ActiveTransaction] {
+        &self.state.active
+    }
+
+    pub fn started(
+        &mut self,
+        evse: Evse,
+        trigger_reason: TriggerReason,
+        timestamp: DateTime,
+        id_token: Option<IdToken>,
+        meter_value: Vec<MeterValue>,
+    ) -> Result<TransactionEventRequest, TransactionError> {
+        let transaction_id =
+            CiString::new(Uuid::new_v4().to_string()).expect("UUIDs have 36 characters");
+        let mut state = self.state.clone();
+        state.active.push(ActiveTransaction {
+            transaction_id: transaction_id.clone(),
+            evse: evse.clone(),
+            seq_no: 0,
+            charging_state: None,
+        });
+        let mut event = state.event(
+            TransactionEventType::Started,
+            &transaction_id,
+            trigger_reason,
+            timestamp,
+            self.offline,
+            meter_value,
+        )?;
+        event.evse = Some(evse);
+        event.id_token = id_token;
+        self.enqueue(state, event)
+    }
+
+    /// Reports a charging state change, the state is only attached if it differs from the last one
+    pub fn updated(
+        &mut self,
+        transaction_id: &str,
+        trigger_reason: TriggerReason,
+        timestamp: DateTime,
+        charging_state: Option<ChargingState>,
+        meter_value: Vec<MeterValue>,
+    ) -> Result<TransactionEventRequest, TransactionError> {
+        let mut state = self.state.clone();
+        let mut event = state.event(
+            TransactionEventType::Updated,
+            transaction_id,
+            trigger_reason,
+            timestamp,
+            self.offline,
+            meter_value,
+        )?;
+        let active = state.find(transaction_id)?;
+        if charging_state.is_some() && charging_state != active.charging_state {
+            active.charging_state = charging_state;
+            event.transaction_info.charging_state = charging_state;
+        }
+        self.enqueue(state, event)
+    }
+
+    pub fn ended(
+        &mut self,
+        transaction_id: &str,
+        trigger_reason: TriggerReason,
+        timestamp: DateTime,
+        stopped_reason: Reason,
+        meter_value: Vec<MeterValue>,
+    ) -> Result<TransactionEventRequest, TransactionError> {
+        let mut state = self.state.clone();
+        let mut event = state.event(
+            TransactionEventType::Ended,
+            transaction_id,
+            trigger_reason,
+            timestamp,
+            self.offline,
+            meter_value,
+        )?;
+        event.transaction_info.stopped_reason = Some(stopped_reason);
+        event.transaction_info.charging_state = Some(ChargingState::Idle);
+        state
+            .active
+            .retain(|active| active.transaction_id != transaction_id);
+        self.enqueue(state, event)
+    }
+
+    /// Events still waiting for a TransactionEventResponse, oldest first
+    pub fn pending(&self) -> impl Iterator<Item = &TransactionEventRequest> {
+        self.state.queue.iter()
+    }
+
+    /// Removes a confirmed event from the queue
+    pub fn acknowledge(
+        &mut self,
+        transaction_id: &str,
+        seq_no: u32,
+    ) -> Result<(), TransactionError> {
+        let mut state = self.state.clone();
+        state.queue.retain(|event| {
+            event.transaction_info.transaction_id != transaction_id || event.seq_no != seq_no
+        });
+        self.commit(state)
+    }
+
+    fn enqueue(
+        &mut self,
+        mut state: TransactionState,
+        event: TransactionEventRequest,
+    ) -> Result<TransactionEventRequest, TransactionError> {
+        state.queue.push_back(event.clone());
+        self.commit(state)?;
+        Ok(event)
+    }
+
+    /// Takes over `state` once it is stored
+    fn commit(&mut self, state: TransactionState) -> Result<(), TransactionError> {
+        self.store.save(&state)?;
+        self.state = state;
+        Ok(())
+    }
+}
+
+impl TransactionState {
+    fn event(
+        &mut self,
+        event_type: TransactionEventType,
+        transaction_id: &str,
+        trigger_reason: TriggerReason,
+        timestamp: DateTime,
+        offline: bool,
+        meter_value: Vec<MeterValue>,
+    ) -> Result<TransactionEventRequest, TransactionError> {
+        let active = self.find(transaction_id)?;
+        let seq_no = match event_type {
+            TransactionEventType::Started => 0,
+            _ => active.seq_no + 1,
+        };
+        active.seq_no = seq_no;
+        Ok(TransactionEventRequest {
+            event_type,
+            timestamp,
+            trigger_reason,
+            seq_no,
+            offline: offline.then_some(true),
+            number_of_phases_used: None,
+            cable_max_current: None,
+            reservation_id: None,
+            transaction_info: Transaction {
+                transaction_id: active.transaction_id.clone(),
+                charging_state: None,
+                time_spent_charging: None,
+                stopped_reason: None,
+                remote_start_id: None,
+            },
+            id_token: None,
+            evse: None,
+            meter_value: (!meter_value.is_empty()).then_some(meter_value),
+        })
+    }
+
+    fn find(&mut self, transaction_id: &str) -> Result<&mut ActiveTransaction, TransactionError> {
+        self.active
+            .iter_mut()
+            .find(|active| active.transaction_id == transaction_id)
+            .ok_or_else(|| TransactionError::UnknownTransaction(transaction_id.to_string()))
+    }
+}
+
+/// Builds a meter value from `(measurand, value)` samples taken at the same time
+pub fn meter_value(
+    timestamp: DateTime,
+    context: ReadingContext,
+    samples: &[(Measurand, f64)],
+) -> MeterValue {
+    let sampled_value = samples
+        .iter()
+        .filter_map(|(measurand, value)| {
+            Some(SampledValue {
+                value: Decimal::try_from(*value).ok()?,
+                context: Some(context),
+                measurand: Some(*measurand),
+                phase: None,
+                location: None,
+                unit_of_measure: None,
+            })
+        })
+        .collect();
+    MeterValue {
+        timestamp,
+        sampled_value,
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn t0() -> DateTime {
+        crate::datetime::parse("2024-05-01T10:00:00Z").unwrap()
+    }
+
+    fn evse() -> Evse {
+        Evse {
+            id: 1,
+            connector_id: Some(1),
+        }
+    }
+
+    fn pipeline() -> TransactionPipeline<MemoryStore> {
+        TransactionPipeline::new(MemoryStore::default()).unwrap()
+    }
+
+    fn energy(context: ReadingContext, wh: f64) -> Vec<MeterValue> {
+        vec![meter_value(
+            t0(),
+            context,
+            &[(Measurand::EnergyActiveImportRegister, wh)],
+        )]
+    }
+
+    #[test]
+    fn given_transaction__when_generating_events__then_seq_no_increases() {
+        let mut pipeline = pipeline();
+
+        let started = pipeline
+            .started(
+                evse(),
+                TriggerReason::CablePluggedIn,
+                t0(),
+                None,
+                energy(ReadingContext::TransactionBegin, 0.0),
+            )
+            .unwrap();
+        let id = started.transaction_info.transaction_id.clone();
+        let updated = pipeline
+            .updated(
+                &id,
+                TriggerReason::ChargingStateChanged,
+                t0(),
+                Some(ChargingState::Charging),
+                vec![],
+            )
+            .unwrap();
+        let ended = pipeline
+            .ended(
+                &id,
+                TriggerReason::EvDeparted,
+                t0(),
+                Reason::EvDisconnected,
+                energy(ReadingContext::TransactionEnd, 1500.0),
+            )
+            .unwrap();
+
+        assert_eq!(id.len(), 36);
+        assert_eq!([started.seq_no, updated.seq_no, ended.seq_no], [0, 1, 2]);
+        assert_eq!(started.evse, Some(evse()));
+        assert_eq!(
+            updated.transaction_info.charging_state,
+            Some(ChargingState::Charging)
+        );
+        assert_eq!(updated.meter_value, None);
+        assert_eq!(
+            ended.transaction_info.stopped_reason,
+            Some(Reason::EvDisconnected)
+        );
+        assert_eq!(
+            ended.meter_value.unwrap()[0].sampled_value[0].context,
+            Some(ReadingContext::TransactionEnd)
+        );
+        assert!(pipeline.active().is_empty());
+    }
+
+    #[test]
+    fn given_unchanged_charging_state__when_updating__then_state_omitted() {
+        let mut pipeline = pipeline();
+        let started = pipeline
+            .started(evse(), TriggerReason::Authorized, t0(), None, vec![])
+            .unwrap();
+        let id = started.transaction_info.transaction_id;
+
+        pipeline
+            .updated(
+                &id,
+                TriggerReason::ChargingStateChanged,
+                t0(),
+                Some(ChargingState::Charging),
+                vec![],
+            )
+            .unwrap();
+        let periodic = pipeline
+            .updated(
+                &id,
+                TriggerReason::MeterValuePeriodic,
+                t0(),
+                Some(ChargingState::Charging),
+                energy(ReadingContext::SamplePeriodic, 100.0),
+            )
+            .unwrap();
+
+        assert_eq!(periodic.transaction_info.charging_state, None);
+    }
+
+    #[test]
+    fn given_offline__when_generating_events__then_flagged_and_queued_until_acknowledged() {
+        let mut pipeline = pipeline();
+        pipeline.set_offline(true);
+        let started = pipeline
+            .started(evse(), TriggerReason::Authorized, t0(), None, vec![])
+            .unwrap();
+        let id = started.transaction_info.transaction_id.clone();
+        pipeline.set_offline(false);
+        let updated = pipeline
+            .updated(&id, TriggerReason::MeterValuePeriodic, t0(), None, vec![])
+            .unwrap();
+
+        assert_eq!(started.offline, Some(true));
+        assert_eq!(updated.offline, None);
+        assert_eq!(
+            pipeline
+                .pending()
+                .map(|event| event.seq_no)
+                .collect::<Vec<_>>(),
+            [0, 1]
+        );
+
+        pipeline.acknowledge(&id, 0).unwrap();
+
+        assert_eq!(
+            pipeline
+                .pending()
+                .map(|event| event.seq_no)
+                .collect::<Vec<_>>(),
+            [1]
+        );
+    }
+
+    #[test]
+    fn given_unknown_transaction__when_updating__then_error() {
+        let result = pipeline().updated("nope", TriggerReason::Trigger, t0(), None, vec![]);
+
+        assert!(matches!(result, Err(TransactionError::UnknownTransaction(id)) if id == "nope"));
+    }
+
+    /// Fails every save once `fail` is set
+    #[derive(Default)]
+    struct FlakyStore {
+        inner: MemoryStore,
+        fail: bool,
+    }
+
+    impl TransactionStore for FlakyStore {
+        fn load(&mut self) -> io::Result<TransactionState> {
+            self.inner.load()
+        }
+
+        fn save(&mut self, state: &TransactionState) -> io::Result<()> {
+            if self.fail {
+                return Err(io::Error::other("disk full"));
+            }
+            self.inner.save(state)
+        }
+    }
+
+    #[test]
+    fn given_failing_store__when_generating_event__then_state_unchanged() {
+        let mut pipeline = TransactionPipeline::new(FlakyStore::default()).unwrap();
+        let started = pipeline
+            .started(evse(), TriggerReason::Authorized, t0(), None, vec![])
+            .unwrap();
+        let id = started.transaction_info.transaction_id;
+        pipeline.store.fail = true;
+
+        let updated = pipeline.updated(
+            &id,
+            TriggerReason::ChargingStateChanged,
+            t0(),
+            Some(ChargingState::Charging),
+            vec![],
+        );
+        let second = pipeline.started(evse(), TriggerReason::Authorized, t0(), None, vec![]);
+
+        assert!(matches!(updated, Err(TransactionError::Store(_))));
+        assert!(matches!(second, Err(TransactionError::Store(_))));
+        assert_eq!(pipeline.state, pipeline.store.inner.state);
+        assert_eq!(pipeline.active()[0].seq_no, 0);
+        assert_eq!(pipeline.active()[0].charging_state, None);
+    }
+
+    #[test]
+    fn given_wiped_store__when_starting__then_transaction_id_not_reused() {
+        let first = pipeline()
+            .started(evse(), TriggerReason::Authorized, t0(), None, vec![])
+            .unwrap();
+        let second = pipeline()
+            .started(evse(), TriggerReason::Authorized, t0(), None, vec![])
+            .unwrap();
+
+        assert_ne!(
+            first.transaction_info.transaction_id,
+            second.transaction_info.transaction_id
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "persistence")]
+    fn given_file_store__when_restarting__then_queue_and_counters_replayed() {
+        let path = std::env::temp_dir().join(format!("ocpp-tx-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut before = TransactionPipeline::new(FileStore::new(&path)).unwrap();
+        let started = before
+            .started(evse(), TriggerReason::Authorized, t0(), None, vec![])
+            .unwrap();
+        let id = started.transaction_info.transaction_id.clone();
+        drop(before);
+
+        let mut after = TransactionPipeline::new(FileStore::new(&path)).unwrap();
+        let updated = after
+            .updated(&id, TriggerReason::MeterValuePeriodic, t0(), None, vec![])
+            .unwrap();
+        let next = after
+            .started(evse(), TriggerReason::Authorized, t0(), None, vec![])
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(updated.seq_no, 1);
+        assert_ne!(next.transaction_info.transaction_id, id);
+        assert_eq!(after.pending().count(), 3);
+    }
+}
@@ -0,0 +1,460 @@
+///// OCPP 2.0.1 enumerations and shared data types /////
+use rust_decimal::Decimal;
+
+use crate::ci_string::CiString;
+use crate::datetime::{DateTime, rfc3339};
+pub use crate::types::{ChargingRateUnit, Phase};
+use crate::types::{Measurand, ReadingContext};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum BootReason {
+    ApplicationReset,
+    FirmwareUpdate,
+    LocalReset,
+    PowerUp,
+    RemoteReset,
+    ScheduledReset,
+    Triggered,
+    Unknown,
+    Watchdog,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum RegistrationStatus {
+    Accepted,
+    Pending,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ConnectorStatus {
+    Available,
+    Occupied,
+    Reserved,
+    Unavailable,
+    Faulted,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum AuthorizationStatus {
+    Accepted,
+    Blocked,
+    ConcurrentTx,
+    Expired,
+    Invalid,
+    NoCredit,
+    #[serde(rename = "NotAllowedTypeEVSE")]
+    NotAllowedTypeEvse,
+    NotAtThisLocation,
+    NotAtThisTime,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum IdTokenType {
+    Central,
+    #[serde(rename = "eMAID")]
+    EMaid,
+    #[serde(rename = "ISO14443")]
+    Iso14443,
+    #[serde(rename = "ISO15693")]
+    Iso15693,
+    KeyCode,
+    Local,
+    MacAddress,
+    NoAuthorization,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum TransactionEventType {
+    Started,
+    Updated,
+    Ended,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum TriggerReason {
+    Authorized,
+    CablePluggedIn,
+    ChargingRateChanged,
+    ChargingStateChanged,
+    Deauthorized,
+    EnergyLimitReached,
+    #[serde(rename = "EVCommunicationLost")]
+    EvCommunicationLost,
+    #[serde(rename = "EVConnectTimeout")]
+    EvConnectTimeout,
+    MeterValueClock,
+    MeterValuePeriodic,
+    TimeLimitReached,
+    Trigger,
+    UnlockCommand,
+    StopAuthorized,
+    #[serde(rename = "EVDeparted")]
+    EvDeparted,
+    #[serde(rename = "EVDetected")]
+    EvDetected,
+    RemoteStop,
+    RemoteStart,
+    AbnormalCondition,
+    SignedDataReceived,
+    ResetCommand,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ChargingState {
+    Charging,
+    #[serde(rename = "EVConnected")]
+    EvConnected,
+    #[serde(rename = "SuspendedEV")]
+    SuspendedEv,
+    #[serde(rename = "SuspendedEVSE")]
+    SuspendedEvse,
+    Idle,
+}
+
+/// `stoppedReason` of a transaction
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Reason {
+    DeAuthorized,
+    EmergencyStop,
+    EnergyLimitReached,
+    #[serde(rename = "EVDisconnected")]
+    EvDisconnected,
+    GroundFault,
+    ImmediateReset,
+    Local,
+    LocalOutOfCredit,
+    MasterPass,
+    Other,
+    OvercurrentFault,
+    PowerLoss,
+    PowerQuality,
+    Reboot,
+    Remote,
+    #[serde(rename = "SOCLimitReached")]
+    SocLimitReached,
+    #[serde(rename = "StoppedByEV")]
+    StoppedByEv,
+    TimeLimitReached,
+    Timeout,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum RequestStartStopStatus {
+    Accepted,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ResetType {
+    Immediate,
+    OnIdle,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ResetStatus {
+    Accepted,
+    Rejected,
+    Scheduled,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ClearCacheStatus {
+    Accepted,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum CancelReservationStatus {
+    Accepted,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ChangeAvailabilityStatus {
+    Accepted,
+    Rejected,
+    Scheduled,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum OperationalStatus {
+    Inoperative,
+    Operative,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum AttributeType {
+    Actual,
+    Target,
+    MinSet,
+    MaxSet,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum GetVariableStatus {
+    Accepted,
+    Rejected,
+    UnknownComponent,
+    UnknownVariable,
+    NotSupportedAttributeType,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum SetVariableStatus {
+    Accepted,
+    Rejected,
+    UnknownComponent,
+    UnknownVariable,
+    NotSupportedAttributeType,
+    RebootRequired,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum GenericDeviceModelStatus {
+    Accepted,
+    Rejected,
+    NotSupported,
+    EmptyResultSet,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[allow(clippy::enum_variant_names)]
+pub enum ReportBase {
+    ConfigurationInventory,
+    FullInventory,
+    SummaryInventory,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ComponentCriterion {
+    Active,
+    Available,
+    Enabled,
+    Problem,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Mutability {
+    ReadOnly,
+    WriteOnly,
+    ReadWrite,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum DataType {
+    #[serde(rename = "string")]
+    String,
+    #[serde(rename = "decimal")]
+    Decimal,
+    #[serde(rename = "integer")]
+    Integer,
+    #[serde(rename = "dateTime")]
+    DateTime,
+    #[serde(rename = "boolean")]
+    Boolean,
+    OptionList,
+    SequenceList,
+    MemberList,
+}
+
+/// Location of a sampled value
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum MeasurementLocation {
+    Body,
+    Cable,
+    #[serde(rename = "EV")]
+    Ev,
+    Inlet,
+    Outlet,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusInfo {
+    pub reason_code: CiString<20>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub additional_info: Option<CiString<512>>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdToken {
+    /// Case insensitive, at most 36 characters since 2.0.1
+    pub id_token: CiString<36>,
+    #[serde(rename = "type")]
+    pub kind: IdTokenType,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdTokenInfo {
+    pub status: AuthorizationStatus,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "rfc3339::option"
+    )]
+    pub cache_expiry_date_time: Option<DateTime>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Evse {
+    pub id: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connector_id: Option<u32>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Component {
+    pub name: CiString<50>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<CiString<50>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub evse: Option<Evse>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Variable {
+    pub name: CiString<50>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<CiString<50>>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentVariable {
+    pub component: Component,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variable: Option<Variable>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VariableAttribute {
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<AttributeType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<CiString<2500>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mutability: Option<Mutability>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub persistent: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub constant: Option<bool>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VariableCharacteristics {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<CiString<16>>,
+    pub data_type: DataType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_limit: Option<serde_json::Number>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_limit: Option<serde_json::Number>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub values_list: Option<CiString<1000>>,
+    pub supports_monitoring: bool,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportData {
+    pub component: Component,
+    pub variable: Variable,
+    pub variable_attribute: Vec<VariableAttribute>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variable_characteristics: Option<VariableCharacteristics>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnitOfMeasure {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<CiString<20>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub multiplier: Option<i32>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SampledValue {
+    #[serde(with = "rust_decimal::serde::float")]
+    pub value: Decimal,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<ReadingContext>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub measurand: Option<Measurand>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phase: Option<Phase>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<MeasurementLocation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit_of_measure: Option<UnitOfMeasure>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeterValue {
+    #[serde(with = "rfc3339")]
+    pub timestamp: DateTime,
+    pub sampled_value: Vec<SampledValue>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Transaction {
+    pub transaction_id: CiString<36>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub charging_state: Option<ChargingState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_spent_charging: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stopped_reason: Option<Reason>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_start_id: Option<i32>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChargingSchedulePeriod {
+    /// Seconds from the start of the schedule
+    pub start_period: u32,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub limit: Decimal,
+    /// Three when absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number_phases: Option<u8>,
+    /// 1 to 3, the phase to charge on with a single phase
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phase_to_use: Option<u8>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChargingSchedule {
+    pub id: i32,
+    #[serde(
+        default,
+        with = "rfc3339::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub start_schedule: Option<DateTime>,
+    /// Seconds, the last period lasts forever when absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u32>,
+    pub charging_rate_unit: ChargingRateUnit,
+    pub charging_schedule_period: Vec<ChargingSchedulePeriod>,
+    #[serde(
+        default,
+        with = "rust_decimal::serde::float_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub min_charging_rate: Option<Decimal>,
+}
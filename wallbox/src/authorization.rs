@@ -0,0 +1,122 @@
+///// Authorization of the id tokens presented at the reader /////
+use std::collections::HashMap;
+
+use ocpp::types::IdToken;
+
+use crate::hal::{HalError, IdTokenReader};
+
+/// Outcome of presenting a token
+#[derive(Debug, Clone, PartialEq)]
+pub enum Authorization {
+    Accepted(IdToken),
+    /// Blocked, expired or invalid according to the local list
+    Rejected(IdToken),
+    /// Not in the local list, to be authorized by the CSMS with an Authorize request
+    Unknown(IdToken),
+}
+
+/// Tokens the charge point decides on without asking the CSMS, `SendLocalList` in 1.6
+#[derive(Debug, Clone, Default)]
+pub struct LocalAuthorizationList {
+    version: i32,
+    /// Keyed by the uppercase token, tokens compare case insensitive
+    entries: HashMap<String, bool>,
+}
+
+impl LocalAuthorizationList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+
+    /// Replaces the whole list
+    pub fn replace(&mut self, version: i32, entries: &[(IdToken, bool)]) {
+        self.entries.clear();
+        self.update(version, entries);
+    }
+
+    /// Adds or changes `entries`, `true` accepts the token
+    pub fn update(&mut self, version: i32, entries: &[(IdToken, bool)]) {
+        self.version = version;
+        for (id_token, accepted) in entries {
+            self.entries.insert(key(id_token), *accepted);
+        }
+    }
+
+    pub fn authorize(&self, id_token: IdToken) -> Authorization {
+        match self.entries.get(&key(&id_token)) {
+            Some(true) => Authorization::Accepted(id_token),
+            Some(false) => Authorization::Rejected(id_token),
+            None => Authorization::Unknown(id_token),
+        }
+    }
+}
+
+fn key(id_token: &IdToken) -> String {
+    id_token.to_ascii_uppercase()
+}
+
+/// Authorizes the tokens of a reader against the local list
+#[derive(Debug)]
+pub struct Authorizer<R: IdTokenReader> {
+    reader: R,
+    list: LocalAuthorizationList,
+}
+
+impl<R: IdTokenReader> Authorizer<R> {
+    pub fn new(reader: R, list: LocalAuthorizationList) -> Self {
+        Self { reader, list }
+    }
+
+    pub fn list_mut(&mut self) -> &mut LocalAuthorizationList {
+        &mut self.list
+    }
+
+    /// Decides on the token presented since the last call, if any
+    pub fn poll(&mut self) -> Result<Option<Authorization>, HalError> {
+        Ok(self
+            .reader
+            .read()?
+            .map(|id_token| self.list.authorize(id_token)))
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::SimulatedReader;
+
+    fn token(id: &str) -> IdToken {
+        IdToken::new(id).unwrap()
+    }
+
+    #[test]
+    fn given_local_list__when_tokens_presented__then_decided_case_insensitive() {
+        let reader = SimulatedReader::new();
+        let mut list = LocalAuthorizationList::new();
+        list.replace(1, &[(token("04A2B3C4"), true), (token("DEADBEEF"), false)]);
+        let mut authorizer = Authorizer::new(reader.clone(), list);
+
+        assert_eq!(authorizer.poll(), Ok(None));
+        for id in ["04a2b3c4", "DEADBEEF", "CAFEBABE"] {
+            reader.present(token(id));
+        }
+
+        assert_eq!(
+            authorizer.poll(),
+            Ok(Some(Authorization::Accepted(token("04A2B3C4"))))
+        );
+        assert_eq!(
+            authorizer.poll(),
+            Ok(Some(Authorization::Rejected(token("DEADBEEF"))))
+        );
+        assert_eq!(
+            authorizer.poll(),
+            Ok(Some(Authorization::Unknown(token("CAFEBABE"))))
+        );
+    }
+}
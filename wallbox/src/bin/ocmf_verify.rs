@@ -0,0 +1,126 @@
+//! Checks the OCMF signatures of recorded transactions.
+//!
+//! Usage: `ocmf-verify <public-key-hex> [file]`
+//!
+//! Reads journaled OCPP frames from `file` or stdin, one per line as JSON or as printed by
+//! `message-broker replay`, i.e. the direction followed by the frame. Verifies every OCMF
+//! record found in them and pairs transaction begin and end records of the same meter.
+//! Exits with 1 if any record fails, a line does not parse, no record is found at all or a
+//! transaction begins without end.
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::process::ExitCode;
+
+use wallbox::ocmf;
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let Some(public_key) = args.next() else {
+        eprintln!("usage: ocmf-verify <public-key-hex> [file]");
+        return ExitCode::FAILURE;
+    };
+    let input: Box<dyn BufRead> = match args.next() {
+        Some(path) => match File::open(&path) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(err) => {
+                eprintln!("cannot open {}: {}", path, err);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => Box::new(io::stdin().lock()),
+    };
+
+    let mut failed = false;
+    let mut records = 0;
+    let mut unparsable = 0;
+    // begin record per meter serial and identification, waiting for its end
+    let mut open = HashMap::new();
+    for (index, line) in input.lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                eprintln!("cannot read input: {}", err);
+                return ExitCode::FAILURE;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        // a replayed line is the direction and the frame, two JSON values in a row
+        let Ok(values) = serde_json::Deserializer::from_str(&line)
+            .into_iter::<serde_json::Value>()
+            .collect::<Result<Vec<_>, _>>()
+        else {
+            println!("line {}: FAILED not JSON", index + 1);
+            unparsable += 1;
+            continue;
+        };
+        for record in values.iter().flat_map(ocmf::find_records) {
+            records += 1;
+            let payload = match ocmf::verify(record, &public_key) {
+                Ok(payload) => payload,
+                Err(err) => {
+                    println!("line {}: FAILED {}", index + 1, err);
+                    failed = true;
+                    continue;
+                }
+            };
+            let key = (payload.meter_serial.clone(), payload.identification.clone());
+            for reading in &payload.readings {
+                println!(
+                    "line {}: OK meter {} {} {} {} {}",
+                    index + 1,
+                    payload.meter_serial,
+                    reading.transaction,
+                    reading.time,
+                    reading.value,
+                    reading.unit
+                );
+            }
+            match payload
+                .readings
+                .last()
+                .map(|reading| reading.transaction.as_str())
+            {
+                Some("B") => {
+                    open.insert(key, record.to_string());
+                }
+                Some("E") => match open.remove(&key) {
+                    Some(begin) => match ocmf::verify_transaction(&begin, record, &public_key) {
+                        Ok(energy_wh) => {
+                            println!("transaction of meter {}: {} Wh", key.0, energy_wh)
+                        }
+                        Err(err) => {
+                            println!("transaction of meter {}: FAILED {}", key.0, err);
+                            failed = true;
+                        }
+                    },
+                    None => {
+                        println!("line {}: FAILED end without begin", index + 1);
+                        failed = true;
+                    }
+                },
+                _ => {}
+            }
+        }
+    }
+    for (meter_serial, _) in open.keys() {
+        println!(
+            "transaction of meter {}: FAILED begin without end",
+            meter_serial
+        );
+    }
+    if unparsable > 0 {
+        eprintln!("{} lines not parsed", unparsable);
+    }
+    if records == 0 {
+        eprintln!("no OCMF record found");
+    }
+
+    if failed || unparsable > 0 || records == 0 || !open.is_empty() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,431 @@
+///// Transaction logic of one connector, on top of the hardware traits /////
+use std::time::Instant;
+
+use ocpp::types::{ChargePointStatus, IdToken, Measurand, Reason};
+
+use crate::hal::{ConnectorLock, Contactor, EnergyMeter, HalError, PilotInterface};
+use crate::iec61851::{ControlPilot, CpState};
+
+/// The devices of one connector
+#[derive(Debug, Clone)]
+pub struct ConnectorHardware<P, C, L, M> {
+    pub pilot: P,
+    pub contactor: C,
+    pub lock: L,
+    pub meter: M,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectorEvent {
+    StatusChanged(ChargePointStatus),
+    TransactionStarted {
+        id_tag: IdToken,
+        meter_start_wh: f64,
+    },
+    TransactionStopped {
+        meter_stop_wh: f64,
+        reason: Reason,
+    },
+    /// The contactor stayed closed when opened, the connector is faulted from now on
+    ContactorWelded,
+}
+
+/// Runs transactions on one connector: locks the plug, starts once the vehicle is connected and
+/// authorized, switches the supply as the pilot asks for and stops when the vehicle goes away.
+///
+/// The supply is only switched on while the plug is locked, and the plug only unlocked while
+/// the supply is off. When the pilot withdraws the offer the contactor stays closed until the
+/// vehicle stops drawing, leaving C and D, or the deadline of the pilot passes.
+///
+/// State changes are committed once the hardware followed, events of a poll failing part way
+/// are returned by the next one.
+#[derive(Debug)]
+pub struct ConnectorController<P, C, L, M> {
+    hardware: ConnectorHardware<P, C, L, M>,
+    pilot: ControlPilot,
+    max_current: f64,
+    current_limit: f64,
+    authorized: Option<IdToken>,
+    /// `meter_start_wh` of the running transaction
+    transaction: Option<f64>,
+    locked: bool,
+    closed: bool,
+    welded: bool,
+    status: ChargePointStatus,
+    /// Not yet returned by [`poll`](Self::poll)
+    events: Vec<ConnectorEvent>,
+}
+
+impl<P, C, L, M> ConnectorController<P, C, L, M>
+where
+    P: PilotInterface,
+    C: Contactor,
+    L: ConnectorLock,
+    M: EnergyMeter,
+{
+    /// `max_current` is the rating of the connector in ampere per phase
+    pub fn new(
+        hardware: ConnectorHardware<P, C, L, M>,
+        pilot: ControlPilot,
+        max_current: f64,
+    ) -> Self {
+        Self {
+            hardware,
+            status: pilot.status(),
+            pilot,
+            max_current,
+            current_limit: max_current,
+            authorized: None,
+            transaction: None,
+            locked: false,
+            closed: false,
+            welded: false,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn hardware_mut(&mut self) -> &mut ConnectorHardware<P, C, L, M> {
+        &mut self.hardware
+    }
+
+    pub fn pilot(&self) -> &ControlPilot {
+        &self.pilot
+    }
+
+    pub fn status(&self) -> ChargePointStatus {
+        self.status
+    }
+
+    pub fn in_transaction(&self) -> bool {
+        self.transaction.is_some()
+    }
+
+    pub fn current_limit(&self) -> f64 {
+        self.current_limit
+    }
+
+    /// Lets `id_tag` start a transaction once a vehicle is connected
+    pub fn authorize(&mut self, id_tag: IdToken) {
+        self.authorized = Some(id_tag);
+    }
+
+    /// Limits the offered current, capped at the rating. Below 6 A the PWM stops and the
+    /// vehicle pauses.
+    pub fn set_current_limit(&mut self, amps: f64, now: Instant) {
+        self.current_limit = amps.clamp(0.0, self.max_current);
+        if self.transaction.is_some() {
+            self.pilot.offer_current(Some(self.current_limit), now);
+        }
+    }
+
+    /// Stops the running transaction, the supply goes off and the plug is released on the next
+    /// [`poll`](Self::poll). Nothing changes if the meter cannot be read.
+    pub fn stop(
+        &mut self,
+        reason: Reason,
+        now: Instant,
+    ) -> Result<Option<ConnectorEvent>, HalError> {
+        if self.transaction.is_none() {
+            return Ok(None);
+        }
+        let meter_stop_wh = self.energy()?;
+        self.transaction = None;
+        self.pilot.set_transaction(false);
+        self.pilot.offer_current(None, now);
+        Ok(Some(ConnectorEvent::TransactionStopped {
+            meter_stop_wh,
+            reason,
+        }))
+    }
+
+    /// Samples the pilot and brings the hardware in line, returns what happened
+    pub fn poll(&mut self, now: Instant) -> Result<Vec<ConnectorEvent>, HalError> {
+        self.step(now)?;
+        Ok(std::mem::take(&mut self.events))
+    }
+
+    fn step(&mut self, now: Instant) -> Result<(), HalError> {
+        let sample = self.hardware.pilot.sample()?;
+        if let Some(transition) = self.pilot.update(sample, now)
+            && transition.to == CpState::A
+        {
+            self.authorized = None;
+        }
+        // retried by every poll until the meter reading is there
+        if self.pilot.state() == CpState::A
+            && let Some(event) = self.stop(Reason::EVDisconnected, now)?
+        {
+            self.events.push(event);
+        }
+        if self.pilot.state().is_connected()
+            && self.transaction.is_none()
+            && !self.welded
+            && let Some(id_tag) = self.authorized.clone()
+        {
+            let meter_start_wh = self.energy()?;
+            if !self.locked {
+                self.hardware.lock.lock()?;
+                self.locked = true;
+            }
+            self.authorized = None;
+            self.transaction = Some(meter_start_wh);
+            self.pilot.set_transaction(true);
+            self.pilot.offer_current(Some(self.current_limit), now);
+            self.events.push(ConnectorEvent::TransactionStarted {
+                id_tag,
+                meter_start_wh,
+            });
+        }
+
+        let closed = self.contactor_closed(now) && self.locked && !self.welded;
+        if closed != self.closed {
+            self.hardware.contactor.set_closed(closed)?;
+            self.closed = closed;
+            if !closed && self.hardware.contactor.is_welded()? {
+                self.welded = true;
+                self.events.push(ConnectorEvent::ContactorWelded);
+            }
+        }
+        // a welded contactor keeps the plug locked, the cable is live
+        if self.transaction.is_none() && self.locked && !self.closed && !self.welded {
+            self.hardware.lock.unlock()?;
+            self.locked = false;
+        }
+        self.hardware
+            .pilot
+            .set_duty_cycle(self.pilot.duty_cycle())?;
+
+        let status = if self.welded {
+            ChargePointStatus::Faulted
+        } else {
+            self.pilot.status()
+        };
+        if status != self.status {
+            self.status = status;
+            self.events.push(ConnectorEvent::StatusChanged(status));
+        }
+        Ok(())
+    }
+
+    /// Opens under load only once the vehicle had its time to stop drawing
+    fn contactor_closed(&self, now: Instant) -> bool {
+        let demand = self.pilot.contactor();
+        let drawing = matches!(self.pilot.state(), CpState::C | CpState::D);
+        demand.closed || (self.closed && drawing && now < demand.deadline)
+    }
+
+    fn energy(&mut self) -> Result<f64, HalError> {
+        self.hardware
+            .meter
+            .read_one(Measurand::EnergyActiveImportRegister, None)?
+            .ok_or_else(|| {
+                HalError::InvalidResponse("meter has no Energy.Active.Import.Register".to_string())
+            })
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iec61851::PWM_STOP_DELAY;
+    use crate::sim::{
+        SimulatedBoard, SimulatedContactor, SimulatedLock, SimulatedMeter, SimulatedPilot,
+    };
+    use std::time::Duration;
+
+    type Controller =
+        ConnectorController<SimulatedPilot, SimulatedContactor, SimulatedLock, SimulatedMeter>;
+
+    fn controller(board: &SimulatedBoard, t0: Instant) -> Controller {
+        ConnectorController::new(
+            board.hardware(),
+            ControlPilot::new(t0).debounce(Duration::ZERO),
+            16.0,
+        )
+    }
+
+    fn token() -> IdToken {
+        IdToken::new("04A2B3C4").unwrap()
+    }
+
+    #[test]
+    fn given_authorized_vehicle__when_charging_and_unplugging__then_full_session() {
+        let t0 = Instant::now();
+        let mut board = SimulatedBoard::new();
+        board.meter.set_energy(1000.0);
+        let mut connector = controller(&board, t0);
+
+        board.pilot.set_vehicle(CpState::B);
+        assert_eq!(
+            connector.poll(t0).unwrap(),
+            vec![ConnectorEvent::StatusChanged(ChargePointStatus::Preparing)]
+        );
+        connector.authorize(token());
+        assert_eq!(
+            connector.poll(t0).unwrap(),
+            vec![
+                ConnectorEvent::TransactionStarted {
+                    id_tag: token(),
+                    meter_start_wh: 1000.0
+                },
+                ConnectorEvent::StatusChanged(ChargePointStatus::SuspendedEv),
+            ]
+        );
+        assert!(board.lock.locked());
+
+        board.pilot.set_vehicle(CpState::C);
+        connector.poll(t0).unwrap();
+        assert_eq!(connector.status(), ChargePointStatus::Charging);
+        assert!(board.contactor.commanded());
+        board.advance(Duration::from_secs(3600));
+
+        board.pilot.set_vehicle(CpState::A);
+        assert_eq!(
+            connector.poll(t0).unwrap(),
+            vec![
+                ConnectorEvent::TransactionStopped {
+                    meter_stop_wh: 1000.0 + 3.0 * 230.0 * 16.0,
+                    reason: Reason::EVDisconnected
+                },
+                ConnectorEvent::StatusChanged(ChargePointStatus::Available),
+            ]
+        );
+        assert!(!board.contactor.commanded());
+        assert!(!board.lock.locked());
+    }
+
+    #[test]
+    fn given_welded_contactor__when_stopping__then_faulted_and_plug_stays_locked() {
+        let t0 = Instant::now();
+        let board = SimulatedBoard::new();
+        let mut connector = controller(&board, t0);
+        board.pilot.set_vehicle(CpState::C);
+        connector.authorize(token());
+        connector.poll(t0).unwrap();
+        board.contactor.weld();
+
+        connector.stop(Reason::Local, t0).unwrap();
+        let events = connector.poll(t0 + PWM_STOP_DELAY).unwrap();
+
+        assert_eq!(
+            events,
+            vec![
+                ConnectorEvent::ContactorWelded,
+                ConnectorEvent::StatusChanged(ChargePointStatus::Faulted),
+            ]
+        );
+        assert!(board.lock.locked());
+    }
+
+    #[test]
+    fn given_limit_below_minimum__when_vehicle_keeps_drawing__then_opened_at_deadline() {
+        let t0 = Instant::now();
+        let board = SimulatedBoard::new();
+        let mut connector = controller(&board, t0);
+        board.pilot.set_vehicle(CpState::C);
+        connector.authorize(token());
+        connector.poll(t0).unwrap();
+
+        connector.set_current_limit(4.0, t0);
+        connector.poll(t0).unwrap();
+        assert_eq!(connector.status(), ChargePointStatus::SuspendedEvse);
+        // not under load before the vehicle had its time to stop
+        assert!(board.contactor.commanded());
+        connector
+            .poll(t0 + PWM_STOP_DELAY - Duration::from_millis(1))
+            .unwrap();
+        assert!(board.contactor.commanded());
+
+        connector.poll(t0 + PWM_STOP_DELAY).unwrap();
+        assert!(!board.contactor.commanded());
+        assert!(board.lock.locked());
+    }
+
+    #[test]
+    fn given_limit_below_minimum__when_vehicle_stops_drawing__then_opened_at_once() {
+        let t0 = Instant::now();
+        let board = SimulatedBoard::new();
+        let mut connector = controller(&board, t0);
+        board.pilot.set_vehicle(CpState::C);
+        connector.authorize(token());
+        connector.poll(t0).unwrap();
+
+        connector.set_current_limit(4.0, t0);
+        connector.poll(t0).unwrap();
+        board.pilot.set_vehicle(CpState::B);
+        let now = t0 + Duration::from_secs(1);
+        connector.poll(now).unwrap();
+
+        assert!(!board.contactor.commanded());
+        assert_eq!(connector.status(), ChargePointStatus::SuspendedEvse);
+    }
+
+    #[test]
+    fn given_jammed_lock__when_starting__then_no_transaction_until_locked() {
+        let t0 = Instant::now();
+        let board = SimulatedBoard::new();
+        let mut connector = controller(&board, t0);
+        board.pilot.set_vehicle(CpState::C);
+        board.lock.set_jammed(true);
+        connector.authorize(token());
+
+        assert!(matches!(connector.poll(t0), Err(HalError::Fault(_))));
+        assert!(!connector.in_transaction());
+        assert!(!board.contactor.commanded());
+
+        board.lock.set_jammed(false);
+        let events = connector.poll(t0).unwrap();
+        assert!(matches!(
+            events.as_slice(),
+            [
+                ConnectorEvent::TransactionStarted { .. },
+                ConnectorEvent::StatusChanged(ChargePointStatus::Charging),
+            ]
+        ));
+        assert!(board.lock.locked());
+    }
+
+    #[test]
+    fn given_meter_not_responding__when_starting_and_unplugging__then_events_once_read() {
+        let t0 = Instant::now();
+        let board = SimulatedBoard::new();
+        board.meter.set_energy(500.0);
+        let mut connector = controller(&board, t0);
+        board.pilot.set_vehicle(CpState::B);
+        connector.authorize(token());
+        board.meter.set_responding(false);
+
+        assert!(matches!(
+            connector.poll(t0),
+            Err(HalError::NotResponding(_))
+        ));
+        assert!(!connector.in_transaction());
+        assert!(!board.lock.locked());
+        board.meter.set_responding(true);
+        let events = connector.poll(t0).unwrap();
+        assert!(events.contains(&ConnectorEvent::TransactionStarted {
+            id_tag: token(),
+            meter_start_wh: 500.0
+        }));
+
+        board.meter.set_responding(false);
+        board.pilot.set_vehicle(CpState::A);
+        assert!(connector.poll(t0).is_err());
+        assert!(connector.in_transaction());
+        assert!(board.lock.locked());
+        board.meter.set_responding(true);
+        let events = connector.poll(t0).unwrap();
+        assert_eq!(
+            events,
+            vec![
+                ConnectorEvent::TransactionStopped {
+                    meter_stop_wh: 500.0,
+                    reason: Reason::EVDisconnected
+                },
+                ConnectorEvent::StatusChanged(ChargePointStatus::Available),
+            ]
+        );
+        assert!(!board.lock.locked());
+    }
+}
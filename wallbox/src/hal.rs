@@ -0,0 +1,153 @@
+///// Hardware abstraction of a wallbox, implemented once per board /////
+use std::fmt;
+
+use ocpp::datetime::DateTime;
+use ocpp::types::{IdToken, Measurand, Phase, ReadingContext};
+
+use crate::iec61851::PilotSample;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HalError {
+    /// The device did not answer, i.e. a meter missing on the bus
+    NotResponding(String),
+    /// The device answered with something that does not parse
+    InvalidResponse(String),
+    /// The device reports a failure, i.e. a jammed lock
+    Fault(String),
+}
+
+impl fmt::Display for HalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HalError::NotResponding(device) => write!(f, "{} does not respond", device),
+            HalError::InvalidResponse(reason) => write!(f, "invalid response: {}", reason),
+            HalError::Fault(reason) => write!(f, "device fault: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for HalError {}
+
+/// One value of a meter, in the default unit OCPP assumes for the measurand: Wh, W, V, A, Hz
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reading {
+    pub measurand: Measurand,
+    /// `None` for the total over all phases
+    pub phase: Option<Phase>,
+    pub value: f64,
+}
+
+impl Reading {
+    pub fn new(measurand: Measurand, phase: Option<Phase>, value: f64) -> Self {
+        Self {
+            measurand,
+            phase,
+            value,
+        }
+    }
+}
+
+pub trait EnergyMeter {
+    /// Everything the meter measures, one reading per measurand and phase
+    fn read(&mut self) -> Result<Vec<Reading>, HalError>;
+
+    /// `measurand` of `phase`, `None` if the meter does not measure it
+    fn read_one(
+        &mut self,
+        measurand: Measurand,
+        phase: Option<Phase>,
+    ) -> Result<Option<f64>, HalError> {
+        Ok(self
+            .read()?
+            .into_iter()
+            .find(|reading| reading.measurand == measurand && reading.phase == phase)
+            .map(|reading| reading.value))
+    }
+}
+
+/// Meter at the grid connection of the site
+pub trait SiteMeter {
+    /// Active power in W over all phases, positive drawn from the grid, negative fed in
+    fn grid_power(&mut self) -> Result<f64, HalError>;
+}
+
+/// Any energy meter installed at the grid connection, import and export as it counts them
+#[derive(Debug, Clone)]
+pub struct GridMeter<M>(pub M);
+
+impl<M: EnergyMeter> SiteMeter for GridMeter<M> {
+    fn grid_power(&mut self) -> Result<f64, HalError> {
+        let readings = self.0.read()?;
+        let total = |measurand| {
+            readings
+                .iter()
+                .find(|reading| reading.measurand == measurand && reading.phase.is_none())
+                .map(|reading| reading.value)
+        };
+        match (
+            total(Measurand::PowerActiveImport),
+            total(Measurand::PowerActiveExport),
+        ) {
+            (None, None) => Err(HalError::InvalidResponse(
+                "meter has no total active power".to_string(),
+            )),
+            (import, export) => Ok(import.unwrap_or(0.0) - export.unwrap_or(0.0)),
+        }
+    }
+}
+
+/// Meter signing its readings for calibration law, i.e. the German Eichrecht
+pub trait SignedMeter: EnergyMeter {
+    /// The energy register signed in the OCMF format, `OCMF|<payload>|<signature>`.
+    /// `context` tells transaction begin and end readings apart.
+    fn signed_reading(
+        &mut self,
+        context: ReadingContext,
+        id_tag: Option<&IdToken>,
+        timestamp: DateTime,
+    ) -> Result<String, HalError>;
+
+    /// Hex encoded DER public key checking the signatures
+    fn public_key(&self) -> String;
+}
+
+/// The relay switching the supply of the vehicle
+pub trait Contactor {
+    fn set_closed(&mut self, closed: bool) -> Result<(), HalError>;
+
+    /// Position reported by the auxiliary contact
+    fn is_closed(&mut self) -> Result<bool, HalError>;
+
+    /// Voltage on the output while the contactor is commanded open, the contacts are welded
+    fn is_welded(&mut self) -> Result<bool, HalError>;
+}
+
+/// Second contactor connecting L2 and L3, only to be switched while the supply is off
+pub trait PhaseSwitch {
+    /// Connects 1 or 3 phases, a single phase is `phase_to_use` from 1 to 3, L1 when `None`
+    fn set_phases(&mut self, number_phases: u8, phase_to_use: Option<u8>) -> Result<(), HalError>;
+}
+
+/// Holds the plug of a type 2 socket while charging
+pub trait ConnectorLock {
+    fn lock(&mut self) -> Result<(), HalError>;
+
+    fn unlock(&mut self) -> Result<(), HalError>;
+
+    /// Position reported by the feedback contact of the actuator
+    fn is_locked(&mut self) -> Result<bool, HalError>;
+}
+
+/// RFID reader or any other source of identification
+pub trait IdTokenReader {
+    /// The token presented since the last call, i.e. a card held to the reader
+    fn read(&mut self) -> Result<Option<IdToken>, HalError>;
+}
+
+/// PWM generator and voltage measurement of the Control Pilot
+pub trait PilotInterface {
+    /// Outputs `percent` duty cycle, [`crate::iec61851::NO_PWM`] for the constant +12 V
+    fn set_duty_cycle(&mut self, percent: f64) -> Result<(), HalError>;
+
+    fn sample(&mut self) -> Result<PilotSample, HalError>;
+}
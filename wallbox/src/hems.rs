@@ -0,0 +1,457 @@
+///// Registers of the wallbox for home energy managers on Modbus TCP /////
+//!
+//! Serve [`HemsRegisters`] with [`crate::modbus::ModbusTcpServer`]. The unit id selects the
+//! connector, 1 for the first. Registers are 16 bit, 32 bit values span two registers with the
+//! high word first.
+//!
+//! Input registers, function code 4:
+//!
+//! | Address | Type | Unit  | Value                                                        |
+//! |---------|------|-------|--------------------------------------------------------------|
+//! | 0       | U16  |       | Status: 0 Available, 1 Preparing, 2 Charging, 3 SuspendedEVSE, 4 SuspendedEV, 5 Finishing, 6 Reserved, 7 Unavailable, 8 Faulted |
+//! | 1       | U16  |       | Transaction: 0 none, 1 running                               |
+//! | 2       | U16  |       | Phases charging, 1 or 3                                      |
+//! | 3       | U16  | 0.1 A | Current offered to the vehicle, 0 while paused               |
+//! | 4 - 6   | U16  | 0.1 A | Current L1, L2, L3                                           |
+//! | 7 - 9   | U16  | 0.1 V | Voltage L1-N, L2-N, L3-N                                     |
+//! | 10 - 11 | U32  | W     | Active power                                                 |
+//! | 12 - 13 | U32  | Wh    | Energy meter reading                                         |
+//! | 14 - 15 | U32  | Wh    | Energy charged in the transaction                            |
+//! | 16 - 17 | U32  | s     | Duration of the transaction                                  |
+//!
+//! Holding registers, function codes 3, 6 and 16:
+//!
+//! | Address | Type | Unit  | Value                                                        |
+//! |---------|------|-------|--------------------------------------------------------------|
+//! | 100     | U16  | 0.1 A | Current limit, 0 pauses, 60 to 800 limit, 65535 lifts it     |
+//! | 101     | U16  | s     | Timeout of the current limit, 0 keeps it until changed, default 60 |
+//! | 102     | U16  | 0.1 A | Failsafe current once the limit timed out, like 100, default 65535 |
+//!
+//! The current limit applies with the other limits of the connector, the lowest wins, see
+//! [`LimitSource::External`]. It holds for the timeout after it was last written, an energy
+//! manager refreshes it by writing it again. Afterwards the failsafe current applies until the
+//! next write, so a crashed energy manager cannot pause the connector for good. Function code 16
+//! writes several registers at once, all or none of them.
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use ocpp::types::{ChargePointStatus, Measurand, Phase};
+
+use crate::hal::Reading;
+use crate::iec61851::{MAX_CURRENT, MIN_CURRENT};
+use crate::modbus::{Exception, RegisterHandler, RegisterTable};
+use crate::smart_charging::{ConnectorLimits, LimitSource};
+
+pub const STATUS: u16 = 0;
+pub const TRANSACTION: u16 = 1;
+pub const NUMBER_PHASES: u16 = 2;
+pub const OFFERED_CURRENT: u16 = 3;
+pub const CURRENTS: u16 = 4;
+pub const VOLTAGES: u16 = 7;
+pub const POWER: u16 = 10;
+pub const ENERGY: u16 = 12;
+pub const SESSION_ENERGY: u16 = 14;
+pub const SESSION_DURATION: u16 = 16;
+/// Input registers per connector
+pub const INPUT_REGISTERS: u16 = 18;
+
+pub const CURRENT_LIMIT: u16 = 100;
+pub const LIMIT_TIMEOUT: u16 = 101;
+pub const FAILSAFE_CURRENT: u16 = 102;
+/// Written to [`CURRENT_LIMIT`] or [`FAILSAFE_CURRENT`] to lift the limit
+pub const NO_LIMIT: u16 = 0xFFFF;
+/// [`LIMIT_TIMEOUT`] until the energy manager writes another
+pub const DEFAULT_LIMIT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// What the registers of a connector report
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectorState {
+    pub status: ChargePointStatus,
+    pub in_transaction: bool,
+    pub number_phases: u8,
+    /// Ampere per phase
+    pub offered_current: f64,
+    pub currents: [f64; 3],
+    pub voltages: [f64; 3],
+    /// W
+    pub power: f64,
+    pub energy_wh: f64,
+    pub session_energy_wh: f64,
+    pub session_duration: Duration,
+}
+
+impl Default for ConnectorState {
+    fn default() -> Self {
+        Self {
+            status: ChargePointStatus::Available,
+            in_transaction: false,
+            number_phases: 3,
+            offered_current: 0.0,
+            currents: [0.0; 3],
+            voltages: [0.0; 3],
+            power: 0.0,
+            energy_wh: 0.0,
+            session_energy_wh: 0.0,
+            session_duration: Duration::ZERO,
+        }
+    }
+}
+
+impl ConnectorState {
+    /// Takes the currents, voltages, power and energy of the connector meter
+    pub fn set_readings(&mut self, readings: &[Reading]) {
+        for reading in readings {
+            let phase = match reading.phase {
+                Some(Phase::L1 | Phase::L1N) => Some(0),
+                Some(Phase::L2 | Phase::L2N) => Some(1),
+                Some(Phase::L3 | Phase::L3N) => Some(2),
+                _ => None,
+            };
+            match (reading.measurand, phase) {
+                (Measurand::CurrentImport, Some(index)) => self.currents[index] = reading.value,
+                (Measurand::Voltage, Some(index)) => self.voltages[index] = reading.value,
+                (Measurand::PowerActiveImport, None) => self.power = reading.value,
+                (Measurand::EnergyActiveImportRegister, None) => self.energy_wh = reading.value,
+                _ => {}
+            }
+        }
+    }
+
+    fn registers(&self) -> Vec<u16> {
+        let tenths = |value: f64| (value * 10.0).round().clamp(0.0, u16::MAX as f64) as u16;
+        let double = |value: f64| {
+            let value = value.round().clamp(0.0, u32::MAX as f64) as u32;
+            [(value >> 16) as u16, value as u16]
+        };
+        let mut registers = vec![
+            status_code(self.status),
+            self.in_transaction as u16,
+            self.number_phases as u16,
+            tenths(self.offered_current),
+        ];
+        registers.extend(self.currents.map(tenths));
+        registers.extend(self.voltages.map(tenths));
+        registers.extend(double(self.power));
+        registers.extend(double(self.energy_wh));
+        registers.extend(double(self.session_energy_wh));
+        registers.extend(double(self.session_duration.as_secs_f64()));
+        registers
+    }
+}
+
+fn status_code(status: ChargePointStatus) -> u16 {
+    match status {
+        ChargePointStatus::Available => 0,
+        ChargePointStatus::Preparing => 1,
+        ChargePointStatus::Charging => 2,
+        ChargePointStatus::SuspendedEvse => 3,
+        ChargePointStatus::SuspendedEv => 4,
+        ChargePointStatus::Finishing => 5,
+        ChargePointStatus::Reserved => 6,
+        ChargePointStatus::Unavailable => 7,
+        ChargePointStatus::Faulted => 8,
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Connector {
+    state: ConnectorState,
+    current_limit: Option<f64>,
+    /// When [`CURRENT_LIMIT`] was last written, `None` if never
+    limit_written: Option<Instant>,
+    limit_timeout: Duration,
+    failsafe_current: Option<f64>,
+}
+
+impl Default for Connector {
+    fn default() -> Self {
+        Self {
+            state: ConnectorState::default(),
+            current_limit: None,
+            limit_written: None,
+            limit_timeout: DEFAULT_LIMIT_TIMEOUT,
+            failsafe_current: None,
+        }
+    }
+}
+
+impl Connector {
+    fn limit_at(&self, now: Instant) -> Option<f64> {
+        let expired = self.limit_written.is_some_and(|written| {
+            !self.limit_timeout.is_zero() && now.duration_since(written) >= self.limit_timeout
+        });
+        if expired {
+            self.failsafe_current
+        } else {
+            self.current_limit
+        }
+    }
+}
+
+/// The register map above for every connector, shared with the server behind a mutex
+#[derive(Debug, Clone)]
+pub struct HemsRegisters {
+    connectors: BTreeMap<u8, Connector>,
+}
+
+impl HemsRegisters {
+    /// Connectors 1 to `connectors`
+    pub fn new(connectors: u8) -> Self {
+        Self {
+            connectors: (1..=connectors)
+                .map(|id| (id, Connector::default()))
+                .collect(),
+        }
+    }
+
+    pub fn update(&mut self, connector: u8, state: ConnectorState) {
+        if let Some(entry) = self.connectors.get_mut(&connector) {
+            entry.state = state;
+        }
+    }
+
+    /// The limit of the energy manager at `now`, the failsafe current once the written limit
+    /// timed out, `None` if there is none
+    pub fn current_limit(&self, connector: u8, now: Instant) -> Option<f64> {
+        self.connectors
+            .get(&connector)
+            .and_then(|entry| entry.limit_at(now))
+    }
+
+    /// Passes the limit of the energy manager at `now` on as [`LimitSource::External`]
+    pub fn apply(&self, connector: u8, limits: &mut ConnectorLimits, now: Instant) {
+        limits.set(LimitSource::External, self.current_limit(connector, now));
+    }
+
+    /// Writes holding registers from `address` on as the energy manager did at `now`.
+    /// A rejected value leaves all registers unchanged.
+    pub fn write_at(
+        &mut self,
+        unit: u8,
+        address: u16,
+        values: &[u16],
+        now: Instant,
+    ) -> Result<(), Exception> {
+        let connector = self
+            .connectors
+            .get_mut(&unit)
+            .ok_or(Exception::IllegalDataAddress)?;
+        let mut written = connector.clone();
+        for (offset, value) in values.iter().enumerate() {
+            let register = address
+                .checked_add(offset as u16)
+                .ok_or(Exception::IllegalDataAddress)?;
+            match register {
+                CURRENT_LIMIT => {
+                    written.current_limit = current_limit(*value)?;
+                    written.limit_written = Some(now);
+                }
+                LIMIT_TIMEOUT => written.limit_timeout = Duration::from_secs(*value as u64),
+                FAILSAFE_CURRENT => written.failsafe_current = current_limit(*value)?,
+                _ => return Err(Exception::IllegalDataAddress),
+            }
+        }
+        *connector = written;
+        Ok(())
+    }
+}
+
+impl RegisterHandler for HemsRegisters {
+    fn read(
+        &mut self,
+        unit: u8,
+        table: RegisterTable,
+        address: u16,
+        count: u16,
+    ) -> Result<Vec<u16>, Exception> {
+        let connector = self
+            .connectors
+            .get(&unit)
+            .ok_or(Exception::IllegalDataAddress)?;
+        let (registers, start) = match table {
+            RegisterTable::Input => (connector.state.registers(), 0),
+            RegisterTable::Holding => {
+                let current = |limit: Option<f64>| {
+                    limit.map_or(NO_LIMIT, |amps| (amps * 10.0).round() as u16)
+                };
+                let timeout = connector.limit_timeout.as_secs().min(u16::MAX as u64) as u16;
+                (
+                    vec![
+                        current(connector.current_limit),
+                        timeout,
+                        current(connector.failsafe_current),
+                    ],
+                    CURRENT_LIMIT,
+                )
+            }
+        };
+        let offset = address
+            .checked_sub(start)
+            .ok_or(Exception::IllegalDataAddress)? as usize;
+        registers
+            .get(offset..offset + count as usize)
+            .map(<[u16]>::to_vec)
+            .ok_or(Exception::IllegalDataAddress)
+    }
+
+    fn write(&mut self, unit: u8, address: u16, values: &[u16]) -> Result<(), Exception> {
+        self.write_at(unit, address, values, Instant::now())
+    }
+}
+
+/// The current of [`CURRENT_LIMIT`] or [`FAILSAFE_CURRENT`], `None` lifts the limit
+fn current_limit(value: u16) -> Result<Option<f64>, Exception> {
+    let amps = value as f64 / 10.0;
+    match value {
+        NO_LIMIT => Ok(None),
+        0 => Ok(Some(0.0)),
+        _ if (MIN_CURRENT..=MAX_CURRENT).contains(&amps) => Ok(Some(amps)),
+        _ => Err(Exception::IllegalDataValue),
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::EnergyMeter;
+    use crate::modbus::{ModbusClient, ModbusError, ModbusTcpClient, ModbusTcpServer};
+    use crate::sim::SimulatedMeter;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn given_charging_connector__when_reading__then_register_map() {
+        let mut meter = SimulatedMeter::new();
+        meter.set_currents([16.0, 16.0, 16.0]);
+        meter.set_energy(123_456.0);
+        let mut state = ConnectorState {
+            status: ChargePointStatus::Charging,
+            in_transaction: true,
+            offered_current: 16.0,
+            session_energy_wh: 2500.0,
+            session_duration: Duration::from_secs(3600),
+            ..ConnectorState::default()
+        };
+        state.set_readings(&meter.read().unwrap());
+        let mut registers = HemsRegisters::new(2);
+        registers.update(2, state);
+
+        let values = registers
+            .read(2, RegisterTable::Input, STATUS, INPUT_REGISTERS)
+            .unwrap();
+
+        assert_eq!(
+            values,
+            vec![
+                2, 1, 3, 160, 160, 160, 160, 2300, 2300, 2300, 0, 11040, 1, 57920, 0, 2500, 0, 3600
+            ]
+        );
+        assert_eq!(
+            registers.read(3, RegisterTable::Input, STATUS, 1),
+            Err(Exception::IllegalDataAddress)
+        );
+        assert_eq!(
+            registers.read(2, RegisterTable::Input, SESSION_DURATION, 3),
+            Err(Exception::IllegalDataAddress)
+        );
+    }
+
+    #[test]
+    fn given_energy_manager__when_writing_limit_over_tcp__then_lowest_limit_applies() {
+        let registers = Arc::new(Mutex::new(HemsRegisters::new(1)));
+        let server = ModbusTcpServer::bind("127.0.0.1:0", registers.clone()).unwrap();
+        let mut client =
+            ModbusTcpClient::connect(server.local_addr(), Duration::from_secs(1)).unwrap();
+        let mut limits = ConnectorLimits::new();
+        limits.set(LimitSource::ChargingProfile, Some(16.0));
+
+        client.write_registers(1, CURRENT_LIMIT, &[100]).unwrap();
+        registers
+            .lock()
+            .unwrap()
+            .apply(1, &mut limits, Instant::now());
+        assert_eq!(limits.effective().unwrap().source, LimitSource::External);
+        assert_eq!(limits.effective().unwrap().current, 10.0);
+        assert_eq!(
+            client
+                .read_registers(1, RegisterTable::Holding, CURRENT_LIMIT, 1)
+                .unwrap(),
+            vec![100]
+        );
+
+        assert!(matches!(
+            client.write_registers(1, CURRENT_LIMIT, &[30]),
+            Err(ModbusError::Exception(Exception::IllegalDataValue))
+        ));
+        client
+            .write_registers(1, CURRENT_LIMIT, &[100, 30, 80])
+            .unwrap();
+        assert!(matches!(
+            client.write_registers(1, CURRENT_LIMIT, &[120, 60, 1]),
+            Err(ModbusError::Exception(Exception::IllegalDataValue))
+        ));
+        assert_eq!(
+            client
+                .read_registers(1, RegisterTable::Holding, CURRENT_LIMIT, 3)
+                .unwrap(),
+            vec![100, 30, 80]
+        );
+        client
+            .write_registers(1, CURRENT_LIMIT, &[NO_LIMIT])
+            .unwrap();
+        registers
+            .lock()
+            .unwrap()
+            .apply(1, &mut limits, Instant::now());
+        assert_eq!(
+            limits.effective().unwrap().source,
+            LimitSource::ChargingProfile
+        );
+    }
+
+    #[test]
+    fn given_limit_not_refreshed__when_timeout_passes__then_failsafe_current_until_written_again() {
+        let mut registers = HemsRegisters::new(1);
+        let written = Instant::now();
+        registers
+            .write_at(1, CURRENT_LIMIT, &[0, 30, 80], written)
+            .unwrap();
+
+        assert_eq!(
+            registers.current_limit(1, written + Duration::from_secs(29)),
+            Some(0.0)
+        );
+        assert_eq!(
+            registers.current_limit(1, written + Duration::from_secs(31)),
+            Some(8.0)
+        );
+        assert_eq!(
+            registers.read(1, RegisterTable::Holding, CURRENT_LIMIT, 3),
+            Ok(vec![0, 30, 80])
+        );
+
+        let rewritten = written + Duration::from_secs(40);
+        registers
+            .write_at(1, CURRENT_LIMIT, &[0], rewritten)
+            .unwrap();
+        assert_eq!(registers.current_limit(1, rewritten), Some(0.0));
+    }
+
+    #[test]
+    fn given_default_registers__when_energy_manager_gone__then_limit_lifted_after_a_minute() {
+        let mut registers = HemsRegisters::new(1);
+        let written = Instant::now();
+        registers.write_at(1, CURRENT_LIMIT, &[0], written).unwrap();
+
+        assert_eq!(
+            registers.current_limit(1, written + DEFAULT_LIMIT_TIMEOUT),
+            None
+        );
+
+        registers.write_at(1, LIMIT_TIMEOUT, &[0], written).unwrap();
+        assert_eq!(
+            registers.current_limit(1, written + Duration::from_secs(3600)),
+            Some(0.0)
+        );
+    }
+}
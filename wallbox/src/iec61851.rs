@@ -0,0 +1,438 @@
+///// IEC 61851-1 Control Pilot: vehicle states, PWM duty cycle and connector status /////
+use std::time::{Duration, Instant};
+
+use ocpp::types::ChargePointStatus;
+
+/// Lowest current a PWM duty cycle may offer, below charging is not allowed
+pub const MIN_CURRENT: f64 = 6.0;
+/// Highest current a PWM duty cycle may offer
+pub const MAX_CURRENT: f64 = 80.0;
+/// Duty cycle in percent asking the vehicle for digital communication, i.e. ISO 15118
+pub const DIGITAL_COMMUNICATION: f64 = 5.0;
+/// Duty cycle in percent of the constant +12 V without PWM, state x1
+pub const NO_PWM: f64 = 100.0;
+
+/// The supply switches on at the latest this long after the vehicle asks for it (x2 → C2)
+pub const SWITCH_ON_DELAY: Duration = Duration::from_secs(3);
+/// The supply switches off at the latest this long after the vehicle stops or goes away
+pub const SWITCH_OFF_DELAY: Duration = Duration::from_millis(100);
+/// The supply switches off at the latest this long after the PWM stopped, if the vehicle
+/// keeps charging
+pub const PWM_STOP_DELAY: Duration = Duration::from_secs(6);
+/// The vehicle follows a lower duty cycle within this long
+pub const CURRENT_ADAPTATION_DELAY: Duration = Duration::from_secs(5);
+
+/// A new state has to be measured this long before it is taken
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// Control Pilot state, named by the positive pilot voltage measured by the EVSE
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum CpState {
+    /// +12 V, no vehicle connected
+    A,
+    /// +9 V, vehicle connected but not ready to charge
+    B,
+    /// +6 V, vehicle ready to charge
+    C,
+    /// +3 V, vehicle ready to charge and asks for ventilation
+    D,
+    /// 0 V, pilot shorted to protective earth or no supply
+    E,
+    /// -12 V, EVSE not available
+    F,
+}
+
+/// Voltage bands of the states, Table A.4, in between a measurement is invalid
+const BANDS: [(CpState, f64, f64); 6] = [
+    (CpState::A, 11.0, 13.0),
+    (CpState::B, 8.0, 10.0),
+    (CpState::C, 5.0, 7.0),
+    (CpState::D, 2.0, 4.0),
+    (CpState::E, -1.0, 1.0),
+    (CpState::F, -13.0, -11.0),
+];
+
+impl CpState {
+    /// State of a pilot level in volt, `None` between the bands
+    pub fn from_voltage(volts: f64) -> Option<Self> {
+        BANDS
+            .iter()
+            .find(|(_, low, high)| (*low..=*high).contains(&volts))
+            .map(|(state, _, _)| *state)
+    }
+
+    /// The vehicle is connected, B, C or D
+    pub fn is_connected(&self) -> bool {
+        matches!(self, CpState::B | CpState::C | CpState::D)
+    }
+}
+
+/// Pilot levels in volt, measured at the high and the low phase of the PWM.
+/// Without PWM both are the constant level.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PilotSample {
+    pub high: f64,
+    pub low: f64,
+}
+
+impl PilotSample {
+    pub fn new(high: f64, low: f64) -> Self {
+        Self { high, low }
+    }
+
+    /// Sample of a constant level, without PWM
+    pub fn constant(volts: f64) -> Self {
+        Self::new(volts, volts)
+    }
+}
+
+/// PWM duty cycle in percent offering `amps`, Table A.8. Capped at 80 A, `None` below 6 A.
+pub fn duty_cycle(amps: f64) -> Option<f64> {
+    if amps.is_nan() || amps < MIN_CURRENT {
+        return None;
+    }
+    let amps = amps.min(MAX_CURRENT);
+    if amps <= 51.0 {
+        Some(amps / 0.6)
+    } else {
+        Some(amps / 2.5 + 64.0)
+    }
+}
+
+/// Current in ampere offered by a duty cycle in percent, as the vehicle reads it, Table B.1.
+/// `None` outside of 8 % to 97 %, where no current is offered.
+pub fn current_from_duty_cycle(percent: f64) -> Option<f64> {
+    if (8.0..=85.0).contains(&percent) {
+        Some(percent * 0.6)
+    } else if percent > 85.0 && percent <= 97.0 {
+        Some(((percent - 64.0) * 2.5).min(MAX_CURRENT))
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Transition {
+    pub from: CpState,
+    pub to: CpState,
+}
+
+/// The contactor position the pilot asks for and by when it has to be reached
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ContactorDemand {
+    pub closed: bool,
+    pub deadline: Instant,
+}
+
+/// Follows the pilot state of one connector and decides the PWM and the contactor.
+///
+/// Samples are fed with [`update`](Self::update) and the current offered with
+/// [`offer_current`](Self::offer_current). [`status`](Self::status) is the connector status to
+/// report, it changes with both.
+#[derive(Debug, Clone)]
+pub struct ControlPilot {
+    state: CpState,
+    /// Measured state which differs from `state`, and since when
+    pending: Option<(CpState, Instant)>,
+    debounce: Duration,
+    ventilation: bool,
+    duty_cycle: f64,
+    transaction: bool,
+    contactor: ContactorDemand,
+    adaptation_deadline: Option<Instant>,
+}
+
+impl ControlPilot {
+    /// No vehicle connected, no PWM, contactor open
+    pub fn new(now: Instant) -> Self {
+        Self {
+            state: CpState::A,
+            pending: None,
+            debounce: DEFAULT_DEBOUNCE,
+            ventilation: false,
+            duty_cycle: NO_PWM,
+            transaction: false,
+            contactor: ContactorDemand {
+                closed: false,
+                deadline: now,
+            },
+            adaptation_deadline: None,
+        }
+    }
+
+    pub fn debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Whether the installation is ventilated, without state D does not charge
+    pub fn ventilation(mut self, ventilation: bool) -> Self {
+        self.ventilation = ventilation;
+        self
+    }
+
+    pub fn state(&self) -> CpState {
+        self.state
+    }
+
+    /// Duty cycle in percent to output, [`NO_PWM`] while no current is offered
+    pub fn duty_cycle(&self) -> f64 {
+        self.duty_cycle
+    }
+
+    pub fn contactor(&self) -> ContactorDemand {
+        self.contactor
+    }
+
+    /// Until when the vehicle may draw more than the last lowered offer
+    pub fn adaptation_deadline(&self) -> Option<Instant> {
+        self.adaptation_deadline
+    }
+
+    /// Whether a transaction runs on the connector, only changes the reported status
+    pub fn set_transaction(&mut self, active: bool) {
+        self.transaction = active;
+    }
+
+    /// Offers `amps` to the vehicle, `None` or less than 6 A stops the PWM.
+    /// Returns the new duty cycle.
+    pub fn offer_current(&mut self, amps: Option<f64>, now: Instant) -> f64 {
+        let duty_cycle = amps.and_then(duty_cycle).unwrap_or(NO_PWM);
+        self.set_duty_cycle(duty_cycle, now);
+        duty_cycle
+    }
+
+    /// Asks the vehicle for digital communication, the current is negotiated there
+    pub fn request_digital_communication(&mut self, now: Instant) {
+        self.set_duty_cycle(DIGITAL_COMMUNICATION, now);
+    }
+
+    /// Takes a measurement, returns the transition once a new state is stable for the debounce
+    pub fn update(&mut self, sample: PilotSample, now: Instant) -> Option<Transition> {
+        let Some(measured) = self.evaluate(sample) else {
+            self.pending = None;
+            return None;
+        };
+        if measured == self.state {
+            self.pending = None;
+            return None;
+        }
+        let since = match self.pending {
+            Some((pending, since)) if pending == measured => since,
+            _ => now,
+        };
+        self.pending = Some((measured, since));
+        if now.saturating_duration_since(since) < self.debounce {
+            return None;
+        }
+        self.pending = None;
+        let from = std::mem::replace(&mut self.state, measured);
+        // timings run from the first measurement of the new state
+        self.update_contactor(since, SWITCH_ON_DELAY, SWITCH_OFF_DELAY);
+        Some(Transition { from, to: measured })
+    }
+
+    /// Connector status following from the pilot state and the offer
+    pub fn status(&self) -> ChargePointStatus {
+        match self.state {
+            CpState::A => ChargePointStatus::Available,
+            CpState::E | CpState::F => ChargePointStatus::Faulted,
+            CpState::B if !self.transaction => ChargePointStatus::Preparing,
+            CpState::B if self.offering() => ChargePointStatus::SuspendedEv,
+            CpState::C | CpState::D if self.charging_allowed() => ChargePointStatus::Charging,
+            _ if self.transaction => ChargePointStatus::SuspendedEvse,
+            _ => ChargePointStatus::Preparing,
+        }
+    }
+
+    /// The pilot signals a current or digital communication
+    fn offering(&self) -> bool {
+        self.duty_cycle < NO_PWM
+    }
+
+    fn charging_allowed(&self) -> bool {
+        self.offering()
+            && match self.state {
+                CpState::C => true,
+                CpState::D => self.ventilation,
+                _ => false,
+            }
+    }
+
+    fn evaluate(&self, sample: PilotSample) -> Option<CpState> {
+        let state = CpState::from_voltage(sample.high)?;
+        // the diode of the vehicle blocks the negative half wave, without it the vehicle is
+        // not trusted and the connector treated as failed
+        if self.offering()
+            && state.is_connected()
+            && CpState::from_voltage(sample.low) != Some(CpState::F)
+        {
+            return Some(CpState::F);
+        }
+        Some(state)
+    }
+
+    fn set_duty_cycle(&mut self, duty_cycle: f64, now: Instant) {
+        let lowered = match (
+            current_from_duty_cycle(self.duty_cycle),
+            current_from_duty_cycle(duty_cycle),
+        ) {
+            (Some(before), Some(after)) => after < before,
+            _ => false,
+        };
+        if lowered && self.charging_allowed() {
+            self.adaptation_deadline = Some(now + CURRENT_ADAPTATION_DELAY);
+        }
+        self.duty_cycle = duty_cycle;
+        self.update_contactor(now, SWITCH_ON_DELAY, PWM_STOP_DELAY);
+    }
+
+    fn update_contactor(&mut self, since: Instant, on_delay: Duration, off_delay: Duration) {
+        let closed = self.charging_allowed();
+        if closed == self.contactor.closed {
+            return;
+        }
+        let delay = if closed { on_delay } else { off_delay };
+        self.contactor = ContactorDemand {
+            closed,
+            deadline: since + delay,
+        };
+        if !closed {
+            self.adaptation_deadline = None;
+        }
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const B: PilotSample = PilotSample {
+        high: 9.0,
+        low: 9.0,
+    };
+    const B_PWM: PilotSample = PilotSample {
+        high: 9.0,
+        low: -12.0,
+    };
+    const C_PWM: PilotSample = PilotSample {
+        high: 6.0,
+        low: -12.0,
+    };
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn given_currents__when_computing_duty_cycle__then_follows_table_a8() {
+        assert_eq!(duty_cycle(5.9), None);
+        assert_eq!(duty_cycle(6.0), Some(10.0));
+        assert_eq!(duty_cycle(16.0), Some(16.0 / 0.6));
+        assert_eq!(duty_cycle(51.0), Some(85.0));
+        assert_eq!(duty_cycle(80.0), Some(96.0));
+        assert_eq!(duty_cycle(100.0), Some(96.0));
+
+        assert_eq!(current_from_duty_cycle(DIGITAL_COMMUNICATION), None);
+        assert_eq!(current_from_duty_cycle(NO_PWM), None);
+        assert_eq!(current_from_duty_cycle(50.0), Some(30.0));
+        assert_eq!(current_from_duty_cycle(96.0), Some(80.0));
+    }
+
+    #[test]
+    fn given_voltages__when_classifying__then_states_of_table_a4() {
+        assert_eq!(CpState::from_voltage(12.2), Some(CpState::A));
+        assert_eq!(CpState::from_voltage(8.6), Some(CpState::B));
+        assert_eq!(CpState::from_voltage(6.0), Some(CpState::C));
+        assert_eq!(CpState::from_voltage(3.1), Some(CpState::D));
+        assert_eq!(CpState::from_voltage(0.2), Some(CpState::E));
+        assert_eq!(CpState::from_voltage(-12.0), Some(CpState::F));
+        assert_eq!(CpState::from_voltage(10.5), None);
+    }
+
+    #[test]
+    fn given_plugged_vehicle__when_offered_and_ready__then_preparing_suspended_charging() {
+        let t0 = Instant::now();
+        let mut pilot = ControlPilot::new(t0);
+
+        assert_eq!(pilot.update(B, t0), None);
+        assert_eq!(
+            pilot.update(B, t0 + ms(50)),
+            Some(Transition {
+                from: CpState::A,
+                to: CpState::B
+            })
+        );
+        assert_eq!(pilot.status(), ChargePointStatus::Preparing);
+
+        pilot.set_transaction(true);
+        assert_eq!(pilot.offer_current(Some(16.0), t0 + ms(100)), 16.0 / 0.6);
+        pilot.update(B_PWM, t0 + ms(100));
+        assert_eq!(pilot.status(), ChargePointStatus::SuspendedEv);
+        assert!(!pilot.contactor().closed);
+
+        pilot.update(C_PWM, t0 + ms(200));
+        pilot.update(C_PWM, t0 + ms(260));
+        assert_eq!(pilot.status(), ChargePointStatus::Charging);
+        assert_eq!(
+            pilot.contactor(),
+            ContactorDemand {
+                closed: true,
+                deadline: t0 + ms(200) + SWITCH_ON_DELAY
+            }
+        );
+
+        pilot.update(PilotSample::new(12.0, -12.0), t0 + ms(300));
+        pilot.update(PilotSample::new(12.0, -12.0), t0 + ms(350));
+        assert_eq!(pilot.state(), CpState::A);
+        assert_eq!(
+            pilot.contactor(),
+            ContactorDemand {
+                closed: false,
+                deadline: t0 + ms(300) + SWITCH_OFF_DELAY
+            }
+        );
+    }
+
+    #[test]
+    fn given_charging__when_lowering_or_stopping_offer__then_vehicle_and_contactor_deadlines() {
+        let t0 = Instant::now();
+        let mut pilot = ControlPilot::new(t0).debounce(Duration::ZERO);
+        pilot.set_transaction(true);
+        pilot.offer_current(Some(32.0), t0);
+        pilot.update(C_PWM, t0);
+
+        pilot.offer_current(Some(10.0), t0 + ms(10));
+        assert_eq!(
+            pilot.adaptation_deadline(),
+            Some(t0 + ms(10) + CURRENT_ADAPTATION_DELAY)
+        );
+
+        assert_eq!(pilot.offer_current(Some(4.0), t0 + ms(20)), NO_PWM);
+        assert_eq!(pilot.status(), ChargePointStatus::SuspendedEvse);
+        assert_eq!(
+            pilot.contactor(),
+            ContactorDemand {
+                closed: false,
+                deadline: t0 + ms(20) + PWM_STOP_DELAY
+            }
+        );
+    }
+
+    #[test]
+    fn given_missing_diode_or_unventilated_d__when_updating__then_faulted_or_not_charging() {
+        let t0 = Instant::now();
+        let mut pilot = ControlPilot::new(t0).debounce(Duration::ZERO);
+        pilot.request_digital_communication(t0);
+        assert_eq!(pilot.duty_cycle(), DIGITAL_COMMUNICATION);
+
+        pilot.update(PilotSample::new(3.0, -12.0), t0);
+        assert_eq!(pilot.status(), ChargePointStatus::Preparing);
+        assert!(!pilot.contactor().closed);
+
+        pilot.update(PilotSample::new(6.0, 6.0), t0);
+        assert_eq!(pilot.state(), CpState::F);
+        assert_eq!(pilot.status(), ChargePointStatus::Faulted);
+    }
+}
@@ -0,0 +1,41 @@
+//! Physical side of the wallbox, the charging interface to the vehicle below the OCPP stack.
+//!
+//! [`iec61851`] follows the Control Pilot states of the vehicle, derives the PWM duty cycle
+//! offering a current and the [`ocpp::types::ChargePointStatus`] of the connector.
+//!
+//! Boards differ in their devices, [`hal`] abstracts them. The logic only depends on these
+//! traits: [`connector::ConnectorController`] runs the transactions of a connector,
+//! [`sampler::MeterSampler`] builds meter values and [`authorization`] decides on the id tokens
+//! presented. [`sim`] simulates every device for tests and desktops.
+//!
+//! Energy meters on RS-485 or Ethernet are read with [`modbus_meter::ModbusMeter`], which
+//! knows the register maps of common models, over the [`modbus`] RTU or TCP client.
+//!
+//! [`ocmf`] signs meter readings for the German Eichrecht and verifies them, the
+//! `ocmf-verify` binary checks the records of journaled transactions.
+//!
+//! [`smart_charging`] reads the limits of charging profiles, [`load_management`] shares the
+//! grid connection of a site among the connectors in a transaction and [`solar`] charges from
+//! the surplus at the [`hal::SiteMeter`], switching to a single phase with
+//! [`phase_switching`] when the surplus is low. The lowest limit of all applies, see
+//! [`smart_charging::ConnectorLimits`].
+//!
+//! Home energy managers read the connectors and limit their current through the Modbus TCP
+//! registers of [`hems`]. Grid operators limit the power through [`lpc`], which outranks
+//! every other limit.
+
+pub mod authorization;
+pub mod connector;
+pub mod hal;
+pub mod hems;
+pub mod iec61851;
+pub mod load_management;
+pub mod lpc;
+pub mod modbus;
+pub mod modbus_meter;
+pub mod ocmf;
+pub mod phase_switching;
+pub mod sampler;
+pub mod sim;
+pub mod smart_charging;
+pub mod solar;
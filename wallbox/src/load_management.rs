@@ -0,0 +1,442 @@
+///// Sharing the grid connection of a site among its charging connectors /////
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::iec61851::MIN_CURRENT;
+use crate::smart_charging::{ConnectorLimits, LimitSource, NOMINAL_VOLTAGE};
+
+/// How often limits are recalculated unless the site is overloaded
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(5);
+/// Smaller changes of a limit are not passed on, so vehicles do not chase noise
+pub const DEFAULT_HYSTERESIS: f64 = 1.0;
+
+pub const THREE_PHASES: [bool; 3] = [true; 3];
+pub const L1: [bool; 3] = [true, false, false];
+
+/// Which connectors get current when there is not enough for all
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Strategy {
+    /// The same current for every connector, the last connected pause first
+    FairShare,
+    /// Higher priorities first, the same priority by arrival
+    Priority,
+    /// In order of arrival
+    FirstCome,
+}
+
+/// A connector in a transaction, on any station of the site
+#[derive(Debug, Clone, PartialEq)]
+pub struct Demand {
+    /// Identifies the connector over all stations of the site
+    pub id: u32,
+    /// The transaction started
+    pub since: Instant,
+    /// Site phases L1 to L3 the connector draws from, after the rotation of its wiring
+    pub phases: [bool; 3],
+    /// Current drawn per site phase, measured by the meter of the connector
+    pub measured: [f64; 3],
+    /// The rating of the connector, the vehicle and the composite schedule, whatever is lowest,
+    /// see [`limited_by`](Self::limited_by)
+    pub max_current: f64,
+    /// Higher comes first with [`Strategy::Priority`]
+    pub priority: u8,
+}
+
+impl Demand {
+    pub fn new(id: u32, since: Instant, max_current: f64) -> Self {
+        Self {
+            id,
+            since,
+            phases: THREE_PHASES,
+            measured: [0.0; 3],
+            max_current,
+            priority: 0,
+        }
+    }
+
+    pub fn phases(mut self, phases: [bool; 3]) -> Self {
+        self.phases = phases;
+        self
+    }
+
+    pub fn measured(mut self, measured: [f64; 3]) -> Self {
+        self.measured = measured;
+        self
+    }
+
+    pub fn priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Caps the maximum current by the other limits of the connector, i.e. the composite
+    /// schedule set as [`LimitSource::ChargingProfile`], so no current is kept for it that it
+    /// may not draw
+    pub fn limited_by(mut self, limits: &ConnectorLimits) -> Self {
+        if let Some(limit) = limits.effective_without(LimitSource::LoadManagement) {
+            self.max_current = self.max_current.min(limit.current);
+        }
+        self
+    }
+
+    fn on_phases(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..3).filter(|phase| self.phases[*phase])
+    }
+
+    fn watts_per_ampere(&self) -> f64 {
+        NOMINAL_VOLTAGE * self.on_phases().count() as f64
+    }
+}
+
+/// New current limit of a connector in ampere per phase, below [`MIN_CURRENT`] it pauses
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LimitChange {
+    pub id: u32,
+    pub current: f64,
+}
+
+/// Distributes the current the site may draw per phase among the connectors in a transaction.
+///
+/// The consumption of the site meter includes the connectors, their measured currents are
+/// taken out to find the headroom of the other loads. Every connector either gets at least
+/// [`MIN_CURRENT`] or pauses with 0 A.
+#[derive(Debug, Clone)]
+pub struct LoadBalancer {
+    site_max_current: f64,
+    strategy: Strategy,
+    interval: Duration,
+    hysteresis: f64,
+    limits: HashMap<u32, f64>,
+    last_update: Option<Instant>,
+}
+
+impl LoadBalancer {
+    /// `site_max_current` is the fuse of the grid connection in ampere per phase
+    pub fn new(site_max_current: f64, strategy: Strategy) -> Self {
+        Self {
+            site_max_current,
+            strategy,
+            interval: DEFAULT_INTERVAL,
+            hysteresis: DEFAULT_HYSTERESIS,
+            limits: HashMap::new(),
+            last_update: None,
+        }
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn hysteresis(mut self, amps: f64) -> Self {
+        self.hysteresis = amps;
+        self
+    }
+
+    pub fn strategy(&self) -> Strategy {
+        self.strategy
+    }
+
+    /// The limit last emitted for connector `id`
+    pub fn limit(&self, id: u32) -> Option<f64> {
+        self.limits.get(&id).copied()
+    }
+
+    /// Recalculates the limits every interval, immediately if `consumption` per phase exceeds
+    /// the site maximum. Returns the limits that changed by at least the hysteresis, reductions
+    /// always while overloaded.
+    pub fn update(
+        &mut self,
+        consumption: [f64; 3],
+        demands: &[Demand],
+        now: Instant,
+    ) -> Vec<LimitChange> {
+        let overloaded = consumption
+            .iter()
+            .any(|current| *current > self.site_max_current);
+        if !overloaded
+            && self
+                .last_update
+                .is_some_and(|last| now.duration_since(last) < self.interval)
+        {
+            return Vec::new();
+        }
+        self.last_update = Some(now);
+
+        let allocation = self.allocate(consumption, demands);
+        self.limits
+            .retain(|id, _| demands.iter().any(|demand| demand.id == *id));
+        let mut changes = Vec::new();
+        for demand in demands {
+            let current = allocation[&demand.id];
+            let changed = match self.limits.get(&demand.id) {
+                None => true,
+                Some(&previous) => {
+                    (current - previous).abs() >= self.hysteresis
+                        || (current == 0.0) != (previous == 0.0)
+                        || (overloaded && current < previous)
+                }
+            };
+            if changed {
+                self.limits.insert(demand.id, current);
+                changes.push(LimitChange {
+                    id: demand.id,
+                    current,
+                });
+            }
+        }
+        changes
+    }
+
+    /// [`update`](Self::update), then sets [`LimitSource::LoadManagement`] of the connectors in
+    /// `limits`, keyed like the demands, to the limit last emitted. Connectors without a demand
+    /// get nothing until the next update takes them in.
+    pub fn apply(
+        &mut self,
+        consumption: [f64; 3],
+        demands: &[Demand],
+        limits: &mut HashMap<u32, ConnectorLimits>,
+        now: Instant,
+    ) -> Vec<LimitChange> {
+        let changes = self.update(consumption, demands, now);
+        for (id, connector_limits) in limits.iter_mut() {
+            let current = self.limit(*id).unwrap_or(0.0);
+            connector_limits.set(LimitSource::LoadManagement, Some(current));
+        }
+        changes
+    }
+
+    fn allocate(&self, consumption: [f64; 3], demands: &[Demand]) -> HashMap<u32, f64> {
+        let mut remaining = [0.0; 3];
+        for phase in 0..3 {
+            let connectors: f64 = demands.iter().map(|demand| demand.measured[phase]).sum();
+            let other_loads = (consumption[phase] - connectors).max(0.0);
+            remaining[phase] = (self.site_max_current - other_loads).max(0.0);
+        }
+        share(self.strategy, remaining, f64::INFINITY, demands)
+    }
+}
+
+/// Shares `watts` over all phases among the connectors in a transaction the way `strategy`
+/// shares the site. Meant for a limit covering the connectors only, like the one of the grid
+/// operator, the fuse and the other loads are left to the [`LoadBalancer`].
+pub fn share_power(watts: f64, strategy: Strategy, demands: &[Demand]) -> HashMap<u32, f64> {
+    share(strategy, [f64::INFINITY; 3], watts, demands)
+}
+
+/// Ampere per phase for every demand out of `remaining` per phase and `power` in W
+fn share(
+    strategy: Strategy,
+    mut remaining: [f64; 3],
+    mut power: f64,
+    demands: &[Demand],
+) -> HashMap<u32, f64> {
+    let mut order: Vec<&Demand> = demands.iter().collect();
+    match strategy {
+        Strategy::FairShare | Strategy::FirstCome => order.sort_by_key(|demand| demand.since),
+        Strategy::Priority => {
+            order.sort_by_key(|demand| (std::cmp::Reverse(demand.priority), demand.since))
+        }
+    }
+
+    let mut allocation: HashMap<u32, f64> = demands.iter().map(|demand| (demand.id, 0.0)).collect();
+    let mut admitted = Vec::new();
+    for demand in order {
+        if demand.max_current >= MIN_CURRENT
+            && demand
+                .on_phases()
+                .all(|phase| remaining[phase] >= MIN_CURRENT)
+            && power >= MIN_CURRENT * demand.watts_per_ampere()
+        {
+            demand
+                .on_phases()
+                .for_each(|phase| remaining[phase] -= MIN_CURRENT);
+            power -= MIN_CURRENT * demand.watts_per_ampere();
+            allocation.insert(demand.id, MIN_CURRENT);
+            admitted.push(demand);
+        }
+    }
+
+    match strategy {
+        Strategy::FairShare => fill_evenly(&admitted, &mut remaining, &mut power, &mut allocation),
+        Strategy::Priority | Strategy::FirstCome => {
+            for demand in admitted {
+                let extra = demand
+                    .on_phases()
+                    .map(|phase| remaining[phase])
+                    .fold(demand.max_current - MIN_CURRENT, f64::min)
+                    .min(power / demand.watts_per_ampere());
+                demand
+                    .on_phases()
+                    .for_each(|phase| remaining[phase] -= extra);
+                power -= extra * demand.watts_per_ampere();
+                *allocation.get_mut(&demand.id).unwrap() += extra;
+            }
+        }
+    }
+    // the PWM resolves a tenth of an ampere, rounding up could exceed the fuse
+    allocation
+        .into_iter()
+        .map(|(id, current)| (id, (current * 10.0 + 1e-9).floor() / 10.0))
+        .collect()
+}
+
+/// Raises the connectors evenly until they reach their maximum or a phase runs out
+fn fill_evenly(
+    admitted: &[&Demand],
+    remaining: &mut [f64; 3],
+    power: &mut f64,
+    allocation: &mut HashMap<u32, f64>,
+) {
+    let mut rising: Vec<&Demand> = admitted.to_vec();
+    while !rising.is_empty() {
+        let watts_per_ampere: f64 = rising.iter().map(|demand| demand.watts_per_ampere()).sum();
+        let level = (0..3)
+            .filter_map(|phase| {
+                let sharing = rising.iter().filter(|demand| demand.phases[phase]).count();
+                (sharing > 0).then(|| remaining[phase] / sharing as f64)
+            })
+            .fold(*power / watts_per_ampere, f64::min);
+        let headroom: Vec<f64> = rising
+            .iter()
+            .map(|demand| demand.max_current - allocation[&demand.id])
+            .collect();
+        // connectors reaching their maximum first, the others rise on in the next round
+        let any_capped = headroom.iter().any(|headroom| *headroom <= level);
+        let mut next = Vec::new();
+        for (demand, headroom) in rising.into_iter().zip(headroom) {
+            let extra = if !any_capped {
+                level
+            } else if headroom <= level {
+                headroom
+            } else {
+                next.push(demand);
+                continue;
+            };
+            demand
+                .on_phases()
+                .for_each(|phase| remaining[phase] -= extra);
+            *power -= extra * demand.watts_per_ampere();
+            *allocation.get_mut(&demand.id).unwrap() += extra;
+        }
+        rising = next;
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changes(changes: &[LimitChange]) -> Vec<(u32, f64)> {
+        let mut changes: Vec<(u32, f64)> = changes
+            .iter()
+            .map(|change| (change.id, change.current))
+            .collect();
+        changes.sort_by_key(|(id, _)| *id);
+        changes
+    }
+
+    #[test]
+    fn given_fair_share__when_site_is_short__then_even_split_capped_at_maximum() {
+        let t0 = Instant::now();
+        let mut balancer = LoadBalancer::new(40.0, Strategy::FairShare);
+        let demands = [
+            Demand::new(1, t0, 32.0),
+            Demand::new(2, t0 + Duration::from_secs(1), 10.0),
+            Demand::new(3, t0 + Duration::from_secs(2), 32.0).phases(L1),
+        ];
+
+        let limits = balancer.update([5.0, 5.0, 5.0], &demands, t0);
+
+        // 35 A left on L1, shared by all three, connector 2 takes 10 A at most
+        assert_eq!(changes(&limits), vec![(1, 12.5), (2, 10.0), (3, 12.5)]);
+    }
+
+    #[test]
+    fn given_priority_or_first_come__when_short__then_order_decides_and_last_pauses() {
+        let t0 = Instant::now();
+        let demands = [
+            Demand::new(1, t0, 32.0),
+            Demand::new(2, t0 + Duration::from_secs(1), 32.0).priority(1),
+            Demand::new(3, t0 + Duration::from_secs(2), 32.0),
+        ];
+
+        let first_come =
+            LoadBalancer::new(15.0, Strategy::FirstCome).update([0.0; 3], &demands, t0);
+        let priority = LoadBalancer::new(15.0, Strategy::Priority).update([0.0; 3], &demands, t0);
+
+        assert_eq!(changes(&first_come), vec![(1, 9.0), (2, 6.0), (3, 0.0)]);
+        assert_eq!(changes(&priority), vec![(1, 6.0), (2, 9.0), (3, 0.0)]);
+    }
+
+    #[test]
+    fn given_emitted_limit__when_updating__then_interval_and_hysteresis_apply() {
+        let t0 = Instant::now();
+        let mut balancer = LoadBalancer::new(32.0, Strategy::FairShare);
+        let charging = |current: f64| [Demand::new(1, t0, 32.0).measured([current; 3])];
+        let at = |seconds: u64| t0 + Duration::from_secs(seconds);
+
+        assert_eq!(
+            changes(&balancer.update([10.0; 3], &charging(0.0), t0)),
+            vec![(1, 22.0)]
+        );
+        // within the interval
+        assert!(
+            balancer
+                .update([31.0; 3], &charging(22.0), at(1))
+                .is_empty()
+        );
+        // overloaded by 0.5 A, reduced at once despite the hysteresis
+        assert_eq!(
+            changes(&balancer.update([32.5; 3], &charging(22.0), at(2))),
+            vec![(1, 21.5)]
+        );
+        // 0.5 A more headroom is below the hysteresis
+        assert!(
+            balancer
+                .update([31.5; 3], &charging(21.5), at(7))
+                .is_empty()
+        );
+        assert_eq!(balancer.limit(1), Some(21.5));
+        assert_eq!(
+            changes(&balancer.update([29.5; 3], &charging(21.5), at(12))),
+            vec![(1, 24.0)]
+        );
+    }
+
+    #[test]
+    fn given_profile_limit_on_a_connector__when_applying__then_others_get_the_rest_as_load_management()
+     {
+        let t0 = Instant::now();
+        let mut balancer = LoadBalancer::new(40.0, Strategy::FairShare);
+        let mut limits: HashMap<u32, ConnectorLimits> =
+            (1..=3).map(|id| (id, ConnectorLimits::new())).collect();
+        limits
+            .get_mut(&1)
+            .unwrap()
+            .set(LimitSource::ChargingProfile, Some(8.0));
+        let demands = [
+            Demand::new(1, t0, 32.0).limited_by(&limits[&1]),
+            Demand::new(2, t0, 32.0).limited_by(&limits[&2]),
+        ];
+
+        balancer.apply([0.0; 3], &demands, &mut limits, t0);
+
+        assert_eq!(demands[0].max_current, 8.0);
+        assert_eq!(limits[&1].get(LimitSource::LoadManagement), Some(8.0));
+        assert_eq!(
+            limits[&1].effective().unwrap().source,
+            LimitSource::ChargingProfile
+        );
+        assert_eq!(
+            limits[&2].effective(),
+            Some(crate::smart_charging::Limit {
+                source: LimitSource::LoadManagement,
+                current: 32.0
+            })
+        );
+        // no transaction on connector 3, it waits for the next update
+        assert_eq!(limits[&3].get(LimitSource::LoadManagement), Some(0.0));
+    }
+}
@@ -0,0 +1,421 @@
+///// EEBus use case Limitation of Power Consumption, controllable loads of §14a EnWG /////
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use ocpp::ci_string::CiString50Type;
+use ocpp::datetime::DateTime;
+use ocpp::ocpp_event::StatusNotificationRequest;
+use ocpp::types::{
+    ChargePointErrorCode, ChargePointStatus, Measurand, ReadingContext, SampledValue,
+};
+use rust_decimal::Decimal;
+
+use crate::load_management::{self, Demand, Strategy};
+use crate::smart_charging::{ConnectorLimits, LimitSource};
+
+/// The energy guard missing heartbeats that long is gone
+pub const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(120);
+pub const MIN_FAILSAFE_DURATION: Duration = Duration::from_secs(2 * 3600);
+pub const MAX_FAILSAFE_DURATION: Duration = Duration::from_secs(24 * 3600);
+/// Power §14a EnWG guarantees a controllable load, the default failsafe limit
+pub const GUARANTEED_POWER: f64 = 4200.0;
+
+/// States of the controllable system in the use case
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum LpcState {
+    /// Waiting for the energy guard after start
+    Init,
+    /// The energy guard is there and sets no limit
+    UnlimitedControlled,
+    /// The energy guard limits the consumption
+    Limited,
+    /// The energy guard is gone, the failsafe limit applies for the failsafe duration
+    Failsafe,
+    /// The failsafe duration passed without the energy guard
+    UnlimitedAutonomous,
+}
+
+/// Active power limit written by the energy guard, the SPINE `LoadControlLimit`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PowerLimit {
+    /// W over all phases
+    pub value: f64,
+    pub active: bool,
+    /// The limit ends after, holds until replaced when `None`
+    pub duration: Option<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LpcError {
+    /// A negative power, or not a number
+    InvalidPower(f64),
+    /// The failsafe duration is outside of 2 to 24 hours
+    InvalidFailsafeDuration(Duration),
+}
+
+impl fmt::Display for LpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LpcError::InvalidPower(watts) => write!(f, "invalid power limit {} W", watts),
+            LpcError::InvalidFailsafeDuration(duration) => {
+                write!(f, "failsafe duration {:?} outside of 2 h to 24 h", duration)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LpcError {}
+
+/// The controllable system side of the use case, fed by the SPINE features an EEBus stack
+/// receives: limit writes, failsafe configuration and heartbeats of the energy guard.
+///
+/// The limit in force outranks every other limit of the connectors, see
+/// [`LimitSource::GridOperator`].
+#[derive(Debug, Clone)]
+pub struct LimitationOfPowerConsumption {
+    state: LpcState,
+    /// The state was entered
+    since: Instant,
+    last_heartbeat: Option<Instant>,
+    limit: Option<PowerLimit>,
+    limit_until: Option<Instant>,
+    failsafe_power: f64,
+    failsafe_duration: Duration,
+}
+
+impl LimitationOfPowerConsumption {
+    /// Starts in [`LpcState::Init`] with the failsafe limit of [`GUARANTEED_POWER`] for two
+    /// hours
+    pub fn new(now: Instant) -> Self {
+        Self {
+            state: LpcState::Init,
+            since: now,
+            last_heartbeat: None,
+            limit: None,
+            limit_until: None,
+            failsafe_power: GUARANTEED_POWER,
+            failsafe_duration: MIN_FAILSAFE_DURATION,
+        }
+    }
+
+    pub fn state(&self) -> LpcState {
+        self.state
+    }
+
+    pub fn failsafe_power(&self) -> f64 {
+        self.failsafe_power
+    }
+
+    pub fn failsafe_duration(&self) -> Duration {
+        self.failsafe_duration
+    }
+
+    /// Heartbeat of the energy guard, the SPINE `DeviceDiagnosisHeartbeatData`. It takes
+    /// control again after start and after the failsafe duration passed without it.
+    pub fn heartbeat(&mut self, now: Instant) {
+        self.last_heartbeat = Some(now);
+        if matches!(self.state, LpcState::Init | LpcState::UnlimitedAutonomous) {
+            self.enter(LpcState::UnlimitedControlled, now);
+        }
+    }
+
+    /// The energy guard writes the consumption limit
+    pub fn write_limit(&mut self, limit: PowerLimit, now: Instant) -> Result<(), LpcError> {
+        if limit.value.is_nan() || limit.value < 0.0 {
+            return Err(LpcError::InvalidPower(limit.value));
+        }
+        self.limit = Some(limit);
+        self.limit_until = limit.duration.map(|duration| now + duration);
+        let state = if limit.active {
+            LpcState::Limited
+        } else {
+            LpcState::UnlimitedControlled
+        };
+        self.enter(state, now);
+        Ok(())
+    }
+
+    /// The energy guard configures the failsafe limit and how long it holds at least
+    pub fn write_failsafe(&mut self, power: f64, duration: Duration) -> Result<(), LpcError> {
+        if power.is_nan() || power < 0.0 {
+            return Err(LpcError::InvalidPower(power));
+        }
+        if !(MIN_FAILSAFE_DURATION..=MAX_FAILSAFE_DURATION).contains(&duration) {
+            return Err(LpcError::InvalidFailsafeDuration(duration));
+        }
+        self.failsafe_power = power;
+        self.failsafe_duration = duration;
+        Ok(())
+    }
+
+    /// Follows the heartbeat and the durations, returns the new state if it changed
+    pub fn update(&mut self, now: Instant) -> Option<LpcState> {
+        let connected = self
+            .last_heartbeat
+            .is_some_and(|heartbeat| now.duration_since(heartbeat) < HEARTBEAT_TIMEOUT);
+        let elapsed = now.duration_since(self.since);
+        let state = match self.state {
+            LpcState::Init if elapsed >= HEARTBEAT_TIMEOUT => LpcState::Failsafe,
+            LpcState::UnlimitedControlled | LpcState::Limited if !connected => LpcState::Failsafe,
+            LpcState::Limited if self.limit_until.is_some_and(|until| now >= until) => {
+                LpcState::UnlimitedControlled
+            }
+            LpcState::Failsafe if elapsed >= self.failsafe_duration => {
+                LpcState::UnlimitedAutonomous
+            }
+            state => state,
+        };
+        if state == self.state {
+            return None;
+        }
+        self.enter(state, now);
+        Some(state)
+    }
+
+    /// Power limit in force in W, `None` while unlimited
+    pub fn power_limit(&self) -> Option<f64> {
+        match self.state {
+            LpcState::Limited => self.limit.map(|limit| limit.value),
+            LpcState::Failsafe => Some(self.failsafe_power),
+            _ => None,
+        }
+    }
+
+    /// Shares [`power_limit`](Self::power_limit) among the connectors in a transaction the
+    /// way `strategy` shares the site, and sets [`LimitSource::GridOperator`] of the connectors
+    /// in `limits`, keyed like the demands. The limit covers all connectors together, so those
+    /// without a demand get nothing while it is in force.
+    pub fn apply(
+        &self,
+        strategy: Strategy,
+        demands: &[Demand],
+        limits: &mut HashMap<u32, ConnectorLimits>,
+    ) {
+        let shares = self
+            .power_limit()
+            .map(|watts| load_management::share_power(watts, strategy, demands));
+        for (id, connector_limits) in limits.iter_mut() {
+            let current = shares
+                .as_ref()
+                .map(|shares| shares.get(id).copied().unwrap_or(0.0));
+            connector_limits.set(LimitSource::GridOperator, current);
+        }
+    }
+
+    /// Tells the CSMS why charging is limited, `None` while unlimited
+    pub fn info(&self) -> Option<String> {
+        let watts = self.power_limit()?;
+        Some(match self.state {
+            LpcState::Failsafe => format!("LPC failsafe {:.0} W", watts),
+            _ => format!("LPC limited to {:.0} W", watts),
+        })
+    }
+
+    /// StatusNotification of `connector_id` carrying [`info`](Self::info)
+    pub fn status_notification(
+        &self,
+        connector_id: u32,
+        status: ChargePointStatus,
+        timestamp: DateTime,
+    ) -> StatusNotificationRequest {
+        StatusNotificationRequest {
+            connector_id,
+            error_code: ChargePointErrorCode::NoError,
+            info: self.info().and_then(|info| CiString50Type::new(&info).ok()),
+            status,
+            timestamp: Some(timestamp),
+            vendor_id: None,
+            vendor_error_code: None,
+        }
+    }
+
+    /// `Power.Offered` of the limit in force, to add to the meter values
+    pub fn sampled_value(&self, context: ReadingContext) -> Option<SampledValue> {
+        let watts = Decimal::try_from(self.power_limit()?).ok()?.round_dp(0);
+        Some(SampledValue {
+            value: watts.into(),
+            context: Some(context),
+            format: None,
+            measurand: Some(Measurand::PowerOffered),
+            phase: None,
+            unit: Some("W".to_string()),
+        })
+    }
+
+    fn enter(&mut self, state: LpcState, now: Instant) {
+        self.state = state;
+        self.since = now;
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_limit_with_duration__when_applied__then_outranks_profile_until_expiry() {
+        let t0 = Instant::now();
+        let mut lpc = LimitationOfPowerConsumption::new(t0);
+        let demands = [Demand::new(1, t0, 16.0)];
+        let mut limits = HashMap::from([(1, ConnectorLimits::new())]);
+        limits
+            .get_mut(&1)
+            .unwrap()
+            .set(LimitSource::ChargingProfile, Some(16.0));
+        lpc.heartbeat(t0);
+        assert_eq!(lpc.state(), LpcState::UnlimitedControlled);
+
+        lpc.write_limit(
+            PowerLimit {
+                value: 4140.0,
+                active: true,
+                duration: Some(Duration::from_secs(60)),
+            },
+            t0,
+        )
+        .unwrap();
+        lpc.apply(Strategy::FairShare, &demands, &mut limits);
+        assert_eq!(lpc.state(), LpcState::Limited);
+        assert_eq!(
+            limits[&1].effective().unwrap().source,
+            LimitSource::GridOperator
+        );
+        assert_eq!(limits[&1].effective().unwrap().current, 6.0);
+
+        lpc.heartbeat(t0 + Duration::from_secs(50));
+        assert_eq!(
+            lpc.update(t0 + Duration::from_secs(60)),
+            Some(LpcState::UnlimitedControlled)
+        );
+        lpc.apply(Strategy::FairShare, &demands, &mut limits);
+        assert_eq!(
+            limits[&1].effective().unwrap().source,
+            LimitSource::ChargingProfile
+        );
+    }
+
+    #[test]
+    fn given_two_connectors__when_limited__then_limit_shared_not_multiplied() {
+        let t0 = Instant::now();
+        let mut lpc = LimitationOfPowerConsumption::new(t0);
+        let demands = [
+            Demand::new(1, t0, 16.0),
+            Demand::new(2, t0 + Duration::from_secs(1), 16.0),
+        ];
+        let mut limits = HashMap::from([(1, ConnectorLimits::new()), (2, ConnectorLimits::new())]);
+        let total = |limits: &HashMap<u32, ConnectorLimits>| -> f64 {
+            limits
+                .values()
+                .map(|limits| limits.get(LimitSource::GridOperator).unwrap() * 3.0 * 230.0)
+                .sum()
+        };
+        lpc.heartbeat(t0);
+
+        lpc.write_limit(
+            PowerLimit {
+                value: 11000.0,
+                active: true,
+                duration: None,
+            },
+            t0,
+        )
+        .unwrap();
+        lpc.apply(Strategy::FairShare, &demands, &mut limits);
+        assert_eq!(limits[&1].get(LimitSource::GridOperator), Some(7.9));
+        assert_eq!(limits[&2].get(LimitSource::GridOperator), Some(7.9));
+        assert!(total(&limits) <= 11000.0);
+
+        // 4.2 kW keep one connector at the minimum, the later one pauses
+        lpc.write_limit(
+            PowerLimit {
+                value: GUARANTEED_POWER,
+                active: true,
+                duration: None,
+            },
+            t0,
+        )
+        .unwrap();
+        lpc.apply(Strategy::FairShare, &demands, &mut limits);
+        assert_eq!(limits[&1].get(LimitSource::GridOperator), Some(6.0));
+        assert_eq!(limits[&2].get(LimitSource::GridOperator), Some(0.0));
+        assert!(total(&limits) <= GUARANTEED_POWER);
+    }
+
+    #[test]
+    fn given_autonomous__when_energy_guard_returns__then_controlled_and_failsafe_again() {
+        let t0 = Instant::now();
+        let mut lpc = LimitationOfPowerConsumption::new(t0);
+        let autonomous_at = t0 + HEARTBEAT_TIMEOUT + MIN_FAILSAFE_DURATION;
+        lpc.update(t0 + HEARTBEAT_TIMEOUT);
+        assert_eq!(
+            lpc.update(autonomous_at),
+            Some(LpcState::UnlimitedAutonomous)
+        );
+
+        lpc.heartbeat(autonomous_at);
+        assert_eq!(lpc.state(), LpcState::UnlimitedControlled);
+
+        assert_eq!(
+            lpc.update(autonomous_at + HEARTBEAT_TIMEOUT),
+            Some(LpcState::Failsafe)
+        );
+        assert_eq!(lpc.power_limit(), Some(GUARANTEED_POWER));
+    }
+
+    #[test]
+    fn given_energy_guard_gone__when_updating__then_failsafe_then_autonomous() {
+        let t0 = Instant::now();
+        let mut lpc = LimitationOfPowerConsumption::new(t0);
+        lpc.write_failsafe(6000.0, Duration::from_secs(3 * 3600))
+            .unwrap();
+        lpc.heartbeat(t0);
+
+        assert_eq!(lpc.update(t0 + Duration::from_secs(119)), None);
+        let failsafe_at = t0 + Duration::from_secs(120);
+        assert_eq!(lpc.update(failsafe_at), Some(LpcState::Failsafe));
+        assert_eq!(lpc.power_limit(), Some(6000.0));
+
+        let timestamp = ocpp::datetime::parse("2025-06-01T12:00:00Z").unwrap();
+        let notification = lpc.status_notification(1, ChargePointStatus::SuspendedEvse, timestamp);
+        assert_eq!(
+            notification.info.map(|info| info.to_string()),
+            Some("LPC failsafe 6000 W".to_string())
+        );
+        let sampled_value = lpc.sampled_value(ReadingContext::SamplePeriodic).unwrap();
+        assert_eq!(sampled_value.measurand, Some(Measurand::PowerOffered));
+        assert_eq!(sampled_value.value.as_decimal(), Some(Decimal::from(6000)));
+
+        assert_eq!(
+            lpc.update(failsafe_at + Duration::from_secs(3 * 3600)),
+            Some(LpcState::UnlimitedAutonomous)
+        );
+        assert_eq!(lpc.power_limit(), None);
+        assert_eq!(lpc.info(), None);
+    }
+
+    #[test]
+    fn given_no_energy_guard_after_start__when_updating__then_failsafe() {
+        let t0 = Instant::now();
+        let mut lpc = LimitationOfPowerConsumption::new(t0);
+
+        assert_eq!(lpc.update(t0 + HEARTBEAT_TIMEOUT), Some(LpcState::Failsafe));
+        assert_eq!(lpc.power_limit(), Some(GUARANTEED_POWER));
+        assert_eq!(
+            lpc.write_failsafe(4200.0, Duration::from_secs(3600)),
+            Err(LpcError::InvalidFailsafeDuration(Duration::from_secs(3600)))
+        );
+        assert!(matches!(
+            lpc.write_limit(
+                PowerLimit {
+                    value: -1.0,
+                    active: true,
+                    duration: None
+                },
+                t0
+            ),
+            Err(LpcError::InvalidPower(_))
+        ));
+    }
+}
@@ -0,0 +1,174 @@
+///// Modbus application protocol, shared by the RTU and TCP framings /////
+use std::fmt;
+use std::io;
+
+pub mod rtu;
+pub mod server;
+pub mod tcp;
+
+pub use rtu::ModbusRtuClient;
+pub use server::{ModbusTcpServer, RegisterBank, RegisterHandler};
+pub use tcp::ModbusTcpClient;
+
+/// Registers of a request cannot exceed one PDU
+pub const MAX_READ_REGISTERS: u16 = 125;
+pub const MAX_WRITE_REGISTERS: u16 = 123;
+
+pub const READ_HOLDING_REGISTERS: u8 = 0x03;
+pub const READ_INPUT_REGISTERS: u8 = 0x04;
+pub const WRITE_SINGLE_REGISTER: u8 = 0x06;
+pub const WRITE_MULTIPLE_REGISTERS: u8 = 0x10;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum RegisterTable {
+    /// Read only, function code 4
+    Input,
+    /// Read and write, function codes 3, 6 and 16
+    Holding,
+}
+
+impl RegisterTable {
+    fn read_function(&self) -> u8 {
+        match self {
+            RegisterTable::Input => READ_INPUT_REGISTERS,
+            RegisterTable::Holding => READ_HOLDING_REGISTERS,
+        }
+    }
+}
+
+/// Exception code of a device refusing a request
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Exception {
+    IllegalFunction,
+    IllegalDataAddress,
+    IllegalDataValue,
+    ServerDeviceFailure,
+    /// Any code without a name here
+    Other(u8),
+}
+
+impl Exception {
+    pub fn code(&self) -> u8 {
+        match self {
+            Exception::IllegalFunction => 0x01,
+            Exception::IllegalDataAddress => 0x02,
+            Exception::IllegalDataValue => 0x03,
+            Exception::ServerDeviceFailure => 0x04,
+            Exception::Other(code) => *code,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Self {
+        match code {
+            0x01 => Exception::IllegalFunction,
+            0x02 => Exception::IllegalDataAddress,
+            0x03 => Exception::IllegalDataValue,
+            0x04 => Exception::ServerDeviceFailure,
+            code => Exception::Other(code),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ModbusError {
+    /// The connection failed or the device did not answer in time
+    Io(io::Error),
+    Exception(Exception),
+    /// The answer does not fit the request, i.e. a wrong CRC or register count
+    InvalidFrame(String),
+}
+
+impl fmt::Display for ModbusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModbusError::Io(err) => write!(f, "modbus connection failed: {}", err),
+            ModbusError::Exception(exception) => {
+                write!(f, "modbus exception {:#04x}", exception.code())
+            }
+            ModbusError::InvalidFrame(reason) => write!(f, "invalid modbus frame: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ModbusError {}
+
+impl From<io::Error> for ModbusError {
+    fn from(err: io::Error) -> Self {
+        ModbusError::Io(err)
+    }
+}
+
+/// The requests a Modbus master sends, independent of the framing
+pub trait ModbusClient {
+    fn read_registers(
+        &mut self,
+        unit: u8,
+        table: RegisterTable,
+        address: u16,
+        count: u16,
+    ) -> Result<Vec<u16>, ModbusError>;
+
+    fn write_registers(
+        &mut self,
+        unit: u8,
+        address: u16,
+        values: &[u16],
+    ) -> Result<(), ModbusError>;
+}
+
+pub(crate) fn read_request(table: RegisterTable, address: u16, count: u16) -> Vec<u8> {
+    let mut pdu = vec![table.read_function()];
+    pdu.extend(address.to_be_bytes());
+    pdu.extend(count.to_be_bytes());
+    pdu
+}
+
+pub(crate) fn write_request(address: u16, values: &[u16]) -> Vec<u8> {
+    let mut pdu = vec![WRITE_MULTIPLE_REGISTERS];
+    pdu.extend(address.to_be_bytes());
+    pdu.extend((values.len() as u16).to_be_bytes());
+    pdu.push((values.len() * 2) as u8);
+    pdu.extend(values.iter().flat_map(|value| value.to_be_bytes()));
+    pdu
+}
+
+/// Checks the response PDU of `request`, returns the registers read
+pub(crate) fn parse_response(request: &[u8], response: &[u8]) -> Result<Vec<u16>, ModbusError> {
+    let function = request[0];
+    match response {
+        [code, exception] if *code == function | 0x80 => {
+            Err(ModbusError::Exception(Exception::from_code(*exception)))
+        }
+        [code, ..] if *code != function => Err(ModbusError::InvalidFrame(format!(
+            "function {:#04x} answered with {:#04x}",
+            function, code
+        ))),
+        [_, byte_count, data @ ..] if function != WRITE_MULTIPLE_REGISTERS => {
+            let count = u16::from_be_bytes([request[3], request[4]]) as usize;
+            if *byte_count as usize != count * 2 || data.len() != count * 2 {
+                return Err(ModbusError::InvalidFrame(format!(
+                    "expected {} registers, got {} bytes",
+                    count,
+                    data.len()
+                )));
+            }
+            Ok(data
+                .chunks(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect())
+        }
+        [_, echo @ ..] if echo == &request[1..5] => Ok(Vec::new()),
+        _ => Err(ModbusError::InvalidFrame(format!(
+            "unexpected response {:02x?}",
+            response
+        ))),
+    }
+}
+
+/// Length of the response PDU to `request` if it succeeds, after the function code
+pub(crate) fn response_length(request: &[u8]) -> usize {
+    match request[0] {
+        WRITE_MULTIPLE_REGISTERS | WRITE_SINGLE_REGISTER => 4,
+        _ => 1 + u16::from_be_bytes([request[3], request[4]]) as usize * 2,
+    }
+}
@@ -0,0 +1,163 @@
+///// Modbus RTU: unit, PDU and CRC on a serial line /////
+use std::io::{Read, Write};
+
+use crate::modbus::{
+    ModbusClient, ModbusError, RegisterTable, parse_response, read_request, response_length,
+    write_request,
+};
+
+/// Master on an RS-485 bus. `S` is the opened serial port, its read timeout decides how long
+/// to wait for a device.
+pub struct ModbusRtuClient<S> {
+    port: S,
+}
+
+impl<S: Read + Write> ModbusRtuClient<S> {
+    pub fn new(port: S) -> Self {
+        Self { port }
+    }
+
+    pub fn port_mut(&mut self) -> &mut S {
+        &mut self.port
+    }
+
+    fn request(&mut self, unit: u8, pdu: &[u8]) -> Result<Vec<u16>, ModbusError> {
+        let mut frame = vec![unit];
+        frame.extend(pdu);
+        frame.extend(crc16(&frame).to_le_bytes());
+        self.port.write_all(&frame)?;
+        self.port.flush()?;
+
+        let mut response = vec![0; 2];
+        self.port.read_exact(&mut response)?;
+        let remaining = if response[1] & 0x80 != 0 {
+            1
+        } else {
+            response_length(pdu)
+        };
+        response.resize(2 + remaining + 2, 0);
+        self.port.read_exact(&mut response[2..])?;
+
+        let (body, crc) = response.split_at(response.len() - 2);
+        if crc16(body).to_le_bytes() != crc {
+            return Err(ModbusError::InvalidFrame("CRC mismatch".to_string()));
+        }
+        if body[0] != unit {
+            return Err(ModbusError::InvalidFrame(format!(
+                "unit {} answered instead of {}",
+                body[0], unit
+            )));
+        }
+        parse_response(pdu, &body[1..])
+    }
+}
+
+impl<S: Read + Write> ModbusClient for ModbusRtuClient<S> {
+    fn read_registers(
+        &mut self,
+        unit: u8,
+        table: RegisterTable,
+        address: u16,
+        count: u16,
+    ) -> Result<Vec<u16>, ModbusError> {
+        self.request(unit, &read_request(table, address, count))
+    }
+
+    fn write_registers(
+        &mut self,
+        unit: u8,
+        address: u16,
+        values: &[u16],
+    ) -> Result<(), ModbusError> {
+        self.request(unit, &write_request(address, values))
+            .map(|_| ())
+    }
+}
+
+/// CRC-16/MODBUS, sent low byte first
+pub fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0xFFFF, |crc, byte| {
+        (0..8).fold(crc ^ *byte as u16, |crc, _| {
+            if crc & 1 != 0 {
+                (crc >> 1) ^ 0xA001
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modbus::Exception;
+    use std::io::{self, Cursor};
+
+    /// Serial line answering with a prepared response, keeping what was written
+    struct Loopback {
+        written: Vec<u8>,
+        response: Cursor<Vec<u8>>,
+    }
+
+    impl Loopback {
+        fn answering(body: &[u8]) -> Self {
+            let mut response = body.to_vec();
+            response.extend(crc16(body).to_le_bytes());
+            Self {
+                written: Vec::new(),
+                response: Cursor::new(response),
+            }
+        }
+    }
+
+    impl Read for Loopback {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.response.read(buf)
+        }
+    }
+
+    impl Write for Loopback {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn given_read_request__when_framing__then_crc_low_byte_first_and_registers_parsed() {
+        let mut client = ModbusRtuClient::new(Loopback::answering(&[
+            0x01, 0x03, 0x04, 0x43, 0x66, 0x00, 0x00,
+        ]));
+
+        let registers = client
+            .read_registers(1, RegisterTable::Holding, 0x0000, 2)
+            .unwrap();
+
+        assert_eq!(
+            client.port_mut().written,
+            [0x01, 0x03, 0x00, 0x00, 0x00, 0x02, 0xC4, 0x0B]
+        );
+        assert_eq!(registers, vec![0x4366, 0x0000]);
+    }
+
+    #[test]
+    fn given_exception_or_corrupt_answer__when_reading__then_errors() {
+        let mut client = ModbusRtuClient::new(Loopback::answering(&[0x01, 0x84, 0x02]));
+        assert!(matches!(
+            client.read_registers(1, RegisterTable::Input, 0x9999, 2),
+            Err(ModbusError::Exception(Exception::IllegalDataAddress))
+        ));
+
+        let mut corrupt = Loopback::answering(&[0x01, 0x04, 0x02, 0x00, 0x01]);
+        corrupt.response.get_mut()[4] = 0x02;
+        let mut client = ModbusRtuClient::new(corrupt);
+        assert!(matches!(
+            client.read_registers(1, RegisterTable::Input, 0x0000, 1),
+            Err(ModbusError::InvalidFrame(_))
+        ));
+    }
+}
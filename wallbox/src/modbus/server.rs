@@ -0,0 +1,295 @@
+///// Modbus TCP server answering from a register handler /////
+use std::collections::BTreeMap;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::modbus::tcp::{HEADER_LENGTH, header};
+use crate::modbus::{
+    Exception, MAX_READ_REGISTERS, MAX_WRITE_REGISTERS, READ_HOLDING_REGISTERS,
+    READ_INPUT_REGISTERS, RegisterTable, WRITE_MULTIPLE_REGISTERS, WRITE_SINGLE_REGISTER,
+};
+
+/// How often idle connections look whether the server shut down
+const IDLE_CHECK: Duration = Duration::from_millis(200);
+/// How long the rest of a frame may take once its first byte arrived
+const FRAME_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The registers a server exposes
+pub trait RegisterHandler: Send {
+    fn read(
+        &mut self,
+        unit: u8,
+        table: RegisterTable,
+        address: u16,
+        count: u16,
+    ) -> Result<Vec<u16>, Exception>;
+
+    /// Writes holding registers
+    fn write(&mut self, unit: u8, address: u16, values: &[u16]) -> Result<(), Exception>;
+}
+
+/// Plain registers answering for every unit, i.e. to simulate a device.
+/// Only registers set before exist, holding registers can be written by clients.
+#[derive(Debug, Clone, Default)]
+pub struct RegisterBank {
+    registers: BTreeMap<(RegisterTable, u16), u16>,
+}
+
+impl RegisterBank {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, table: RegisterTable, address: u16, values: &[u16]) {
+        for (offset, value) in values.iter().enumerate() {
+            self.registers
+                .insert((table, address + offset as u16), *value);
+        }
+    }
+
+    pub fn get(&self, table: RegisterTable, address: u16) -> Option<u16> {
+        self.registers.get(&(table, address)).copied()
+    }
+}
+
+impl RegisterHandler for RegisterBank {
+    fn read(
+        &mut self,
+        _unit: u8,
+        table: RegisterTable,
+        address: u16,
+        count: u16,
+    ) -> Result<Vec<u16>, Exception> {
+        (0..count)
+            .map(|offset| {
+                address
+                    .checked_add(offset)
+                    .and_then(|address| self.get(table, address))
+                    .ok_or(Exception::IllegalDataAddress)
+            })
+            .collect()
+    }
+
+    fn write(&mut self, _unit: u8, address: u16, values: &[u16]) -> Result<(), Exception> {
+        for offset in 0..values.len() as u16 {
+            address
+                .checked_add(offset)
+                .and_then(|address| self.get(RegisterTable::Holding, address))
+                .ok_or(Exception::IllegalDataAddress)?;
+        }
+        self.set(RegisterTable::Holding, address, values);
+        Ok(())
+    }
+}
+
+/// Serves a handler to any number of clients, each connection on its own thread.
+/// Dropping the server stops it.
+pub struct ModbusTcpServer {
+    local_addr: SocketAddr,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl ModbusTcpServer {
+    /// Listens on `address`, port 0 picks a free port, see [`local_addr`](Self::local_addr)
+    pub fn bind<H: RegisterHandler + 'static>(
+        address: impl ToSocketAddrs,
+        handler: Arc<Mutex<H>>,
+    ) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let local_addr = listener.local_addr()?;
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let stop = shutdown.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                let handler = handler.clone();
+                let stop = stop.clone();
+                thread::spawn(move || serve(stream, handler, stop));
+            }
+        });
+        Ok(Self {
+            local_addr,
+            shutdown,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for ModbusTcpServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        // wakes the accepting thread
+        let _ = TcpStream::connect(self.local_addr);
+    }
+}
+
+fn serve<H: RegisterHandler>(
+    mut stream: TcpStream,
+    handler: Arc<Mutex<H>>,
+    shutdown: Arc<AtomicBool>,
+) -> io::Result<()> {
+    loop {
+        // waiting for a frame only peeks, so timing out never consumes part of a header
+        stream.set_read_timeout(Some(IDLE_CHECK))?;
+        match stream.peek(&mut [0]) {
+            Ok(0) => return Ok(()),
+            Ok(_) => {}
+            Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                if shutdown.load(Ordering::Relaxed) {
+                    return Ok(());
+                }
+                continue;
+            }
+            Err(err) => return Err(err),
+        }
+        // a frame cut short drops the connection, the stream is out of sync after it
+        stream.set_read_timeout(Some(FRAME_TIMEOUT))?;
+        let mut header_bytes = [0; HEADER_LENGTH];
+        stream.read_exact(&mut header_bytes)?;
+        let transaction_id = u16::from_be_bytes([header_bytes[0], header_bytes[1]]);
+        let length = u16::from_be_bytes([header_bytes[4], header_bytes[5]]) as usize;
+        let unit = header_bytes[6];
+        let mut request = vec![0; length.saturating_sub(1)];
+        stream.read_exact(&mut request)?;
+
+        let response = {
+            let mut handler = handler
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            respond(&mut *handler, unit, &request)
+        };
+        stream.write_all(&header(transaction_id, unit, response.len()))?;
+        stream.write_all(&response)?;
+    }
+}
+
+/// Response PDU to the request PDU `request`, an exception response if it fails
+pub(crate) fn respond(handler: &mut impl RegisterHandler, unit: u8, request: &[u8]) -> Vec<u8> {
+    let Some(&function) = request.first() else {
+        return vec![0x80, Exception::IllegalFunction.code()];
+    };
+    match execute(handler, unit, request) {
+        Ok(response) => response,
+        Err(exception) => vec![function | 0x80, exception.code()],
+    }
+}
+
+fn execute(
+    handler: &mut impl RegisterHandler,
+    unit: u8,
+    request: &[u8],
+) -> Result<Vec<u8>, Exception> {
+    let word = |index: usize| {
+        request
+            .get(index..index + 2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+            .ok_or(Exception::IllegalDataValue)
+    };
+    let function = request[0];
+    match function {
+        READ_HOLDING_REGISTERS | READ_INPUT_REGISTERS => {
+            let table = if function == READ_INPUT_REGISTERS {
+                RegisterTable::Input
+            } else {
+                RegisterTable::Holding
+            };
+            let (address, count) = (word(1)?, word(3)?);
+            if count == 0 || count > MAX_READ_REGISTERS {
+                return Err(Exception::IllegalDataValue);
+            }
+            let values = handler.read(unit, table, address, count)?;
+            let mut response = vec![function, (values.len() * 2) as u8];
+            response.extend(values.iter().flat_map(|value| value.to_be_bytes()));
+            Ok(response)
+        }
+        WRITE_SINGLE_REGISTER => {
+            handler.write(unit, word(1)?, &[word(3)?])?;
+            Ok(request[..5].to_vec())
+        }
+        WRITE_MULTIPLE_REGISTERS => {
+            let (address, count) = (word(1)?, word(3)?);
+            if count == 0 || count > MAX_WRITE_REGISTERS {
+                return Err(Exception::IllegalDataValue);
+            }
+            let values = (0..count as usize)
+                .map(|index| word(6 + index * 2))
+                .collect::<Result<Vec<_>, _>>()?;
+            handler.write(unit, address, &values)?;
+            Ok(request[..5].to_vec())
+        }
+        _ => Err(Exception::IllegalFunction),
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modbus::{ModbusClient, ModbusError, ModbusTcpClient};
+
+    #[test]
+    fn given_register_bank__when_client_reads_and_writes__then_served_over_tcp() {
+        let mut bank = RegisterBank::new();
+        bank.set(RegisterTable::Input, 0x0000, &[0x4366, 0x0000]);
+        bank.set(RegisterTable::Holding, 0x1000, &[0]);
+        let bank = Arc::new(Mutex::new(bank));
+        let server = ModbusTcpServer::bind("127.0.0.1:0", bank.clone()).unwrap();
+        let mut client =
+            ModbusTcpClient::connect(server.local_addr(), Duration::from_secs(1)).unwrap();
+
+        assert_eq!(
+            client
+                .read_registers(1, RegisterTable::Input, 0x0000, 2)
+                .unwrap(),
+            vec![0x4366, 0x0000]
+        );
+        client.write_registers(1, 0x1000, &[160]).unwrap();
+        assert_eq!(
+            bank.lock().unwrap().get(RegisterTable::Holding, 0x1000),
+            Some(160)
+        );
+        assert!(matches!(
+            client.write_registers(1, 0x0000, &[1]),
+            Err(ModbusError::Exception(Exception::IllegalDataAddress))
+        ));
+    }
+
+    #[test]
+    fn given_header_split_across_idle_checks__when_serving__then_frame_answered() {
+        let mut bank = RegisterBank::new();
+        bank.set(RegisterTable::Holding, 0x1000, &[160]);
+        let server = ModbusTcpServer::bind("127.0.0.1:0", Arc::new(Mutex::new(bank))).unwrap();
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(1)))
+            .unwrap();
+        let request = [
+            0x00, 0x07, 0x00, 0x00, 0x00, 0x06, 0x01, 0x03, 0x10, 0x00, 0x00, 0x01,
+        ];
+
+        stream.write_all(&request[..3]).unwrap();
+        thread::sleep(IDLE_CHECK * 2);
+        stream.write_all(&request[3..]).unwrap();
+        let mut response = [0; 11];
+        stream.read_exact(&mut response).unwrap();
+
+        assert_eq!(
+            response,
+            [
+                0x00, 0x07, 0x00, 0x00, 0x00, 0x05, 0x01, 0x03, 0x02, 0x00, 0xa0
+            ]
+        );
+    }
+}
@@ -0,0 +1,181 @@
+///// Modbus TCP: the PDU behind a 7 byte MBAP header /////
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::modbus::{
+    ModbusClient, ModbusError, RegisterTable, parse_response, read_request, write_request,
+};
+
+pub(crate) const HEADER_LENGTH: usize = 7;
+
+pub struct ModbusTcpClient {
+    address: SocketAddr,
+    timeout: Duration,
+    /// Dropped when an exchange fails, a half read answer would be taken for the next one.
+    /// The next request connects again.
+    stream: Option<TcpStream>,
+    transaction_id: u16,
+}
+
+impl ModbusTcpClient {
+    /// Connects to a device or gateway, usually on port 502. `timeout` applies to the
+    /// connection and to every answer.
+    pub fn connect(address: impl ToSocketAddrs, timeout: Duration) -> Result<Self, ModbusError> {
+        let mut last_error = None;
+        for address in address.to_socket_addrs()? {
+            match open(address, timeout) {
+                Ok(stream) => {
+                    return Ok(Self {
+                        address,
+                        timeout,
+                        stream: Some(stream),
+                        transaction_id: 0,
+                    });
+                }
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(last_error
+            .unwrap_or_else(|| std::io::Error::other("no address to connect to"))
+            .into())
+    }
+
+    fn request(&mut self, unit: u8, pdu: &[u8]) -> Result<Vec<u16>, ModbusError> {
+        self.transaction_id = self.transaction_id.wrapping_add(1);
+        let transaction_id = self.transaction_id;
+        let stream = match &mut self.stream {
+            Some(stream) => stream,
+            None => self.stream.insert(open(self.address, self.timeout)?),
+        };
+        let response = exchange(stream, transaction_id, unit, pdu);
+        if response.is_err() {
+            self.stream = None;
+        }
+        parse_response(pdu, &response?)
+    }
+}
+
+fn open(address: SocketAddr, timeout: Duration) -> std::io::Result<TcpStream> {
+    let stream = TcpStream::connect_timeout(&address, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_nodelay(true)?;
+    Ok(stream)
+}
+
+/// Sends `pdu` and reads the PDU of the answer
+fn exchange(
+    stream: &mut TcpStream,
+    transaction_id: u16,
+    unit: u8,
+    pdu: &[u8],
+) -> Result<Vec<u8>, ModbusError> {
+    stream.write_all(&header(transaction_id, unit, pdu.len()))?;
+    stream.write_all(pdu)?;
+
+    let mut header = [0; HEADER_LENGTH];
+    stream.read_exact(&mut header)?;
+    let answered = u16::from_be_bytes([header[0], header[1]]);
+    let protocol_id = u16::from_be_bytes([header[2], header[3]]);
+    let length = u16::from_be_bytes([header[4], header[5]]) as usize;
+    if answered != transaction_id || protocol_id != 0 || header[6] != unit || length < 2 {
+        return Err(ModbusError::InvalidFrame(format!(
+            "header {:02x?} does not answer transaction {} of unit {}",
+            header, transaction_id, unit
+        )));
+    }
+    let mut response = vec![0; length - 1];
+    stream.read_exact(&mut response)?;
+    Ok(response)
+}
+
+/// MBAP header of a frame carrying a PDU of `length` bytes
+pub(crate) fn header(transaction_id: u16, unit: u8, length: usize) -> [u8; HEADER_LENGTH] {
+    let [t0, t1] = transaction_id.to_be_bytes();
+    let [l0, l1] = (length as u16 + 1).to_be_bytes();
+    [t0, t1, 0, 0, l0, l1, unit]
+}
+
+impl ModbusClient for ModbusTcpClient {
+    fn read_registers(
+        &mut self,
+        unit: u8,
+        table: RegisterTable,
+        address: u16,
+        count: u16,
+    ) -> Result<Vec<u16>, ModbusError> {
+        self.request(unit, &read_request(table, address, count))
+    }
+
+    fn write_registers(
+        &mut self,
+        unit: u8,
+        address: u16,
+        values: &[u16],
+    ) -> Result<(), ModbusError> {
+        self.request(unit, &write_request(address, values))
+            .map(|_| ())
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Answers the first request of each connection with the next of `headers` followed by
+    /// a one register read response
+    fn device(headers: Vec<[u8; HEADER_LENGTH]>) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for header in headers {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = [0; 12];
+                stream.read_exact(&mut request).unwrap();
+                stream.write_all(&header).unwrap();
+                stream.write_all(&[0x03, 0x02, 0x00, 0xa0]).unwrap();
+            }
+        });
+        address
+    }
+
+    fn read(address: SocketAddr) -> Result<Vec<u16>, ModbusError> {
+        ModbusTcpClient::connect(address, Duration::from_secs(1))
+            .unwrap()
+            .read_registers(1, RegisterTable::Holding, 0x1000, 1)
+    }
+
+    #[test]
+    fn given_matching_header__when_reading__then_values() {
+        let values = read(device(vec![[0x00, 0x01, 0x00, 0x00, 0x00, 0x05, 0x01]]));
+
+        assert_eq!(values.unwrap(), vec![160]);
+    }
+
+    #[test]
+    fn given_other_unit_or_protocol__when_reading__then_invalid_frame() {
+        let other_unit = read(device(vec![[0x00, 0x01, 0x00, 0x00, 0x00, 0x05, 0x02]]));
+        let other_protocol = read(device(vec![[0x00, 0x01, 0x00, 0x01, 0x00, 0x05, 0x01]]));
+
+        assert!(matches!(other_unit, Err(ModbusError::InvalidFrame(_))));
+        assert!(matches!(other_protocol, Err(ModbusError::InvalidFrame(_))));
+    }
+
+    #[test]
+    fn given_invalid_frame__when_reading_again__then_reconnected() {
+        let address = device(vec![
+            [0x00, 0x01, 0x00, 0x00, 0x00, 0x05, 0x02],
+            [0x00, 0x02, 0x00, 0x00, 0x00, 0x05, 0x01],
+        ]);
+        let mut client = ModbusTcpClient::connect(address, Duration::from_secs(1)).unwrap();
+
+        let first = client.read_registers(1, RegisterTable::Holding, 0x1000, 1);
+        let second = client.read_registers(1, RegisterTable::Holding, 0x1000, 1);
+
+        assert!(matches!(first, Err(ModbusError::InvalidFrame(_))));
+        assert_eq!(second.unwrap(), vec![160]);
+    }
+}
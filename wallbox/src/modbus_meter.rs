@@ -0,0 +1,376 @@
+///// Energy meters on Modbus, with the register maps of common models /////
+use ocpp::types::{Measurand, Phase};
+
+use crate::hal::{EnergyMeter, HalError, Reading};
+use crate::modbus::{MAX_READ_REGISTERS, ModbusClient, ModbusError, RegisterBank, RegisterTable};
+
+/// Encoding of a value in consecutive registers
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ValueKind {
+    F32,
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+}
+
+impl ValueKind {
+    pub fn registers(&self) -> u16 {
+        match self {
+            ValueKind::U16 | ValueKind::I16 => 1,
+            ValueKind::F32 | ValueKind::U32 | ValueKind::I32 => 2,
+            ValueKind::U64 => 4,
+        }
+    }
+}
+
+/// Order of the registers of a value spanning several, within a register the high byte comes
+/// first
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum WordOrder {
+    HighFirst,
+    LowFirst,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegisterDef {
+    pub measurand: Measurand,
+    pub phase: Option<Phase>,
+    pub address: u16,
+    pub kind: ValueKind,
+    /// Factor from the register value to the OCPP unit, i.e. 1000 for kWh to Wh
+    pub scale: f64,
+}
+
+const fn register(
+    measurand: Measurand,
+    phase: Option<Phase>,
+    address: u16,
+    kind: ValueKind,
+    scale: f64,
+) -> RegisterDef {
+    RegisterDef {
+        measurand,
+        phase,
+        address,
+        kind,
+        scale,
+    }
+}
+
+impl RegisterDef {
+    fn end(&self) -> u16 {
+        self.address + self.kind.registers()
+    }
+
+    /// Value in the OCPP unit from the registers of this value
+    pub fn decode(&self, registers: &[u16], order: WordOrder) -> f64 {
+        let ordered = |registers: &mut dyn Iterator<Item = &u16>| {
+            registers.fold(0u64, |raw, register| (raw << 16) | *register as u64)
+        };
+        let raw = match order {
+            WordOrder::HighFirst => ordered(&mut registers.iter()),
+            WordOrder::LowFirst => ordered(&mut registers.iter().rev()),
+        };
+        let value = match self.kind {
+            ValueKind::F32 => f32::from_bits(raw as u32) as f64,
+            ValueKind::U16 => raw as u16 as f64,
+            ValueKind::I16 => raw as u16 as i16 as f64,
+            ValueKind::U32 => raw as u32 as f64,
+            ValueKind::I32 => raw as u32 as i32 as f64,
+            ValueKind::U64 => raw as f64,
+        };
+        value * self.scale
+    }
+
+    /// Registers holding `value` given in the OCPP unit, the inverse of [`decode`](Self::decode)
+    pub fn encode(&self, value: f64, order: WordOrder) -> Vec<u16> {
+        let value = value / self.scale;
+        let raw = match self.kind {
+            ValueKind::F32 => (value as f32).to_bits() as u64,
+            ValueKind::U16 => value.round() as u16 as u64,
+            ValueKind::I16 => value.round() as i16 as u16 as u64,
+            ValueKind::U32 => value.round() as u32 as u64,
+            ValueKind::I32 => value.round() as i32 as u32 as u64,
+            ValueKind::U64 => value.round() as u64,
+        };
+        let count = self.kind.registers();
+        let mut registers = (0..count)
+            .rev()
+            .map(|index| (raw >> (16 * index)) as u16)
+            .collect::<Vec<_>>();
+        if order == WordOrder::LowFirst {
+            registers.reverse();
+        }
+        registers
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeterModel {
+    pub name: &'static str,
+    pub table: RegisterTable,
+    pub word_order: WordOrder,
+    pub registers: &'static [RegisterDef],
+}
+
+impl MeterModel {
+    /// Registers of a device of this model measuring `readings`, to serve from a simulator
+    pub fn simulate(&self, readings: &[Reading]) -> RegisterBank {
+        let mut bank = RegisterBank::new();
+        // the gaps of a block read as zero, like on the devices
+        for (start, end) in self.blocks() {
+            bank.set(self.table, start, &vec![0; (end - start) as usize]);
+        }
+        for register in self.registers {
+            let value = readings
+                .iter()
+                .find(|reading| {
+                    reading.measurand == register.measurand && reading.phase == register.phase
+                })
+                .map_or(0.0, |reading| reading.value);
+            bank.set(
+                self.table,
+                register.address,
+                &register.encode(value, self.word_order),
+            );
+        }
+        bank
+    }
+
+    /// Address ranges covering all registers with as few requests as possible
+    fn blocks(&self) -> Vec<(u16, u16)> {
+        let mut registers = self.registers.to_vec();
+        registers.sort_by_key(|register| register.address);
+        let mut blocks: Vec<(u16, u16)> = Vec::new();
+        for register in registers {
+            match blocks.last_mut() {
+                Some((start, end)) if register.end() - *start <= MAX_READ_REGISTERS => {
+                    *end = (*end).max(register.end());
+                }
+                _ => blocks.push((register.address, register.end())),
+            }
+        }
+        blocks
+    }
+}
+
+use Measurand::{
+    CurrentImport, EnergyActiveImportRegister, Frequency, PowerActiveImport, PowerFactor, Voltage,
+};
+use Phase::{L1, L1N, L2, L2N, L3, L3N};
+
+/// Eastron SDM630, input registers holding IEEE 754 floats
+pub const EASTRON_SDM630: MeterModel = MeterModel {
+    name: "Eastron SDM630",
+    table: RegisterTable::Input,
+    word_order: WordOrder::HighFirst,
+    registers: &[
+        register(Voltage, Some(L1N), 0x0000, ValueKind::F32, 1.0),
+        register(Voltage, Some(L2N), 0x0002, ValueKind::F32, 1.0),
+        register(Voltage, Some(L3N), 0x0004, ValueKind::F32, 1.0),
+        register(CurrentImport, Some(L1), 0x0006, ValueKind::F32, 1.0),
+        register(CurrentImport, Some(L2), 0x0008, ValueKind::F32, 1.0),
+        register(CurrentImport, Some(L3), 0x000A, ValueKind::F32, 1.0),
+        register(PowerActiveImport, Some(L1), 0x000C, ValueKind::F32, 1.0),
+        register(PowerActiveImport, Some(L2), 0x000E, ValueKind::F32, 1.0),
+        register(PowerActiveImport, Some(L3), 0x0010, ValueKind::F32, 1.0),
+        register(PowerActiveImport, None, 0x0034, ValueKind::F32, 1.0),
+        register(PowerFactor, None, 0x003E, ValueKind::F32, 1.0),
+        register(Frequency, None, 0x0046, ValueKind::F32, 1.0),
+        register(
+            EnergyActiveImportRegister,
+            None,
+            0x0048,
+            ValueKind::F32,
+            1000.0,
+        ),
+    ],
+};
+
+/// ABB B23/B24, holding registers holding scaled integers
+pub const ABB_B23: MeterModel = MeterModel {
+    name: "ABB B23",
+    table: RegisterTable::Holding,
+    word_order: WordOrder::HighFirst,
+    registers: &[
+        register(
+            EnergyActiveImportRegister,
+            None,
+            0x5000,
+            ValueKind::U64,
+            10.0,
+        ),
+        register(Voltage, Some(L1N), 0x5B00, ValueKind::U32, 0.1),
+        register(Voltage, Some(L2N), 0x5B02, ValueKind::U32, 0.1),
+        register(Voltage, Some(L3N), 0x5B04, ValueKind::U32, 0.1),
+        register(CurrentImport, Some(L1), 0x5B0C, ValueKind::U32, 0.01),
+        register(CurrentImport, Some(L2), 0x5B0E, ValueKind::U32, 0.01),
+        register(CurrentImport, Some(L3), 0x5B10, ValueKind::U32, 0.01),
+        register(PowerActiveImport, None, 0x5B14, ValueKind::I32, 0.01),
+        register(PowerActiveImport, Some(L1), 0x5B16, ValueKind::I32, 0.01),
+        register(PowerActiveImport, Some(L2), 0x5B18, ValueKind::I32, 0.01),
+        register(PowerActiveImport, Some(L3), 0x5B1A, ValueKind::I32, 0.01),
+        register(Frequency, None, 0x5B2C, ValueKind::U16, 0.01),
+        register(PowerFactor, None, 0x5B3A, ValueKind::I16, 0.001),
+    ],
+};
+
+/// Carlo Gavazzi EM340, scaled integers with the low word first
+pub const CARLO_GAVAZZI_EM340: MeterModel = MeterModel {
+    name: "Carlo Gavazzi EM340",
+    table: RegisterTable::Holding,
+    word_order: WordOrder::LowFirst,
+    registers: &[
+        register(Voltage, Some(L1N), 0x0000, ValueKind::I32, 0.1),
+        register(Voltage, Some(L2N), 0x0002, ValueKind::I32, 0.1),
+        register(Voltage, Some(L3N), 0x0004, ValueKind::I32, 0.1),
+        register(CurrentImport, Some(L1), 0x000C, ValueKind::I32, 0.001),
+        register(CurrentImport, Some(L2), 0x000E, ValueKind::I32, 0.001),
+        register(CurrentImport, Some(L3), 0x0010, ValueKind::I32, 0.001),
+        register(PowerActiveImport, Some(L1), 0x0012, ValueKind::I32, 0.1),
+        register(PowerActiveImport, Some(L2), 0x0014, ValueKind::I32, 0.1),
+        register(PowerActiveImport, Some(L3), 0x0016, ValueKind::I32, 0.1),
+        register(PowerActiveImport, None, 0x0028, ValueKind::I32, 0.1),
+        register(PowerFactor, None, 0x0031, ValueKind::I16, 0.001),
+        register(Frequency, None, 0x0033, ValueKind::I16, 0.1),
+        register(
+            EnergyActiveImportRegister,
+            None,
+            0x0034,
+            ValueKind::I32,
+            100.0,
+        ),
+    ],
+};
+
+/// A meter of `model` at `unit` on a Modbus RTU bus or TCP gateway
+pub struct ModbusMeter<C> {
+    client: C,
+    unit: u8,
+    model: &'static MeterModel,
+}
+
+impl<C: ModbusClient> ModbusMeter<C> {
+    pub fn new(client: C, unit: u8, model: &'static MeterModel) -> Self {
+        Self {
+            client,
+            unit,
+            model,
+        }
+    }
+
+    pub fn model(&self) -> &'static MeterModel {
+        self.model
+    }
+
+    fn hal_error(&self, err: ModbusError) -> HalError {
+        let device = format!("{} at unit {}", self.model.name, self.unit);
+        match err {
+            ModbusError::Io(err) => HalError::NotResponding(format!("{}: {}", device, err)),
+            err => HalError::InvalidResponse(format!("{}: {}", device, err)),
+        }
+    }
+}
+
+impl<C: ModbusClient> EnergyMeter for ModbusMeter<C> {
+    fn read(&mut self) -> Result<Vec<Reading>, HalError> {
+        let mut readings = Vec::with_capacity(self.model.registers.len());
+        for (start, end) in self.model.blocks() {
+            let values = self
+                .client
+                .read_registers(self.unit, self.model.table, start, end - start)
+                .map_err(|err| self.hal_error(err))?;
+            for register in self.model.registers {
+                if register.address < start || register.end() > end {
+                    continue;
+                }
+                let offset = (register.address - start) as usize;
+                let raw = &values[offset..offset + register.kind.registers() as usize];
+                readings.push(Reading::new(
+                    register.measurand,
+                    register.phase,
+                    register.decode(raw, self.model.word_order),
+                ));
+            }
+        }
+        Ok(readings)
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modbus::{ModbusTcpClient, ModbusTcpServer};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    fn readings() -> Vec<Reading> {
+        vec![
+            Reading::new(Voltage, Some(L1N), 230.5),
+            Reading::new(Voltage, Some(L2N), 229.5),
+            Reading::new(Voltage, Some(L3N), 231.0),
+            Reading::new(CurrentImport, Some(L1), 16.0),
+            Reading::new(CurrentImport, Some(L2), 15.5),
+            Reading::new(CurrentImport, Some(L3), 0.25),
+            Reading::new(PowerActiveImport, Some(L1), 3688.0),
+            Reading::new(PowerActiveImport, Some(L2), 3557.0),
+            Reading::new(PowerActiveImport, Some(L3), 57.5),
+            Reading::new(PowerActiveImport, None, 7302.5),
+            Reading::new(PowerFactor, None, 0.75),
+            Reading::new(Frequency, None, 50.0),
+            Reading::new(EnergyActiveImportRegister, None, 1_234_500.0),
+        ]
+    }
+
+    fn sorted(mut readings: Vec<Reading>) -> Vec<Reading> {
+        readings.sort_by_key(|reading| format!("{:?}{:?}", reading.measurand, reading.phase));
+        readings
+    }
+
+    #[test]
+    fn given_simulated_models__when_reading_over_tcp__then_measurands_per_phase() {
+        for model in [&EASTRON_SDM630, &ABB_B23, &CARLO_GAVAZZI_EM340] {
+            let bank = Arc::new(Mutex::new(model.simulate(&readings())));
+            let server = ModbusTcpServer::bind("127.0.0.1:0", bank).unwrap();
+            let client =
+                ModbusTcpClient::connect(server.local_addr(), Duration::from_secs(1)).unwrap();
+            let mut meter = ModbusMeter::new(client, 1, model);
+
+            let read = meter.read().unwrap();
+
+            assert_eq!(sorted(read), sorted(readings()), "{}", model.name);
+        }
+    }
+
+    #[test]
+    fn given_value_kinds__when_encoding__then_word_order_and_sign_kept() {
+        let energy = register(EnergyActiveImportRegister, None, 0, ValueKind::U64, 10.0);
+        assert_eq!(
+            energy.encode(
+                10.0 * 0x0001_0002_0003_0004_u64 as f64,
+                WordOrder::HighFirst
+            ),
+            vec![1, 2, 3, 4]
+        );
+        let power = register(PowerActiveImport, None, 0, ValueKind::I32, 0.1);
+        let registers = power.encode(-1.5, WordOrder::LowFirst);
+        assert_eq!(registers, vec![0xFFF1, 0xFFFF]);
+        assert_eq!(power.decode(&registers, WordOrder::LowFirst), -1.5);
+    }
+
+    #[test]
+    fn given_registers_missing__when_reading__then_invalid_response_of_device() {
+        let bank = Arc::new(Mutex::new(RegisterBank::new()));
+        let server = ModbusTcpServer::bind("127.0.0.1:0", bank).unwrap();
+        let client = ModbusTcpClient::connect(server.local_addr(), Duration::from_secs(1)).unwrap();
+        let mut meter = ModbusMeter::new(client, 7, &ABB_B23);
+
+        assert!(matches!(
+            meter.read(),
+            Err(HalError::InvalidResponse(reason)) if reason.starts_with("ABB B23 at unit 7")
+        ));
+    }
+}
@@ -0,0 +1,519 @@
+///// OCMF signed meter values for calibration law, i.e. the German Eichrecht /////
+use std::fmt;
+
+use ocpp::data_transfer::VendorCall;
+use ocpp::datetime::DateTime;
+use ocpp::types::{
+    IdToken, Measurand, MeterValue, ReadingContext, SampledValue, SampledValueData, ValueFormat,
+};
+use p256::ecdsa::signature::{Signer, Verifier};
+use p256::ecdsa::{Signature, SigningKey, VerifyingKey};
+
+use crate::hal::{EnergyMeter, HalError, Reading, SignedMeter};
+
+pub const SIGNATURE_ALGORITHM: &str = "ECDSA-secp256r1-SHA256";
+/// OBIS code of the imported active energy
+const ENERGY_IMPORT: &str = "1-b:1.8.0";
+/// DER prefix of a P-256 SubjectPublicKeyInfo, the uncompressed point follows
+const SPKI_PREFIX: &str = "3059301306072a8648ce3d020106082a8648ce3d030107034200";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OcmfError {
+    /// Not `OCMF|<payload>|<signature>`, or a section does not parse
+    Format(String),
+    /// The public key is not a hex encoded P-256 key
+    PublicKey(String),
+    /// The signature does not match the payload
+    Signature,
+    /// The readings do not make up one transaction
+    Transaction(String),
+}
+
+impl fmt::Display for OcmfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OcmfError::Format(reason) => write!(f, "invalid OCMF: {}", reason),
+            OcmfError::PublicKey(reason) => write!(f, "invalid public key: {}", reason),
+            OcmfError::Signature => write!(f, "signature does not match"),
+            OcmfError::Transaction(reason) => write!(f, "invalid transaction: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for OcmfError {}
+
+/// The signed section of an OCMF record
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Payload {
+    #[serde(rename = "FV")]
+    pub format_version: String,
+    #[serde(rename = "GI")]
+    pub gateway_id: String,
+    #[serde(rename = "GS")]
+    pub gateway_serial: String,
+    #[serde(rename = "GV")]
+    pub gateway_version: String,
+    /// `T<n>`, counting the transaction readings of the meter
+    #[serde(rename = "PG")]
+    pub pagination: String,
+    #[serde(rename = "MV")]
+    pub meter_vendor: String,
+    #[serde(rename = "MM")]
+    pub meter_model: String,
+    #[serde(rename = "MS")]
+    pub meter_serial: String,
+    /// Whether the user was identified
+    #[serde(rename = "IS")]
+    pub identified: bool,
+    #[serde(rename = "IT")]
+    pub identification_type: String,
+    #[serde(rename = "ID")]
+    pub identification: String,
+    #[serde(rename = "CI")]
+    pub connector_id: String,
+    #[serde(rename = "RD")]
+    pub readings: Vec<OcmfReading>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct OcmfReading {
+    /// i.e. `2025-06-01T12:00:00,000+0000 S`, the suffix tells the clock is synchronized
+    #[serde(rename = "TM")]
+    pub time: String,
+    /// `B` at the begin of a transaction, `E` at its end, `T` in between
+    #[serde(rename = "TX")]
+    pub transaction: String,
+    #[serde(rename = "RV")]
+    pub value: f64,
+    /// OBIS code of the register
+    #[serde(rename = "RI")]
+    pub register: String,
+    #[serde(rename = "RU")]
+    pub unit: String,
+    /// `G` for a good reading
+    #[serde(rename = "ST")]
+    pub status: String,
+}
+
+impl OcmfReading {
+    pub fn energy_wh(&self) -> f64 {
+        match self.unit.as_str() {
+            "kWh" => self.value * 1000.0,
+            _ => self.value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+struct SignatureSection {
+    #[serde(rename = "SA")]
+    algorithm: String,
+    /// Hex encoded DER signature
+    #[serde(rename = "SD")]
+    data: String,
+}
+
+/// Identifies the gateway, i.e. the wallbox, and the meter in the signed payload
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcmfIdentity {
+    pub gateway_id: String,
+    pub gateway_serial: String,
+    pub gateway_version: String,
+    pub meter_vendor: String,
+    pub meter_model: String,
+    pub meter_serial: String,
+}
+
+/// Signs the readings of a meter in software.
+///
+/// Only a calibrated meter signing by itself satisfies the calibration law, this one serves
+/// meters without signature and tests.
+///
+/// The `PG` pagination of the records must rise across restarts, the caller persists
+/// [`pagination`](Self::pagination) and passes it to [`new`](Self::new) again.
+pub struct OcmfSigner<M> {
+    meter: M,
+    key: SigningKey,
+    identity: OcmfIdentity,
+    connector_id: u32,
+    pagination: u32,
+}
+
+impl<M: EnergyMeter> OcmfSigner<M> {
+    /// `secret_key` is the 32 byte P-256 scalar, `pagination` the last `PG` counter issued
+    /// before, 0 for a new meter
+    pub fn new(
+        meter: M,
+        secret_key: &[u8],
+        identity: OcmfIdentity,
+        connector_id: u32,
+        pagination: u32,
+    ) -> Result<Self, OcmfError> {
+        let key = SigningKey::from_slice(secret_key)
+            .map_err(|err| OcmfError::PublicKey(err.to_string()))?;
+        Ok(Self {
+            meter,
+            key,
+            identity,
+            connector_id,
+            pagination,
+        })
+    }
+
+    /// The last `PG` counter issued
+    pub fn pagination(&self) -> u32 {
+        self.pagination
+    }
+}
+
+impl<M: EnergyMeter> EnergyMeter for OcmfSigner<M> {
+    fn read(&mut self) -> Result<Vec<Reading>, HalError> {
+        self.meter.read()
+    }
+}
+
+impl<M: EnergyMeter> SignedMeter for OcmfSigner<M> {
+    fn signed_reading(
+        &mut self,
+        context: ReadingContext,
+        id_tag: Option<&IdToken>,
+        timestamp: DateTime,
+    ) -> Result<String, HalError> {
+        let energy_wh = self
+            .meter
+            .read_one(Measurand::EnergyActiveImportRegister, None)?
+            .ok_or_else(|| {
+                HalError::InvalidResponse("meter has no Energy.Active.Import.Register".to_string())
+            })?;
+        let transaction = match context {
+            ReadingContext::TransactionBegin => "B",
+            ReadingContext::TransactionEnd => "E",
+            _ => "T",
+        };
+        self.pagination += 1;
+        let identity = &self.identity;
+        let payload = Payload {
+            format_version: "1.0".to_string(),
+            gateway_id: identity.gateway_id.clone(),
+            gateway_serial: identity.gateway_serial.clone(),
+            gateway_version: identity.gateway_version.clone(),
+            pagination: format!("T{}", self.pagination),
+            meter_vendor: identity.meter_vendor.clone(),
+            meter_model: identity.meter_model.clone(),
+            meter_serial: identity.meter_serial.clone(),
+            identified: id_tag.is_some(),
+            identification_type: if id_tag.is_some() { "ISO14443" } else { "NONE" }.to_string(),
+            identification: id_tag.map(|id_tag| id_tag.to_string()).unwrap_or_default(),
+            connector_id: self.connector_id.to_string(),
+            readings: vec![OcmfReading {
+                time: timestamp.format("%Y-%m-%dT%H:%M:%S,%3f%z S").to_string(),
+                transaction: transaction.to_string(),
+                value: energy_wh / 1000.0,
+                register: ENERGY_IMPORT.to_string(),
+                unit: "kWh".to_string(),
+                status: "G".to_string(),
+            }],
+        };
+        let payload = serde_json::to_string(&payload)
+            .map_err(|err| HalError::InvalidResponse(err.to_string()))?;
+        let signature: Signature = self.key.sign(payload.as_bytes());
+        let signature = serde_json::json!({
+            "SA": SIGNATURE_ALGORITHM,
+            "SD": to_hex(signature.to_der().as_bytes()),
+        });
+        Ok(format!("OCMF|{}|{}", payload, signature))
+    }
+
+    fn public_key(&self) -> String {
+        public_key_hex(self.key.verifying_key())
+    }
+}
+
+/// Hex encoded DER SubjectPublicKeyInfo, the form transparency software expects
+pub fn public_key_hex(key: &VerifyingKey) -> String {
+    format!(
+        "{}{}",
+        SPKI_PREFIX,
+        to_hex(key.to_encoded_point(false).as_bytes())
+    )
+}
+
+/// Signed meter value of the energy register, to send with StartTransaction or as
+/// `transactionData` of StopTransaction
+pub fn signed_meter_value(
+    meter: &mut impl SignedMeter,
+    context: ReadingContext,
+    id_tag: Option<&IdToken>,
+    timestamp: DateTime,
+) -> Result<MeterValue, HalError> {
+    let ocmf = meter.signed_reading(context, id_tag, timestamp)?;
+    Ok(MeterValue {
+        timestamp,
+        sampled_value: vec![SampledValue {
+            value: SampledValueData::Signed(ocmf),
+            context: Some(context),
+            format: Some(ValueFormat::SignedData),
+            measurand: Some(Measurand::EnergyActiveImportRegister),
+            phase: None,
+            unit: None,
+        }],
+    })
+}
+
+/// Checks the signature of an OCMF record with the hex encoded `public_key`, DER or SEC1
+pub fn verify(ocmf: &str, public_key: &str) -> Result<Payload, OcmfError> {
+    let (payload_text, signature) = ocmf
+        .strip_prefix("OCMF|")
+        .and_then(|sections| sections.rsplit_once('|'))
+        .ok_or_else(|| OcmfError::Format("expected OCMF|<payload>|<signature>".to_string()))?;
+    let payload = serde_json::from_str::<Payload>(payload_text)
+        .map_err(|err| OcmfError::Format(format!("payload: {}", err)))?;
+    let signature = serde_json::from_str::<SignatureSection>(signature)
+        .map_err(|err| OcmfError::Format(format!("signature: {}", err)))?;
+    if signature.algorithm != SIGNATURE_ALGORITHM {
+        return Err(OcmfError::Format(format!(
+            "unsupported algorithm {}",
+            signature.algorithm
+        )));
+    }
+
+    let key = from_hex(public_key).ok_or_else(|| OcmfError::PublicKey("not hex".to_string()))?;
+    let point = key
+        .strip_prefix(&from_hex(SPKI_PREFIX).unwrap_or_default()[..])
+        .unwrap_or(&key);
+    let key = VerifyingKey::from_sec1_bytes(point)
+        .map_err(|err| OcmfError::PublicKey(err.to_string()))?;
+    let signature = from_hex(&signature.data)
+        .and_then(|der| Signature::from_der(&der).ok())
+        .ok_or_else(|| OcmfError::Format("signature is no hex DER".to_string()))?;
+    key.verify(payload_text.as_bytes(), &signature)
+        .map_err(|_| OcmfError::Signature)?;
+    Ok(payload)
+}
+
+/// Checks the begin and end record of a transaction, returns the energy charged in Wh
+pub fn verify_transaction(begin: &str, end: &str, public_key: &str) -> Result<f64, OcmfError> {
+    let begin = verify(begin, public_key)?;
+    let end = verify(end, public_key)?;
+    let (Some(first), Some(last)) = (begin.readings.first(), end.readings.last()) else {
+        return Err(OcmfError::Transaction("record without reading".to_string()));
+    };
+    if first.transaction != "B" || last.transaction != "E" {
+        return Err(OcmfError::Transaction(format!(
+            "expected readings B and E, got {} and {}",
+            first.transaction, last.transaction
+        )));
+    }
+    if begin.meter_serial != end.meter_serial || begin.identification != end.identification {
+        return Err(OcmfError::Transaction(
+            "begin and end differ in meter or identification".to_string(),
+        ));
+    }
+    let energy_wh = last.energy_wh() - first.energy_wh();
+    if energy_wh < 0.0 {
+        return Err(OcmfError::Transaction("meter ran backwards".to_string()));
+    }
+    Ok(energy_wh)
+}
+
+/// Every OCMF record anywhere in a JSON document, i.e. a recorded OCPP frame
+pub fn find_records(value: &serde_json::Value) -> Vec<&str> {
+    match value {
+        serde_json::Value::String(text) if text.starts_with("OCMF|") => vec![text],
+        serde_json::Value::Array(values) => values.iter().flat_map(find_records).collect(),
+        serde_json::Value::Object(fields) => fields.values().flat_map(find_records).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Meter public key announced to the CSMS, as of the OCA application note on signed meter
+/// values in 1.6
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeterConfiguration {
+    pub connector_id: u32,
+    pub meter_serial: String,
+    /// Always `SIGNATURE`
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub public_key: String,
+}
+
+impl MeterConfiguration {
+    pub fn new(connector_id: u32, meter: &impl SignedMeter, meter_serial: &str) -> Self {
+        Self {
+            connector_id,
+            meter_serial: meter_serial.to_string(),
+            kind: "SIGNATURE".to_string(),
+            public_key: meter.public_key(),
+        }
+    }
+
+    /// Configuration key reporting the key through GetConfiguration, i.e. `MeterPublicKey1`
+    pub fn configuration_key(&self) -> String {
+        format!("MeterPublicKey{}", self.connector_id)
+    }
+}
+
+/// DataTransfer announcing the meter public keys
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SetMeterConfiguration {
+    pub meters: Vec<MeterConfiguration>,
+}
+
+impl VendorCall for SetMeterConfiguration {
+    const VENDOR_ID: &'static str = "generalConfiguration";
+    const MESSAGE_ID: Option<&'static str> = Some("SetMeterConfiguration");
+    type Response = ();
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::SimulatedMeter;
+
+    const SECRET_KEY: [u8; 32] = [7; 32];
+
+    fn identity() -> OcmfIdentity {
+        OcmfIdentity {
+            gateway_id: "WALLBOX-1".to_string(),
+            gateway_serial: "WB0001".to_string(),
+            gateway_version: "0.1.0".to_string(),
+            meter_vendor: "ABB".to_string(),
+            meter_model: "B23".to_string(),
+            meter_serial: "4711".to_string(),
+        }
+    }
+
+    fn signer(meter: &SimulatedMeter) -> OcmfSigner<SimulatedMeter> {
+        OcmfSigner::new(meter.clone(), &SECRET_KEY, identity(), 1, 0).unwrap()
+    }
+
+    fn t0() -> DateTime {
+        ocpp::datetime::parse("2025-06-01T12:00:00Z").unwrap()
+    }
+
+    #[test]
+    fn given_transaction__when_signing_begin_and_end__then_verified_with_energy() {
+        let meter = SimulatedMeter::new();
+        meter.set_energy(1000.0);
+        let mut signer = signer(&meter);
+        let id_tag = IdToken::new("04A2B3C4").unwrap();
+
+        let begin = signed_meter_value(
+            &mut signer,
+            ReadingContext::TransactionBegin,
+            Some(&id_tag),
+            t0(),
+        )
+        .unwrap();
+        meter.set_energy(12_500.0);
+        let end = signed_meter_value(
+            &mut signer,
+            ReadingContext::TransactionEnd,
+            Some(&id_tag),
+            t0(),
+        )
+        .unwrap();
+
+        let record = |meter_value: &MeterValue| match &meter_value.sampled_value[0].value {
+            SampledValueData::Signed(ocmf) => ocmf.clone(),
+            value => panic!("unsigned {:?}", value),
+        };
+        let begin = record(&begin);
+        let payload = verify(&begin, &signer.public_key()).unwrap();
+        assert_eq!(payload.pagination, "T1");
+        assert_eq!(payload.readings[0].time, "2025-06-01T12:00:00,000+0000 S");
+        assert_eq!(
+            verify_transaction(&begin, &record(&end), &signer.public_key()),
+            Ok(11_500.0)
+        );
+    }
+
+    #[test]
+    fn given_pagination_of_before_a_restart__when_signing__then_continued() {
+        let meter = SimulatedMeter::new();
+        let mut signer = OcmfSigner::new(meter.clone(), &SECRET_KEY, identity(), 1, 41).unwrap();
+
+        let ocmf = signer
+            .signed_reading(ReadingContext::SamplePeriodic, None, t0())
+            .unwrap();
+
+        let payload = verify(&ocmf, &signer.public_key()).unwrap();
+        assert_eq!(payload.pagination, "T42");
+        assert_eq!(signer.pagination(), 42);
+    }
+
+    #[test]
+    fn given_tampered_record_or_other_key__when_verifying__then_rejected() {
+        let mut signer = signer(&SimulatedMeter::new());
+        let ocmf = signer
+            .signed_reading(ReadingContext::SamplePeriodic, None, t0())
+            .unwrap();
+        let other_key = public_key_hex(SigningKey::from_slice(&[9; 32]).unwrap().verifying_key());
+
+        let tampered = ocmf.replace("\"RV\":0.0", "\"RV\":1.0");
+
+        assert_ne!(tampered, ocmf);
+        assert_eq!(
+            verify(&tampered, &signer.public_key()),
+            Err(OcmfError::Signature)
+        );
+        assert_eq!(verify(&ocmf, &other_key), Err(OcmfError::Signature));
+        assert!(matches!(
+            verify("OCMF|{}", &signer.public_key()),
+            Err(OcmfError::Format(_))
+        ));
+    }
+
+    #[test]
+    fn given_meter_configuration__when_announcing__then_data_transfer_and_configuration_key() {
+        let signer = signer(&SimulatedMeter::new());
+        let configuration = MeterConfiguration::new(1, &signer, "4711");
+
+        let request = SetMeterConfiguration {
+            meters: vec![configuration.clone()],
+        }
+        .to_request()
+        .unwrap();
+
+        assert_eq!(configuration.configuration_key(), "MeterPublicKey1");
+        assert_eq!(request.vendor_id.as_str(), "generalConfiguration");
+        let data: serde_json::Value =
+            serde_json::from_str(request.data.as_deref().unwrap()).unwrap();
+        assert_eq!(data["meters"][0]["type"], "SIGNATURE");
+        assert!(
+            data["meters"][0]["publicKey"]
+                .as_str()
+                .unwrap()
+                .starts_with(SPKI_PREFIX)
+        );
+    }
+
+    #[test]
+    fn given_recorded_frame__when_searching__then_records_found() {
+        let frame = serde_json::json!([2, "1", "StopTransaction", {
+            "transactionData": [{"sampledValue": [
+                {"value": "OCMF|{}|{}", "format": "SignedData"},
+                {"value": "1234"}
+            ]}]
+        }]);
+
+        assert_eq!(find_records(&frame), vec!["OCMF|{}|{}"]);
+    }
+}
@@ -0,0 +1,417 @@
+///// Switching between one and three phases, i.e. to charge from little solar surplus /////
+use std::time::{Duration, Instant};
+
+use ocpp::datetime::DateTime;
+use ocpp::ocpp_event::GetCompositeScheduleResponse;
+use ocpp::types::GetCompositeScheduleStatus;
+
+use crate::hal::{HalError, PhaseSwitch};
+use crate::iec61851::MIN_CURRENT;
+use crate::smart_charging::{
+    self, ActiveTransaction, ConnectorLimits, InstalledProfile, LimitSource, NOMINAL_VOLTAGE,
+    ScheduleLimit,
+};
+
+/// Switches for the available power are at least that far apart, vehicles and contactors
+/// should not cycle with every cloud
+pub const DEFAULT_MIN_INTERVAL: Duration = Duration::from_secs(300);
+/// The supply stays off that long before the phases switch
+pub const DEFAULT_SWITCH_DELAY: Duration = Duration::from_secs(2);
+/// And that long after, before current is offered again
+pub const DEFAULT_RESUME_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Stage {
+    Idle,
+    /// Waiting for the vehicle to stop and the contactor to open
+    Pausing,
+    /// The supply went off at
+    Off(Instant),
+    /// The phases switched at
+    Switched(Instant),
+}
+
+/// Connects three phases when the available power covers [`MIN_CURRENT`] on all of them, a
+/// single one otherwise.
+///
+/// The phases only switch with the supply off: the connector is held at 0 A as
+/// [`LimitSource::PhaseSwitching`], see [`apply`](Self::apply), until the contactor opens, then the phases switch after the switch
+/// delay and current is offered again after the resume delay. `numberPhases` and
+/// `phaseToUse` of the composite schedule apply at once, switches for the power only after
+/// the minimum interval.
+#[derive(Debug, Clone)]
+pub struct PhaseSwitcher<S> {
+    switch: S,
+    number_phases: u8,
+    phase_to_use: Option<u8>,
+    target: (u8, Option<u8>),
+    stage: Stage,
+    /// `numberPhases` and `phaseToUse` of the composite schedule
+    schedule: (u8, Option<u8>),
+    min_interval: Duration,
+    switch_delay: Duration,
+    resume_delay: Duration,
+    last_switch: Option<Instant>,
+}
+
+impl<S: PhaseSwitch> PhaseSwitcher<S> {
+    /// `switch` has three phases connected
+    pub fn new(switch: S) -> Self {
+        Self {
+            switch,
+            number_phases: 3,
+            phase_to_use: None,
+            target: (3, None),
+            stage: Stage::Idle,
+            schedule: (3, None),
+            min_interval: DEFAULT_MIN_INTERVAL,
+            switch_delay: DEFAULT_SWITCH_DELAY,
+            resume_delay: DEFAULT_RESUME_DELAY,
+            last_switch: None,
+        }
+    }
+
+    pub fn min_interval(mut self, interval: Duration) -> Self {
+        self.min_interval = interval;
+        self
+    }
+
+    pub fn switch_delay(mut self, delay: Duration) -> Self {
+        self.switch_delay = delay;
+        self
+    }
+
+    pub fn resume_delay(mut self, delay: Duration) -> Self {
+        self.resume_delay = delay;
+        self
+    }
+
+    pub fn number_phases(&self) -> u8 {
+        self.number_phases
+    }
+
+    pub fn phase_to_use(&self) -> Option<u8> {
+        self.phase_to_use
+    }
+
+    /// Phases L1 to L3 connected now
+    pub fn connected(&self) -> [bool; 3] {
+        match self.number_phases {
+            1 => {
+                let phase = self.phase_to_use.unwrap_or(1) as usize;
+                [phase == 1, phase == 2, phase == 3]
+            }
+            _ => [true; 3],
+        }
+    }
+
+    pub fn is_switching(&self) -> bool {
+        self.stage != Stage::Idle
+    }
+
+    /// 0 A while switching, to be combined with the other limits of the connector
+    pub fn limit(&self) -> Option<f64> {
+        self.is_switching().then_some(0.0)
+    }
+
+    /// Sets [`LimitSource::PhaseSwitching`] of the connector to [`limit`](Self::limit), after
+    /// every [`update`](Self::update)
+    pub fn apply(&self, limits: &mut ConnectorLimits) {
+        limits.set(LimitSource::PhaseSwitching, self.limit());
+    }
+
+    /// Follows `numberPhases` and `phaseToUse` of the composite schedule, `None` without one
+    pub fn set_schedule_limit(&mut self, limit: Option<&ScheduleLimit>) {
+        self.schedule = match limit {
+            Some(limit) => (limit.number_phases.clamp(1, 3), limit.phase_to_use),
+            None => (3, None),
+        };
+    }
+
+    /// Phases `available_power` in W suffices for
+    pub fn preferred_phases(available_power: f64) -> u8 {
+        if available_power >= MIN_CURRENT * NOMINAL_VOLTAGE * 3.0 {
+            3
+        } else {
+            1
+        }
+    }
+
+    /// Decides on the phases for `available_power` in W and advances a running switch.
+    /// `supply_on` tells whether the contactor of the connector is closed.
+    pub fn update(
+        &mut self,
+        available_power: f64,
+        supply_on: bool,
+        now: Instant,
+    ) -> Result<(), HalError> {
+        if let Stage::Switched(since) = self.stage
+            && now.duration_since(since) >= self.resume_delay
+        {
+            self.stage = Stage::Idle;
+        }
+        if self.stage == Stage::Idle {
+            let (max_phases, scheduled_phase) = self.schedule;
+            let number_phases = Self::preferred_phases(available_power).min(max_phases);
+            let target = match number_phases {
+                1 => (1, scheduled_phase),
+                _ => (3, None),
+            };
+            let current = (self.number_phases, self.phase_to_use);
+            let forced = self.number_phases > max_phases
+                || (self.number_phases == 1 && self.phase_to_use != scheduled_phase);
+            let waiting = self
+                .last_switch
+                .is_some_and(|last| now.duration_since(last) < self.min_interval);
+            if target == current || (waiting && !forced) {
+                return Ok(());
+            }
+            self.target = target;
+            self.stage = Stage::Pausing;
+        }
+
+        if self.stage == Stage::Pausing && !supply_on {
+            self.stage = Stage::Off(now);
+        }
+        if matches!(self.stage, Stage::Off(_)) && supply_on {
+            self.stage = Stage::Pausing;
+        }
+        if let Stage::Off(since) = self.stage
+            && now.duration_since(since) >= self.switch_delay
+        {
+            let (number_phases, phase_to_use) = self.target;
+            self.switch.set_phases(number_phases, phase_to_use)?;
+            self.number_phases = number_phases;
+            self.phase_to_use = phase_to_use;
+            self.last_switch = Some(now);
+            self.stage = Stage::Switched(now);
+        }
+        Ok(())
+    }
+
+    /// GetCompositeSchedule response of `connector_id` with the composite schedule of
+    /// `profiles`, see [`smart_charging::composite_schedule`], `numberPhases` reporting the
+    /// phases connected where the schedule allows more
+    pub fn composite_schedule(
+        &self,
+        profiles: &[InstalledProfile],
+        connector_id: u32,
+        transaction: Option<ActiveTransaction>,
+        from: DateTime,
+        duration: u32,
+        max_current: f64,
+    ) -> GetCompositeScheduleResponse {
+        let mut schedule = smart_charging::composite_schedule(
+            profiles,
+            connector_id,
+            transaction,
+            from,
+            duration,
+            max_current,
+        );
+        for period in &mut schedule.charging_schedule_period {
+            let number_phases = period.number_phases.unwrap_or(3).min(self.number_phases);
+            period.number_phases = Some(number_phases);
+        }
+        schedule.charging_schedule_period.dedup_by(|next, period| {
+            (&next.limit, next.number_phases) == (&period.limit, period.number_phases)
+        });
+        GetCompositeScheduleResponse {
+            status: GetCompositeScheduleStatus::Accepted,
+            connector_id: Some(connector_id),
+            schedule_start: Some(from),
+            charging_schedule: Some(schedule),
+        }
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::Contactor;
+    use crate::sampler::MeterSampler;
+    use crate::sim::{SimulatedContactor, SimulatedMeter, SimulatedPhaseSwitch};
+    use ocpp::types::{
+        ChargingProfile, ChargingProfileKind, ChargingProfilePurpose, ChargingRateUnit,
+        ChargingSchedule, ChargingSchedulePeriod, Measurand, Phase, ReadingContext,
+    };
+    use rust_decimal::Decimal;
+
+    fn setup() -> (
+        SimulatedContactor,
+        SimulatedPhaseSwitch,
+        PhaseSwitcher<SimulatedPhaseSwitch>,
+    ) {
+        let contactor = SimulatedContactor::new();
+        let switch = SimulatedPhaseSwitch::new(contactor.clone());
+        let switcher = PhaseSwitcher::new(switch.clone());
+        (contactor, switch, switcher)
+    }
+
+    #[test]
+    fn given_little_power__when_charging__then_paused_switched_and_resumed() {
+        let (mut contactor, switch, mut switcher) = setup();
+        let t0 = Instant::now();
+        let at = |millis: u64| t0 + Duration::from_millis(millis);
+        let mut limits = ConnectorLimits::new();
+        limits.set(LimitSource::SolarSurplus, Some(13.0));
+        contactor.set_closed(true).unwrap();
+
+        switcher.update(3000.0, true, t0).unwrap();
+        switcher.apply(&mut limits);
+        assert_eq!(switcher.limit(), Some(0.0));
+        assert_eq!(
+            limits.effective().unwrap().source,
+            LimitSource::PhaseSwitching
+        );
+        // the vehicle still draws, nothing switches
+        switcher.update(3000.0, true, at(5000)).unwrap();
+        assert_eq!(switch.switches(), 0);
+
+        contactor.set_closed(false).unwrap();
+        switcher.update(3000.0, false, at(6000)).unwrap();
+        switcher.update(3000.0, false, at(7000)).unwrap();
+        assert_eq!(switch.switches(), 0);
+        switcher.update(3000.0, false, at(8000)).unwrap();
+        assert_eq!(switch.connected(), [true, false, false]);
+        assert_eq!(switcher.limit(), Some(0.0));
+        switcher.update(3000.0, false, at(10_000)).unwrap();
+        switcher.apply(&mut limits);
+        assert_eq!(switcher.limit(), None);
+        assert_eq!(
+            limits.effective().unwrap().source,
+            LimitSource::SolarSurplus
+        );
+
+        // more power within the minimum interval keeps the single phase
+        switcher.update(6000.0, false, at(60_000)).unwrap();
+        assert!(!switcher.is_switching());
+        switcher.update(6000.0, false, at(310_000)).unwrap();
+        assert!(switcher.is_switching());
+    }
+
+    #[test]
+    fn given_schedule_with_phase_to_use__when_updating__then_switched_at_once() {
+        let (_contactor, switch, mut switcher) = setup();
+        let switcher_start = Instant::now();
+        switcher.update(3000.0, false, switcher_start).unwrap();
+        switcher
+            .update(3000.0, false, switcher_start + DEFAULT_SWITCH_DELAY)
+            .unwrap();
+        assert_eq!(switch.connected(), [true, false, false]);
+
+        switcher.set_schedule_limit(Some(&ScheduleLimit {
+            current: 16.0,
+            number_phases: 1,
+            phase_to_use: Some(2),
+        }));
+        let now = switcher_start + Duration::from_secs(10);
+        switcher.update(11_000.0, false, now).unwrap();
+        switcher
+            .update(11_000.0, false, now + DEFAULT_SWITCH_DELAY)
+            .unwrap();
+
+        assert_eq!(switch.connected(), [false, true, false]);
+        assert_eq!(switch.switches(), 2);
+    }
+
+    #[test]
+    fn given_supply_back_on__when_switch_delay_passed__then_not_switched() {
+        let (_contactor, switch, mut switcher) = setup();
+        let t0 = Instant::now();
+        switcher.update(3000.0, false, t0).unwrap();
+        assert!(switcher.is_switching());
+
+        // the vehicle resumes drawing before the delay passed
+        switcher
+            .update(3000.0, true, t0 + DEFAULT_SWITCH_DELAY)
+            .unwrap();
+        assert_eq!(switch.switches(), 0);
+        assert!(switcher.is_switching());
+
+        let off = t0 + DEFAULT_SWITCH_DELAY + Duration::from_secs(1);
+        switcher.update(3000.0, false, off).unwrap();
+        assert_eq!(switch.switches(), 0);
+        switcher
+            .update(3000.0, false, off + DEFAULT_SWITCH_DELAY)
+            .unwrap();
+        assert_eq!(switch.connected(), [true, false, false]);
+    }
+
+    #[test]
+    fn given_single_phase__when_reporting__then_number_phases_in_schedule_and_meter_values() {
+        let (_contactor, _switch, mut switcher) = setup();
+        let t0 = Instant::now();
+        switcher.update(2000.0, false, t0).unwrap();
+        switcher
+            .update(2000.0, false, t0 + DEFAULT_SWITCH_DELAY)
+            .unwrap();
+        let profiles = [InstalledProfile::new(
+            0,
+            ChargingProfile {
+                charging_profile_id: 1,
+                transaction_id: None,
+                stack_level: 0,
+                charging_profile_purpose: ChargingProfilePurpose::TxDefaultProfile,
+                charging_profile_kind: ChargingProfileKind::Relative,
+                recurrency_kind: None,
+                valid_from: None,
+                valid_to: None,
+                charging_schedule: ChargingSchedule {
+                    duration: None,
+                    start_schedule: None,
+                    charging_rate_unit: ChargingRateUnit::A,
+                    charging_schedule_period: vec![
+                        ChargingSchedulePeriod {
+                            start_period: 0,
+                            limit: Decimal::from(16),
+                            number_phases: None,
+                        },
+                        ChargingSchedulePeriod {
+                            start_period: 1800,
+                            limit: Decimal::from(10),
+                            number_phases: Some(1),
+                        },
+                    ],
+                    min_charging_rate: None,
+                },
+            },
+        )];
+        let start = ocpp::datetime::parse("2025-06-01T12:00:00Z").unwrap();
+        let meter = SimulatedMeter::new();
+        meter.set_currents([8.0, 0.0, 0.0]);
+        let mut sampler = MeterSampler::new(&[Measurand::CurrentImport]);
+        sampler.set_connected_phases(switcher.connected());
+
+        let transaction = Some(ActiveTransaction { id: 1, start });
+        let response = switcher.composite_schedule(&profiles, 1, transaction, start, 3600, 32.0);
+        let meter_value = sampler
+            .sample(&mut meter.clone(), start, ReadingContext::SamplePeriodic)
+            .unwrap();
+
+        // both periods report the single phase connected
+        let periods = response.charging_schedule.unwrap().charging_schedule_period;
+        assert_eq!(
+            periods,
+            vec![
+                ChargingSchedulePeriod {
+                    start_period: 0,
+                    limit: Decimal::from(16),
+                    number_phases: Some(1),
+                },
+                ChargingSchedulePeriod {
+                    start_period: 1800,
+                    limit: Decimal::from(10),
+                    number_phases: Some(1),
+                },
+            ]
+        );
+        let phases: Vec<Option<Phase>> = meter_value
+            .sampled_value
+            .iter()
+            .map(|value| value.phase)
+            .collect();
+        assert_eq!(phases, vec![Some(Phase::L1)]);
+    }
+}
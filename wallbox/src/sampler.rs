@@ -0,0 +1,179 @@
+///// Meter values of the configured measurands, read from an energy meter /////
+use ocpp::datetime::DateTime;
+use ocpp::types::{Measurand, MeterValue, Phase, ReadingContext, SampledValue};
+use rust_decimal::Decimal;
+
+use crate::hal::{EnergyMeter, HalError};
+
+/// Samples the measurands configured by `MeterValuesSampledData`, every phase the meter
+/// measures them on
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeterSampler {
+    measurands: Vec<Measurand>,
+    connected: [bool; 3],
+}
+
+impl Default for MeterSampler {
+    /// The default of `MeterValuesSampledData`
+    fn default() -> Self {
+        Self::new(&[Measurand::EnergyActiveImportRegister])
+    }
+}
+
+impl MeterSampler {
+    pub fn new(measurands: &[Measurand]) -> Self {
+        Self {
+            measurands: measurands.to_vec(),
+            connected: [true; 3],
+        }
+    }
+
+    /// Parses the comma separated value of the `MeterValuesSampledData` configuration key
+    pub fn from_configuration(value: &str) -> Result<Self, String> {
+        let measurands = value
+            .split(',')
+            .map(str::trim)
+            .filter(|measurand| !measurand.is_empty())
+            .map(|measurand| {
+                serde_json::from_value(serde_json::Value::String(measurand.to_string()))
+                    .map_err(|_| format!("unknown measurand {}", measurand))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::new(&measurands))
+    }
+
+    pub fn measurands(&self) -> &[Measurand] {
+        &self.measurands
+    }
+
+    /// Leaves out the phases L1 to L3 not connected, 1.6 knows no `numberPhases` in meter
+    /// values, the phases sampled tell it
+    pub fn set_connected_phases(&mut self, connected: [bool; 3]) {
+        self.connected = connected;
+    }
+
+    fn is_connected(&self, phase: Option<Phase>) -> bool {
+        let [l1, l2, l3] = self.connected;
+        match phase {
+            None | Some(Phase::N) => true,
+            Some(Phase::L1 | Phase::L1N) => l1,
+            Some(Phase::L2 | Phase::L2N) => l2,
+            Some(Phase::L3 | Phase::L3N) => l3,
+            Some(Phase::L1L2) => l1 && l2,
+            Some(Phase::L2L3) => l2 && l3,
+            Some(Phase::L3L1) => l3 && l1,
+        }
+    }
+
+    pub fn sample(
+        &self,
+        meter: &mut impl EnergyMeter,
+        timestamp: DateTime,
+        context: ReadingContext,
+    ) -> Result<MeterValue, HalError> {
+        let sampled_value = meter
+            .read()?
+            .into_iter()
+            .filter(|reading| self.measurands.contains(&reading.measurand))
+            .filter(|reading| self.is_connected(reading.phase))
+            .filter_map(|reading| {
+                Some(SampledValue {
+                    value: Decimal::try_from(reading.value).ok()?.into(),
+                    context: Some(context),
+                    format: None,
+                    measurand: Some(reading.measurand),
+                    phase: reading.phase,
+                    unit: unit(reading.measurand).map(str::to_string),
+                })
+            })
+            .collect();
+        Ok(MeterValue {
+            timestamp,
+            sampled_value,
+        })
+    }
+}
+
+/// Unit of the readings of `measurand`, `None` where OCPP 1.6 has none
+fn unit(measurand: Measurand) -> Option<&'static str> {
+    match measurand {
+        Measurand::EnergyActiveExportRegister
+        | Measurand::EnergyActiveImportRegister
+        | Measurand::EnergyActiveExportInterval
+        | Measurand::EnergyActiveImportInterval => Some("Wh"),
+        Measurand::PowerActiveExport | Measurand::PowerActiveImport | Measurand::PowerOffered => {
+            Some("W")
+        }
+        Measurand::CurrentExport
+        | Measurand::CurrentImport
+        | Measurand::CurrentOffered
+        | Measurand::CurrentAvailable => Some("A"),
+        Measurand::Voltage => Some("V"),
+        Measurand::SoC => Some("Percent"),
+        _ => None,
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::SimulatedMeter;
+
+    #[test]
+    fn given_configured_measurands__when_sampling__then_every_phase_with_unit() {
+        let mut meter = SimulatedMeter::new();
+        meter.set_currents([16.0, 16.0, 0.0]);
+        meter.set_energy(1234.5);
+        let sampler =
+            MeterSampler::from_configuration("Energy.Active.Import.Register,Current.Import")
+                .unwrap();
+        let timestamp = ocpp::datetime::parse("2025-06-01T12:00:00Z").unwrap();
+
+        let meter_value = sampler
+            .sample(&mut meter, timestamp, ReadingContext::SamplePeriodic)
+            .unwrap();
+
+        let values = meter_value
+            .sampled_value
+            .iter()
+            .map(|value| {
+                (
+                    value.measurand.unwrap(),
+                    value.phase,
+                    value.value.as_decimal().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec![
+                (
+                    Measurand::CurrentImport,
+                    Some(Phase::L1),
+                    Decimal::new(16, 0)
+                ),
+                (
+                    Measurand::CurrentImport,
+                    Some(Phase::L2),
+                    Decimal::new(16, 0)
+                ),
+                (
+                    Measurand::CurrentImport,
+                    Some(Phase::L3),
+                    Decimal::new(0, 0)
+                ),
+                (
+                    Measurand::EnergyActiveImportRegister,
+                    None,
+                    Decimal::new(12345, 1)
+                ),
+            ]
+        );
+        assert_eq!(meter_value.sampled_value[3].unit.as_deref(), Some("Wh"));
+        assert_eq!(
+            MeterSampler::from_configuration("Energy.Active.Import.Register,Vibration"),
+            Err("unknown measurand Vibration".to_string())
+        );
+    }
+}
@@ -0,0 +1,542 @@
+///// Simulated hardware, for tests and for running the stack on a desktop /////
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use ocpp::types::{IdToken, Measurand, Phase};
+
+use crate::connector::ConnectorHardware;
+use crate::hal::{
+    ConnectorLock, Contactor, EnergyMeter, HalError, IdTokenReader, PhaseSwitch, PilotInterface,
+    Reading, SiteMeter,
+};
+use crate::iec61851::{self, CpState, NO_PWM, PilotSample};
+
+const PHASES: [Phase; 3] = [Phase::L1, Phase::L2, Phase::L3];
+const PHASE_VOLTAGES: [Phase; 3] = [Phase::L1N, Phase::L2N, Phase::L3N];
+
+/// Three phase meter integrating the energy of the currents set, see [`advance`](Self::advance).
+///
+/// Clones share the meter, so a test keeps one to steer the copy handed to the stack.
+#[derive(Debug, Clone)]
+pub struct SimulatedMeter {
+    state: Arc<Mutex<MeterState>>,
+}
+
+#[derive(Debug)]
+struct MeterState {
+    voltages: [f64; 3],
+    currents: [f64; 3],
+    power_factor: f64,
+    frequency: f64,
+    energy_wh: f64,
+    responding: bool,
+}
+
+impl Default for SimulatedMeter {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(MeterState {
+                voltages: [230.0; 3],
+                currents: [0.0; 3],
+                power_factor: 1.0,
+                frequency: 50.0,
+                energy_wh: 0.0,
+                responding: true,
+            })),
+        }
+    }
+}
+
+impl SimulatedMeter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_currents(&self, currents: [f64; 3]) {
+        lock(&self.state).currents = currents;
+    }
+
+    pub fn set_voltages(&self, voltages: [f64; 3]) {
+        lock(&self.state).voltages = voltages;
+    }
+
+    pub fn set_energy(&self, energy_wh: f64) {
+        lock(&self.state).energy_wh = energy_wh;
+    }
+
+    /// A meter which does not respond fails every read
+    pub fn set_responding(&self, responding: bool) {
+        lock(&self.state).responding = responding;
+    }
+
+    /// Active power over all phases in W
+    pub fn power(&self) -> f64 {
+        lock(&self.state).power()
+    }
+
+    /// Integrates the present power over `elapsed`
+    pub fn advance(&self, elapsed: Duration) {
+        let mut state = lock(&self.state);
+        state.energy_wh += state.power() * elapsed.as_secs_f64() / 3600.0;
+    }
+}
+
+impl MeterState {
+    fn phase_power(&self, phase: usize) -> f64 {
+        self.voltages[phase] * self.currents[phase] * self.power_factor
+    }
+
+    fn power(&self) -> f64 {
+        (0..3).map(|phase| self.phase_power(phase)).sum()
+    }
+}
+
+impl EnergyMeter for SimulatedMeter {
+    fn read(&mut self) -> Result<Vec<Reading>, HalError> {
+        let state = lock(&self.state);
+        if !state.responding {
+            return Err(HalError::NotResponding("simulated meter".to_string()));
+        }
+        let mut readings = Vec::new();
+        for index in 0..3 {
+            readings.extend([
+                Reading::new(
+                    Measurand::Voltage,
+                    Some(PHASE_VOLTAGES[index]),
+                    state.voltages[index],
+                ),
+                Reading::new(
+                    Measurand::CurrentImport,
+                    Some(PHASES[index]),
+                    state.currents[index],
+                ),
+                Reading::new(
+                    Measurand::PowerActiveImport,
+                    Some(PHASES[index]),
+                    state.phase_power(index),
+                ),
+            ]);
+        }
+        readings.extend([
+            Reading::new(Measurand::PowerActiveImport, None, state.power()),
+            Reading::new(Measurand::EnergyActiveImportRegister, None, state.energy_wh),
+            Reading::new(Measurand::Frequency, None, state.frequency),
+            Reading::new(Measurand::PowerFactor, None, state.power_factor),
+        ]);
+        Ok(readings)
+    }
+}
+
+/// Contactor whose contacts may be welded, then they stay closed whatever is commanded
+#[derive(Debug, Clone, Default)]
+pub struct SimulatedContactor {
+    state: Arc<Mutex<ContactorState>>,
+}
+
+#[derive(Debug, Default)]
+struct ContactorState {
+    commanded: bool,
+    welded: bool,
+}
+
+impl SimulatedContactor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Welds the contacts closed
+    pub fn weld(&self) {
+        lock(&self.state).welded = true;
+    }
+
+    pub fn commanded(&self) -> bool {
+        lock(&self.state).commanded
+    }
+}
+
+impl Contactor for SimulatedContactor {
+    fn set_closed(&mut self, closed: bool) -> Result<(), HalError> {
+        lock(&self.state).commanded = closed;
+        Ok(())
+    }
+
+    fn is_closed(&mut self) -> Result<bool, HalError> {
+        let state = lock(&self.state);
+        Ok(state.commanded || state.welded)
+    }
+
+    fn is_welded(&mut self) -> Result<bool, HalError> {
+        let state = lock(&self.state);
+        Ok(state.welded && !state.commanded)
+    }
+}
+
+/// Phase switch refusing to switch while the supply contactor it is wired behind is closed
+#[derive(Debug, Clone)]
+pub struct SimulatedPhaseSwitch {
+    contactor: SimulatedContactor,
+    state: Arc<Mutex<PhaseSwitchState>>,
+}
+
+#[derive(Debug)]
+struct PhaseSwitchState {
+    number_phases: u8,
+    phase_to_use: Option<u8>,
+    switches: u32,
+}
+
+impl SimulatedPhaseSwitch {
+    /// Starts with three phases
+    pub fn new(contactor: SimulatedContactor) -> Self {
+        Self {
+            contactor,
+            state: Arc::new(Mutex::new(PhaseSwitchState {
+                number_phases: 3,
+                phase_to_use: None,
+                switches: 0,
+            })),
+        }
+    }
+
+    /// Phases L1 to L3 connected
+    pub fn connected(&self) -> [bool; 3] {
+        let state = lock(&self.state);
+        match state.number_phases {
+            1 => {
+                let phase = state.phase_to_use.unwrap_or(1) as usize;
+                [phase == 1, phase == 2, phase == 3]
+            }
+            _ => [true; 3],
+        }
+    }
+
+    /// How often the phases were switched
+    pub fn switches(&self) -> u32 {
+        lock(&self.state).switches
+    }
+}
+
+impl PhaseSwitch for SimulatedPhaseSwitch {
+    fn set_phases(&mut self, number_phases: u8, phase_to_use: Option<u8>) -> Result<(), HalError> {
+        if self.contactor.clone().is_closed()? {
+            return Err(HalError::Fault("phases switched under load".to_string()));
+        }
+        let mut state = lock(&self.state);
+        if (state.number_phases, state.phase_to_use) != (number_phases, phase_to_use) {
+            state.switches += 1;
+        }
+        state.number_phases = number_phases;
+        state.phase_to_use = phase_to_use;
+        Ok(())
+    }
+}
+
+/// Lock actuator which may jam, then it neither locks nor unlocks
+#[derive(Debug, Clone, Default)]
+pub struct SimulatedLock {
+    state: Arc<Mutex<LockState>>,
+}
+
+#[derive(Debug, Default)]
+struct LockState {
+    locked: bool,
+    jammed: bool,
+}
+
+impl SimulatedLock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_jammed(&self, jammed: bool) {
+        lock(&self.state).jammed = jammed;
+    }
+
+    pub fn locked(&self) -> bool {
+        lock(&self.state).locked
+    }
+
+    fn move_to(&self, locked: bool) -> Result<(), HalError> {
+        let mut state = lock(&self.state);
+        if state.jammed {
+            return Err(HalError::Fault("lock actuator jammed".to_string()));
+        }
+        state.locked = locked;
+        Ok(())
+    }
+}
+
+impl ConnectorLock for SimulatedLock {
+    fn lock(&mut self) -> Result<(), HalError> {
+        self.move_to(true)
+    }
+
+    fn unlock(&mut self) -> Result<(), HalError> {
+        self.move_to(false)
+    }
+
+    fn is_locked(&mut self) -> Result<bool, HalError> {
+        Ok(self.locked())
+    }
+}
+
+/// Reader handing out the tokens presented, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct SimulatedReader {
+    tokens: Arc<Mutex<VecDeque<IdToken>>>,
+}
+
+impl SimulatedReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Holds a card with `id_token` to the reader
+    pub fn present(&self, id_token: IdToken) {
+        lock(&self.tokens).push_back(id_token);
+    }
+}
+
+impl IdTokenReader for SimulatedReader {
+    fn read(&mut self) -> Result<Option<IdToken>, HalError> {
+        Ok(lock(&self.tokens).pop_front())
+    }
+}
+
+/// Pilot with a simulated vehicle on the other end of the cable
+#[derive(Debug, Clone)]
+pub struct SimulatedPilot {
+    state: Arc<Mutex<PilotState>>,
+}
+
+#[derive(Debug)]
+struct PilotState {
+    vehicle: CpState,
+    diode: bool,
+    duty_cycle: f64,
+}
+
+impl Default for SimulatedPilot {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(PilotState {
+                vehicle: CpState::A,
+                diode: true,
+                duty_cycle: NO_PWM,
+            })),
+        }
+    }
+}
+
+impl SimulatedPilot {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// State the vehicle pulls the pilot to, [`CpState::A`] unplugs it
+    pub fn set_vehicle(&self, state: CpState) {
+        lock(&self.state).vehicle = state;
+    }
+
+    /// A vehicle without the diode fails the pilot check
+    pub fn set_diode(&self, diode: bool) {
+        lock(&self.state).diode = diode;
+    }
+
+    pub fn vehicle(&self) -> CpState {
+        lock(&self.state).vehicle
+    }
+
+    /// Duty cycle last output by the stack
+    pub fn duty_cycle(&self) -> f64 {
+        lock(&self.state).duty_cycle
+    }
+}
+
+impl PilotInterface for SimulatedPilot {
+    fn set_duty_cycle(&mut self, percent: f64) -> Result<(), HalError> {
+        lock(&self.state).duty_cycle = percent;
+        Ok(())
+    }
+
+    fn sample(&mut self) -> Result<PilotSample, HalError> {
+        let state = lock(&self.state);
+        let high = match state.vehicle {
+            CpState::A => 12.0,
+            CpState::B => 9.0,
+            CpState::C => 6.0,
+            CpState::D => 3.0,
+            CpState::E => 0.0,
+            CpState::F => -12.0,
+        };
+        if state.duty_cycle >= NO_PWM {
+            return Ok(PilotSample::constant(high));
+        }
+        // without the diode the vehicle loads the negative half wave like the positive one
+        let low = if state.diode || !state.vehicle.is_connected() {
+            -12.0
+        } else {
+            -high
+        };
+        Ok(PilotSample::new(high, low))
+    }
+}
+
+/// A vehicle and what it draws when the supply is on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vehicle {
+    /// Current the on-board charger draws at most per phase
+    pub max_current: f64,
+    /// Phases the on-board charger uses, 1 to 3
+    pub phases: usize,
+}
+
+impl Default for Vehicle {
+    fn default() -> Self {
+        Self {
+            max_current: 16.0,
+            phases: 3,
+        }
+    }
+}
+
+/// Grid connection of a site with solar production, household loads and the chargers, whose
+/// meters add what the vehicles draw
+#[derive(Debug, Clone, Default)]
+pub struct SimulatedSite {
+    state: Arc<Mutex<SiteState>>,
+}
+
+#[derive(Debug, Default)]
+struct SiteState {
+    production: f64,
+    consumption: f64,
+    chargers: Vec<SimulatedMeter>,
+}
+
+impl SimulatedSite {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Solar production in W
+    pub fn set_production(&self, watts: f64) {
+        lock(&self.state).production = watts;
+    }
+
+    /// Household loads in W
+    pub fn set_consumption(&self, watts: f64) {
+        lock(&self.state).consumption = watts;
+    }
+
+    pub fn add_charger(&self, meter: SimulatedMeter) {
+        lock(&self.state).chargers.push(meter);
+    }
+}
+
+impl SiteMeter for SimulatedSite {
+    fn grid_power(&mut self) -> Result<f64, HalError> {
+        let state = lock(&self.state);
+        let charging: f64 = state.chargers.iter().map(SimulatedMeter::power).sum();
+        Ok(state.consumption + charging - state.production)
+    }
+}
+
+/// Complete set of simulated hardware of one connector, wired so that the meter sees what the
+/// vehicle draws from the offered current while the contactor is closed
+#[derive(Debug, Clone, Default)]
+pub struct SimulatedBoard {
+    pub meter: SimulatedMeter,
+    pub contactor: SimulatedContactor,
+    pub lock: SimulatedLock,
+    pub reader: SimulatedReader,
+    pub pilot: SimulatedPilot,
+    pub vehicle: Vehicle,
+}
+
+impl SimulatedBoard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handles to the devices, sharing them with the board
+    pub fn hardware(
+        &self,
+    ) -> ConnectorHardware<SimulatedPilot, SimulatedContactor, SimulatedLock, SimulatedMeter> {
+        ConnectorHardware {
+            pilot: self.pilot.clone(),
+            contactor: self.contactor.clone(),
+            lock: self.lock.clone(),
+            meter: self.meter.clone(),
+        }
+    }
+
+    /// Lets `elapsed` pass, the vehicle draws the offered current and the meter counts it
+    pub fn advance(&mut self, elapsed: Duration) {
+        let charging = self.contactor.is_closed().unwrap_or(false)
+            && matches!(self.pilot.vehicle(), CpState::C | CpState::D);
+        let current = match iec61851::current_from_duty_cycle(self.pilot.duty_cycle()) {
+            Some(offered) if charging => offered.min(self.vehicle.max_current),
+            _ => 0.0,
+        };
+        let mut currents = [0.0; 3];
+        for phase in currents.iter_mut().take(self.vehicle.phases) {
+            *phase = current;
+        }
+        self.meter.set_currents(currents);
+        self.meter.advance(elapsed);
+    }
+}
+
+fn lock<T>(state: &Mutex<T>) -> MutexGuard<'_, T> {
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_charging_vehicle__when_advancing__then_meter_counts_offered_current() {
+        let mut board = SimulatedBoard::new();
+        board.vehicle = Vehicle {
+            max_current: 13.0,
+            phases: 1,
+        };
+        board.pilot.set_vehicle(CpState::C);
+        board.pilot.clone().set_duty_cycle(25.0).unwrap();
+        board.contactor.clone().set_closed(true).unwrap();
+
+        board.advance(Duration::from_secs(3600));
+
+        let mut meter = board.meter.clone();
+        assert_eq!(
+            meter.read_one(Measurand::CurrentImport, Some(Phase::L1)),
+            Ok(Some(13.0))
+        );
+        assert_eq!(
+            meter.read_one(Measurand::CurrentImport, Some(Phase::L2)),
+            Ok(Some(0.0))
+        );
+        assert_eq!(
+            meter.read_one(Measurand::EnergyActiveImportRegister, None),
+            Ok(Some(2990.0))
+        );
+    }
+
+    #[test]
+    fn given_welded_contactor__when_opening__then_stays_closed_and_reports_welding() {
+        let mut contactor = SimulatedContactor::new();
+        contactor.set_closed(true).unwrap();
+        contactor.weld();
+
+        contactor.set_closed(false).unwrap();
+
+        assert_eq!(contactor.is_closed(), Ok(true));
+        assert_eq!(contactor.is_welded(), Ok(true));
+    }
+}
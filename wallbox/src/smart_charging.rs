@@ -0,0 +1,701 @@
+///// Limits of charging profiles in ampere per phase, and how they combine with local ones /////
+use std::collections::BTreeMap;
+
+use chrono::{Months, TimeDelta};
+use ocpp::datetime::DateTime;
+use ocpp::types::{
+    ChargingProfile, ChargingProfileKind, ChargingProfilePurpose, ChargingRateUnit,
+    ChargingSchedule, ChargingSchedulePeriod, RecurrencyKind,
+};
+use ocpp::v201::types as v201;
+use rust_decimal::Decimal;
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+
+/// Voltage between phase and neutral converting watt limits to ampere
+pub const NOMINAL_VOLTAGE: f64 = 230.0;
+
+/// The period of a schedule in force
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScheduleLimit {
+    /// Ampere per phase
+    pub current: f64,
+    pub number_phases: u8,
+    /// 1 to 3, the phase a single phase charges on. Only 2.0.1 schedules tell, see
+    /// [`InstalledProfile::with_v201_schedule`], `None` from 1.6 ones.
+    pub phase_to_use: Option<u8>,
+}
+
+/// A charging profile installed with SetChargingProfile or RemoteStartTransaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstalledProfile {
+    /// 0 for the whole charge point
+    pub connector_id: u32,
+    pub profile: ChargingProfile,
+    /// `phaseToUse` of each period of a 2.0.1 schedule, empty for 1.6 profiles
+    pub phases_to_use: Vec<Option<u8>>,
+}
+
+impl InstalledProfile {
+    pub fn new(connector_id: u32, profile: ChargingProfile) -> Self {
+        Self {
+            connector_id,
+            profile,
+            phases_to_use: Vec::new(),
+        }
+    }
+
+    /// A profile received with 2.0.1: `profile` gives purpose, kind and validity, `schedule`
+    /// replaces its schedule and keeps the `phaseToUse` of its periods
+    pub fn with_v201_schedule(
+        connector_id: u32,
+        mut profile: ChargingProfile,
+        schedule: &v201::ChargingSchedule,
+    ) -> Self {
+        let periods = &schedule.charging_schedule_period;
+        profile.charging_schedule = ChargingSchedule {
+            duration: schedule.duration,
+            start_schedule: schedule.start_schedule,
+            charging_rate_unit: schedule.charging_rate_unit,
+            charging_schedule_period: periods
+                .iter()
+                .map(|period| ChargingSchedulePeriod {
+                    start_period: period.start_period,
+                    limit: period.limit,
+                    number_phases: period.number_phases,
+                })
+                .collect(),
+            min_charging_rate: schedule.min_charging_rate,
+        };
+        Self {
+            connector_id,
+            profile,
+            phases_to_use: periods.iter().map(|period| period.phase_to_use).collect(),
+        }
+    }
+}
+
+/// The transaction of a connector, for its TxProfiles and relative schedules
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ActiveTransaction {
+    pub id: i32,
+    pub start: DateTime,
+}
+
+/// Limit of `schedule` at `now`, `None` before it starts and after its duration.
+/// `start` applies to schedules without `startSchedule`, i.e. the begin of the transaction.
+pub fn schedule_limit(
+    schedule: &ChargingSchedule,
+    start: DateTime,
+    now: DateTime,
+) -> Option<ScheduleLimit> {
+    limit_from(schedule, &[], schedule.start_schedule.unwrap_or(start), now)
+}
+
+/// Limit of the charging profiles of `connector_id` at `now`: the TxProfile in force, else the
+/// TxDefaultProfile, capped by the ChargePointMaxProfile. Among the profiles of one purpose the
+/// highest stack level in force wins, a TxDefaultProfile of the connector before one of
+/// connector 0. `None` if no profile limits the connector.
+pub fn composite_limit(
+    profiles: &[InstalledProfile],
+    connector_id: u32,
+    transaction: Option<ActiveTransaction>,
+    now: DateTime,
+) -> Option<ScheduleLimit> {
+    let purpose_limit = |purpose| purpose_limit(profiles, purpose, connector_id, transaction, now);
+    let transaction_limit = purpose_limit(ChargingProfilePurpose::TxProfile)
+        .or_else(|| purpose_limit(ChargingProfilePurpose::TxDefaultProfile));
+    match (
+        transaction_limit,
+        purpose_limit(ChargingProfilePurpose::ChargePointMaxProfile),
+    ) {
+        (Some(transaction_limit), Some(charge_point_limit)) => {
+            let number_phases = transaction_limit
+                .number_phases
+                .min(charge_point_limit.number_phases);
+            let lowest = if charge_point_limit.current < transaction_limit.current {
+                charge_point_limit
+            } else {
+                transaction_limit
+            };
+            Some(ScheduleLimit {
+                number_phases,
+                ..lowest
+            })
+        }
+        (transaction_limit, charge_point_limit) => transaction_limit.or(charge_point_limit),
+    }
+}
+
+/// The composite schedule of `connector_id` for GetCompositeSchedule, over `duration` seconds
+/// from `from`, in ampere. Periods no profile limits report `max_current`, the rating of the
+/// connector, which also caps the others.
+pub fn composite_schedule(
+    profiles: &[InstalledProfile],
+    connector_id: u32,
+    transaction: Option<ActiveTransaction>,
+    from: DateTime,
+    duration: u32,
+    max_current: f64,
+) -> ChargingSchedule {
+    let to = from + TimeDelta::seconds(duration as i64);
+    let mut boundaries = vec![from];
+    for installed in profiles
+        .iter()
+        .filter(|installed| applies(installed, connector_id, transaction))
+    {
+        boundaries.extend(boundaries_of(&installed.profile, transaction, from, to));
+    }
+    boundaries.retain(|at| from <= *at && *at < to);
+    boundaries.sort();
+    boundaries.dedup();
+
+    let mut periods: Vec<ChargingSchedulePeriod> = Vec::new();
+    for at in boundaries {
+        let limit = composite_limit(profiles, connector_id, transaction, at);
+        let (current, number_phases) = match limit {
+            Some(limit) => (limit.current.min(max_current), limit.number_phases),
+            None => (max_current, 3),
+        };
+        let period = ChargingSchedulePeriod {
+            start_period: (at - from).num_seconds() as u32,
+            limit: Decimal::from_f64(current).unwrap_or_default().round_dp(1),
+            number_phases: Some(number_phases),
+        };
+        let unchanged = periods.last().is_some_and(|last| {
+            (&last.limit, last.number_phases) == (&period.limit, period.number_phases)
+        });
+        if !unchanged {
+            periods.push(period);
+        }
+    }
+    ChargingSchedule {
+        duration: Some(duration),
+        start_schedule: Some(from),
+        charging_rate_unit: ChargingRateUnit::A,
+        charging_schedule_period: periods,
+        min_charging_rate: None,
+    }
+}
+
+/// Whether `installed` may limit `connector_id`, in force or not
+fn applies(
+    installed: &InstalledProfile,
+    connector_id: u32,
+    transaction: Option<ActiveTransaction>,
+) -> bool {
+    let profile = &installed.profile;
+    match profile.charging_profile_purpose {
+        ChargingProfilePurpose::ChargePointMaxProfile => installed.connector_id == 0,
+        ChargingProfilePurpose::TxDefaultProfile => {
+            installed.connector_id == 0 || installed.connector_id == connector_id
+        }
+        ChargingProfilePurpose::TxProfile => {
+            installed.connector_id == connector_id
+                && transaction.is_some_and(|transaction| {
+                    profile.transaction_id.is_none_or(|id| id == transaction.id)
+                })
+        }
+    }
+}
+
+fn purpose_limit(
+    profiles: &[InstalledProfile],
+    purpose: ChargingProfilePurpose,
+    connector_id: u32,
+    transaction: Option<ActiveTransaction>,
+    now: DateTime,
+) -> Option<ScheduleLimit> {
+    profiles
+        .iter()
+        .filter(|installed| {
+            installed.profile.charging_profile_purpose == purpose
+                && applies(installed, connector_id, transaction)
+        })
+        .filter_map(|installed| {
+            let rank = (installed.connector_id != 0, installed.profile.stack_level);
+            Some((rank, profile_limit(installed, transaction, now)?))
+        })
+        .max_by_key(|(rank, _)| *rank)
+        .map(|(_, limit)| limit)
+}
+
+fn profile_limit(
+    installed: &InstalledProfile,
+    transaction: Option<ActiveTransaction>,
+    now: DateTime,
+) -> Option<ScheduleLimit> {
+    let profile = &installed.profile;
+    if profile.valid_from.is_some_and(|from| now < from)
+        || profile.valid_to.is_some_and(|to| now >= to)
+    {
+        return None;
+    }
+    let schedule = &profile.charging_schedule;
+    // without a transaction a relative schedule starts right away
+    let transaction_start = transaction.map_or(now, |transaction| transaction.start);
+    let begin = match profile.charging_profile_kind {
+        ChargingProfileKind::Relative => transaction_start,
+        ChargingProfileKind::Absolute => schedule.start_schedule.unwrap_or(transaction_start),
+        ChargingProfileKind::Recurring => {
+            let first = schedule.start_schedule.unwrap_or(transaction_start);
+            let kind = profile.recurrency_kind.unwrap_or(RecurrencyKind::Daily);
+            recurrence(first, kind, recurrence_at(first, kind, now)?)?
+        }
+    };
+    limit_from(schedule, &installed.phases_to_use, begin, now)
+}
+
+/// Where the limit of `profile` may change between `from` and `to`
+fn boundaries_of(
+    profile: &ChargingProfile,
+    transaction: Option<ActiveTransaction>,
+    from: DateTime,
+    to: DateTime,
+) -> Vec<DateTime> {
+    let schedule = &profile.charging_schedule;
+    let transaction_start = transaction.map_or(from, |transaction| transaction.start);
+    let begins = match profile.charging_profile_kind {
+        ChargingProfileKind::Relative => vec![transaction_start],
+        ChargingProfileKind::Absolute => vec![schedule.start_schedule.unwrap_or(transaction_start)],
+        ChargingProfileKind::Recurring => {
+            let first = schedule.start_schedule.unwrap_or(transaction_start);
+            let kind = profile.recurrency_kind.unwrap_or(RecurrencyKind::Daily);
+            let n = recurrence_at(first, kind, from).unwrap_or(0);
+            (n..)
+                .map_while(|n| recurrence(first, kind, n))
+                .take_while(|begin| *begin < to)
+                .collect()
+        }
+    };
+    let mut boundaries: Vec<DateTime> = [profile.valid_from, profile.valid_to]
+        .into_iter()
+        .flatten()
+        .collect();
+    for begin in begins {
+        let seconds = schedule
+            .charging_schedule_period
+            .iter()
+            .map(|period| period.start_period)
+            .chain(schedule.duration);
+        boundaries.extend(seconds.map(|seconds| begin + TimeDelta::seconds(seconds as i64)));
+    }
+    boundaries
+}
+
+/// Start of the `n`th recurrence of a schedule first starting at `first`
+fn recurrence(first: DateTime, kind: RecurrencyKind, n: u32) -> Option<DateTime> {
+    let seconds: i64 = match kind {
+        RecurrencyKind::Minutely => 60,
+        RecurrencyKind::Hourly => 3600,
+        RecurrencyKind::Daily => 86_400,
+        RecurrencyKind::Weekly => 604_800,
+        RecurrencyKind::Monthly => return first.checked_add_months(Months::new(n)),
+        RecurrencyKind::Yearly => return first.checked_add_months(Months::new(n.checked_mul(12)?)),
+    };
+    first.checked_add_signed(TimeDelta::seconds(seconds * n as i64))
+}
+
+/// The recurrence in force at `now`, `None` before the first
+fn recurrence_at(first: DateTime, kind: RecurrencyKind, now: DateTime) -> Option<u32> {
+    if now < first {
+        return None;
+    }
+    // months and years differ in length, counting from the longest ones stays below
+    let longest: i64 = match kind {
+        RecurrencyKind::Minutely => 60,
+        RecurrencyKind::Hourly => 3600,
+        RecurrencyKind::Daily => 86_400,
+        RecurrencyKind::Weekly => 604_800,
+        RecurrencyKind::Monthly => 31 * 86_400,
+        RecurrencyKind::Yearly => 366 * 86_400,
+    };
+    let mut n = ((now - first).num_seconds() / longest) as u32;
+    while recurrence(first, kind, n + 1).is_some_and(|next| next <= now) {
+        n += 1;
+    }
+    Some(n)
+}
+
+/// `phases_to_use` holds the `phaseToUse` of the periods of a 2.0.1 schedule by index
+fn limit_from(
+    schedule: &ChargingSchedule,
+    phases_to_use: &[Option<u8>],
+    begin: DateTime,
+    now: DateTime,
+) -> Option<ScheduleLimit> {
+    let elapsed = (now - begin).num_seconds();
+    if elapsed < 0
+        || schedule
+            .duration
+            .is_some_and(|duration| elapsed >= duration as i64)
+    {
+        return None;
+    }
+    let (index, period) = schedule
+        .charging_schedule_period
+        .iter()
+        .enumerate()
+        .take_while(|(_, period)| period.start_period as i64 <= elapsed)
+        .last()?;
+    let number_phases = period.number_phases.unwrap_or(3);
+    let limit = period.limit.to_f64()?;
+    let current = match schedule.charging_rate_unit {
+        ChargingRateUnit::A => limit,
+        ChargingRateUnit::W => limit / (NOMINAL_VOLTAGE * number_phases as f64),
+    };
+    Some(ScheduleLimit {
+        current,
+        number_phases,
+        phase_to_use: phases_to_use.get(index).copied().flatten(),
+    })
+}
+
+/// Where a limit on the current of a connector comes from, highest priority first
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum LimitSource {
+    /// The grid operator controlling the load, [`crate::lpc`]
+    GridOperator,
+    /// The composite schedule of the OCPP charging profiles
+    ChargingProfile,
+    /// [`crate::load_management::LoadBalancer`]
+    LoadManagement,
+    /// [`crate::solar::SurplusCharger`]
+    SolarSurplus,
+    /// [`crate::phase_switching::PhaseSwitcher`] pausing to switch
+    PhaseSwitching,
+    /// A home energy manager, i.e. through the registers of [`crate::hems`]
+    External,
+}
+
+/// The limit in force
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limit {
+    pub source: LimitSource,
+    /// Ampere per phase
+    pub current: f64,
+}
+
+/// Limits of one connector from every source, the lowest applies
+#[derive(Debug, Clone, Default)]
+pub struct ConnectorLimits {
+    limits: BTreeMap<LimitSource, f64>,
+}
+
+impl ConnectorLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the limit of `source`, `None` lifts it
+    pub fn set(&mut self, source: LimitSource, current: Option<f64>) {
+        match current {
+            Some(current) => self.limits.insert(source, current),
+            None => self.limits.remove(&source),
+        };
+    }
+
+    pub fn get(&self, source: LimitSource) -> Option<f64> {
+        self.limits.get(&source).copied()
+    }
+
+    /// The lowest limit, the source of the highest priority among equal ones. `None` if
+    /// nothing limits the connector.
+    pub fn effective(&self) -> Option<Limit> {
+        self.lowest(None)
+    }
+
+    /// The lowest limit of the other sources, what `source` may hand out at most
+    pub fn effective_without(&self, source: LimitSource) -> Option<Limit> {
+        self.lowest(Some(source))
+    }
+
+    fn lowest(&self, except: Option<LimitSource>) -> Option<Limit> {
+        self.limits
+            .iter()
+            .filter(|(source, _)| Some(**source) != except)
+            .map(|(source, current)| Limit {
+                source: *source,
+                current: *current,
+            })
+            .reduce(|lowest, limit| {
+                if limit.current < lowest.current {
+                    limit
+                } else {
+                    lowest
+                }
+            })
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(value: &str) -> DateTime {
+        ocpp::datetime::parse(value).unwrap()
+    }
+
+    #[test]
+    fn given_watt_schedule__when_looking_up__then_period_in_ampere() {
+        let schedule = ChargingSchedule {
+            duration: Some(7200),
+            start_schedule: None,
+            charging_rate_unit: ChargingRateUnit::W,
+            charging_schedule_period: vec![
+                ChargingSchedulePeriod {
+                    start_period: 0,
+                    limit: Decimal::from(11040),
+                    number_phases: None,
+                },
+                ChargingSchedulePeriod {
+                    start_period: 3600,
+                    limit: Decimal::from(1380),
+                    number_phases: Some(1),
+                },
+            ],
+            min_charging_rate: None,
+        };
+        let start = at("2025-06-01T12:00:00Z");
+
+        assert_eq!(
+            schedule_limit(&schedule, start, at("2025-06-01T12:30:00Z")),
+            Some(ScheduleLimit {
+                current: 16.0,
+                number_phases: 3,
+                phase_to_use: None
+            })
+        );
+        assert_eq!(
+            schedule_limit(&schedule, start, at("2025-06-01T13:00:00Z")),
+            Some(ScheduleLimit {
+                current: 6.0,
+                number_phases: 1,
+                phase_to_use: None
+            })
+        );
+        assert_eq!(
+            schedule_limit(&schedule, start, at("2025-06-01T14:00:00Z")),
+            None
+        );
+    }
+
+    #[test]
+    fn given_profile_and_solar_limits__when_combining__then_lowest_applies() {
+        let mut limits = ConnectorLimits::new();
+        assert_eq!(limits.effective(), None);
+
+        limits.set(LimitSource::ChargingProfile, Some(10.0));
+        limits.set(LimitSource::SolarSurplus, Some(13.2));
+        assert_eq!(
+            limits.effective(),
+            Some(Limit {
+                source: LimitSource::ChargingProfile,
+                current: 10.0
+            })
+        );
+
+        limits.set(LimitSource::SolarSurplus, Some(0.0));
+        assert_eq!(
+            limits.effective().unwrap().source,
+            LimitSource::SolarSurplus
+        );
+        limits.set(LimitSource::SolarSurplus, None);
+        assert_eq!(limits.effective().unwrap().current, 10.0);
+    }
+
+    #[test]
+    fn given_equal_limits__when_combining__then_highest_priority_reported() {
+        let mut limits = ConnectorLimits::new();
+        limits.set(LimitSource::ChargingProfile, Some(6.0));
+        limits.set(LimitSource::GridOperator, Some(6.0));
+
+        assert_eq!(
+            limits.effective().unwrap().source,
+            LimitSource::GridOperator
+        );
+    }
+
+    fn profile(
+        connector_id: u32,
+        stack_level: u32,
+        purpose: ChargingProfilePurpose,
+        kind: ChargingProfileKind,
+        start_schedule: Option<&str>,
+        duration: Option<u32>,
+        periods: &[(u32, i64)],
+    ) -> InstalledProfile {
+        InstalledProfile::new(
+            connector_id,
+            ChargingProfile {
+                charging_profile_id: stack_level as i32,
+                transaction_id: None,
+                stack_level,
+                charging_profile_purpose: purpose,
+                charging_profile_kind: kind,
+                recurrency_kind: (kind == ChargingProfileKind::Recurring)
+                    .then_some(RecurrencyKind::Daily),
+                valid_from: None,
+                valid_to: None,
+                charging_schedule: ChargingSchedule {
+                    duration,
+                    start_schedule: start_schedule.map(at),
+                    charging_rate_unit: ChargingRateUnit::A,
+                    charging_schedule_period: periods
+                        .iter()
+                        .map(|(start_period, limit)| ChargingSchedulePeriod {
+                            start_period: *start_period,
+                            limit: Decimal::from(*limit),
+                            number_phases: None,
+                        })
+                        .collect(),
+                    min_charging_rate: None,
+                },
+            },
+        )
+    }
+
+    fn profiles() -> Vec<InstalledProfile> {
+        use ChargingProfileKind::*;
+        use ChargingProfilePurpose::*;
+        vec![
+            // 12 A for the whole charge point every evening from 17:00 to 20:00
+            profile(
+                0,
+                0,
+                ChargePointMaxProfile,
+                Recurring,
+                Some("2025-05-01T17:00:00Z"),
+                Some(10_800),
+                &[(0, 12)],
+            ),
+            profile(0, 0, TxDefaultProfile, Relative, None, None, &[(0, 16)]),
+            profile(
+                1,
+                0,
+                TxDefaultProfile,
+                Relative,
+                None,
+                None,
+                &[(0, 10), (3600, 32)],
+            ),
+            profile(
+                1,
+                1,
+                TxProfile,
+                Absolute,
+                Some("2025-06-01T16:30:00Z"),
+                Some(600),
+                &[(0, 8)],
+            ),
+        ]
+    }
+
+    #[test]
+    fn given_stacked_profiles__when_looking_up__then_tx_over_default_capped_by_charge_point_max() {
+        let profiles = profiles();
+        let transaction = Some(ActiveTransaction {
+            id: 7,
+            start: at("2025-06-01T16:00:00Z"),
+        });
+        let current = |connector_id, transaction, now| {
+            composite_limit(&profiles, connector_id, transaction, at(now))
+                .map(|limit| limit.current)
+        };
+
+        // the default of connector 1 overrides the one of connector 0
+        assert_eq!(current(1, transaction, "2025-06-01T16:10:00Z"), Some(10.0));
+        assert_eq!(current(2, None, "2025-06-01T16:10:00Z"), Some(16.0));
+        assert_eq!(current(1, transaction, "2025-06-01T16:35:00Z"), Some(8.0));
+        // the TxProfile needs a transaction
+        assert_eq!(current(1, None, "2025-06-01T16:35:00Z"), Some(10.0));
+        assert_eq!(current(1, transaction, "2025-06-01T18:00:00Z"), Some(12.0));
+        assert_eq!(current(1, transaction, "2025-06-01T20:00:00Z"), Some(32.0));
+        assert_eq!(
+            composite_limit(&[], 1, transaction, at("2025-06-01T16:10:00Z")),
+            None
+        );
+    }
+
+    #[test]
+    fn given_v201_schedule_with_phase_to_use__when_looking_up__then_phase_of_period_kept() {
+        let schedule = v201::ChargingSchedule {
+            id: 1,
+            start_schedule: Some(at("2025-06-01T16:00:00Z")),
+            duration: None,
+            charging_rate_unit: ChargingRateUnit::A,
+            charging_schedule_period: vec![
+                v201::ChargingSchedulePeriod {
+                    start_period: 0,
+                    limit: Decimal::from(16),
+                    number_phases: None,
+                    phase_to_use: None,
+                },
+                v201::ChargingSchedulePeriod {
+                    start_period: 1800,
+                    limit: Decimal::from(16),
+                    number_phases: Some(1),
+                    phase_to_use: Some(2),
+                },
+            ],
+            min_charging_rate: None,
+        };
+        let base = profile(
+            0,
+            0,
+            ChargingProfilePurpose::TxDefaultProfile,
+            ChargingProfileKind::Absolute,
+            None,
+            None,
+            &[],
+        );
+        let profiles = [InstalledProfile::with_v201_schedule(
+            0,
+            base.profile,
+            &schedule,
+        )];
+        let limit = |now| composite_limit(&profiles, 1, None, at(now)).unwrap();
+
+        assert_eq!(limit("2025-06-01T16:10:00Z").phase_to_use, None);
+        assert_eq!(
+            limit("2025-06-01T16:40:00Z"),
+            ScheduleLimit {
+                current: 16.0,
+                number_phases: 1,
+                phase_to_use: Some(2)
+            }
+        );
+    }
+
+    #[test]
+    fn given_stacked_profiles__when_computing_composite_schedule__then_periods_where_limit_changes()
+    {
+        let transaction = Some(ActiveTransaction {
+            id: 7,
+            start: at("2025-06-01T16:00:00Z"),
+        });
+
+        let schedule = composite_schedule(
+            &profiles(),
+            1,
+            transaction,
+            at("2025-06-01T16:00:00Z"),
+            18_000,
+            20.0,
+        );
+
+        let periods: Vec<(u32, Decimal)> = schedule
+            .charging_schedule_period
+            .iter()
+            .map(|period| (period.start_period, period.limit))
+            .collect();
+        assert_eq!(
+            periods,
+            [(0, 10), (1800, 8), (2400, 10), (3600, 12), (14_400, 20)]
+                .map(|(start, limit)| (start, Decimal::from(limit)))
+                .to_vec()
+        );
+        assert_eq!(schedule.start_schedule, Some(at("2025-06-01T16:00:00Z")));
+        assert_eq!(schedule.charging_rate_unit, ChargingRateUnit::A);
+    }
+}
@@ -0,0 +1,260 @@
+///// Charging from the solar surplus of the site /////
+use std::time::{Duration, Instant};
+
+use ocpp::types::Measurand;
+
+use crate::hal::{EnergyMeter, HalError, SiteMeter};
+use crate::iec61851::MIN_CURRENT;
+use crate::smart_charging::NOMINAL_VOLTAGE;
+
+/// The surplus has to last that long before charging starts
+pub const DEFAULT_START_DELAY: Duration = Duration::from_secs(60);
+/// A lacking surplus has to last that long before charging stops, passing clouds do not
+pub const DEFAULT_STOP_DELAY: Duration = Duration::from_secs(300);
+
+/// Follows the power the site feeds into the grid with the charging current.
+///
+/// The surplus is the export at the grid connection plus what the vehicle draws already.
+/// Charging starts once the surplus covers [`MIN_CURRENT`] on every phase and keeps at least
+/// that current until the surplus stays too low for the stop delay, drawing the rest from the
+/// grid meanwhile.
+#[derive(Debug, Clone)]
+pub struct SurplusCharger {
+    max_current: f64,
+    phases: u8,
+    start_margin: f64,
+    tolerated_import: f64,
+    start_delay: Duration,
+    stop_delay: Duration,
+    charging: bool,
+    /// The start or stop condition holds since
+    pending_since: Option<Instant>,
+    current: f64,
+}
+
+impl SurplusCharger {
+    /// `max_current` in ampere per phase, `phases` the vehicle charges with. A maximum below
+    /// [`MIN_CURRENT`] never starts charging, the vehicle could not follow it.
+    pub fn new(max_current: f64, phases: u8) -> Self {
+        Self {
+            max_current,
+            phases,
+            start_margin: 0.0,
+            tolerated_import: 0.0,
+            start_delay: DEFAULT_START_DELAY,
+            stop_delay: DEFAULT_STOP_DELAY,
+            charging: false,
+            pending_since: None,
+            current: 0.0,
+        }
+    }
+
+    /// Surplus in W beyond the minimum power needed to start
+    pub fn start_margin(mut self, watts: f64) -> Self {
+        self.start_margin = watts;
+        self
+    }
+
+    /// Grid import in W at the minimum current before the stop delay runs
+    pub fn tolerated_import(mut self, watts: f64) -> Self {
+        self.tolerated_import = watts;
+        self
+    }
+
+    pub fn start_delay(mut self, delay: Duration) -> Self {
+        self.start_delay = delay;
+        self
+    }
+
+    pub fn stop_delay(mut self, delay: Duration) -> Self {
+        self.stop_delay = delay;
+        self
+    }
+
+    pub fn set_phases(&mut self, phases: u8) {
+        self.phases = phases;
+    }
+
+    pub fn phases(&self) -> u8 {
+        self.phases
+    }
+
+    pub fn is_charging(&self) -> bool {
+        self.charging
+    }
+
+    /// The current last calculated, 0 A while paused
+    pub fn current(&self) -> f64 {
+        self.current
+    }
+
+    /// Power in W of [`MIN_CURRENT`] on the phases charging
+    pub fn minimum_power(&self) -> f64 {
+        MIN_CURRENT * NOMINAL_VOLTAGE * self.phases as f64
+    }
+
+    /// Reads the site meter and the meter of the connector, see [`update`](Self::update)
+    pub fn poll(
+        &mut self,
+        site: &mut impl SiteMeter,
+        charger: &mut impl EnergyMeter,
+        now: Instant,
+    ) -> Result<f64, HalError> {
+        let grid_power = site.grid_power()?;
+        let charging_power = charger
+            .read_one(Measurand::PowerActiveImport, None)?
+            .unwrap_or(0.0);
+        Ok(self.update(grid_power, charging_power, now))
+    }
+
+    /// `grid_power` in W at the grid connection, positive drawn, and `charging_power` the
+    /// vehicle draws now. Returns the current to offer in ampere per phase, 0 A pauses.
+    pub fn update(&mut self, grid_power: f64, charging_power: f64, now: Instant) -> f64 {
+        let surplus = charging_power - grid_power;
+        let (condition, delay) = if self.charging {
+            (
+                surplus < self.minimum_power() - self.tolerated_import,
+                self.stop_delay,
+            )
+        } else {
+            (
+                self.max_current >= MIN_CURRENT
+                    && surplus >= self.minimum_power() + self.start_margin,
+                self.start_delay,
+            )
+        };
+        if condition {
+            let since = *self.pending_since.get_or_insert(now);
+            if now.duration_since(since) >= delay {
+                self.charging = !self.charging;
+                self.pending_since = None;
+            }
+        } else {
+            self.pending_since = None;
+        }
+
+        self.current = if self.charging {
+            let current = surplus / (NOMINAL_VOLTAGE * self.phases as f64);
+            (current.max(MIN_CURRENT).min(self.max_current) * 10.0).floor() / 10.0
+        } else {
+            0.0
+        };
+        self.current
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{SimulatedMeter, SimulatedSite};
+    use crate::smart_charging::{ConnectorLimits, LimitSource};
+
+    /// One-minute samples of a partly cloudy day, a passing cloud at minute 50 and a cloud
+    /// bank from minute 80 to 92
+    const SOLAR_DAY: &str = include_str!("../testdata/solar_day.csv");
+
+    fn solar_day() -> Vec<(f64, f64)> {
+        SOLAR_DAY
+            .lines()
+            .skip(1)
+            .map(|line| {
+                let fields: Vec<f64> = line
+                    .split(',')
+                    .map(|field| field.parse().unwrap())
+                    .collect();
+                (fields[1], fields[2])
+            })
+            .collect()
+    }
+
+    struct Run {
+        /// Minutes charging started and stopped
+        starts: Vec<usize>,
+        stops: Vec<usize>,
+        charged_wh: f64,
+        imported_wh: f64,
+    }
+
+    /// Replays the day in steps of 10 s, the vehicle draws the offered current at once
+    fn replay(profile_limit: Option<f64>) -> Run {
+        let site = SimulatedSite::new();
+        let charger = SimulatedMeter::new();
+        site.add_charger(charger.clone());
+        let mut solar = SurplusCharger::new(16.0, 3);
+        let mut limits = ConnectorLimits::new();
+        limits.set(LimitSource::ChargingProfile, profile_limit);
+        let step = Duration::from_secs(10);
+        let t0 = Instant::now();
+        let mut run = Run {
+            starts: Vec::new(),
+            stops: Vec::new(),
+            charged_wh: 0.0,
+            imported_wh: 0.0,
+        };
+
+        for (minute, (production, consumption)) in solar_day().into_iter().enumerate() {
+            site.set_production(production);
+            site.set_consumption(consumption);
+            for tick in 0..6 {
+                let now = t0 + step * (minute as u32 * 6 + tick);
+                let was_charging = solar.is_charging();
+                let surplus = solar
+                    .poll(&mut site.clone(), &mut charger.clone(), now)
+                    .unwrap();
+                limits.set(LimitSource::SolarSurplus, Some(surplus));
+                let offered = limits.effective().unwrap().current;
+                charger.set_currents([if offered < MIN_CURRENT { 0.0 } else { offered }; 3]);
+
+                match (was_charging, solar.is_charging()) {
+                    (false, true) => run.starts.push(minute),
+                    (true, false) => run.stops.push(minute),
+                    _ => {}
+                }
+                let hours = step.as_secs_f64() / 3600.0;
+                run.charged_wh += charger.power() * hours;
+                run.imported_wh += site.clone().grid_power().unwrap().max(0.0) * hours;
+            }
+        }
+        run
+    }
+
+    #[test]
+    fn given_recorded_solar_day__when_charging_from_surplus__then_clouds_bridged_or_paused() {
+        let run = replay(None);
+
+        // 4140 W for 6 A on three phases are there from minute 36 on, after the start delay
+        assert_eq!(run.starts, vec![37, 93]);
+        // the passing cloud is bridged, the cloud bank stops charging after five minutes and so
+        // does the production falling below the minimum in the afternoon
+        assert_eq!(run.stops, vec![85, 120]);
+        assert!(run.charged_wh > 6_000.0, "charged {} Wh", run.charged_wh);
+        assert!(
+            run.imported_wh < run.charged_wh * 0.1,
+            "imported {} Wh",
+            run.imported_wh
+        );
+    }
+
+    #[test]
+    fn given_charging_profile__when_surplus_is_higher__then_profile_limits() {
+        let unlimited = replay(None);
+        let limited = replay(Some(8.0));
+
+        assert_eq!(limited.starts, unlimited.starts);
+        assert!(limited.charged_wh < unlimited.charged_wh);
+        // 8 A on three phases for at most the 150 minutes of the day
+        assert!(limited.charged_wh <= 8.0 * 690.0 * 2.5);
+    }
+
+    #[test]
+    fn given_max_current_below_minimum__when_surplus__then_never_charges() {
+        let t0 = Instant::now();
+        let mut solar = SurplusCharger::new(5.0, 1).start_delay(Duration::ZERO);
+
+        let current = solar.update(-5000.0, 0.0, t0);
+
+        assert_eq!(current, 0.0);
+        assert!(!solar.is_charging());
+    }
+}